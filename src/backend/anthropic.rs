@@ -0,0 +1,181 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::TranslatorError;
+use crate::progress::Progress;
+use super::cassette::Cassette;
+use super::deepl::Formality;
+use super::openai::{translation_prompt, with_notes};
+use super::retry::{parse_json, send_with_retry, RetryPolicy};
+use crate::rate_limit::RateLimiter;
+use crate::prompt::PromptTemplate;
+use crate::translator::{ContextParagraph, TextFormat, Translator};
+
+pub const DEFAULT_API_URL: &str = "https://api.anthropic.com/v1/messages";
+pub const DEFAULT_MODEL: &str = "claude-haiku-4-5";
+
+/// The version of the Messages API the requests are written for.
+const API_VERSION: &str = "2023-06-01";
+
+/// The longest reply asked for, far more than the translation of a chunk needs.
+const MAX_TOKENS: u32 = 8192;
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    messages: Vec<Message>,
+    temperature: f32,
+}
+
+#[derive(Serialize)]
+struct Message {
+    role: &'static str,
+    content: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
+}
+
+/// Client for Anthropic's Messages API (Claude models).
+pub struct AnthropicClient {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: String,
+    model: String,
+    prompt_template: PromptTemplate,
+    domain: Option<String>,
+    formality: Option<Formality>,
+    text_format: TextFormat,
+    progress: Progress,
+    retry: RetryPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl AnthropicClient {
+    pub fn new(client: reqwest::Client, api_url: impl Into<String>, api_key: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_url: api_url.into(),
+            api_key: api_key.into(),
+            model: model.into(),
+            prompt_template: PromptTemplate::default(),
+            domain: None,
+            formality: None,
+            text_format: TextFormat::Text,
+            progress: Progress::hidden(),
+            retry: RetryPolicy::default(),
+            limiter: None,
+            cassette: None,
+        }
+    }
+
+    /// Replaces the default translation instructions sent with every chunk.
+    pub fn with_prompt_template(mut self, prompt_template: PromptTemplate) -> Self {
+        self.prompt_template = prompt_template;
+        self
+    }
+
+    /// Describes the field the texts come from, so the model uses its terminology and style.
+    pub fn with_domain(mut self, domain: Option<String>) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    /// Asks the model for a formal or informal tone.
+    pub fn with_formality(mut self, formality: Option<Formality>) -> Self {
+        self.formality = formality;
+        self
+    }
+
+    /// Sets whether the text is plain text or HTML.
+    pub fn with_text_format(mut self, text_format: TextFormat) -> Self {
+        self.text_format = text_format;
+        self
+    }
+
+    /// Reports retries as events of `progress`.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets how often and after what delay failed requests are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Reports the server's rate limit headers to the limiter pacing the requests.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Records the exchanges with the server in `cassette`, or answers requests from it.
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Sends `prompt` as a user message and returns the model's reply.
+    pub async fn complete(&self, prompt: String) -> Result<String, TranslatorError> {
+        let request_payload = MessagesRequest {
+            model: &self.model,
+            max_tokens: MAX_TOKENS,
+            messages: vec![Message { role: "user", content: with_notes(prompt, self.domain.as_deref(), self.formality) }],
+            temperature: 0.0,
+        };
+        let body_text = send_with_retry(
+            || {
+                self.client
+                    .post(&self.api_url)
+                    .header("x-api-key", &self.api_key)
+                    .header("anthropic-version", API_VERSION)
+                    .json(&request_payload)
+            },
+            &self.progress,
+            &self.retry,
+            self.limiter.as_deref(),
+            self.cassette.as_deref(),
+        )
+        .await?;
+        let response: MessagesResponse = parse_json(&body_text)?;
+        if response.stop_reason.as_deref() == Some("max_tokens") {
+            return Err(TranslatorError::Parse("The reply of the Messages API was cut off at its maximum length".to_string()));
+        }
+        let reply: String = response.content.into_iter().filter(|block| block.kind == "text").map(|block| block.text).collect();
+        if reply.trim().is_empty() {
+            return Err(TranslatorError::Parse("The Messages API returned no text".to_string()));
+        }
+        Ok(reply.trim().to_string())
+    }
+}
+
+impl Translator for AnthropicClient {
+    async fn translate(&self, chunk: &str, source_lang: &str, target_lang: &str) -> Result<String, TranslatorError> {
+        self.translate_in_context(chunk, source_lang, target_lang, &[]).await
+    }
+
+    async fn translate_in_context(
+        &self,
+        chunk: &str,
+        source_lang: &str,
+        target_lang: &str,
+        context: &[ContextParagraph],
+    ) -> Result<String, TranslatorError> {
+        self.complete(translation_prompt(&self.prompt_template, self.text_format, chunk, source_lang, target_lang, context)).await
+    }
+}
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::TranslatorError;
+use crate::progress::Progress;
+use super::cassette::Cassette;
+use super::retry::{parse_json, send_with_retry, RetryPolicy};
+use crate::rate_limit::RateLimiter;
+use crate::translator::{TextFormat, Translator, AUTO_LANGUAGE};
+
+pub const DEFAULT_API_URL: &str = "https://api.cognitive.microsofttranslator.com/translate";
+
+#[derive(Serialize)]
+struct TranslationRequest<'a> {
+    #[serde(rename = "Text")]
+    text: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct TranslationResponse {
+    translations: Vec<Translation>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Translation {
+    text: String,
+}
+
+/// Client for the Azure AI Translator (v3.0) API.
+pub struct AzureClient {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: String,
+    region: Option<String>,
+    text_format: TextFormat,
+    progress: Progress,
+    retry: RetryPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl AzureClient {
+    pub fn new(client: reqwest::Client, api_url: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_url: api_url.into(),
+            api_key: api_key.into(),
+            region: None,
+            text_format: TextFormat::Text,
+            progress: Progress::hidden(),
+            retry: RetryPolicy::default(),
+            limiter: None,
+            cassette: None,
+        }
+    }
+
+    /// Sets the Azure resource region; required for regional and multi-service resources.
+    pub fn with_region(mut self, region: Option<String>) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Sets whether the text is plain text or HTML.
+    pub fn with_text_format(mut self, text_format: TextFormat) -> Self {
+        self.text_format = text_format;
+        self
+    }
+
+    /// Reports retries as events of `progress`.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets how often and after what delay failed requests are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Reports the server's rate limit headers to the limiter pacing the requests.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Records the exchanges with the server in `cassette`, or answers requests from it.
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+}
+
+impl Translator for AzureClient {
+    async fn translate(
+        &self,
+        chunk: &str,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> Result<String, TranslatorError> {
+        let request_payload = [TranslationRequest { text: chunk }];
+
+        let body_text = send_with_retry(
+            || {
+                let mut request = self
+                    .client
+                    .post(&self.api_url)
+                    .query(&[
+                        ("api-version", "3.0"),
+                        ("to", target_lang),
+                        ("textType", self.text_format.as_str()),
+                    ])
+                    .header("Ocp-Apim-Subscription-Key", &self.api_key);
+                // Azure detects the language when `from` is omitted.
+                if source_lang != AUTO_LANGUAGE {
+                    request = request.query(&[("from", source_lang)]);
+                }
+                if let Some(region) = &self.region {
+                    request = request.header("Ocp-Apim-Subscription-Region", region);
+                }
+                request.json(&request_payload)
+            },
+            &self.progress,
+            &self.retry,
+            self.limiter.as_deref(),
+            self.cassette.as_deref(),
+        )
+        .await?;
+        let response: Vec<TranslationResponse> = parse_json(&body_text)?;
+        response
+            .into_iter()
+            .next()
+            .and_then(|r| r.translations.into_iter().next())
+            .map(|t| t.text)
+            .ok_or_else(|| TranslatorError::Parse("Azure returned no translations".to_string()))
+    }
+}
@@ -0,0 +1,122 @@
+//! Recording the exchanges with the translation service and replaying them later, so runs can
+//! be tested and demonstrated without a live API.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::retry::redacted;
+use crate::error::TranslatorError;
+use crate::output;
+
+/// Fields of request bodies holding API keys, which must not end up in fixture files.
+const SECRET_FIELDS: &[&str] = &["api_key", "auth_key"];
+
+/// A request and the response it got.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    url: String,
+    /// The JSON body of the request, or its text for other bodies.
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    body: Value,
+    status: u16,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    response: String,
+}
+
+impl Interaction {
+    /// The recorded response, as if it came from the server.
+    fn to_response(&self) -> Result<reqwest::Response, http::Error> {
+        let mut response = http::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            response = response.header(name, value);
+        }
+        Ok(reqwest::Response::from(response.body(self.response.clone())?))
+    }
+}
+
+/// A fixture file of interactions, being recorded or replayed.
+///
+/// While recording, every response is added to the file as it arrives. While replaying,
+/// requests are answered from the file, each recorded response used once.
+pub struct Cassette {
+    path: PathBuf,
+    replaying: bool,
+    interactions: Mutex<Vec<Interaction>>,
+}
+
+impl Cassette {
+    /// Starts recording to `path`, replacing an existing file.
+    pub fn record(path: &Path) -> Self {
+        Self { path: path.to_path_buf(), replaying: false, interactions: Mutex::new(Vec::new()) }
+    }
+
+    /// Loads the interactions recorded in `path` to replay them.
+    pub fn replay(path: &Path) -> Result<Self, TranslatorError> {
+        let interactions = serde_json::from_str(&fs::read_to_string(path)?)?;
+        Ok(Self { path: path.to_path_buf(), replaying: true, interactions: Mutex::new(interactions) })
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    /// The recorded response to `request`; requests that weren't recorded are an error, as
+    /// nothing may reach the network while replaying.
+    pub(crate) fn answer(&self, request: &reqwest::Request) -> Result<reqwest::Response, TranslatorError> {
+        let (method, url, body) = key(request);
+        let mut interactions = self.interactions.lock().unwrap();
+        let index = interactions
+            .iter()
+            .position(|interaction| interaction.method == method && interaction.url == url && interaction.body == body)
+            .ok_or_else(|| format!("No response to {} {} is recorded in {:?}", method, url, self.path))?;
+        let interaction = interactions.remove(index);
+        interaction
+            .to_response()
+            .map_err(|error| TranslatorError::Parse(format!("Invalid recorded response in {:?}: {}", self.path, error)))
+    }
+
+    /// Adds the response to `request` to the file and returns it again, read completely.
+    pub(crate) async fn store(
+        &self,
+        request: (String, String, Value),
+        response: reqwest::Response,
+    ) -> Result<reqwest::Response, TranslatorError> {
+        let status = response.status();
+        let headers: BTreeMap<String, String> = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+            .collect();
+        let text = response.text().await?;
+        let (method, url, body) = request;
+        let interaction = Interaction { method, url, body, status: status.as_u16(), headers, response: text };
+        let response = interaction.to_response().expect("the response was valid when received");
+
+        // The file is rewritten after every response, so an interrupted run still leaves a usable recording.
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions.push(interaction);
+        output::write_atomic(&self.path, serde_json::to_string_pretty(&*interactions)?)?;
+        Ok(response)
+    }
+}
+
+/// What a request is recognized by: its method, its URL and its body, without API keys.
+pub(crate) fn key(request: &reqwest::Request) -> (String, String, Value) {
+    let body = request.body().and_then(reqwest::Body::as_bytes).unwrap_or_default();
+    let body = match serde_json::from_slice::<Value>(body) {
+        Ok(Value::Object(mut fields)) => {
+            fields.retain(|name, _| !SECRET_FIELDS.contains(&name.as_str()));
+            Value::Object(fields)
+        }
+        Ok(value) => value,
+        Err(_) if body.is_empty() => Value::Null,
+        Err(_) => Value::String(String::from_utf8_lossy(body).into_owned()),
+    };
+    (request.method().to_string(), redacted(request.url()).to_string(), body)
+}
@@ -0,0 +1,210 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::error::TranslatorError;
+use crate::progress::Progress;
+use super::cassette::Cassette;
+use super::retry::{parse_json, send_with_retry, RetryPolicy};
+use crate::rate_limit::RateLimiter;
+use crate::translator::{ContextParagraph, TextFormat, Translator};
+
+pub const DEFAULT_API_URL: &str = "https://api.deepl.com/v2/translate";
+/// Endpoint for DeepL API Free accounts, whose keys end with `:fx`.
+pub const FREE_API_URL: &str = "https://api-free.deepl.com/v2/translate";
+
+/// Picks the DeepL endpoint matching the account type of the given key.
+pub fn default_api_url(api_key: &str) -> &'static str {
+    if api_key.ends_with(":fx") {
+        FREE_API_URL
+    } else {
+        DEFAULT_API_URL
+    }
+}
+
+/// Tone of the translation; only supported by some target languages (e.g. German, Polish).
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Formality {
+    Default,
+    More,
+    Less,
+    /// Like `more`, but falls back to the default for languages without formality support.
+    PreferMore,
+    /// Like `less`, but falls back to the default for languages without formality support.
+    PreferLess,
+}
+
+impl Formality {
+    fn as_str(self) -> &'static str {
+        match self {
+            Formality::Default => "default",
+            Formality::More => "more",
+            Formality::Less => "less",
+            Formality::PreferMore => "prefer_more",
+            Formality::PreferLess => "prefer_less",
+        }
+    }
+
+    /// The instruction asking an LLM for this tone, if any.
+    pub(super) fn instruction(self) -> Option<&'static str> {
+        match self {
+            Formality::Default => None,
+            Formality::More | Formality::PreferMore => {
+                Some("Use a formal register, addressing the reader formally (e.g. 'Sie' in German, 'Ön' in Hungarian).")
+            }
+            Formality::Less | Formality::PreferLess => {
+                Some("Use an informal register, addressing the reader informally (e.g. 'du' in German, 'te' in Hungarian).")
+            }
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct TranslationResponse {
+    translations: Vec<Translation>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Translation {
+    text: String,
+}
+
+/// Client for the DeepL `/v2/translate` API endpoint.
+pub struct DeepLClient {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: String,
+    formality: Option<Formality>,
+    domain: Option<String>,
+    text_format: TextFormat,
+    progress: Progress,
+    retry: RetryPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl DeepLClient {
+    pub fn new(client: reqwest::Client, api_url: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_url: api_url.into(),
+            api_key: api_key.into(),
+            formality: None,
+            domain: None,
+            text_format: TextFormat::Text,
+            progress: Progress::hidden(),
+            retry: RetryPolicy::default(),
+            limiter: None,
+            cassette: None,
+        }
+    }
+
+    /// Requests a more or less formal tone from DeepL.
+    pub fn with_formality(mut self, formality: Option<Formality>) -> Self {
+        self.formality = formality;
+        self
+    }
+
+    /// Describes the field the texts come from, e.g. "medical report", so DeepL picks its terms.
+    pub fn with_domain(mut self, domain: Option<String>) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    /// Sets whether the text is plain text or HTML.
+    pub fn with_text_format(mut self, text_format: TextFormat) -> Self {
+        self.text_format = text_format;
+        self
+    }
+
+    /// Reports retries as events of `progress`.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets how often and after what delay failed requests are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Reports the server's rate limit headers to the limiter pacing the requests.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Records the exchanges with the server in `cassette`, or answers requests from it.
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+}
+
+impl Translator for DeepLClient {
+    async fn translate(
+        &self,
+        chunk: &str,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> Result<String, TranslatorError> {
+        self.translate_in_context(chunk, source_lang, target_lang, &[]).await
+    }
+
+    async fn translate_in_context(
+        &self,
+        chunk: &str,
+        source_lang: &str,
+        target_lang: &str,
+        context: &[ContextParagraph],
+    ) -> Result<String, TranslatorError> {
+        // DeepL takes form-encoded parameters where `text` may be repeated to
+        // translate several texts at once, and expects upper-case language codes.
+        let source_lang = source_lang.to_uppercase();
+        let target_lang = target_lang.to_uppercase();
+        let mut request_payload = vec![("text", chunk), ("target_lang", &target_lang)];
+        // Without a source language DeepL detects it.
+        if source_lang != "AUTO" {
+            request_payload.push(("source_lang", &source_lang));
+        }
+        if self.text_format == TextFormat::Html {
+            request_payload.push(("tag_handling", "html"));
+        }
+        if let Some(formality) = self.formality {
+            request_payload.push(("formality", formality.as_str()));
+        }
+        // DeepL uses the `context` to translate the text but doesn't translate it.
+        let domain = self.domain.as_ref().map(|domain| format!("The text is from a {}.", domain));
+        let context = domain
+            .iter()
+            .map(String::as_str)
+            .chain(context.iter().map(|paragraph| paragraph.source.as_str()))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if !context.is_empty() {
+            request_payload.push(("context", &context));
+        }
+
+        let body_text = send_with_retry(
+            || {
+                self.client
+                    .post(&self.api_url)
+                    .header("Authorization", format!("DeepL-Auth-Key {}", self.api_key))
+                    .form(&request_payload)
+            },
+            &self.progress,
+            &self.retry,
+            self.limiter.as_deref(),
+            self.cassette.as_deref(),
+        )
+        .await?;
+        let response: TranslationResponse = parse_json(&body_text)?;
+        response
+            .translations
+            .into_iter()
+            .next()
+            .map(|t| t.text)
+            .ok_or_else(|| TranslatorError::Parse("DeepL returned no translations".to_string()))
+    }
+}
@@ -0,0 +1,120 @@
+use futures::future::join_all;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::error::TranslatorError;
+use crate::format::collapse_blank_lines;
+use super::openai::OpenAiClient;
+use super::Backend;
+use crate::rate_limit::RateLimiter;
+use crate::translator::{ContextParagraph, Translator};
+
+/// Instructions for the model choosing among the candidate translations of a paragraph.
+const JUDGE_PROMPT: &str = "Below is a text in the language with code '{source}' and several candidate translations \
+of it into the language with code '{target}'. Reply with the best translation: pick the most accurate and fluent \
+candidate, or merge the best parts of several. Keep tokens like ⟦0⟧ exactly as they are. Reply with the translation \
+only, without any notes or explanations.";
+
+/// Several backends translating the same text, with an LLM picking or merging the best
+/// translation of every paragraph.
+pub struct Ensemble {
+    candidates: Vec<Backend>,
+    judge: OpenAiClient,
+}
+
+impl Ensemble {
+    /// Creates the ensemble; the first candidate is the main backend, whose languages and
+    /// detection are used.
+    pub fn new(candidates: Vec<Backend>, judge: OpenAiClient) -> Self {
+        Self { candidates, judge }
+    }
+
+    /// Applies `f` to every candidate backend, and `g` to the judge.
+    pub(super) fn map(self, f: impl Fn(Backend) -> Backend, g: impl FnOnce(OpenAiClient) -> OpenAiClient) -> Self {
+        Self { candidates: self.candidates.into_iter().map(f).collect(), judge: g(self.judge) }
+    }
+
+    /// Lets the server of the main backend adjust the pace of `limiter`; the other services
+    /// have limits of their own.
+    pub(super) fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        let main = self.candidates.remove(0);
+        self.candidates.insert(0, main.with_rate_limiter(limiter));
+        self
+    }
+
+    /// The main backend.
+    pub fn main(&self) -> &Backend {
+        &self.candidates[0]
+    }
+
+    /// Translates the text with every candidate, then has the judge choose the translation of
+    /// each paragraph. Candidates that fail, or don't keep the paragraphs apart, are left out.
+    async fn translate_with<'a, F, Fut>(&'a self, text: &str, source: &str, target: &str, translate: F) -> Result<String, TranslatorError>
+    where
+        F: Fn(&'a Backend) -> Fut,
+        Fut: std::future::Future<Output = Result<String, TranslatorError>>,
+    {
+        let paragraphs: Vec<&str> = text.split("\n\n").collect();
+        let mut translations = Vec::new();
+        let mut first_error = None;
+        for (candidate, result) in self.candidates.iter().zip(join_all(self.candidates.iter().map(translate)).await) {
+            match result {
+                Ok(translated) if translated.split("\n\n").count() == paragraphs.len() => translations.push(translated),
+                Ok(_) => warn!("The {:?} candidate merged or split paragraphs; leaving it out.", candidate.kind()),
+                Err(error) => {
+                    warn!("The {:?} candidate failed: {}", candidate.kind(), error);
+                    first_error.get_or_insert(error);
+                }
+            }
+        }
+        match translations.len() {
+            0 => return Err(first_error.unwrap_or_else(|| "No candidate kept the paragraphs apart".into())),
+            1 => return Ok(translations.remove(0)),
+            _ => {}
+        }
+
+        let split: Vec<Vec<&str>> = translations.iter().map(|translated| translated.split("\n\n").collect()).collect();
+        let mut chosen = Vec::with_capacity(paragraphs.len());
+        for (i, paragraph) in paragraphs.iter().enumerate() {
+            let mut candidates: Vec<&str> = Vec::new();
+            for candidate in split.iter().map(|paragraphs| paragraphs[i].trim()) {
+                if !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+            if candidates.len() == 1 || !paragraph.chars().any(char::is_alphabetic) {
+                chosen.push(candidates[0].to_string());
+                continue;
+            }
+            chosen.push(self.judge(paragraph, &candidates, source, target).await?);
+        }
+        Ok(chosen.join("\n\n"))
+    }
+
+    /// Asks the judge for the best translation of `paragraph` among `candidates`.
+    async fn judge(&self, paragraph: &str, candidates: &[&str], source: &str, target: &str) -> Result<String, TranslatorError> {
+        let mut prompt = JUDGE_PROMPT.replace("{source}", source).replace("{target}", target);
+        prompt.push_str(&format!("\n\nText:\n{}", paragraph.trim()));
+        for (i, candidate) in candidates.iter().enumerate() {
+            prompt.push_str(&format!("\n\nCandidate {}:\n{}", i + 1, candidate));
+        }
+        // A reply in several paragraphs would break up the chunk.
+        Ok(collapse_blank_lines(self.judge.complete(prompt).await?.trim()))
+    }
+}
+
+impl Translator for Ensemble {
+    async fn translate(&self, text: &str, source: &str, target: &str) -> Result<String, TranslatorError> {
+        self.translate_with(text, source, target, |backend| backend.translate(text, source, target)).await
+    }
+
+    async fn translate_in_context(
+        &self,
+        text: &str,
+        source: &str,
+        target: &str,
+        context: &[ContextParagraph],
+    ) -> Result<String, TranslatorError> {
+        self.translate_with(text, source, target, |backend| backend.translate_in_context(text, source, target, context)).await
+    }
+}
@@ -0,0 +1,188 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::error::TranslatorError;
+use crate::progress::Progress;
+use crate::rate_limit::RateLimiter;
+use super::retry::RetryPolicy;
+use super::Backend;
+use crate::translator::{ContextParagraph, Detection, Language, TextFormat, Translator};
+
+/// Mirrors of the same service, tried in order: requests go to the current endpoint until it
+/// becomes unavailable, then to the next one. Only the last endpoint retries failed requests
+/// with backoff; the others hand them on after one attempt.
+///
+/// In round-robin mode every request goes to the next endpoint in turn instead, each paced by a
+/// rate limiter of its own, so that several mirrors together translate faster than one.
+pub struct Failover {
+    endpoints: Vec<Endpoint>,
+    /// Index of the endpoint requests are sent to, or of the next one in round-robin mode.
+    current: AtomicUsize,
+    round_robin: bool,
+}
+
+struct Endpoint {
+    url: String,
+    backend: Backend,
+    /// Number of requests the endpoint answered.
+    served: AtomicUsize,
+    /// Paces the requests to this endpoint alone, in round-robin mode.
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl Failover {
+    /// Creates the chain from `(url, backend)` pairs, the first one used first.
+    pub fn new(endpoints: Vec<(String, Backend)>) -> Self {
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|(url, backend)| Endpoint { url, backend, served: AtomicUsize::new(0), limiter: None })
+                .collect(),
+            current: AtomicUsize::new(0),
+            round_robin: false,
+        }
+    }
+
+    /// Creates a pool from `(url, backend)` pairs that takes turns answering requests, allowing
+    /// each endpoint `requests_per_minute` requests (0 for no limit).
+    pub fn round_robin(endpoints: Vec<(String, Backend)>, requests_per_minute: u32) -> Self {
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|(url, backend)| {
+                    let limiter = Arc::new(RateLimiter::per_minute(requests_per_minute, 1));
+                    // The server's rate limit headers pace the mirror that sent them.
+                    let backend = backend.with_rate_limiter(limiter.clone());
+                    Endpoint { url, backend, served: AtomicUsize::new(0), limiter: Some(limiter) }
+                })
+                .collect(),
+            current: AtomicUsize::new(0),
+            round_robin: true,
+        }
+    }
+
+    /// Whether requests take turns among the endpoints rather than sticking to one.
+    pub fn is_round_robin(&self) -> bool {
+        self.round_robin
+    }
+
+    /// Applies `f` to the backend of every endpoint.
+    pub(super) fn map(mut self, f: impl Fn(Backend) -> Backend) -> Self {
+        self.endpoints = self
+            .endpoints
+            .into_iter()
+            .map(|endpoint| Endpoint { backend: f(endpoint.backend), ..endpoint })
+            .collect();
+        self
+    }
+
+    /// Reports the retries of every endpoint to `progress`.
+    pub fn with_progress(self, progress: Progress) -> Self {
+        self.map(|backend| backend.with_progress(progress.clone()))
+    }
+
+    /// Retries the requests to the last endpoint of the chain as `retry` says. The others get a
+    /// single attempt: when one of them fails, the next endpoint is a better bet than waiting.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        let last = self.endpoints.len().saturating_sub(1);
+        self.endpoints = self
+            .endpoints
+            .into_iter()
+            .enumerate()
+            .map(|(index, endpoint)| {
+                let retry = if index == last { retry } else { RetryPolicy { max_retries: 0, ..retry } };
+                Endpoint { backend: endpoint.backend.with_retry_policy(retry), ..endpoint }
+            })
+            .collect();
+        self
+    }
+
+    pub fn with_text_format(self, text_format: TextFormat) -> Self {
+        self.map(|backend| backend.with_text_format(text_format))
+    }
+
+    /// The backend requests are currently sent to.
+    pub fn current(&self) -> &Backend {
+        &self.endpoints[self.current.load(Ordering::Relaxed)].backend
+    }
+
+    /// Number of requests each endpoint answered, in the order of the chain.
+    pub fn usage(&self) -> Vec<(String, usize)> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| (endpoint.url.clone(), endpoint.served.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Sends a request to the current endpoint (or the next one in turn), moving on to the next
+    /// ones while they are unavailable (see [`TranslatorError::is_transient`]).
+    ///
+    /// Other errors are returned right away: another mirror would reject the request just the same.
+    async fn call<'a, T, F, Fut>(&'a self, request: F) -> Result<T, TranslatorError>
+    where
+        F: Fn(&'a Backend) -> Fut,
+        Fut: Future<Output = Result<T, TranslatorError>>,
+    {
+        let first = if self.round_robin {
+            self.current.fetch_add(1, Ordering::Relaxed) % self.endpoints.len()
+        } else {
+            self.current.load(Ordering::Relaxed)
+        };
+        let mut index = first;
+        loop {
+            let endpoint = &self.endpoints[index];
+            if let Some(limiter) = &endpoint.limiter {
+                limiter.acquire().await;
+            }
+            match request(&endpoint.backend).await {
+                Ok(result) => {
+                    endpoint.served.fetch_add(1, Ordering::Relaxed);
+                    return Ok(result);
+                }
+                Err(error) if error.is_transient() => {
+                    let next = (index + 1) % self.endpoints.len();
+                    if next == first {
+                        return Err(error);
+                    }
+                    warn!("{} is unavailable ({}); switching to {}.", endpoint.url, error, self.endpoints[next].url);
+                    // Concurrent requests may have switched already; only move forward from where this one started.
+                    if !self.round_robin {
+                        let _ = self.current.compare_exchange(index, next, Ordering::Relaxed, Ordering::Relaxed);
+                    }
+                    index = next;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    pub async fn languages(&self) -> Result<Vec<Language>, TranslatorError> {
+        self.call(|backend| backend.languages()).await
+    }
+
+    pub async fn detect(&self, text: &str) -> Result<Vec<Detection>, TranslatorError> {
+        self.call(|backend| backend.detect(text)).await
+    }
+
+    pub async fn alternatives(&self, text: &str, source: &str, target: &str, count: usize) -> Result<Vec<String>, TranslatorError> {
+        self.call(|backend| backend.alternatives(text, source, target, count)).await
+    }
+}
+
+impl Translator for Failover {
+    async fn translate(&self, text: &str, source: &str, target: &str) -> Result<String, TranslatorError> {
+        self.call(|backend| backend.translate(text, source, target)).await
+    }
+
+    async fn translate_in_context(
+        &self,
+        text: &str,
+        source: &str,
+        target: &str,
+        context: &[ContextParagraph],
+    ) -> Result<String, TranslatorError> {
+        self.call(|backend| backend.translate_in_context(text, source, target, context)).await
+    }
+}
@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::TranslatorError;
+use crate::progress::Progress;
+use super::cassette::Cassette;
+use super::deepl::Formality;
+use super::openai::{translation_prompt, with_notes};
+use super::retry::{parse_json, send_with_retry, RetryPolicy};
+use crate::rate_limit::RateLimiter;
+use crate::prompt::PromptTemplate;
+use crate::translator::{ContextParagraph, TextFormat, Translator};
+
+/// The Gemini API; the model is appended, e.g. `.../models/gemini-2.5-flash:generateContent`.
+pub const DEFAULT_API_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+pub const DEFAULT_MODEL: &str = "gemini-2.5-flash";
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateRequest {
+    contents: Vec<Content>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Serialize)]
+struct GenerationConfig {
+    temperature: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Content {
+    #[serde(default)]
+    role: String,
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Part {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct GenerateResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    prompt_feedback: Option<PromptFeedback>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: Option<Content>,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct PromptFeedback {
+    block_reason: Option<String>,
+}
+
+/// Client for Google's Gemini API (`generateContent`).
+pub struct GeminiClient {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: String,
+    prompt_template: PromptTemplate,
+    domain: Option<String>,
+    formality: Option<Formality>,
+    text_format: TextFormat,
+    progress: Progress,
+    retry: RetryPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl GeminiClient {
+    /// A client of `model` at the API whose base URL is `api_url`.
+    pub fn new(client: reqwest::Client, api_url: &str, api_key: impl Into<String>, model: &str) -> Self {
+        Self {
+            client,
+            api_url: format!("{}/models/{}:generateContent", api_url.trim_end_matches('/'), model),
+            api_key: api_key.into(),
+            prompt_template: PromptTemplate::default(),
+            domain: None,
+            formality: None,
+            text_format: TextFormat::Text,
+            progress: Progress::hidden(),
+            retry: RetryPolicy::default(),
+            limiter: None,
+            cassette: None,
+        }
+    }
+
+    /// Replaces the default translation instructions sent with every chunk.
+    pub fn with_prompt_template(mut self, prompt_template: PromptTemplate) -> Self {
+        self.prompt_template = prompt_template;
+        self
+    }
+
+    /// Describes the field the texts come from, so the model uses its terminology and style.
+    pub fn with_domain(mut self, domain: Option<String>) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    /// Asks the model for a formal or informal tone.
+    pub fn with_formality(mut self, formality: Option<Formality>) -> Self {
+        self.formality = formality;
+        self
+    }
+
+    /// Sets whether the text is plain text or HTML.
+    pub fn with_text_format(mut self, text_format: TextFormat) -> Self {
+        self.text_format = text_format;
+        self
+    }
+
+    /// Reports retries as events of `progress`.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets how often and after what delay failed requests are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Reports the server's rate limit headers to the limiter pacing the requests.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Records the exchanges with the server in `cassette`, or answers requests from it.
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Sends `prompt` as a user message and returns the model's reply.
+    pub async fn complete(&self, prompt: String) -> Result<String, TranslatorError> {
+        let request_payload = GenerateRequest {
+            contents: vec![Content {
+                role: "user".to_string(),
+                parts: vec![Part { text: with_notes(prompt, self.domain.as_deref(), self.formality) }],
+            }],
+            generation_config: GenerationConfig { temperature: 0.0 },
+        };
+        let body_text = send_with_retry(
+            // The key goes in a header rather than the URL, where it would end up in logs.
+            || self.client.post(&self.api_url).header("x-goog-api-key", &self.api_key).json(&request_payload),
+            &self.progress,
+            &self.retry,
+            self.limiter.as_deref(),
+            self.cassette.as_deref(),
+        )
+        .await?;
+        let response: GenerateResponse = parse_json(&body_text)?;
+        if let Some(reason) = response.prompt_feedback.and_then(|feedback| feedback.block_reason) {
+            return Err(TranslatorError::Parse(format!("Gemini refused to translate the text ({})", reason)));
+        }
+        let candidate = response
+            .candidates
+            .into_iter()
+            .next()
+            .ok_or_else(|| TranslatorError::Parse("The Gemini API returned no candidates".to_string()))?;
+        if candidate.finish_reason.as_deref() == Some("MAX_TOKENS") {
+            return Err(TranslatorError::Parse("The reply of the Gemini API was cut off at its maximum length".to_string()));
+        }
+        let reply: String = candidate.content.map(|content| content.parts.into_iter().map(|part| part.text).collect()).unwrap_or_default();
+        if reply.trim().is_empty() {
+            let reason = candidate.finish_reason.unwrap_or_default();
+            return Err(TranslatorError::Parse(format!("The Gemini API returned no text ({})", reason)));
+        }
+        Ok(reply.trim().to_string())
+    }
+}
+
+impl Translator for GeminiClient {
+    async fn translate(&self, chunk: &str, source_lang: &str, target_lang: &str) -> Result<String, TranslatorError> {
+        self.translate_in_context(chunk, source_lang, target_lang, &[]).await
+    }
+
+    async fn translate_in_context(
+        &self,
+        chunk: &str,
+        source_lang: &str,
+        target_lang: &str,
+        context: &[ContextParagraph],
+    ) -> Result<String, TranslatorError> {
+        self.complete(translation_prompt(&self.prompt_template, self.text_format, chunk, source_lang, target_lang, context)).await
+    }
+}
@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::TranslatorError;
+use crate::progress::Progress;
+use super::cassette::Cassette;
+use super::retry::{parse_json, send_with_retry, RetryPolicy};
+use crate::rate_limit::RateLimiter;
+use crate::translator::{TextFormat, Translator, AUTO_LANGUAGE};
+
+pub const DEFAULT_API_URL: &str = "https://translation.googleapis.com/language/translate/v2";
+
+#[derive(Serialize)]
+struct TranslationRequest<'a> {
+    q: &'a str,
+    /// Google detects the language when no source is given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<&'a str>,
+    target: &'a str,
+    format: &'a str,
+}
+
+#[derive(Deserialize, Debug)]
+struct TranslationResponse {
+    data: TranslationData,
+}
+
+#[derive(Deserialize, Debug)]
+struct TranslationData {
+    translations: Vec<Translation>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Translation {
+    #[serde(rename = "translatedText")]
+    translated_text: String,
+}
+
+/// Client for the Google Cloud Translation (v2, "basic") API.
+pub struct GoogleClient {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: String,
+    text_format: TextFormat,
+    progress: Progress,
+    retry: RetryPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl GoogleClient {
+    pub fn new(client: reqwest::Client, api_url: impl Into<String>, api_key: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_url: api_url.into(),
+            api_key: api_key.into(),
+            text_format: TextFormat::Text,
+            progress: Progress::hidden(),
+            retry: RetryPolicy::default(),
+            limiter: None,
+            cassette: None,
+        }
+    }
+
+    /// Sets whether the text is plain text or HTML.
+    pub fn with_text_format(mut self, text_format: TextFormat) -> Self {
+        self.text_format = text_format;
+        self
+    }
+
+    /// Reports retries as events of `progress`.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets how often and after what delay failed requests are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Reports the server's rate limit headers to the limiter pacing the requests.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Records the exchanges with the server in `cassette`, or answers requests from it.
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+}
+
+impl Translator for GoogleClient {
+    async fn translate(
+        &self,
+        chunk: &str,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> Result<String, TranslatorError> {
+        let request_payload = TranslationRequest {
+            q: chunk,
+            source: (source_lang != AUTO_LANGUAGE).then_some(source_lang),
+            target: target_lang,
+            format: self.text_format.as_str(),
+        };
+
+        let body_text = send_with_retry(
+            || {
+                self.client
+                    .post(&self.api_url)
+                    .query(&[("key", &self.api_key)])
+                    .json(&request_payload)
+            },
+            &self.progress,
+            &self.retry,
+            self.limiter.as_deref(),
+            self.cassette.as_deref(),
+        )
+        .await?;
+        let response: TranslationResponse = parse_json(&body_text)?;
+        response
+            .data
+            .translations
+            .into_iter()
+            .next()
+            .map(|t| t.translated_text)
+            .ok_or_else(|| TranslatorError::Parse("Google returned no translations".to_string()))
+    }
+}
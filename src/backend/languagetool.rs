@@ -0,0 +1,131 @@
+use serde::Deserialize;
+
+use crate::error::TranslatorError;
+use crate::progress::Progress;
+use super::retry::{parse_json, send_with_retry, RetryPolicy};
+use crate::rate_limit::RateLimiter;
+
+/// The public LanguageTool API, which takes 20 requests per minute without an account.
+pub const DEFAULT_API_URL: &str = "https://api.languagetool.org/v2/check";
+
+/// Requests per minute sent to the public API; self-hosted servers have no limit.
+const PUBLIC_REQUESTS_PER_MINUTE: u32 = 20;
+
+/// A grammar or spelling mistake LanguageTool found in a text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GrammarIssue {
+    pub message: String,
+    /// Byte range of the mistake in the text.
+    pub start: usize,
+    pub end: usize,
+    /// The corrections offered, best first.
+    pub replacements: Vec<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CheckResponse {
+    matches: Vec<Match>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Match {
+    message: String,
+    /// Offset and length in UTF-16 code units, as LanguageTool counts in Java strings.
+    offset: usize,
+    length: usize,
+    #[serde(default)]
+    replacements: Vec<Replacement>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Replacement {
+    value: String,
+}
+
+/// A client of a LanguageTool server (`/v2/check`), self-hosted or the public API.
+pub struct LanguageToolClient {
+    client: reqwest::Client,
+    api_url: String,
+    /// The account name and API key of LanguageTool Premium.
+    credentials: Option<(String, String)>,
+    progress: Progress,
+    retry: RetryPolicy,
+    limiter: RateLimiter,
+}
+
+impl LanguageToolClient {
+    pub fn new(client: reqwest::Client, api_url: Option<String>) -> Self {
+        let public = api_url.is_none();
+        Self {
+            client,
+            api_url: api_url.unwrap_or_else(|| DEFAULT_API_URL.to_string()),
+            credentials: None,
+            progress: Progress::hidden(),
+            retry: RetryPolicy::default(),
+            limiter: RateLimiter::per_minute(if public { PUBLIC_REQUESTS_PER_MINUTE } else { 0 }, 1),
+        }
+    }
+
+    /// Sets the account name and API key of LanguageTool Premium.
+    pub fn with_credentials(mut self, credentials: Option<(String, String)>) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// Reports retries as events of `progress`.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets how often and after what delay failed requests are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// The mistakes found in `text`, written in the language with the code `language`.
+    pub async fn check(&self, text: &str, language: &str) -> Result<Vec<GrammarIssue>, TranslatorError> {
+        let language = variant(language);
+        let mut form = vec![("text", text), ("language", language)];
+        if let Some((username, api_key)) = &self.credentials {
+            form.extend([("username", username.as_str()), ("apiKey", api_key.as_str())]);
+        }
+        self.limiter.acquire().await;
+        let body_text = send_with_retry(|| self.client.post(&self.api_url).form(&form), &self.progress, &self.retry, Some(&self.limiter), None).await?;
+        let response: CheckResponse = parse_json(&body_text)?;
+        Ok(response
+            .matches
+            .into_iter()
+            .map(|found| GrammarIssue {
+                message: found.message,
+                start: byte_offset(text, found.offset),
+                end: byte_offset(text, found.offset + found.length),
+                replacements: found.replacements.into_iter().map(|replacement| replacement.value).collect(),
+            })
+            .collect())
+    }
+}
+
+/// LanguageTool wants a regional variant for some languages, e.g. `de-DE` rather than `de`.
+fn variant(language: &str) -> &str {
+    match language {
+        "en" => "en-US",
+        "de" => "de-DE",
+        "pt" => "pt-PT",
+        "ca" => "ca-ES",
+        language => language,
+    }
+}
+
+/// The byte offset in `text` of an offset counted in UTF-16 code units.
+fn byte_offset(text: &str, utf16_offset: usize) -> usize {
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= utf16_offset {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
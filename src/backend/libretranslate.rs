@@ -0,0 +1,230 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::TranslatorError;
+use crate::progress::Progress;
+use super::cassette::Cassette;
+use super::retry::{parse_json, send_with_retry, RetryPolicy};
+use crate::format::collapse_blank_lines;
+use crate::rate_limit::RateLimiter;
+use crate::translator::{Detection, Language, TextFormat, Translator};
+
+pub const DEFAULT_API_URL: &str = "https://translate.fedilab.app/translate";
+
+/// Public instances that translate without an API key, for spreading a long document over
+/// several servers. Public instances come and go; more can be added on the command line.
+pub const PUBLIC_MIRRORS: &[&str] = &[
+    DEFAULT_API_URL,
+    "https://translate.terraprint.co/translate",
+    "https://lt.vern.cc/translate",
+    "https://translate.argosopentech.com/translate",
+];
+
+#[derive(Serialize)]
+struct TranslationRequest<'a> {
+    q: &'a str,
+    source: &'a str,
+    target: &'a str,
+    format: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+    /// Number of other translations to return besides the best one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alternatives: Option<usize>,
+}
+
+/// A translation request with an array of texts, answered with an array of translations.
+#[derive(Serialize)]
+struct BatchRequest<'a> {
+    q: &'a [&'a str],
+    source: &'a str,
+    target: &'a str,
+    format: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchResponse {
+    #[serde(rename = "translatedText")]
+    translated_text: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DetectRequest<'a> {
+    q: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TranslationResponse {
+    #[serde(rename = "translatedText")]
+    translated_text: String,
+    #[serde(default)]
+    alternatives: Vec<String>,
+}
+
+/// Client for the LibreTranslate `/translate` API endpoint.
+pub struct LibreTranslateClient {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: Option<String>,
+    text_format: TextFormat,
+    batch: bool,
+    progress: Progress,
+    retry: RetryPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl LibreTranslateClient {
+    pub fn new(client: reqwest::Client, api_url: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_url: api_url.into(),
+            api_key: None,
+            text_format: TextFormat::Text,
+            batch: false,
+            progress: Progress::hidden(),
+            retry: RetryPolicy::default(),
+            limiter: None,
+            cassette: None,
+        }
+    }
+
+    /// Sets the key required by self-hosted instances and mirrors with API keys enabled.
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// Sends the paragraphs of a chunk (separated by blank lines) as an array in one request,
+    /// so each comes back as a translation of its own.
+    pub fn with_batch(mut self, batch: bool) -> Self {
+        self.batch = batch;
+        self
+    }
+
+    /// Sets whether the text is plain text or HTML.
+    pub fn with_text_format(mut self, text_format: TextFormat) -> Self {
+        self.text_format = text_format;
+        self
+    }
+
+    /// Reports retries as events of `progress`.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets how often and after what delay failed requests are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Reports the server's rate limit headers to the limiter pacing the requests.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Records the exchanges with the server in `cassette`, or answers requests from it.
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// URL of another endpoint of the same server, e.g. `/languages` next to `/translate`.
+    fn endpoint_url(&self, endpoint: &str) -> String {
+        let base = self.api_url.strip_suffix("/translate").unwrap_or(self.api_url.trim_end_matches('/'));
+        format!("{}/{}", base, endpoint)
+    }
+
+    /// Lists the languages supported by the server (`/languages`).
+    pub async fn languages(&self) -> Result<Vec<Language>, TranslatorError> {
+        let url = self.endpoint_url("languages");
+        let body_text = send_with_retry(|| self.client.get(&url), &self.progress, &self.retry, self.limiter.as_deref(), self.cassette.as_deref()).await?;
+        parse_json(&body_text)
+    }
+
+    /// Guesses the language of the text (`/detect`), most likely candidates first.
+    pub async fn detect(&self, text: &str) -> Result<Vec<Detection>, TranslatorError> {
+        let url = self.endpoint_url("detect");
+        let request_payload = DetectRequest {
+            q: text,
+            api_key: self.api_key.as_deref(),
+        };
+        let body_text = send_with_retry(|| self.client.post(&url).json(&request_payload), &self.progress, &self.retry, self.limiter.as_deref(), self.cassette.as_deref()).await?;
+        parse_json(&body_text)
+    }
+
+    /// Asks for up to `count` other translations of the text besides the best one; servers that
+    /// don't offer alternatives return none.
+    pub async fn alternatives(&self, text: &str, source_lang: &str, target_lang: &str, count: usize) -> Result<Vec<String>, TranslatorError> {
+        let request_payload = TranslationRequest {
+            q: text,
+            source: source_lang,
+            target: target_lang,
+            format: self.text_format.as_str(),
+            api_key: self.api_key.as_deref(),
+            alternatives: Some(count),
+        };
+        let body_text = send_with_retry(|| self.client.post(&self.api_url).json(&request_payload), &self.progress, &self.retry, self.limiter.as_deref(), self.cassette.as_deref()).await?;
+        let response: TranslationResponse = parse_json(&body_text)?;
+        Ok(response.alternatives)
+    }
+
+    /// Translates the paragraphs of a chunk in one request with an array `q`.
+    async fn translate_batch(&self, chunk: &str, source_lang: &str, target_lang: &str) -> Result<String, TranslatorError> {
+        let paragraphs: Vec<&str> = chunk.split("\n\n").collect();
+        let request_payload = BatchRequest {
+            q: &paragraphs,
+            source: source_lang,
+            target: target_lang,
+            format: self.text_format.as_str(),
+            api_key: self.api_key.as_deref(),
+        };
+
+        let body_text = send_with_retry(|| self.client.post(&self.api_url).json(&request_payload), &self.progress, &self.retry, self.limiter.as_deref(), self.cassette.as_deref()).await?;
+        let response: BatchResponse = parse_json(&body_text)?;
+        if response.translated_text.len() != paragraphs.len() {
+            return Err(TranslatorError::Parse(format!(
+                "The server returned {} translations for {} paragraphs",
+                response.translated_text.len(),
+                paragraphs.len()
+            )));
+        }
+        // Blank lines inside a translation would split it into several paragraphs again.
+        let translations: Vec<String> =
+            response.translated_text.iter().map(|text| collapse_blank_lines(text.trim())).collect();
+        Ok(translations.join("\n\n"))
+    }
+}
+
+impl Translator for LibreTranslateClient {
+    /// Sends a chunk of text to the translation API.
+    async fn translate(
+        &self,
+        chunk: &str,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> Result<String, TranslatorError> {
+        if self.batch && chunk.contains("\n\n") {
+            return self.translate_batch(chunk, source_lang, target_lang).await;
+        }
+        let request_payload = TranslationRequest {
+            q: chunk,
+            source: source_lang,
+            target: target_lang,
+            format: self.text_format.as_str(),
+            api_key: self.api_key.as_deref(),
+            alternatives: None,
+        };
+
+        let body_text = send_with_retry(|| self.client.post(&self.api_url).json(&request_payload), &self.progress, &self.retry, self.limiter.as_deref(), self.cassette.as_deref()).await?;
+        let response: TranslationResponse = parse_json(&body_text)?;
+        Ok(response.translated_text)
+    }
+}
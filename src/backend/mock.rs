@@ -0,0 +1,34 @@
+use crate::error::TranslatorError;
+use crate::translator::Translator;
+
+/// Pseudo-translates without any server: every paragraph gets the target language in brackets
+/// in front, e.g. `[hu] Hello world.`, so chunking, formats and protected terms can be checked
+/// on a new file without spending API quota.
+#[derive(Debug, Clone, Default)]
+pub struct MockClient;
+
+impl MockClient {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl MockClient {
+    /// Numbered variants of the pseudo-translation, e.g. `[hu 2] Hello world.`.
+    pub fn alternatives(&self, text: &str, target: &str, count: usize) -> Vec<String> {
+        (2..count + 2).map(|n| format!("[{} {}] {}", target, n, text.trim())).collect()
+    }
+}
+
+impl Translator for MockClient {
+    async fn translate(&self, text: &str, _source: &str, target: &str) -> Result<String, TranslatorError> {
+        let paragraphs: Vec<String> = text
+            .split("\n\n")
+            .map(|paragraph| match paragraph.trim() {
+                "" => paragraph.to_string(),
+                _ => format!("[{}] {}", target, paragraph),
+            })
+            .collect();
+        Ok(paragraphs.join("\n\n"))
+    }
+}
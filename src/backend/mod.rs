@@ -0,0 +1,462 @@
+//! Translation providers sharing the same chunking and retry pipeline.
+
+pub mod anthropic;
+pub mod azure;
+pub mod cassette;
+pub mod deepl;
+pub mod ensemble;
+pub mod failover;
+pub mod gemini;
+pub mod google;
+pub mod languagetool;
+pub mod libretranslate;
+pub mod mock;
+pub mod offline;
+pub mod ollama;
+pub mod openai;
+pub mod post_edit;
+mod retry;
+
+pub use retry::RetryPolicy;
+
+use clap::ValueEnum;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::TranslatorError;
+use crate::progress::Progress;
+use crate::prompt::PromptTemplate;
+use crate::rate_limit::RateLimiter;
+use crate::translator::{ContextParagraph, Detection, Language, TextFormat, Translator};
+use anthropic::AnthropicClient;
+use azure::AzureClient;
+use cassette::Cassette;
+use deepl::DeepLClient;
+use ensemble::Ensemble;
+use failover::Failover;
+use gemini::GeminiClient;
+use google::GoogleClient;
+use libretranslate::LibreTranslateClient;
+use mock::MockClient;
+use offline::OfflineClient;
+use ollama::OllamaClient;
+use openai::OpenAiClient;
+use post_edit::PostEdit;
+
+/// The supported translation providers.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackendKind {
+    #[value(name = "libretranslate")]
+    LibreTranslate,
+    #[value(name = "deepl")]
+    DeepL,
+    Google,
+    Azure,
+    #[value(name = "openai")]
+    OpenAi,
+    Anthropic,
+    Gemini,
+    Ollama,
+    Mock,
+    Offline,
+}
+
+impl BackendKind {
+    /// List price in US dollars per million characters for the services billed by character.
+    pub fn price_per_million_characters(self) -> Option<f64> {
+        match self {
+            BackendKind::DeepL => Some(25.0),
+            BackendKind::Google => Some(20.0),
+            BackendKind::Azure => Some(10.0),
+            BackendKind::LibreTranslate
+            | BackendKind::OpenAi
+            | BackendKind::Anthropic
+            | BackendKind::Gemini
+            | BackendKind::Ollama
+            | BackendKind::Mock
+            | BackendKind::Offline => None,
+        }
+    }
+
+    /// Whether the backend asks a large language model to translate, with a prompt.
+    pub fn is_llm(self) -> bool {
+        matches!(self, BackendKind::OpenAi | BackendKind::Anthropic | BackendKind::Gemini | BackendKind::Ollama)
+    }
+
+    /// The environment variable the provider's own tools read the API key from, used when no key is given.
+    pub fn api_key_variable(self) -> Option<&'static str> {
+        match self {
+            BackendKind::Anthropic => Some("ANTHROPIC_API_KEY"),
+            BackendKind::Gemini => Some("GEMINI_API_KEY"),
+            _ => None,
+        }
+    }
+}
+
+/// Connection settings shared by all backends; unset values fall back to backend defaults.
+#[derive(Debug, Clone, Default)]
+pub struct BackendOptions {
+    pub api_url: Option<String>,
+    pub api_key: Option<String>,
+    /// Azure resource region.
+    pub region: Option<String>,
+    /// Model name for LLM backends, or the local model of the offline backend.
+    pub model: Option<String>,
+    /// Formality for DeepL and LLM backends.
+    pub formality: Option<deepl::Formality>,
+    /// The field the texts come from, for DeepL and LLM backends.
+    pub domain: Option<String>,
+    /// Instructions for LLM backends; the built-in prompt is used when unset.
+    pub prompt_template: Option<PromptTemplate>,
+    /// Ollama: how long the server may send nothing before a request is retried; the offline
+    /// backend: how long the local translator may take for a chunk.
+    pub idle_timeout: Option<Duration>,
+    /// The command running the local model of the offline backend.
+    pub offline_command: Option<String>,
+    /// LibreTranslate: send the paragraphs of a chunk as an array.
+    pub batch: bool,
+}
+
+/// A configured translation provider.
+pub enum Backend {
+    LibreTranslate(LibreTranslateClient),
+    DeepL(DeepLClient),
+    Google(GoogleClient),
+    Azure(AzureClient),
+    OpenAi(OpenAiClient),
+    Anthropic(AnthropicClient),
+    Gemini(GeminiClient),
+    Ollama(OllamaClient),
+    Mock(MockClient),
+    /// A local translation model run through its command-line translator.
+    Offline(OfflineClient),
+    /// Several endpoints of one provider, moving on to the next when one becomes unavailable.
+    Failover(Failover),
+    /// Several providers translating the same text, with an LLM choosing among their translations.
+    Ensemble(Ensemble),
+    /// A provider whose translations are edited by an LLM for grammar and fluency.
+    PostEdit(PostEdit),
+}
+
+impl Backend {
+    /// Creates the client for `kind`, failing if a required setting such as the API key is missing.
+    pub fn new(kind: BackendKind, client: reqwest::Client, options: BackendOptions) -> Result<Self, TranslatorError> {
+        let require_key = || {
+            options
+                .api_key
+                .clone()
+                .ok_or_else(|| match kind.api_key_variable() {
+                    Some(variable) => format!("The {:?} backend requires an API key (--api-key or {})", kind, variable),
+                    None => format!("The {:?} backend requires an API key (--api-key)", kind),
+                })
+        };
+
+        Ok(match kind {
+            BackendKind::LibreTranslate => Backend::LibreTranslate(
+                LibreTranslateClient::new(
+                    client,
+                    options.api_url.as_deref().unwrap_or(libretranslate::DEFAULT_API_URL),
+                )
+                .with_api_key(options.api_key.clone())
+                .with_batch(options.batch),
+            ),
+            BackendKind::DeepL => {
+                let api_key = require_key()?;
+                let api_url = options
+                    .api_url
+                    .clone()
+                    .unwrap_or_else(|| deepl::default_api_url(&api_key).to_string());
+                Backend::DeepL(DeepLClient::new(client, api_url, api_key).with_formality(options.formality).with_domain(options.domain.clone()))
+            }
+            BackendKind::Google => Backend::Google(GoogleClient::new(
+                client,
+                options.api_url.as_deref().unwrap_or(google::DEFAULT_API_URL),
+                require_key()?,
+            )),
+            BackendKind::Azure => Backend::Azure(
+                AzureClient::new(
+                    client,
+                    options.api_url.as_deref().unwrap_or(azure::DEFAULT_API_URL),
+                    require_key()?,
+                )
+                .with_region(options.region.clone()),
+            ),
+            BackendKind::OpenAi => Backend::OpenAi(
+                OpenAiClient::new(
+                    client,
+                    options.api_url.as_deref().unwrap_or(openai::DEFAULT_API_URL),
+                    options.model.as_deref().unwrap_or(openai::DEFAULT_MODEL),
+                )
+                .with_api_key(options.api_key.clone())
+                .with_prompt_template(options.prompt_template.clone().unwrap_or_default())
+                .with_domain(options.domain.clone())
+                .with_formality(options.formality),
+            ),
+            BackendKind::Anthropic => Backend::Anthropic(
+                AnthropicClient::new(
+                    client,
+                    options.api_url.as_deref().unwrap_or(anthropic::DEFAULT_API_URL),
+                    require_key()?,
+                    options.model.as_deref().unwrap_or(anthropic::DEFAULT_MODEL),
+                )
+                .with_prompt_template(options.prompt_template.clone().unwrap_or_default())
+                .with_domain(options.domain.clone())
+                .with_formality(options.formality),
+            ),
+            BackendKind::Gemini => Backend::Gemini(
+                GeminiClient::new(
+                    client,
+                    options.api_url.as_deref().unwrap_or(gemini::DEFAULT_API_URL),
+                    require_key()?,
+                    options.model.as_deref().unwrap_or(gemini::DEFAULT_MODEL),
+                )
+                .with_prompt_template(options.prompt_template.clone().unwrap_or_default())
+                .with_domain(options.domain.clone())
+                .with_formality(options.formality),
+            ),
+            BackendKind::Ollama => Backend::Ollama(
+                OllamaClient::new(
+                    client,
+                    options.api_url.as_deref().unwrap_or(ollama::DEFAULT_URL),
+                    options.model.as_deref().unwrap_or(ollama::DEFAULT_MODEL),
+                )
+                .with_api_key(options.api_key.clone())
+                .with_prompt_template(options.prompt_template.clone().unwrap_or_default())
+                .with_domain(options.domain.clone())
+                .with_formality(options.formality)
+                .with_idle_timeout(options.idle_timeout.unwrap_or(ollama::DEFAULT_IDLE_TIMEOUT)),
+            ),
+            BackendKind::Mock => Backend::Mock(MockClient::new()),
+            BackendKind::Offline => Backend::Offline(OfflineClient::new(
+                options.offline_command.as_deref().unwrap_or(offline::DEFAULT_COMMAND),
+                options.model.as_deref().unwrap_or(offline::DEFAULT_MODEL),
+            )
+            .with_timeout(options.idle_timeout.unwrap_or(offline::DEFAULT_TIMEOUT))),
+        })
+    }
+
+    /// Reports retries as events of `progress`.
+    pub fn with_progress(self, progress: Progress) -> Self {
+        match self {
+            Backend::LibreTranslate(c) => Backend::LibreTranslate(c.with_progress(progress)),
+            Backend::DeepL(c) => Backend::DeepL(c.with_progress(progress)),
+            Backend::Google(c) => Backend::Google(c.with_progress(progress)),
+            Backend::Azure(c) => Backend::Azure(c.with_progress(progress)),
+            Backend::OpenAi(c) => Backend::OpenAi(c.with_progress(progress)),
+            Backend::Anthropic(c) => Backend::Anthropic(c.with_progress(progress)),
+            Backend::Gemini(c) => Backend::Gemini(c.with_progress(progress)),
+            Backend::Ollama(c) => Backend::Ollama(c.with_progress(progress)),
+            // The mock and offline backends send no requests and treat all text alike.
+            Backend::Mock(c) => Backend::Mock(c),
+            Backend::Offline(c) => Backend::Offline(c),
+            Backend::Failover(f) => Backend::Failover(f.with_progress(progress)),
+            Backend::Ensemble(e) => Backend::Ensemble(e.map(|backend| backend.with_progress(progress.clone()), |judge| judge.with_progress(progress.clone()))),
+            Backend::PostEdit(p) => Backend::PostEdit(p.map(|backend| backend.with_progress(progress.clone()), |editor| editor.with_progress(progress.clone()))),
+        }
+    }
+
+    /// Sets how often and after what delay failed requests are retried.
+    pub fn with_retry_policy(self, retry: RetryPolicy) -> Self {
+        match self {
+            Backend::LibreTranslate(c) => Backend::LibreTranslate(c.with_retry_policy(retry)),
+            Backend::DeepL(c) => Backend::DeepL(c.with_retry_policy(retry)),
+            Backend::Google(c) => Backend::Google(c.with_retry_policy(retry)),
+            Backend::Azure(c) => Backend::Azure(c.with_retry_policy(retry)),
+            Backend::OpenAi(c) => Backend::OpenAi(c.with_retry_policy(retry)),
+            Backend::Anthropic(c) => Backend::Anthropic(c.with_retry_policy(retry)),
+            Backend::Gemini(c) => Backend::Gemini(c.with_retry_policy(retry)),
+            Backend::Ollama(c) => Backend::Ollama(c.with_retry_policy(retry)),
+            Backend::Mock(c) => Backend::Mock(c),
+            Backend::Offline(c) => Backend::Offline(c),
+            Backend::Failover(f) => Backend::Failover(f.with_retry_policy(retry)),
+            Backend::Ensemble(e) => Backend::Ensemble(e.map(|backend| backend.with_retry_policy(retry), |judge| judge.with_retry_policy(retry))),
+            Backend::PostEdit(p) => Backend::PostEdit(p.map(|backend| backend.with_retry_policy(retry), |editor| editor.with_retry_policy(retry))),
+        }
+    }
+
+    /// Lets the server's `Retry-After` and rate limit headers adjust the pace of `limiter`.
+    pub fn with_rate_limiter(self, limiter: Arc<RateLimiter>) -> Self {
+        match self {
+            Backend::LibreTranslate(c) => Backend::LibreTranslate(c.with_rate_limiter(limiter)),
+            Backend::DeepL(c) => Backend::DeepL(c.with_rate_limiter(limiter)),
+            Backend::Google(c) => Backend::Google(c.with_rate_limiter(limiter)),
+            Backend::Azure(c) => Backend::Azure(c.with_rate_limiter(limiter)),
+            Backend::OpenAi(c) => Backend::OpenAi(c.with_rate_limiter(limiter)),
+            Backend::Anthropic(c) => Backend::Anthropic(c.with_rate_limiter(limiter)),
+            Backend::Gemini(c) => Backend::Gemini(c.with_rate_limiter(limiter)),
+            Backend::Ollama(c) => Backend::Ollama(c.with_rate_limiter(limiter)),
+            Backend::Mock(c) => Backend::Mock(c),
+            Backend::Offline(c) => Backend::Offline(c),
+            // Round-robin mirrors each keep following their own limiter.
+            Backend::Failover(f) if f.is_round_robin() => Backend::Failover(f),
+            Backend::Failover(f) => Backend::Failover(f.map(|backend| backend.with_rate_limiter(limiter.clone()))),
+            Backend::Ensemble(e) => Backend::Ensemble(e.with_rate_limiter(limiter)),
+            Backend::PostEdit(p) => Backend::PostEdit(p.with_rate_limiter(limiter)),
+        }
+    }
+
+    /// Records the exchanges with the server in `cassette`, or answers requests from it.
+    pub fn with_cassette(self, cassette: Arc<Cassette>) -> Self {
+        match self {
+            Backend::LibreTranslate(c) => Backend::LibreTranslate(c.with_cassette(cassette)),
+            Backend::DeepL(c) => Backend::DeepL(c.with_cassette(cassette)),
+            Backend::Google(c) => Backend::Google(c.with_cassette(cassette)),
+            Backend::Azure(c) => Backend::Azure(c.with_cassette(cassette)),
+            Backend::OpenAi(c) => Backend::OpenAi(c.with_cassette(cassette)),
+            Backend::Anthropic(c) => Backend::Anthropic(c.with_cassette(cassette)),
+            Backend::Gemini(c) => Backend::Gemini(c.with_cassette(cassette)),
+            Backend::Ollama(c) => Backend::Ollama(c.with_cassette(cassette)),
+            Backend::Mock(c) => Backend::Mock(c),
+            Backend::Offline(c) => Backend::Offline(c),
+            Backend::Failover(f) => Backend::Failover(f.map(|backend| backend.with_cassette(cassette.clone()))),
+            Backend::Ensemble(e) => Backend::Ensemble(e.map(|backend| backend.with_cassette(cassette.clone()), |judge| judge.with_cassette(cassette.clone()))),
+            Backend::PostEdit(p) => Backend::PostEdit(p.map(|backend| backend.with_cassette(cassette.clone()), |editor| editor.with_cassette(cassette.clone()))),
+        }
+    }
+
+    /// Sets whether the text is plain text or HTML.
+    pub fn with_text_format(self, text_format: TextFormat) -> Self {
+        match self {
+            Backend::LibreTranslate(c) => Backend::LibreTranslate(c.with_text_format(text_format)),
+            Backend::DeepL(c) => Backend::DeepL(c.with_text_format(text_format)),
+            Backend::Google(c) => Backend::Google(c.with_text_format(text_format)),
+            Backend::Azure(c) => Backend::Azure(c.with_text_format(text_format)),
+            Backend::OpenAi(c) => Backend::OpenAi(c.with_text_format(text_format)),
+            Backend::Anthropic(c) => Backend::Anthropic(c.with_text_format(text_format)),
+            Backend::Gemini(c) => Backend::Gemini(c.with_text_format(text_format)),
+            Backend::Ollama(c) => Backend::Ollama(c.with_text_format(text_format)),
+            Backend::Mock(c) => Backend::Mock(c),
+            Backend::Offline(c) => Backend::Offline(c),
+            Backend::Failover(f) => Backend::Failover(f.with_text_format(text_format)),
+            Backend::Ensemble(e) => Backend::Ensemble(e.map(|backend| backend.with_text_format(text_format), |judge| judge)),
+            Backend::PostEdit(p) => Backend::PostEdit(p.map(|backend| backend.with_text_format(text_format), |editor| editor)),
+        }
+    }
+
+    /// Whether `languages` is available for this backend.
+    pub fn supports_language_list(&self) -> bool {
+        match self {
+            Backend::Failover(f) => f.current().supports_language_list(),
+            Backend::Ensemble(e) => e.main().supports_language_list(),
+            Backend::PostEdit(p) => p.translator().supports_language_list(),
+            _ => matches!(self, Backend::LibreTranslate(_)),
+        }
+    }
+
+    /// Whether `detect` is available for this backend. The others accept `auto` as the source language.
+    pub fn supports_detection(&self) -> bool {
+        match self {
+            Backend::Failover(f) => f.current().supports_detection(),
+            Backend::Ensemble(e) => e.main().supports_detection(),
+            Backend::PostEdit(p) => p.translator().supports_detection(),
+            _ => matches!(self, Backend::LibreTranslate(_)),
+        }
+    }
+
+    /// Lists the languages supported by the server.
+    pub async fn languages(&self) -> Result<Vec<Language>, TranslatorError> {
+        match self {
+            Backend::LibreTranslate(c) => c.languages().await,
+            // Boxed, because the endpoints are backends themselves.
+            Backend::Failover(f) => Box::pin(f.languages()).await,
+            Backend::Ensemble(e) => Box::pin(e.main().languages()).await,
+            Backend::PostEdit(p) => Box::pin(p.translator().languages()).await,
+            _ => Err(self.unsupported("listing languages")),
+        }
+    }
+
+    /// Guesses the language of the text, most likely candidates first.
+    pub async fn detect(&self, text: &str) -> Result<Vec<Detection>, TranslatorError> {
+        match self {
+            Backend::LibreTranslate(c) => c.detect(text).await,
+            Backend::Failover(f) => Box::pin(f.detect(text)).await,
+            Backend::Ensemble(e) => Box::pin(e.main().detect(text)).await,
+            Backend::PostEdit(p) => Box::pin(p.translator().detect(text)).await,
+            _ => Err(self.unsupported("language detection")),
+        }
+    }
+
+    /// Other ways to translate the text besides the best one, up to `count` of them.
+    pub async fn alternatives(&self, text: &str, source: &str, target: &str, count: usize) -> Result<Vec<String>, TranslatorError> {
+        match self {
+            Backend::LibreTranslate(c) => c.alternatives(text, source, target, count).await,
+            Backend::Mock(c) => Ok(c.alternatives(text, target, count)),
+            Backend::Failover(f) => Box::pin(f.alternatives(text, source, target, count)).await,
+            Backend::Ensemble(e) => Box::pin(e.main().alternatives(text, source, target, count)).await,
+            Backend::PostEdit(p) => Box::pin(p.translator().alternatives(text, source, target, count)).await,
+            _ => Err(self.unsupported("alternative translations")),
+        }
+    }
+
+    /// Number of requests each endpoint of a failover chain answered; empty for a single endpoint.
+    pub fn endpoint_usage(&self) -> Vec<(String, usize)> {
+        match self {
+            Backend::Failover(f) => f.usage(),
+            Backend::Ensemble(e) => e.main().endpoint_usage(),
+            Backend::PostEdit(p) => p.translator().endpoint_usage(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn kind(&self) -> BackendKind {
+        match self {
+            Backend::LibreTranslate(_) => BackendKind::LibreTranslate,
+            Backend::DeepL(_) => BackendKind::DeepL,
+            Backend::Google(_) => BackendKind::Google,
+            Backend::Azure(_) => BackendKind::Azure,
+            Backend::OpenAi(_) => BackendKind::OpenAi,
+            Backend::Anthropic(_) => BackendKind::Anthropic,
+            Backend::Gemini(_) => BackendKind::Gemini,
+            Backend::Ollama(_) => BackendKind::Ollama,
+            Backend::Mock(_) => BackendKind::Mock,
+            Backend::Offline(_) => BackendKind::Offline,
+            Backend::Failover(f) => f.current().kind(),
+            Backend::Ensemble(e) => e.main().kind(),
+            Backend::PostEdit(p) => p.translator().kind(),
+        }
+    }
+
+    fn unsupported(&self, feature: &str) -> TranslatorError {
+        format!("The {:?} backend doesn't support {}", self.kind(), feature).into()
+    }
+}
+
+impl Translator for Backend {
+    async fn translate(&self, text: &str, source: &str, target: &str) -> Result<String, TranslatorError> {
+        match self {
+            Backend::LibreTranslate(c) => c.translate(text, source, target).await,
+            Backend::DeepL(c) => c.translate(text, source, target).await,
+            Backend::Google(c) => c.translate(text, source, target).await,
+            Backend::Azure(c) => c.translate(text, source, target).await,
+            Backend::OpenAi(c) => c.translate(text, source, target).await,
+            Backend::Anthropic(c) => c.translate(text, source, target).await,
+            Backend::Gemini(c) => c.translate(text, source, target).await,
+            Backend::Ollama(c) => c.translate(text, source, target).await,
+            Backend::Mock(c) => c.translate(text, source, target).await,
+            Backend::Offline(c) => c.translate(text, source, target).await,
+            Backend::Failover(f) => Box::pin(f.translate(text, source, target)).await,
+            Backend::Ensemble(e) => Box::pin(e.translate(text, source, target)).await,
+            Backend::PostEdit(p) => Box::pin(p.translate(text, source, target)).await,
+        }
+    }
+
+    async fn translate_in_context(
+        &self,
+        text: &str,
+        source: &str,
+        target: &str,
+        context: &[ContextParagraph],
+    ) -> Result<String, TranslatorError> {
+        match self {
+            Backend::DeepL(c) => c.translate_in_context(text, source, target, context).await,
+            Backend::OpenAi(c) => c.translate_in_context(text, source, target, context).await,
+            Backend::Anthropic(c) => c.translate_in_context(text, source, target, context).await,
+            Backend::Gemini(c) => c.translate_in_context(text, source, target, context).await,
+            Backend::Ollama(c) => c.translate_in_context(text, source, target, context).await,
+            Backend::Failover(f) => Box::pin(f.translate_in_context(text, source, target, context)).await,
+            Backend::Ensemble(e) => Box::pin(e.translate_in_context(text, source, target, context)).await,
+            Backend::PostEdit(p) => Box::pin(p.translate_in_context(text, source, target, context)).await,
+            _ => self.translate(text, source, target).await,
+        }
+    }
+}
@@ -0,0 +1,295 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tracing::debug;
+
+use crate::error::TranslatorError;
+use crate::no_translate::protect_matches;
+use crate::placeholder;
+use crate::translator::{Translator, AUTO_LANGUAGE};
+
+/// The translator run by default: Marian, the engine of the Opus-MT models, translating every
+/// line as soon as it is read.
+pub const DEFAULT_COMMAND: &str = "marian-decoder -c {model}/decoder.yml --mini-batch 1 --maxi-batch 1 --quiet";
+
+/// The program of [`DEFAULT_COMMAND`], named in the error when it isn't installed.
+const DEFAULT_PROGRAM: &str = "marian-decoder";
+
+/// The model used by default: the directory of an Opus-MT model, e.g. `opus-mt-en-de`.
+pub const DEFAULT_MODEL: &str = "opus-mt-{source}-{target}";
+
+/// How long the translator may take for the lines of a chunk when no other timeout is set.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Kind of the tokens standing in for the line breaks inside a paragraph, e.g. `⟦b0⟧`.
+const LINE_BREAK_KIND: &str = "b";
+
+/// Translates on this machine with a local neural translation model, such as an Opus-MT model
+/// with `marian-decoder` or a CTranslate2 model with a small script around it: no request leaves
+/// the machine and there are no rate limits.
+///
+/// The command is started once per language pair and kept running, so the model is loaded only
+/// once. It reads one paragraph per line on its standard input, with the line breaks inside the
+/// paragraph replaced by tokens, and has to write the translation of each line on its standard
+/// output as soon as it has read it. `{source}`, `{target}` and `{model}` are substituted in its
+/// arguments.
+pub struct OfflineClient {
+    command: String,
+    model: String,
+    timeout: Duration,
+    /// The running translators by source and target language.
+    workers: Mutex<HashMap<(String, String), SharedWorker>>,
+}
+
+/// A translator shared by the chunks of its language pair, one at a time.
+type SharedWorker = Arc<tokio::sync::Mutex<Worker>>;
+
+/// A running translator for one language pair.
+struct Worker {
+    program: String,
+    // Killed when the worker is dropped.
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+    /// The last line the translator wrote on its standard error, to explain why it stopped.
+    last_error: Arc<Mutex<String>>,
+}
+
+impl OfflineClient {
+    pub fn new(command: &str, model: &str) -> Self {
+        Self { command: command.to_string(), model: model.to_string(), timeout: DEFAULT_TIMEOUT, workers: Mutex::new(HashMap::new()) }
+    }
+
+    /// Sets how long the translator may take for the lines of a chunk before it is restarted.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// The program and its arguments for translating from `source` to `target`.
+    fn command_line(&self, source: &str, target: &str) -> Result<Vec<String>, TranslatorError> {
+        let substitute = |text: &str| text.replace("{source}", source).replace("{target}", target);
+        let model = substitute(&self.model);
+        let words = split_words(&self.command);
+        if words.is_empty() {
+            return Err("The offline translator command is empty".into());
+        }
+        Ok(words.iter().map(|word| substitute(&word.replace("{model}", &model))).collect())
+    }
+
+    /// The running translator for a language pair, started on first use.
+    fn worker(&self, source: &str, target: &str) -> Result<SharedWorker, TranslatorError> {
+        let mut workers = self.workers.lock().unwrap();
+        let key = (source.to_string(), target.to_string());
+        if let Some(worker) = workers.get(&key) {
+            return Ok(worker.clone());
+        }
+        let worker = Arc::new(tokio::sync::Mutex::new(Worker::start(&self.command_line(source, target)?)?));
+        workers.insert(key, worker.clone());
+        Ok(worker)
+    }
+
+    /// Sends the lines to the translator of the language pair, returning a translated line for each.
+    /// A translator that fails is stopped, and started again for the next chunk.
+    async fn run(&self, lines: &[String], source: &str, target: &str) -> Result<Vec<String>, TranslatorError> {
+        let worker = self.worker(source, target)?;
+        let result = {
+            let mut worker = worker.lock().await;
+            match tokio::time::timeout(self.timeout, worker.translate(lines)).await {
+                Ok(result) => result,
+                Err(_) => Err(TranslatorError::Unavailable(format!(
+                    "The offline translator '{}' gave no translation for {:.0} s; it has to answer every line as soon as it reads it",
+                    worker.program,
+                    self.timeout.as_secs_f64()
+                ))),
+            }
+        };
+        if result.is_err() {
+            let mut workers = self.workers.lock().unwrap();
+            let key = (source.to_string(), target.to_string());
+            if workers.get(&key).is_some_and(|running| Arc::ptr_eq(running, &worker)) {
+                workers.remove(&key);
+            }
+        }
+        result
+    }
+}
+
+impl Worker {
+    fn start(command_line: &[String]) -> Result<Self, TranslatorError> {
+        let program = command_line[0].clone();
+        let mut child = Command::new(&program)
+            .args(&command_line[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound if program == DEFAULT_PROGRAM => format!(
+                    "The offline backend needs Marian's '{}', which isn't installed or not on the PATH; install Marian \
+                     and an Opus-MT model, or give another local translator with '--offline-command'",
+                    program
+                ),
+                std::io::ErrorKind::NotFound => {
+                    format!("The offline translator '{}' of '--offline-command' isn't installed or not on the PATH", program)
+                }
+                _ => format!("Can't run the offline translator '{}': {}", program, e),
+            })?;
+        let missing = || TranslatorError::from(format!("The offline translator '{}' has no standard streams", program));
+        let stdin = child.stdin.take().ok_or_else(missing)?;
+        let stdout = BufReader::new(child.stdout.take().ok_or_else(missing)?).lines();
+        let stderr = child.stderr.take().ok_or_else(missing)?;
+        // The log of the translator is read all the time, or it could fill the pipe and block it.
+        let last_error = Arc::new(Mutex::new(String::new()));
+        let last = last_error.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                debug!(target: "offline", "{}", line);
+                if !line.trim().is_empty() {
+                    *last.lock().unwrap() = line;
+                }
+            }
+        });
+        Ok(Self { program, _child: child, stdin, stdout, last_error })
+    }
+
+    async fn translate(&mut self, lines: &[String]) -> Result<Vec<String>, TranslatorError> {
+        let mut input = lines.join("\n");
+        input.push('\n');
+        let Self { stdin, stdout, .. } = self;
+        // Written while the output is read, so that a long chunk can't fill both pipes.
+        let write = async {
+            stdin.write_all(input.as_bytes()).await?;
+            stdin.flush().await
+        };
+        let read = async {
+            let mut translated = Vec::with_capacity(lines.len());
+            while translated.len() < lines.len() {
+                match stdout.next_line().await? {
+                    Some(line) => translated.push(line.trim_end_matches('\r').to_string()),
+                    None => break,
+                }
+            }
+            Ok::<_, std::io::Error>(translated)
+        };
+        let (written, translated) = tokio::join!(write, read);
+        let translated = translated?;
+        if written.is_err() || translated.len() < lines.len() {
+            let reason = self.last_error.lock().unwrap().clone();
+            return Err(format!("The offline translator '{}' stopped: {}", self.program, reason.trim()).into());
+        }
+        Ok(translated)
+    }
+}
+
+impl Translator for OfflineClient {
+    async fn translate(&self, text: &str, source: &str, target: &str) -> Result<String, TranslatorError> {
+        if source == AUTO_LANGUAGE {
+            return Err("The offline backend can't detect the language; give it with '--source'".into());
+        }
+        let paragraphs: Vec<&str> = text.split("\n\n").collect();
+        let (lines, breaks): (Vec<String>, Vec<Vec<String>>) = paragraphs
+            .iter()
+            .filter(|paragraph| !paragraph.trim().is_empty())
+            .map(|paragraph| join_lines(paragraph))
+            .unzip();
+        if lines.is_empty() {
+            return Ok(text.to_string());
+        }
+        let mut translated = self.run(&lines, source, target).await?.into_iter().zip(breaks);
+        let paragraphs: Vec<String> = paragraphs
+            .iter()
+            .map(|paragraph| match paragraph.trim() {
+                "" => paragraph.to_string(),
+                trimmed => {
+                    let (line, breaks) = translated.next().expect("the translator answers every line");
+                    let start = paragraph.len() - paragraph.trim_start().len();
+                    let end = start + trimmed.len();
+                    format!("{}{}{}", &paragraph[..start], split_lines(&line, &breaks), &paragraph[end..])
+                }
+            })
+            .collect();
+        Ok(paragraphs.join("\n\n"))
+    }
+}
+
+/// Makes a paragraph one line for the translator, which would take each of its lines for a
+/// paragraph of its own: the line breaks, with the spaces around them, are replaced by tokens.
+/// Returns the line and the breaks the tokens stand for.
+fn join_lines(paragraph: &str) -> (String, Vec<String>) {
+    static LINE_BREAK: OnceLock<Regex> = OnceLock::new();
+    let line_break = LINE_BREAK.get_or_init(|| Regex::new(r"[ \t]*\r?\n[ \t]*").unwrap());
+    protect_matches(std::slice::from_ref(line_break), LINE_BREAK_KIND, paragraph.trim())
+}
+
+/// Puts the line breaks of [`join_lines`] back into the translation of a line. Breaks whose
+/// token the translator dropped are left out, leaving the translation on fewer lines.
+fn split_lines(line: &str, breaks: &[String]) -> String {
+    // The breaks come with the spaces around them, so the ones the translator put around the tokens go.
+    static TOKEN: OnceLock<Regex> = OnceLock::new();
+    let token = TOKEN.get_or_init(|| Regex::new(r"[ \t]*⟦\s*[bB]\s*(\d+)\s*⟧[ \t]*").unwrap());
+    let line = token.replace_all(line, |captures: &regex::Captures| placeholder::kind_token(LINE_BREAK_KIND, captures[1].parse().unwrap_or(usize::MAX)));
+    let (restored, missing) = placeholder::restore_kind(&line, LINE_BREAK_KIND, breaks);
+    if missing.is_empty() {
+        return restored;
+    }
+    debug!("The offline translator dropped {} of {} line breaks of a paragraph", missing.len(), breaks.len());
+    // The dropped breaks were appended at the end.
+    restored.trim_end().to_string()
+}
+
+/// Splits a command line into words at spaces, except within single or double quotes.
+fn split_words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    for c in command.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_breaks_and_indentation_survive_the_single_line() {
+        let (line, breaks) = join_lines("Roses are red,\n    violets are blue. \n");
+        assert_eq!(line, "Roses are red,⟦b0⟧violets are blue.");
+        assert_eq!(split_lines("A rózsa piros, ⟦B0⟧ az ibolya kék.", &breaks), "A rózsa piros,\n    az ibolya kék.");
+    }
+
+    #[test]
+    fn dropped_line_breaks_are_left_out() {
+        let (_, breaks) = join_lines("one\ntwo\nthree");
+        assert_eq!(split_lines("egy ⟦b0⟧ kettő három", &breaks), "egy\nkettő három");
+    }
+}
@@ -0,0 +1,210 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::TranslatorError;
+use crate::progress::Progress;
+use super::cassette::Cassette;
+use super::deepl::Formality;
+use super::openai::{translation_prompt, with_notes};
+use super::retry::{parse_json, send_streaming_with_retry, RetryPolicy};
+use crate::rate_limit::RateLimiter;
+use crate::prompt::PromptTemplate;
+use crate::translator::{ContextParagraph, TextFormat, Translator};
+
+/// The address Ollama listens on by default.
+pub const DEFAULT_URL: &str = "http://localhost:11434";
+pub const DEFAULT_MODEL: &str = "llama3.1:8b";
+
+/// How long the server may send nothing, e.g. while it loads the model, before the request is retried.
+pub const DEFAULT_IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The longest a whole reply may take; a slow model writing steadily is not cut off by the
+/// timeout of the HTTP client.
+const MAX_GENERATION_TIME: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage>,
+    stream: bool,
+    options: ModelOptions,
+}
+
+#[derive(Serialize)]
+struct ModelOptions {
+    temperature: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+/// A line of the streamed reply: a piece of the message, or an error the model ran into.
+#[derive(Deserialize, Debug)]
+struct ChatChunk {
+    message: Option<ChatMessage>,
+    error: Option<String>,
+    #[serde(default)]
+    done: bool,
+}
+
+/// Client for the chat API (`/api/chat`) of an Ollama server, which runs open models such as
+/// Llama, Mistral or Qwen on a local GPU.
+pub struct OllamaClient {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: Option<String>,
+    model: String,
+    prompt_template: PromptTemplate,
+    domain: Option<String>,
+    formality: Option<Formality>,
+    text_format: TextFormat,
+    idle_timeout: Duration,
+    progress: Progress,
+    retry: RetryPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl OllamaClient {
+    /// A client of the server at `url`, e.g. `http://localhost:11434`.
+    pub fn new(client: reqwest::Client, url: &str, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_url: format!("{}/api/chat", url.trim_end_matches('/')),
+            api_key: None,
+            model: model.into(),
+            prompt_template: PromptTemplate::default(),
+            domain: None,
+            formality: None,
+            text_format: TextFormat::Text,
+            idle_timeout: DEFAULT_IDLE_TIMEOUT,
+            progress: Progress::hidden(),
+            retry: RetryPolicy::default(),
+            limiter: None,
+            cassette: None,
+        }
+    }
+
+    /// Replaces the default translation instructions sent with every chunk.
+    pub fn with_prompt_template(mut self, prompt_template: PromptTemplate) -> Self {
+        self.prompt_template = prompt_template;
+        self
+    }
+
+    /// Describes the field the texts come from, so the model uses its terminology and style.
+    pub fn with_domain(mut self, domain: Option<String>) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    /// Asks the model for a formal or informal tone.
+    pub fn with_formality(mut self, formality: Option<Formality>) -> Self {
+        self.formality = formality;
+        self
+    }
+
+    /// Sets the bearer token of a server behind an authenticating proxy.
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// Sets whether the text is plain text or HTML.
+    pub fn with_text_format(mut self, text_format: TextFormat) -> Self {
+        self.text_format = text_format;
+        self
+    }
+
+    /// Sets how long the server may send nothing before the request is retried.
+    pub fn with_idle_timeout(mut self, idle_timeout: Duration) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Reports retries as events of `progress`.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets how often and after what delay failed requests are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Reports the server's rate limit headers to the limiter pacing the requests.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Records the exchanges with the server in `cassette`, or answers requests from it.
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Sends `prompt` as a user message and returns the model's reply. The reply is streamed, one
+    /// JSON object per line as the model writes it, so a long reply from a slow model is only
+    /// given up on when the server stops sending for the idle timeout.
+    pub async fn complete(&self, prompt: String) -> Result<String, TranslatorError> {
+        let request_payload = ChatRequest {
+            model: &self.model,
+            messages: vec![ChatMessage { role: "user".to_string(), content: with_notes(prompt, self.domain.as_deref(), self.formality) }],
+            stream: true,
+            options: ModelOptions { temperature: 0.0 },
+        };
+        let body_text = send_streaming_with_retry(
+            || {
+                let request = self.client.post(&self.api_url).timeout(MAX_GENERATION_TIME).json(&request_payload);
+                match &self.api_key {
+                    Some(key) => request.bearer_auth(key),
+                    None => request,
+                }
+            },
+            &self.progress,
+            &self.retry,
+            self.limiter.as_deref(),
+            self.cassette.as_deref(),
+            self.idle_timeout,
+        )
+        .await?;
+        let mut reply = String::new();
+        let mut done = false;
+        for line in body_text.lines().filter(|line| !line.trim().is_empty()) {
+            let chunk: ChatChunk = parse_json(line)?;
+            if let Some(error) = chunk.error {
+                return Err(TranslatorError::Parse(format!("Ollama failed to answer: {}", error)));
+            }
+            if let Some(message) = chunk.message {
+                reply.push_str(&message.content);
+            }
+            done |= chunk.done;
+        }
+        if !done {
+            return Err(TranslatorError::Parse("Ollama's reply ended before the model finished".to_string()));
+        }
+        Ok(reply.trim().to_string())
+    }
+}
+
+impl Translator for OllamaClient {
+    async fn translate(&self, chunk: &str, source_lang: &str, target_lang: &str) -> Result<String, TranslatorError> {
+        self.translate_in_context(chunk, source_lang, target_lang, &[]).await
+    }
+
+    async fn translate_in_context(
+        &self,
+        chunk: &str,
+        source_lang: &str,
+        target_lang: &str,
+        context: &[ContextParagraph],
+    ) -> Result<String, TranslatorError> {
+        self.complete(translation_prompt(&self.prompt_template, self.text_format, chunk, source_lang, target_lang, context)).await
+    }
+}
@@ -0,0 +1,222 @@
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::error::TranslatorError;
+use crate::progress::Progress;
+use super::cassette::Cassette;
+use super::deepl::Formality;
+use super::retry::{parse_json, send_with_retry, RetryPolicy};
+use crate::rate_limit::RateLimiter;
+use crate::prompt::PromptTemplate;
+use crate::translator::{ContextParagraph, TextFormat, Translator};
+
+pub const DEFAULT_API_URL: &str = "https://api.openai.com/v1/chat/completions";
+pub const DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage>,
+    temperature: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct ChatMessage {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+/// Client for any OpenAI-compatible chat completions endpoint.
+pub struct OpenAiClient {
+    client: reqwest::Client,
+    api_url: String,
+    api_key: Option<String>,
+    model: String,
+    prompt_template: PromptTemplate,
+    domain: Option<String>,
+    formality: Option<Formality>,
+    text_format: TextFormat,
+    progress: Progress,
+    retry: RetryPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+    cassette: Option<Arc<Cassette>>,
+}
+
+impl OpenAiClient {
+    pub fn new(client: reqwest::Client, api_url: impl Into<String>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            api_url: api_url.into(),
+            api_key: None,
+            model: model.into(),
+            prompt_template: PromptTemplate::default(),
+            domain: None,
+            formality: None,
+            text_format: TextFormat::Text,
+            progress: Progress::hidden(),
+            retry: RetryPolicy::default(),
+            limiter: None,
+            cassette: None,
+        }
+    }
+
+    /// Replaces the default translation instructions sent with every chunk.
+    pub fn with_prompt_template(mut self, prompt_template: PromptTemplate) -> Self {
+        self.prompt_template = prompt_template;
+        self
+    }
+
+    /// Describes the field the texts come from, e.g. "medical report", so the model uses its
+    /// terminology and style.
+    pub fn with_domain(mut self, domain: Option<String>) -> Self {
+        self.domain = domain;
+        self
+    }
+
+    /// Asks the model for a formal or informal tone.
+    pub fn with_formality(mut self, formality: Option<Formality>) -> Self {
+        self.formality = formality;
+        self
+    }
+
+    /// Sets the bearer token; local servers usually don't need one.
+    pub fn with_api_key(mut self, api_key: Option<String>) -> Self {
+        self.api_key = api_key;
+        self
+    }
+
+    /// Sets whether the text is plain text or HTML.
+    pub fn with_text_format(mut self, text_format: TextFormat) -> Self {
+        self.text_format = text_format;
+        self
+    }
+
+    /// Reports retries as events of `progress`.
+    pub fn with_progress(mut self, progress: Progress) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Sets how often and after what delay failed requests are retried.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Reports the server's rate limit headers to the limiter pacing the requests.
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Records the exchanges with the server in `cassette`, or answers requests from it.
+    pub fn with_cassette(mut self, cassette: Arc<Cassette>) -> Self {
+        self.cassette = Some(cassette);
+        self
+    }
+
+    /// Sends `prompt` as a user message, after notes on the domain of the text and the tone, and
+    /// returns the model's reply.
+    pub async fn complete(&self, prompt: String) -> Result<String, TranslatorError> {
+        let prompt = with_notes(prompt, self.domain.as_deref(), self.formality);
+        let request_payload = ChatRequest {
+            model: &self.model,
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: prompt,
+            }],
+            temperature: 0.0,
+        };
+
+        let body_text = send_with_retry(
+            || {
+                let request = self.client.post(&self.api_url).json(&request_payload);
+                match &self.api_key {
+                    Some(key) => request.bearer_auth(key),
+                    None => request,
+                }
+            },
+            &self.progress,
+            &self.retry,
+            self.limiter.as_deref(),
+            self.cassette.as_deref(),
+        )
+        .await?;
+        let response: ChatResponse = parse_json(&body_text)?;
+        response
+            .choices
+            .into_iter()
+            .next()
+            .map(|c| c.message.content.trim().to_string())
+            .ok_or_else(|| TranslatorError::Parse("The chat completions API returned no choices".to_string()))
+    }
+}
+
+impl Translator for OpenAiClient {
+    async fn translate(
+        &self,
+        chunk: &str,
+        source_lang: &str,
+        target_lang: &str,
+    ) -> Result<String, TranslatorError> {
+        self.translate_in_context(chunk, source_lang, target_lang, &[]).await
+    }
+
+    async fn translate_in_context(
+        &self,
+        chunk: &str,
+        source_lang: &str,
+        target_lang: &str,
+        context: &[ContextParagraph],
+    ) -> Result<String, TranslatorError> {
+        self.complete(translation_prompt(&self.prompt_template, self.text_format, chunk, source_lang, target_lang, context)).await
+    }
+}
+
+/// The prompt asking an LLM to translate `chunk`, with notes on HTML and the paragraphs before it.
+pub(super) fn translation_prompt(
+    template: &PromptTemplate,
+    text_format: TextFormat,
+    chunk: &str,
+    source_lang: &str,
+    target_lang: &str,
+    context: &[ContextParagraph],
+) -> String {
+    let mut prompt = template.render(source_lang, target_lang, chunk);
+    if text_format == TextFormat::Html {
+        prompt = format!("The text is an HTML fragment: keep every tag and attribute unchanged.\n\n{}", prompt);
+    }
+    if !context.is_empty() {
+        prompt = format!("{}\n\n{}", context_note(context), prompt);
+    }
+    prompt
+}
+
+/// `prompt` after notes on the domain of the text and the tone.
+pub(super) fn with_notes(prompt: String, domain: Option<&str>, formality: Option<Formality>) -> String {
+    let domain = domain.map(|domain| format!("The text is from a {}: use the terminology and style of that field.", domain));
+    let formality = formality.and_then(Formality::instruction).map(str::to_string);
+    domain.into_iter().chain(formality).chain(std::iter::once(prompt)).collect::<Vec<_>>().join("\n\n")
+}
+
+/// Describes the paragraphs before the text and their translations, so the model keeps the
+/// pronouns and terms it used there.
+fn context_note(context: &[ContextParagraph]) -> String {
+    let mut note = String::from(
+        "For context, the text follows these paragraphs, already translated as shown. Do not translate them again.",
+    );
+    for paragraph in context {
+        note.push_str(&format!("\n\nOriginal: {}\nTranslation: {}", paragraph.source, paragraph.translation));
+    }
+    note
+}
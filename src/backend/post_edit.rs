@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::error::TranslatorError;
+use super::openai::OpenAiClient;
+use super::Backend;
+use crate::rate_limit::RateLimiter;
+use crate::translator::{ContextParagraph, Translator};
+
+/// Instructions for the model editing a machine translation.
+const EDIT_PROMPT: &str = "Below is a text in the language with code '{source}' and its machine translation into the \
+language with code '{target}'. Fix the grammar and fluency of the translation so it reads as if written by a native \
+speaker, keeping its meaning and the meaning of the original. Keep the paragraphs separated by blank lines as they are, \
+and tokens like ⟦0⟧ exactly as they are. Reply with the edited translation only, without any notes or explanations.";
+
+/// A backend whose translations are edited by an LLM for grammar and fluency.
+pub struct PostEdit {
+    backend: Box<Backend>,
+    editor: OpenAiClient,
+}
+
+impl PostEdit {
+    pub fn new(backend: Backend, editor: OpenAiClient) -> Self {
+        Self { backend: Box::new(backend), editor }
+    }
+
+    /// Applies `f` to the translating backend, and `g` to the editor.
+    pub(super) fn map(self, f: impl FnOnce(Backend) -> Backend, g: impl FnOnce(OpenAiClient) -> OpenAiClient) -> Self {
+        Self { backend: Box::new(f(*self.backend)), editor: g(self.editor) }
+    }
+
+    /// Lets the translating server adjust the pace of `limiter`; the LLM has limits of its own.
+    pub(super) fn with_rate_limiter(self, limiter: Arc<RateLimiter>) -> Self {
+        self.map(|backend| backend.with_rate_limiter(limiter), |editor| editor)
+    }
+
+    /// The backend making the translations.
+    pub fn translator(&self) -> &Backend {
+        &self.backend
+    }
+
+    /// Has the editor improve `translated`, a translation of `text`. An edit that merges or
+    /// splits paragraphs can't be matched up with the original, so the translation is kept.
+    async fn edit(&self, text: &str, translated: String, source: &str, target: &str) -> Result<String, TranslatorError> {
+        if !translated.chars().any(char::is_alphabetic) {
+            return Ok(translated);
+        }
+        let prompt = format!(
+            "{}\n\nOriginal:\n{}\n\nTranslation:\n{}",
+            EDIT_PROMPT.replace("{source}", source).replace("{target}", target),
+            text,
+            translated
+        );
+        let edited = self.editor.complete(prompt).await?;
+        if edited.split("\n\n").count() != translated.split("\n\n").count() {
+            warn!("The post-edit merged or split paragraphs; keeping the unedited translation.");
+            return Ok(translated);
+        }
+        Ok(edited)
+    }
+}
+
+impl Translator for PostEdit {
+    async fn translate(&self, text: &str, source: &str, target: &str) -> Result<String, TranslatorError> {
+        let translated = self.backend.translate(text, source, target).await?;
+        self.edit(text, translated, source, target).await
+    }
+
+    async fn translate_in_context(
+        &self,
+        text: &str,
+        source: &str,
+        target: &str,
+        context: &[ContextParagraph],
+    ) -> Result<String, TranslatorError> {
+        let translated = self.backend.translate_in_context(text, source, target, context).await?;
+        self.edit(text, translated, source, target).await
+    }
+}
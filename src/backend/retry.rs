@@ -0,0 +1,339 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, trace, warn};
+
+use super::cassette::{self, Cassette};
+use crate::error::TranslatorError;
+use crate::progress::{Event, Progress};
+use crate::rate_limit::RateLimiter;
+
+/// How failed requests are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt.
+    pub max_retries: u32,
+    /// Backoff unit; the n-th retry waits `base_delay * 2^n`.
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait before the given retry, counted from 1: 2, 4, 8, ... times the base delay.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.base_delay.saturating_mul(1 << attempt.min(16))
+    }
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Extracts the message from an `{"error": "..."}` style error body.
+fn error_message(body_text: &str) -> Option<String> {
+    serde_json::from_str::<ErrorResponse>(body_text).ok().map(|e| e.error)
+}
+
+/// Sends the request produced by `build_request`, retrying on connection errors, timeouts, 5xx and
+/// 429 (too many requests) responses, and returns the body of the first successful response.
+///
+/// When the retries run out, the error is that of the last attempt: a
+/// [`Network`](TranslatorError::Network), [`RateLimited`](TranslatorError::RateLimited) or
+/// [`Unavailable`](TranslatorError::Unavailable) error, which says nothing about the request
+/// itself. Rejected requests fail right away with an [`Api`](TranslatorError::Api) error.
+///
+/// A `Retry-After` header on 429 and 503 responses replaces the backoff delay, and rate limit
+/// headers on any response are passed on to `limiter` so it can pace the following requests;
+/// an adaptive limiter also slows down on 429 and 5xx responses and speeds up on successes.
+///
+/// The request is rebuilt for every attempt because a sent `RequestBuilder` is consumed.
+pub(crate) async fn send_with_retry<F>(
+    build_request: F,
+    progress: &Progress,
+    retry: &RetryPolicy,
+    limiter: Option<&RateLimiter>,
+    cassette: Option<&Cassette>,
+) -> Result<String, TranslatorError>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    send(build_request, progress, retry, limiter, cassette, None).await
+}
+
+/// Like [`send_with_retry`], for responses streamed piece by piece: the body is read as it
+/// arrives, and the attempt fails (and is retried) when the server sends nothing for `idle_timeout`.
+pub(crate) async fn send_streaming_with_retry<F>(
+    build_request: F,
+    progress: &Progress,
+    retry: &RetryPolicy,
+    limiter: Option<&RateLimiter>,
+    cassette: Option<&Cassette>,
+    idle_timeout: Duration,
+) -> Result<String, TranslatorError>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    send(build_request, progress, retry, limiter, cassette, Some(idle_timeout)).await
+}
+
+async fn send<F>(
+    build_request: F,
+    progress: &Progress,
+    retry: &RetryPolicy,
+    limiter: Option<&RateLimiter>,
+    cassette: Option<&Cassette>,
+    idle_timeout: Option<Duration>,
+) -> Result<String, TranslatorError>
+where
+    F: Fn() -> reqwest::RequestBuilder,
+{
+    let mut last_error = None;
+    let mut retry_after = None;
+
+    for attempt in 0..=retry.max_retries {
+        if attempt > 0 {
+            // The delay the server asked for, or exponential backoff.
+            let delay = retry_after.take().unwrap_or_else(|| retry.backoff(attempt));
+            warn!("Chunk translation failed. Retrying in {:?}... (Attempt {}/{})", delay, attempt, retry.max_retries);
+            progress.count_retry();
+            progress.emit(&Event::Retry {
+                attempt,
+                max_retries: retry.max_retries,
+                delay_seconds: delay.as_secs_f64(),
+                error: last_error.as_ref().map(ToString::to_string).unwrap_or_default(),
+            });
+            tokio::time::sleep(delay).await;
+        }
+
+        let (client, request) = build_request().build_split();
+        let request = request?;
+        let (method, url) = (request.method().clone(), redacted(request.url()));
+        debug!(%method, %url, attempt, "Sending request");
+        let sent = Instant::now();
+        let recorded = cassette.filter(|cassette| !cassette.is_replaying()).map(|_| cassette::key(&request));
+        let response = match cassette {
+            Some(cassette) if cassette.is_replaying() => cassette.answer(&request)?,
+            _ => match client.execute(request).await {
+                Ok(resp) => resp,
+                Err(e) => {
+                    if e.is_timeout() {
+                        warn!("{} {} timed out after {:.1} s", method, url, sent.elapsed().as_secs_f64());
+                    } else {
+                        debug!(%method, %url, error = %e, "Request failed");
+                    }
+                    last_error = Some(TranslatorError::Network(e));
+                    continue; // Retry on connection errors and timeouts
+                }
+            },
+        };
+        let response = match (cassette, recorded) {
+            (Some(cassette), Some(request)) => match cassette.store(request, response).await {
+                Ok(response) => response,
+                Err(error) if error.is_transient() => {
+                    last_error = Some(error);
+                    continue; // Retry on error reading body
+                }
+                Err(error) => return Err(error),
+            },
+            _ => response,
+        };
+
+        let status = response.status();
+        debug!(
+            %method, %url, %status, elapsed_ms = sent.elapsed().as_millis() as u64, headers = ?response.headers(),
+            "Received response"
+        );
+        let hints = RateLimitHints::from_headers(response.headers());
+        if let (Some(limiter), Some(remaining), Some(reset)) = (limiter, hints.remaining, hints.reset) {
+            limiter.adapt(remaining, reset);
+        }
+        if let Some(limiter) = limiter {
+            if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+                limiter.back_off();
+            } else if status.is_success() {
+                limiter.speed_up();
+            }
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::SERVICE_UNAVAILABLE {
+            retry_after = hints.retry_after.or(hints.remaining.filter(|&remaining| remaining == 0).and(hints.reset));
+            if let (Some(limiter), Some(delay)) = (limiter, retry_after) {
+                // Concurrent requests would only be turned away as well.
+                limiter.pause_for(delay);
+            }
+        }
+        if status.is_success() {
+            match read_body(response, idle_timeout).await {
+                Ok(text) => {
+                    trace!(body = %text, "Response body");
+                    return Ok(text);
+                }
+                Err(error) => {
+                    last_error = Some(error);
+                    continue; // Retry on error reading body
+                }
+            }
+        } else if status.is_client_error() && status != StatusCode::TOO_MANY_REQUESTS {
+            // 4xx errors are final, don't retry.
+            let body_text = response.text().await.unwrap_or_else(|e| format!("Could not read error body: {}", e));
+            let err_msg = if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+                // Most servers answer like LibreTranslate: `{"error": "Invalid API key"}`.
+                let reason = error_message(&body_text).unwrap_or_else(|| "invalid or missing API key".to_string());
+                format!(
+                    "API request was rejected with status {} ({}). Check the key given via --api-key or TRANSLATOR_API_KEY",
+                    status, reason
+                )
+            } else {
+                format!("API request failed with client error status {}", status)
+            };
+            warn!("{}", err_msg);
+            trace!(body = %body_text, "Error response body");
+            return Err(TranslatorError::Api { status: status.as_u16(), message: err_msg });
+        } else {
+            // 5xx server errors, rate limiting or others, worth retrying.
+            let body_text = response.text().await.unwrap_or_else(|e| format!("Could not read error body: {}", e));
+            let message = format!("API request failed with status {}: {}", status, body_text);
+            last_error = Some(if status == StatusCode::TOO_MANY_REQUESTS {
+                TranslatorError::RateLimited(message)
+            } else {
+                TranslatorError::Unavailable(message)
+            });
+            // Loop continues to retry
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| TranslatorError::Unavailable("Translation failed after multiple retries".to_string())))
+}
+
+/// Reads the body of a response at once or, with an `idle_timeout`, piece by piece as the server sends it.
+async fn read_body(mut response: reqwest::Response, idle_timeout: Option<Duration>) -> Result<String, TranslatorError> {
+    let Some(idle_timeout) = idle_timeout else { return Ok(response.text().await?) };
+    let mut body = Vec::new();
+    loop {
+        match tokio::time::timeout(idle_timeout, response.chunk()).await {
+            Ok(Ok(Some(piece))) => body.extend_from_slice(&piece),
+            Ok(Ok(None)) => return Ok(String::from_utf8_lossy(&body).into_owned()),
+            Ok(Err(e)) => return Err(TranslatorError::Network(e)),
+            Err(_) => {
+                warn!("The server sent nothing for {:.1} s", idle_timeout.as_secs_f64());
+                return Err(TranslatorError::Unavailable(format!("The server sent nothing for {:.1} s", idle_timeout.as_secs_f64())));
+            }
+        }
+    }
+}
+
+/// What a server says about its rate limits in the headers of a response.
+#[derive(Debug, Default, PartialEq)]
+struct RateLimitHints {
+    /// How long to wait before the next request, from `Retry-After`.
+    retry_after: Option<Duration>,
+    /// Requests left in the current window, from `X-RateLimit-Remaining` or `RateLimit-Remaining`.
+    remaining: Option<u64>,
+    /// Time until the window resets, from `X-RateLimit-Reset` or `RateLimit-Reset`.
+    reset: Option<Duration>,
+}
+
+impl RateLimitHints {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| headers.get(*name))
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+        };
+        let now = SystemTime::now();
+        Self {
+            // Either a number of seconds or an HTTP date.
+            retry_after: header(&[RETRY_AFTER.as_str()]).and_then(|value| match value.parse::<f64>() {
+                Ok(seconds) => Duration::try_from_secs_f64(seconds).ok(),
+                Err(_) => httpdate::parse_http_date(value).ok().map(|date| date.duration_since(now).unwrap_or_default()),
+            }),
+            remaining: header(&["x-ratelimit-remaining", "ratelimit-remaining"]).and_then(|value| value.parse().ok()),
+            // Either seconds until the reset or, for large values, the Unix time of the reset.
+            reset: header(&["x-ratelimit-reset", "ratelimit-reset"])
+                .and_then(|value| value.parse::<f64>().ok())
+                .and_then(|seconds| {
+                    let unix_now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs_f64();
+                    Duration::try_from_secs_f64(if seconds > 1e9 { seconds - unix_now } else { seconds }).ok()
+                }),
+        }
+    }
+}
+
+/// Query parameters holding API keys, which must not end up in log files.
+const SECRET_PARAMETERS: &[&str] = &["key", "api_key", "auth_key"];
+
+/// The URL of a request, with the API keys in its query replaced for logging.
+pub(crate) fn redacted(url: &Url) -> Url {
+    let mut url = url.clone();
+    if url.query_pairs().any(|(name, _)| SECRET_PARAMETERS.contains(&name.as_ref())) {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let value = if SECRET_PARAMETERS.contains(&name.as_ref()) { "***".into() } else { value };
+                (name.into_owned(), value.into_owned())
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url
+}
+
+/// Decodes a successful response body, logging the body on failure to help debugging.
+///
+/// JSON decoding errors are final and never retried.
+pub(crate) fn parse_json<T: DeserializeOwned>(body_text: &str) -> Result<T, TranslatorError> {
+    serde_json::from_str::<T>(body_text).map_err(|e| {
+        let err_msg = format!("Failed to parse JSON from API: {}", e);
+        warn!("{}", err_msg);
+        trace!(body = %body_text, "Unparsable response body");
+        TranslatorError::Parse(err_msg)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_with_every_retry_and_saturates() {
+        let retry = RetryPolicy { max_retries: 3, base_delay: Duration::from_millis(500) };
+        let delays: Vec<Duration> = (1..=3).map(|attempt| retry.backoff(attempt)).collect();
+        assert_eq!(delays, [Duration::from_secs(1), Duration::from_secs(2), Duration::from_secs(4)]);
+        assert_eq!(RetryPolicy { max_retries: 100, base_delay: Duration::MAX }.backoff(100), Duration::MAX);
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs.iter().map(|(name, value)| (reqwest::header::HeaderName::from_static(name), value.parse().unwrap())).collect()
+    }
+
+    #[test]
+    fn retry_after_may_be_seconds_or_a_date() {
+        assert_eq!(RateLimitHints::from_headers(&headers(&[("retry-after", "1.5")])).retry_after, Some(Duration::from_millis(1500)));
+        let past = RateLimitHints::from_headers(&headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT")]));
+        assert_eq!(past.retry_after, Some(Duration::ZERO));
+        assert_eq!(RateLimitHints::from_headers(&headers(&[("retry-after", "soon")])), RateLimitHints::default());
+    }
+
+    #[test]
+    fn rate_limit_reset_may_be_seconds_or_a_unix_time() {
+        let hints = RateLimitHints::from_headers(&headers(&[("x-ratelimit-remaining", "0"), ("x-ratelimit-reset", "30")]));
+        assert_eq!(hints, RateLimitHints { retry_after: None, remaining: Some(0), reset: Some(Duration::from_secs(30)) });
+
+        let in_a_minute = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() + 60;
+        let hints = RateLimitHints::from_headers(&headers(&[("ratelimit-remaining", "12"), ("ratelimit-reset", &in_a_minute.to_string())]));
+        assert_eq!(hints.remaining, Some(12));
+        assert!(hints.reset.is_some_and(|reset| reset > Duration::from_secs(58) && reset <= Duration::from_secs(60)));
+    }
+}
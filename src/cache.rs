@@ -0,0 +1,244 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::error::TranslatorError;
+use crate::fuzzy::FuzzyMatching;
+use crate::translator::Translator;
+
+/// Persistent store of earlier translations, keyed by language pair and the SHA-256 of the source text.
+///
+/// Besides whole chunks, the individual paragraphs of each chunk are stored too, so re-running
+/// on a slightly modified file only sends the changed paragraphs to the API. With fuzzy
+/// matching, paragraphs similar enough to a cached one reuse its translation too.
+pub struct TranslationCache {
+    conn: Connection,
+    fuzzy: Option<FuzzyMatching>,
+    /// The cached paragraphs of each language pair, loaded on the first fuzzy lookup.
+    fuzzy_candidates: Mutex<Candidates>,
+    fuzzy_matches: AtomicUsize,
+    hits: AtomicUsize,
+}
+
+impl TranslationCache {
+    /// Opens (or creates) the cache database at `path`.
+    pub fn open(path: &Path) -> Result<Self, TranslatorError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS translations (
+                source_lang TEXT NOT NULL,
+                target_lang TEXT NOT NULL,
+                source_hash TEXT NOT NULL,
+                translation TEXT NOT NULL,
+                source_text TEXT,
+                PRIMARY KEY (source_lang, target_lang, source_hash)
+            )",
+        )?;
+        // Caches created before the source text was stored only know its hash.
+        let has_source_text = conn
+            .prepare("SELECT 1 FROM pragma_table_info('translations') WHERE name = 'source_text'")?
+            .exists([])?;
+        if !has_source_text {
+            conn.execute_batch("ALTER TABLE translations ADD COLUMN source_text TEXT")?;
+        }
+        Ok(Self {
+            conn,
+            fuzzy: None,
+            fuzzy_candidates: Mutex::new(HashMap::new()),
+            fuzzy_matches: AtomicUsize::new(0),
+            hits: AtomicUsize::new(0),
+        })
+    }
+
+    /// Reuses the translations of cached paragraphs similar to the one being translated.
+    pub fn with_fuzzy_matching(self, fuzzy: FuzzyMatching) -> Self {
+        Self { fuzzy: Some(fuzzy), ..self }
+    }
+
+    /// Number of paragraphs translated from a fuzzy match so far.
+    pub fn fuzzy_matches(&self) -> usize {
+        self.fuzzy_matches.load(Ordering::Relaxed)
+    }
+
+    /// Number of chunks whose translation came entirely from the cache so far.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// The per-user cache location, e.g. `~/.cache/translator/cache.sqlite` on Linux.
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join("translator").join("cache.sqlite"))
+    }
+
+    pub fn get(&self, source: &str, target: &str, text: &str) -> Result<Option<String>, TranslatorError> {
+        let translation = self
+            .conn
+            .query_row(
+                "SELECT translation FROM translations WHERE source_lang = ?1 AND target_lang = ?2 AND source_hash = ?3",
+                params![source, target, hash(text)],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(translation)
+    }
+
+    pub fn put(&self, source: &str, target: &str, text: &str, translation: &str) -> Result<(), TranslatorError> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO translations (source_lang, target_lang, source_hash, translation, source_text)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![source, target, hash(text), translation, text],
+        )?;
+        Ok(())
+    }
+
+    /// The cached paragraphs of a language pair with their translations, in no particular order.
+    ///
+    /// Whole chunks of several paragraphs are left out, as are entries stored before the cache
+    /// kept the source text.
+    pub fn entries(&self, source: &str, target: &str) -> Result<Vec<(String, String)>, TranslatorError> {
+        let mut statement = self.conn.prepare(
+            "SELECT source_text, translation FROM translations
+             WHERE source_lang = ?1 AND target_lang = ?2 AND source_text IS NOT NULL",
+        )?;
+        let rows = statement.query_map(params![source, target], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        let mut entries = Vec::new();
+        for row in rows {
+            let (text, translation): (String, String) = row?;
+            if !text.contains("\n\n") {
+                entries.push((text, translation));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// The translation of a paragraph: the exact one if it is cached, else that of the most
+    /// similar cached paragraph when fuzzy matching is on, flagged as such.
+    fn get_paragraph(&self, source: &str, target: &str, text: &str) -> Result<Option<Paragraph>, TranslatorError> {
+        if let Some(translation) = self.get(source, target, text)? {
+            return Ok(Some(Paragraph { translation, fuzzy: false }));
+        }
+        let Some(fuzzy) = &self.fuzzy else { return Ok(None) };
+
+        let mut candidates = self.fuzzy_candidates.lock().unwrap();
+        let key = (source.to_string(), target.to_string());
+        if !candidates.contains_key(&key) {
+            candidates.insert(key.clone(), self.entries(source, target)?);
+        }
+        let best = candidates[&key]
+            .iter()
+            .filter(|(candidate, _)| fuzzy.may_match(candidate, text))
+            .map(|(candidate, translation)| (fuzzy.similarity(candidate, text), translation))
+            .filter(|(similarity, _)| *similarity >= fuzzy.threshold)
+            .max_by(|(a, _), (b, _)| a.total_cmp(b));
+        Ok(best.map(|(similarity, translation)| Paragraph { translation: fuzzy.apply(translation, similarity), fuzzy: true }))
+    }
+
+    /// Joins the translations of the paragraphs of a chunk, counting the fuzzy matches among them.
+    fn join(&self, paragraphs: Vec<Paragraph>) -> String {
+        let fuzzy = paragraphs.iter().filter(|paragraph| paragraph.fuzzy).count();
+        self.fuzzy_matches.fetch_add(fuzzy, Ordering::Relaxed);
+        paragraphs.into_iter().map(|paragraph| paragraph.translation).collect::<Vec<_>>().join("\n\n")
+    }
+
+    /// Returns the translation of a chunk if it (or every one of its paragraphs) is cached.
+    pub fn lookup(&self, source: &str, target: &str, chunk: &str) -> Result<Option<String>, TranslatorError> {
+        if let Some(translation) = self.get(source, target, chunk)? {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(Some(translation));
+        }
+
+        let mut translations = Vec::new();
+        for paragraph in chunk.split("\n\n") {
+            match self.get_paragraph(source, target, paragraph)? {
+                Some(translation) => translations.push(translation),
+                None => return Ok(None),
+            }
+        }
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Ok(Some(self.join(translations)))
+    }
+
+    /// Whether `lookup` would find a translation of a chunk, without counting it as a hit.
+    pub fn contains(&self, source: &str, target: &str, chunk: &str) -> Result<bool, TranslatorError> {
+        if self.get(source, target, chunk)?.is_some() {
+            return Ok(true);
+        }
+        for paragraph in chunk.split("\n\n") {
+            if self.get_paragraph(source, target, paragraph)?.is_none() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Translates the paragraphs of a chunk that are not cached yet and stores the results.
+    pub async fn translate<T: Translator>(
+        &self,
+        translator: &T,
+        chunk: &str,
+        source: &str,
+        target: &str,
+    ) -> Result<String, TranslatorError> {
+        let paragraphs: Vec<&str> = chunk.split("\n\n").collect();
+        let mut translations = Vec::with_capacity(paragraphs.len());
+        for paragraph in &paragraphs {
+            translations.push(self.get_paragraph(source, target, paragraph)?);
+        }
+        let missing: Vec<usize> = (0..paragraphs.len()).filter(|&i| translations[i].is_none()).collect();
+        if missing.is_empty() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(self.join(translations.into_iter().flatten().collect()));
+        }
+        // Translations reused from fuzzy matches are not stored as those of the chunk.
+        let mut exact = true;
+
+        let request: Vec<&str> = missing.iter().map(|&i| paragraphs[i]).collect();
+        let translated = translator.translate(&request.join("\n\n"), source, target).await?;
+        let parts: Vec<&str> = translated.split("\n\n").collect();
+
+        let result = if parts.len() == missing.len() {
+            for (&i, part) in missing.iter().zip(parts) {
+                self.put(source, target, paragraphs[i], part)?;
+                translations[i] = Some(Paragraph { translation: part.to_string(), fuzzy: false });
+            }
+            exact = translations.iter().flatten().all(|paragraph| !paragraph.fuzzy);
+            self.join(translations.into_iter().flatten().collect())
+        } else if missing.len() == paragraphs.len() {
+            // The paragraphs can't be matched up, but the result still covers the whole chunk.
+            translated
+        } else {
+            // The partial result can't be merged with the cached paragraphs, so translate the whole chunk.
+            translator.translate(chunk, source, target).await?
+        };
+
+        if exact {
+            self.put(source, target, chunk, &result)?;
+        }
+        Ok(result)
+    }
+}
+
+/// Cached `(source text, translation)` pairs by `(source, target)` language.
+type Candidates = HashMap<(String, String), Vec<(String, String)>>;
+
+/// The translation of a paragraph found in the cache.
+struct Paragraph {
+    translation: String,
+    /// Whether it is the translation of a similar paragraph rather than of this one.
+    fuzzy: bool,
+}
+
+/// Hex encoded SHA-256 digest of the text.
+fn hash(text: &str) -> String {
+    Sha256::digest(text.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::TranslatorError;
+
+/// Progress of a translation run, persisted after every chunk so an interrupted run can be resumed.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Checkpoint {
+    source: String,
+    target: String,
+    /// The source chunks, used to detect that the input or chunking changed since the checkpoint was written.
+    chunks: Vec<String>,
+    translations: Vec<Option<String>>,
+}
+
+impl Checkpoint {
+    pub fn new(source: &str, target: &str, chunks: &[String]) -> Self {
+        Self {
+            source: source.to_string(),
+            target: target.to_string(),
+            chunks: chunks.to_vec(),
+            translations: vec![None; chunks.len()],
+        }
+    }
+
+    /// The sidecar state file belonging to an output (or input) file, e.g. `output.translator-state.json`.
+    pub fn path_for(file: &Path) -> PathBuf {
+        file.with_extension("translator-state.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TranslatorError> {
+        let json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Writes the checkpoint to a temporary file first and renames it into place, so a crash
+    /// while saving never leaves a corrupt checkpoint behind.
+    pub fn save(&self, path: &Path) -> Result<(), TranslatorError> {
+        let tmp_path = path.with_extension("translator-state.json.tmp");
+        fs::write(&tmp_path, serde_json::to_string(self)?)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Whether this checkpoint was written for the same language pair and chunks.
+    pub fn matches(&self, source: &str, target: &str, chunks: &[String]) -> bool {
+        self.source == source && self.target == target && self.chunks == chunks
+    }
+
+    /// The stored translation of the chunk at `index`, if it was already translated.
+    pub fn translation(&self, index: usize) -> Option<&str> {
+        self.translations.get(index).and_then(|t| t.as_deref())
+    }
+
+    pub fn record(&mut self, index: usize, translation: String) {
+        self.translations[index] = Some(translation);
+    }
+
+    /// Total number of chunks.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Number of chunks that are already translated.
+    pub fn completed(&self) -> usize {
+        self.translations.iter().filter(|t| t.is_some()).count()
+    }
+}
@@ -0,0 +1,254 @@
+use std::io::{self, BufRead};
+use unicode_segmentation::UnicodeSegmentation;
+
+pub const MAX_CHUNK_SIZE: usize = 4500; // A bit less than the 5000 byte API limit to be safe
+
+/// Splits the content into chunks based on paragraphs to respect the API limit.
+///
+/// Paragraphs are separated by blank lines and packed together while they fit into
+/// `max_chunk_size` bytes; paragraphs larger than that are split with [`split_paragraph`].
+pub fn split_into_chunks(content: &str, max_chunk_size: usize) -> Vec<String> {
+    let paragraphs: Vec<&str> = content.split("\n\n").filter(|p| !p.trim().is_empty()).collect();
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current_chunk = String::new();
+
+    for paragraph in paragraphs {
+        // If a single paragraph is too large, it must be split.
+        if paragraph.len() > max_chunk_size {
+            // Push the current chunk if it has anything, before we deal with the big one.
+            if !current_chunk.is_empty() {
+                chunks.push(current_chunk);
+                current_chunk = String::new();
+            }
+
+            // Split the large paragraph into smaller pieces.
+            chunks.extend(split_paragraph(paragraph, max_chunk_size).into_iter().map(str::to_string));
+        } else if current_chunk.len() + paragraph.len() + 2 > max_chunk_size {
+            // The paragraph fits in a chunk by itself, but not in the current one.
+            // So, push the current chunk and start a new one.
+            chunks.push(current_chunk);
+            current_chunk = String::from(paragraph);
+        } else {
+            // The paragraph fits in the current chunk.
+            if !current_chunk.is_empty() {
+                current_chunk.push_str("\n\n");
+            }
+            current_chunk.push_str(paragraph);
+        }
+    }
+    if !current_chunk.is_empty() {
+        chunks.push(current_chunk);
+    }
+
+    chunks
+}
+
+/// Splits a paragraph into pieces of at most `max_len` bytes.
+///
+/// Pieces end at sentence boundaries where possible. A sentence that doesn't fit is split
+/// between words, and a word that doesn't fit between grapheme clusters, so multi-byte
+/// characters, emoji sequences and scripts without spaces such as Japanese are never cut.
+/// The whitespace between pieces is dropped.
+pub fn split_paragraph(paragraph: &str, max_len: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    pack_units(paragraph, max_len, &mut pieces, 0);
+    pieces.into_iter().map(str::trim).filter(|piece| !piece.is_empty()).collect()
+}
+
+/// Greedily packs the units of `text` at the given level (sentences, words, graphemes) into
+/// pieces, going one level finer for units that are too large on their own.
+fn pack_units<'a>(text: &'a str, max_len: usize, pieces: &mut Vec<&'a str>, level: usize) {
+    let units: Vec<&str> = match level {
+        0 => text.split_sentence_bounds().collect(),
+        1 => text.split_word_bounds().collect(),
+        _ => text.graphemes(true).collect(),
+    };
+    let mut start = 0;
+    let mut end = 0;
+    for unit in units {
+        let unit_start = end;
+        end += unit.len();
+        if end - start <= max_len {
+            continue;
+        }
+        if unit_start > start {
+            pieces.push(&text[start..unit_start]);
+        }
+        start = unit_start;
+        if unit.len() > max_len {
+            if level < 2 {
+                pack_units(unit, max_len, pieces, level + 1);
+            } else {
+                // A single grapheme cluster larger than the limit can't be split any further.
+                pieces.push(unit);
+            }
+            start = end;
+        }
+    }
+    if end > start {
+        pieces.push(&text[start..end]);
+    }
+}
+
+/// Returns the longest prefix of `text` that is at most `max_len` bytes and ends on a character boundary.
+pub fn truncate_at_char_boundary(text: &str, max_len: usize) -> &str {
+    let mut end = text.len().min(max_len);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Packs segments into as few chunks as the size limit allows, joined by blank lines.
+///
+/// Returns the chunks together with the number of segments each one holds. A segment larger
+/// than `max_chunk_size` gets a chunk of its own.
+pub fn pack_segments(segments: &[&str], max_chunk_size: usize) -> Vec<(String, usize)> {
+    let mut chunks = Vec::new();
+    let mut current_chunk = String::new();
+    let mut current_count = 0;
+
+    for segment in segments {
+        if current_count > 0 && current_chunk.len() + segment.len() + 2 > max_chunk_size {
+            chunks.push((current_chunk, current_count));
+            current_chunk = String::new();
+            current_count = 0;
+        }
+        if current_count > 0 {
+            current_chunk.push_str("\n\n");
+        }
+        current_chunk.push_str(segment);
+        current_count += 1;
+    }
+    if current_count > 0 {
+        chunks.push((current_chunk, current_count));
+    }
+
+    chunks
+}
+
+/// Reads plain text in blocks of whole paragraphs, so files far larger than memory can be
+/// translated block by block.
+///
+/// A block ends at the first blank line after `block_size` bytes, with the blank lines
+/// included, so the blocks put back together give the original text. A paragraph running on
+/// for more than four times `block_size` is cut at a line break.
+pub struct Blocks<R> {
+    reader: R,
+    block_size: usize,
+    records: bool,
+}
+
+impl<R: BufRead> Blocks<R> {
+    pub fn new(reader: R, block_size: usize) -> Self {
+        Self { reader, block_size, records: false }
+    }
+
+    /// Ends blocks at the first line break after `block_size` bytes that isn't inside double
+    /// quotes, instead of at a blank line, for the rows of CSV files.
+    pub fn with_records(mut self) -> Self {
+        self.records = true;
+        self
+    }
+}
+
+impl<R: BufRead> Iterator for Blocks<R> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut block = String::new();
+        let mut quotes = 0;
+        loop {
+            if self.records && block.len() >= self.block_size && quotes % 2 == 0 {
+                break;
+            }
+            // Blank lines are taken along, so the next block starts with a paragraph.
+            let at_paragraph_end = block.ends_with("\n\n") || block.ends_with("\n\r\n");
+            let full = !self.records && block.len() >= self.block_size && at_paragraph_end;
+            let next_is_blank = match self.reader.fill_buf() {
+                Ok(buffer) => matches!(buffer.first(), Some(b'\n' | b'\r')),
+                Err(error) => return Some(Err(error)),
+            };
+            if (full && !next_is_blank) || (!self.records && block.len() >= 4 * self.block_size) {
+                break;
+            }
+            let start = block.len();
+            match self.reader.read_line(&mut block) {
+                Ok(0) => break,
+                Ok(_) => quotes += block[start..].matches('"').count(),
+                Err(error) => return Some(Err(error)),
+            }
+        }
+        (!block.is_empty()).then_some(Ok(block))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks that no piece is over the limit and that nothing but whitespace was lost.
+    fn assert_split(text: &str, max_len: usize) -> Vec<&str> {
+        let pieces = split_paragraph(text, max_len);
+        for piece in &pieces {
+            assert!(piece.len() <= max_len, "piece of {} bytes: {:?}", piece.len(), piece);
+        }
+        let without_whitespace = |s: &str| s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+        assert_eq!(without_whitespace(&pieces.concat()), without_whitespace(text));
+        pieces
+    }
+
+    #[test]
+    fn splits_hungarian_at_sentence_boundaries() {
+        let text = "Árvíztűrő tükörfúrógép. Öt szép szűzlány őrült írót nyúz. Egy hűtlen vejét fülöncsípő, dühös mexikói úr.";
+        let pieces = assert_split(text, 80);
+        assert_eq!(
+            pieces,
+            ["Árvíztűrő tükörfúrógép. Öt szép szűzlány őrült írót nyúz.", "Egy hűtlen vejét fülöncsípő, dühös mexikói úr."]
+        );
+    }
+
+    #[test]
+    fn splits_long_hungarian_sentence_between_words() {
+        let text = "Az ősz ágyú öt hűvös őrt űzött végig a széles völgyön át.";
+        let pieces = assert_split(text, 20);
+        assert!(pieces.len() > 1);
+        assert!(pieces.iter().all(|piece| !piece.starts_with(char::is_whitespace)));
+    }
+
+    #[test]
+    fn splits_japanese_without_spaces() {
+        let text = "吾輩は猫である。名前はまだ無い。どこで生れたかとんと見当がつかぬ。何でも薄暗いじめじめした所でニャーニャー泣いていた事だけは記憶している。";
+        let pieces = assert_split(text, 50);
+        assert_eq!(pieces[0], "吾輩は猫である。名前はまだ無い。");
+        // Even a sentence longer than the limit is split on character boundaries.
+        assert_split(text, 10);
+    }
+
+    #[test]
+    fn keeps_emoji_sequences_intact() {
+        let text = "👨‍👩‍👧‍👦👨‍👩‍👧‍👦👨‍👩‍👧‍👦 Family time! 🎉🎉🎉🇭🇺🇭🇺🇯🇵🇯🇵 Party! 👍🏽👍🏽👍🏽";
+        let pieces = assert_split(text, 30);
+        for piece in pieces {
+            // Every piece must still start with a complete grapheme cluster, not a joiner or modifier.
+            assert!(!piece.starts_with(['\u{200d}', '\u{1f3fd}']));
+            assert!(piece.graphemes(true).all(|g| text.contains(g)));
+        }
+    }
+
+    #[test]
+    fn oversized_paragraphs_are_chunked_without_panicking() {
+        let paragraph = "Ünnepi köszöntő 🎉 és 日本語のテキスト。".repeat(50);
+        let content = format!("Első bekezdés.\n\n{}\n\nUtolsó bekezdés.", paragraph);
+        let chunks = split_into_chunks(&content, 100);
+        assert!(chunks.iter().all(|chunk| chunk.len() <= 100));
+        assert_eq!(chunks.first().map(String::as_str), Some("Első bekezdés."));
+        assert_eq!(chunks.last().map(String::as_str), Some("Utolsó bekezdés."));
+    }
+
+    #[test]
+    fn truncates_on_character_boundaries() {
+        assert_eq!(truncate_at_char_boundary("árvíz", 2), "á");
+        assert_eq!(truncate_at_char_boundary("árvíz", 100), "árvíz");
+    }
+}
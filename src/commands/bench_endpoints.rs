@@ -0,0 +1,115 @@
+use clap::{ArgMatches, Args};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use text_translator::{Progress, RateLimiter, Translator, TranslatorError};
+
+use super::{config, BackendArgs, Subcommand};
+
+/// Send a small probe to every endpoint and rank them by reliability and speed, to pick the
+/// fastest mirror before a long run
+///
+/// The endpoints are those given with '--api-url', or the built-in public LibreTranslate
+/// mirrors along with those added with '--mirror'.
+#[derive(Args, Debug)]
+pub struct BenchEndpointsArgs {
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Number of probe requests sent to each endpoint
+    #[arg(long, default_value_t = 3)]
+    probes: u32,
+
+    /// The text to translate
+    #[arg(long, default_value = "The quick brown fox jumps over the lazy dog.")]
+    text: String,
+
+    /// Source language of the probe text
+    #[arg(short, long, default_value = "en")]
+    source: String,
+
+    /// Target language
+    #[arg(short, long, default_value = "hu")]
+    target: String,
+}
+
+impl Subcommand for BenchEndpointsArgs {
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.backend.apply_profile(profile, matches)
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+/// What the probes of one endpoint found.
+struct Measurement {
+    url: String,
+    answered: u32,
+    latencies: Vec<Duration>,
+    /// The requests left and the time until the reset, from the rate limit headers.
+    quota: Option<(u64, Duration)>,
+    last_error: Option<String>,
+}
+
+impl Measurement {
+    fn median_latency(&self) -> Option<Duration> {
+        let mut latencies = self.latencies.clone();
+        latencies.sort();
+        latencies.get(latencies.len() / 2).copied()
+    }
+}
+
+pub async fn run(args: BenchEndpointsArgs) -> Result<(), TranslatorError> {
+    if args.probes == 0 {
+        return Err("'--probes' must be at least 1".into());
+    }
+    let urls = args.backend.mirrors()?;
+    // The endpoints are probed at the same time; each one only gets its own probes one after the other.
+    let mut measurements = futures::future::try_join_all(urls.iter().map(|url| probe(&args, url))).await?;
+    // The most reliable endpoints first, the fastest of them at the top.
+    measurements.sort_by_key(|measurement| (std::cmp::Reverse(measurement.answered), measurement.median_latency().unwrap_or(Duration::MAX)));
+
+    let url_width = measurements.iter().map(|measurement| measurement.url.chars().count()).max().unwrap_or(0).max("ENDPOINT".len());
+    println!("{:>2}  {:<url_width$}  {:>8}  {:>7}  {:>10}  {:<18}  ERROR", "#", "ENDPOINT", "ANSWERED", "ERRORS", "LATENCY", "RATE LIMIT");
+    for (rank, measurement) in measurements.iter().enumerate() {
+        let errors = args.probes - measurement.answered;
+        let row = format!(
+            "{:>2}  {:<url_width$}  {:>8}  {:>6.0}%  {:>10}  {:<18}  {}",
+            rank + 1,
+            measurement.url,
+            format!("{}/{}", measurement.answered, args.probes),
+            f64::from(errors) * 100.0 / f64::from(args.probes),
+            measurement.median_latency().map_or_else(|| "-".to_string(), |latency| format!("{} ms", latency.as_millis())),
+            measurement.quota.map_or_else(
+                || "-".to_string(),
+                |(remaining, reset)| format!("{} left, {:.0} s", remaining, reset.as_secs_f64())
+            ),
+            measurement.last_error.as_deref().unwrap_or_default(),
+        );
+        println!("{}", row.trim_end());
+    }
+    Ok(())
+}
+
+/// Sends the probes to one endpoint, without retries so that failures count as such.
+async fn probe(args: &BenchEndpointsArgs, url: &str) -> Result<Measurement, TranslatorError> {
+    let backend = BackendArgs { max_retries: 0, ..args.backend.for_endpoint(url) };
+    // Follows the server's rate limit headers, so the probes themselves stay within the limit.
+    let limiter = Arc::new(RateLimiter::unlimited());
+    let translator = backend.build(Progress::hidden())?.with_rate_limiter(limiter.clone());
+    let mut measurement = Measurement { url: url.to_string(), answered: 0, latencies: Vec::new(), quota: None, last_error: None };
+    for _ in 0..args.probes {
+        limiter.acquire().await;
+        let sent = Instant::now();
+        match translator.translate(&args.text, &args.source, &args.target).await {
+            Ok(_) => {
+                measurement.answered += 1;
+                measurement.latencies.push(sent.elapsed());
+            }
+            Err(error) => measurement.last_error = Some(error.to_string()),
+        }
+    }
+    measurement.quota = limiter.quota();
+    Ok(measurement)
+}
@@ -0,0 +1,293 @@
+//! The checks of a translated file, run once all of its chunks are in.
+//!
+//! The requests some checks need, such as translating a chunk back or asking LanguageTool, are
+//! sent along with the chunk's translation; the checks here judge what came back. Each one
+//! warns about the problems it finds, adds them to the flags of the segments for the review
+//! sheet, and saves its report next to the output (or input) file.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use text_translator::backend::languagetool::GrammarIssue;
+use text_translator::consistency::{self, ConsistencyReport};
+use text_translator::entities;
+use text_translator::quality::{
+    self, AlteredEntities, EntityReport, FlaggedChunk, GrammarMistake, GrammarReport, GrammarSegment, RoundtripReport,
+};
+use text_translator::TranslatorError;
+
+use super::translate::{Console, LanguageCheck};
+
+/// A chunk of a file with its translation, and what was found out about it while translating.
+pub(super) struct TranslatedChunk<'a> {
+    /// The chunk as it was sent, with its segments separated by blank lines.
+    pub text: &'a str,
+    /// The translation, or the chunk marked as untranslated if it failed.
+    pub translation: String,
+    pub failed: bool,
+    pub segment_count: usize,
+    /// The language the translation seems to be written in instead of the target.
+    pub wrong_language: Option<&'static str>,
+    /// The translation translated back into the source language, with '--verify-roundtrip'.
+    pub back_translation: Option<Result<String, TranslatorError>>,
+    /// The mistakes LanguageTool found in the translation, with '--check-grammar'.
+    pub grammar: Option<Result<Vec<GrammarIssue>, TranslatorError>>,
+}
+
+/// The translated file the checks report on.
+#[derive(Clone, Copy)]
+pub(super) struct Checked<'a> {
+    pub input_file: &'a Path,
+    /// The output (or input) file the reports are saved next to; `None` for a pipeline from stdin to stdout.
+    pub sidecar_file: Option<&'a Path>,
+    pub source: &'a str,
+    pub target: &'a str,
+    pub console: Console,
+}
+
+/// The problems found with every segment of a file, for the review sheet.
+pub(super) struct Flags {
+    segments: Vec<Vec<String>>,
+    /// The index of the first segment of every chunk.
+    first_segments: Vec<usize>,
+}
+
+impl Flags {
+    /// Flags the segments of failed chunks as not translated.
+    pub fn new(chunks: &[TranslatedChunk]) -> Self {
+        let mut flags = Self { segments: Vec::new(), first_segments: Vec::new() };
+        for chunk in chunks {
+            flags.first_segments.push(flags.segments.len());
+            let flag = chunk.failed.then(|| "not translated".to_string());
+            flags.segments.extend(std::iter::repeat_n(flag.into_iter().collect(), chunk.segment_count));
+        }
+        flags
+    }
+
+    /// Adds a flag to all segments of a chunk.
+    fn chunk(&mut self, index: usize, flag: String) {
+        let first = self.first_segments[index];
+        let end = self.first_segments.get(index + 1).copied().unwrap_or(self.segments.len());
+        self.segments[first..end].iter_mut().for_each(|flags| flags.push(flag.clone()));
+    }
+
+    pub fn into_segments(self) -> Vec<Vec<String>> {
+        self.segments
+    }
+}
+
+/// Saves a report next to the checked file when it found something, or removes the one a
+/// previous run left there; `found` describes what was found, for the warning.
+fn save_report<R>(
+    file: &Checked,
+    report: &R,
+    found: Option<String>,
+    path_for: fn(&Path) -> PathBuf,
+    save: fn(&R, &Path) -> Result<(), TranslatorError>,
+) -> Result<(), TranslatorError> {
+    match (file.sidecar_file.map(path_for), found) {
+        (Some(report_path), Some(found)) => {
+            save(report, &report_path)?;
+            file.console.warn(format_args!("{}; see {:?}.", found, report_path));
+        }
+        (Some(report_path), None) if report_path.exists() => fs::remove_file(report_path)?,
+        (None, Some(found)) => file.console.warn(format_args!("{}.", found)),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Warns about the translations that seem to be written in another language than the target.
+pub(super) fn language(file: &Checked, chunks: &[TranslatedChunk], flags: &mut Flags, mode: LanguageCheck) {
+    let mut wrong_language_chunks = 0;
+    for (index, chunk) in chunks.iter().enumerate() {
+        let Some(language) = chunk.wrong_language else { continue };
+        wrong_language_chunks += 1;
+        flags.chunk(index, format!("seems to be in {}", language));
+        file.console.warn(format_args!("Warning: chunk {} seems to be in {}, not in {}.", index + 1, language, file.target));
+    }
+    if wrong_language_chunks > 0 {
+        let advice = match mode {
+            LanguageCheck::Retry => "even when translated again; check them",
+            _ => "check them, or run again with '--language-check retry'",
+        };
+        file.console.warn(format_args!(
+            "Warning: {} chunks don't seem to be translated into {}; {}.",
+            wrong_language_chunks, file.target, advice
+        ));
+    }
+}
+
+/// Scores the back-translations against the chunks with chrF, and lists those below `threshold`.
+pub(super) fn back_translation(
+    file: &Checked,
+    chunks: &[TranslatedChunk],
+    flags: &mut Flags,
+    threshold: f64,
+) -> Result<(), TranslatorError> {
+    let mut scores = Vec::new();
+    let mut flagged_chunks = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        match &chunk.back_translation {
+            Some(Ok(back_translation)) => {
+                let score = quality::chrf(back_translation, chunk.text);
+                scores.push(score);
+                if score < threshold {
+                    flags.chunk(index, format!("back-translation chrF {:.1}", score));
+                    flagged_chunks.push(FlaggedChunk {
+                        chunk: index + 1,
+                        score,
+                        text: chunk.text.to_string(),
+                        translation: chunk.translation.clone(),
+                        back_translation: back_translation.clone(),
+                    });
+                }
+            }
+            Some(Err(error)) => file.console.warn(format_args!("Chunk {} could not be translated back: {}", index + 1, error)),
+            None => {}
+        }
+    }
+    if scores.is_empty() {
+        return Ok(());
+    }
+
+    let report = RoundtripReport {
+        input_file: file.input_file.to_path_buf(),
+        source: file.source.to_string(),
+        target: file.target.to_string(),
+        threshold,
+        average_score: scores.iter().sum::<f64>() / scores.len() as f64,
+        chunks: flagged_chunks,
+    };
+    file.console.info(format_args!(
+        "Back-translation check: average chrF {:.1} over {} chunks, {} below {}.",
+        report.average_score,
+        scores.len(),
+        report.chunks.len(),
+        report.threshold
+    ));
+    // Without a file to save the report next to, the summary above is all there is.
+    let found = (file.sidecar_file.is_some() && !report.chunks.is_empty()).then(|| "Review the flagged chunks".to_string());
+    save_report(file, &report, found, RoundtripReport::path_for, RoundtripReport::save)
+}
+
+/// Lists the chunks whose translation altered or dropped names, product names or acronyms.
+pub(super) fn entities(file: &Checked, chunks: &[TranslatedChunk], flags: &mut Flags) -> Result<(), TranslatorError> {
+    let mut altered_entities = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate().filter(|(_, chunk)| !chunk.failed) {
+        let altered = entities::altered(chunk.text, &chunk.translation);
+        if !altered.is_empty() {
+            flags.chunk(index, format!("altered names: {}", altered.join(", ")));
+            altered_entities.push(AlteredEntities {
+                chunk: index + 1,
+                entities: altered,
+                text: chunk.text.to_string(),
+                translation: chunk.translation.clone(),
+            });
+        }
+    }
+
+    let report = EntityReport {
+        input_file: file.input_file.to_path_buf(),
+        source: file.source.to_string(),
+        target: file.target.to_string(),
+        chunks: altered_entities,
+    };
+    let found = (!report.chunks.is_empty())
+        .then(|| format!("Warning: {} chunks altered names, product names or acronyms", report.chunks.len()));
+    save_report(file, &report, found, EntityReport::path_for, EntityReport::save)
+}
+
+/// Compares the translations with their chunks for signs of a bad translation: a length far
+/// from the source's, paragraphs left untranslated, and numbers, dates, units or URLs dropped.
+pub(super) fn sanity(file: &Checked, chunks: &[TranslatedChunk], flags: &mut Flags) {
+    let mut sanity_issues = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate().filter(|(_, chunk)| !chunk.failed) {
+        let issues = quality::sanity_check(chunk.text, &chunk.translation);
+        for issue in &issues {
+            flags.chunk(index, issue.to_string());
+        }
+        sanity_issues.extend(issues.into_iter().map(|issue| (index + 1, issue)));
+    }
+    if !sanity_issues.is_empty() {
+        file.console.warn(format_args!("\nSanity check warnings ({}):", sanity_issues.len()));
+        for (chunk, issue) in &sanity_issues {
+            file.console.warn(format_args!("  chunk {}: {}", chunk, issue));
+        }
+    }
+}
+
+/// Sorts the mistakes LanguageTool found into the segments of the chunks and lists them.
+pub(super) fn grammar(file: &Checked, chunks: &[TranslatedChunk], flags: &mut Flags) -> Result<(), TranslatorError> {
+    let mut grammar_segments = Vec::new();
+    for (index, chunk) in chunks.iter().enumerate() {
+        let issues = match &chunk.grammar {
+            Some(Ok(issues)) if !chunk.failed => issues,
+            Some(Err(error)) => {
+                file.console.warn(format_args!("Chunk {} could not be checked for grammar: {}", index + 1, error));
+                continue;
+            }
+            _ => continue,
+        };
+        let translated = &chunk.translation;
+        // Mistakes in placeholder tokens don't count.
+        let mut start = 0;
+        for (i, segment) in translated.split("\n\n").enumerate() {
+            let end = start + segment.len();
+            let mistakes: Vec<GrammarMistake> = issues
+                .iter()
+                .filter(|issue| issue.start >= start && issue.end <= end && !translated[issue.start..issue.end].contains(['⟦', '⟧']))
+                .map(|issue| GrammarMistake {
+                    message: issue.message.clone(),
+                    excerpt: translated[issue.start..issue.end].to_string(),
+                    replacements: issue.replacements.iter().take(3).cloned().collect(),
+                })
+                .collect();
+            let segment_index = flags.first_segments[index] + i;
+            if let (false, Some(segment_flags)) = (mistakes.is_empty(), flags.segments.get_mut(segment_index)) {
+                segment_flags.extend(mistakes.iter().map(|mistake| format!("grammar: {} ({:?})", mistake.message, mistake.excerpt)));
+                grammar_segments.push(GrammarSegment { segment: segment_index + 1, translation: segment.to_string(), mistakes });
+            }
+            start = end + 2;
+        }
+    }
+
+    let mistakes: usize = grammar_segments.iter().map(|segment| segment.mistakes.len()).sum();
+    let report = GrammarReport { input_file: file.input_file.to_path_buf(), target: file.target.to_string(), segments: grammar_segments };
+    let found = (!report.segments.is_empty())
+        .then(|| format!("LanguageTool found {} mistakes in {} segments", mistakes, report.segments.len()));
+    save_report(file, &report, found, GrammarReport::path_for, GrammarReport::save)
+}
+
+/// Lists the terms translated in several ways across the `segments` of the file and, with
+/// `fix`, returns the translation of every segment with the most common rendering used throughout.
+pub(super) fn consistency(
+    file: &Checked,
+    segments: &[&str],
+    chunks: &[TranslatedChunk],
+    fix: bool,
+) -> Result<Option<Vec<String>>, TranslatorError> {
+    // The segments of failed chunks have no translation to compare.
+    let translations = chunks.iter().flat_map(|chunk| chunk.translation.split("\n\n").map(move |segment| (chunk.failed, segment)));
+    let pairs: Vec<(&str, &str)> = segments
+        .iter()
+        .copied()
+        .zip(translations.map(|(failed, segment)| if failed { "" } else { segment }))
+        .collect();
+    let report = ConsistencyReport {
+        input_file: file.input_file.to_path_buf(),
+        source: file.source.to_string(),
+        target: file.target.to_string(),
+        terms: consistency::check(&pairs),
+    };
+    let mut fixed_translations = None;
+    if fix && !report.terms.is_empty() {
+        let mut translations: Vec<String> =
+            chunks.iter().flat_map(|chunk| chunk.translation.split("\n\n")).map(str::to_string).collect();
+        let replaced = consistency::fix(&mut translations, &report.terms);
+        file.console.info(format_args!("Made {} translations of recurring terms consistent.", replaced));
+        fixed_translations = Some(translations).filter(|_| replaced > 0);
+    }
+    let found = (!report.terms.is_empty()).then(|| format!("{} terms were translated in several ways", report.terms.len()));
+    save_report(file, &report, found, ConsistencyReport::path_for, ConsistencyReport::save)?;
+    Ok(fixed_translations)
+}
@@ -0,0 +1,140 @@
+use arboard::Clipboard;
+use clap::{ArgMatches, Args};
+use std::time::Duration;
+use text_translator::TranslatorError;
+use text_translator::format::Format;
+use text_translator::{truncate_at_char_boundary, Progress, AUTO_LANGUAGE, MAX_CHUNK_SIZE};
+
+use super::translate::Pipeline;
+use super::{config, serve, BackendArgs, PipelineArgs, Subcommand};
+
+/// Translate the text in the clipboard and put the translation back into it
+///
+/// On Linux the clipboard belongs to the program that set it, so a clipboard manager has to
+/// keep the translation once this command exits; with '--watch-clipboard' it keeps it itself.
+#[derive(Args, Debug)]
+pub struct ClipArgs {
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Source language of the copied text (e.g., 'en', or 'auto' to detect it every time)
+    #[arg(short, long, default_value = AUTO_LANGUAGE)]
+    source: String,
+
+    /// Target language
+    #[arg(short, long, default_value = "hu")]
+    target: String,
+
+    /// Print the translation instead of putting it into the clipboard
+    #[arg(long)]
+    print: bool,
+
+    /// Keep running and translate everything copied to the clipboard, until Ctrl-C
+    #[arg(long)]
+    watch_clipboard: bool,
+
+    /// Seconds between two looks at the clipboard with '--watch-clipboard'
+    #[arg(long, value_name = "SECONDS", default_value_t = 0.5)]
+    poll_interval: f64,
+
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+}
+
+impl Subcommand for ClipArgs {
+    /// Fills in the options that weren't given on the command line from a config profile.
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.backend.apply_profile(profile, matches)?;
+        config::apply(matches, "source", &mut self.source, profile.source.clone());
+        let target = profile.target.as_ref().and_then(|target| target.to_vec().into_iter().next());
+        config::apply(matches, "target", &mut self.target, target);
+        self.pipeline.apply_profile(profile, matches);
+        Ok(())
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(args: ClipArgs) -> Result<(), TranslatorError> {
+    let mut clipboard = Clipboard::new().map_err(clipboard_error)?;
+    let limiter = args.pipeline.rate_limiter(&args.backend)?;
+    let translator = args.backend.build(Progress::hidden())?;
+    let no_translate = args.pipeline.text.no_translate()?;
+    let redactor = args.pipeline.text.redactor()?;
+    let glossary = args.pipeline.text.glossary()?;
+    let cache = args.pipeline.open_cache()?;
+    let pipeline = Pipeline {
+        translator: &translator,
+        cache: cache.as_ref(),
+        limiter: &limiter,
+        redactor: &redactor,
+        no_translate: &no_translate,
+        glossary: glossary.as_ref(),
+        skip_target_language: false,
+    };
+
+    if !args.watch_clipboard {
+        let text = clipboard.get_text().map_err(clipboard_error)?;
+        if text.trim().is_empty() {
+            return Err("The clipboard holds no text to translate".into());
+        }
+        let translation = translate(&pipeline, &text, &args.source, &args.target).await?;
+        return output(&mut clipboard, &args, translation);
+    }
+
+    let interval = Duration::try_from_secs_f64(args.poll_interval)?;
+    // What is in the clipboard when watching starts was copied before, so it isn't translated.
+    let mut last = clipboard.get_text().unwrap_or_default();
+    eprintln!("Translating everything copied to the clipboard into '{}' (Ctrl-C to stop)", args.target);
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        tokio::select! {
+            _ = &mut ctrl_c => return Ok(()),
+            _ = tokio::time::sleep(interval) => {}
+        }
+        // The clipboard is briefly unavailable while other programs write to it.
+        let Ok(text) = clipboard.get_text() else { continue };
+        if text == last || text.trim().is_empty() {
+            continue;
+        }
+        last = text.clone();
+        match translate(&pipeline, &text, &args.source, &args.target).await {
+            Ok(translation) => {
+                if !args.print {
+                    // Our own translation must not be translated again when it shows up in the clipboard.
+                    last = translation.clone();
+                }
+                output(&mut clipboard, &args, translation)?;
+            }
+            Err(error) => eprintln!("Error: {}", error),
+        }
+    }
+}
+
+/// Translates a piece of plain text, first detecting its language when the source is 'auto'.
+pub(super) async fn translate(pipeline: &Pipeline<'_>, text: &str, source: &str, target: &str) -> Result<String, TranslatorError> {
+    let source = if source == AUTO_LANGUAGE && pipeline.translator.supports_detection() {
+        let detections = pipeline.translator.detect(truncate_at_char_boundary(text, MAX_CHUNK_SIZE)).await?;
+        detections.into_iter().next().map_or_else(|| source.to_string(), |detection| detection.language)
+    } else {
+        source.to_string()
+    };
+    serve::translate(pipeline, Format::Text, text, (&source, target), MAX_CHUNK_SIZE).await
+}
+
+/// Puts the translation into the clipboard, or prints it with '--print'.
+fn output(clipboard: &mut Clipboard, args: &ClipArgs, translation: String) -> Result<(), TranslatorError> {
+    if args.print {
+        println!("{}", translation);
+        Ok(())
+    } else {
+        clipboard.set_text(translation).map_err(clipboard_error)
+    }
+}
+
+fn clipboard_error(error: arboard::Error) -> TranslatorError {
+    format!("Can't use the clipboard: {}", error).into()
+}
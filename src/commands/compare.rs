@@ -0,0 +1,249 @@
+use clap::{ArgMatches, Args, ValueEnum};
+use std::fs;
+use std::path::PathBuf;
+use text_translator::format::{self, Format, FormatOptions};
+use text_translator::quality;
+use text_translator::TranslatorError;
+use text_translator::{pack_segments, BackendKind, NoTranslate, Progress, RateLimiter, Redactor, MAX_CHUNK_SIZE};
+use unicode_width::UnicodeWidthChar;
+
+use super::translate::Pipeline;
+use super::{config, BackendArgs, Subcommand};
+
+/// How the two translations of a paragraph are shown.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompareLayout {
+    /// In two columns next to each other
+    #[default]
+    SideBySide,
+    /// As one text, with the words only the first backend used in '[-...-]' and those only the second used in '{+...+}'
+    Unified,
+}
+
+/// Translate a file with two backends and show the translations of every paragraph next to
+/// each other, to see which one suits a language pair better
+///
+/// The first backend is configured by the usual options, the second one by those ending in '-b'.
+/// The translations aren't cached, as the cache doesn't tell the backends apart.
+#[derive(Args, Debug)]
+pub struct CompareArgs {
+    /// Path to the file to translate
+    input_file: PathBuf,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// The first translation service (defaults to '--backend')
+    #[arg(long, value_enum)]
+    backend_a: Option<BackendKind>,
+
+    /// The second translation service
+    #[arg(long, value_enum)]
+    backend_b: BackendKind,
+
+    /// The API endpoint URL of the second service (defaults to its public endpoint)
+    #[arg(long, value_delimiter = ',')]
+    api_url_b: Vec<String>,
+
+    /// API key or token of the second service
+    #[arg(long, env = "TRANSLATOR_API_KEY_B", hide_env_values = true)]
+    api_key_b: Option<String>,
+
+    /// Source language (e.g., 'en')
+    #[arg(short, long, default_value = "en")]
+    source: String,
+
+    /// Target language
+    #[arg(short, long, default_value = "hu")]
+    target: String,
+
+    /// How the translations are shown
+    #[arg(long, value_enum, default_value_t)]
+    layout: CompareLayout,
+
+    /// Width of the side-by-side layout in columns
+    #[arg(long, default_value_t = 120, value_parser = clap::value_parser!(u16).range(20..))]
+    width: u16,
+
+    /// Maximum number of API requests per minute to each service (0 for no limit)
+    #[arg(long, default_value_t = 6)]
+    requests_per_minute: u32,
+}
+
+impl Subcommand for CompareArgs {
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.backend.apply_profile(profile, matches)?;
+        config::apply(matches, "source", &mut self.source, profile.source.clone());
+        let target = profile.target.as_ref().and_then(|target| target.to_vec().into_iter().next());
+        config::apply(matches, "target", &mut self.target, target);
+        config::apply(matches, "requests_per_minute", &mut self.requests_per_minute, profile.requests_per_minute);
+        Ok(())
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(args: CompareArgs) -> Result<(), TranslatorError> {
+    let backend_a = match args.backend_a {
+        Some(kind) if kind != args.backend.kind() => args.backend.for_backend(kind, &[], None),
+        _ => args.backend.clone(),
+    };
+    let backend_b = args.backend.for_backend(args.backend_b, &args.api_url_b, args.api_key_b.as_deref());
+    let format = Format::from_path(&args.input_file);
+    let options = FormatOptions { max_segment_len: MAX_CHUNK_SIZE, ..FormatOptions::default() };
+    let document = format::parse(format, &fs::read_to_string(&args.input_file)?.replace("\r\n", "\n"), &options)?;
+    let segments = document.segments();
+
+    let limiter = |backend: &BackendArgs| -> Result<RateLimiter, TranslatorError> {
+        Ok(backend.rate_limiter(args.requests_per_minute, None)?.unwrap_or_else(RateLimiter::unlimited))
+    };
+    let (limiter_a, limiter_b) = (limiter(&backend_a)?, limiter(&backend_b)?);
+    let text_format = format.text_format();
+    let (translator_a, translator_b) =
+        (backend_a.build(Progress::hidden())?.with_text_format(text_format), backend_b.build(Progress::hidden())?.with_text_format(text_format));
+    let no_translate = NoTranslate::new(&[])?;
+    let redactor = Redactor::new(false, &[])?;
+    let pipeline = |translator, limiter| Pipeline {
+        translator,
+        cache: None,
+        limiter,
+        redactor: &redactor,
+        no_translate: &no_translate,
+        glossary: None,
+        skip_target_language: false,
+    };
+    let (pipeline_a, pipeline_b) = (pipeline(&translator_a, &limiter_a), pipeline(&translator_b, &limiter_b));
+
+    let (mut translations_a, mut translations_b) = (Vec::new(), Vec::new());
+    // Both services work on a chunk at the same time, each at its own pace.
+    for (chunk, count) in pack_segments(&segments, MAX_CHUNK_SIZE) {
+        let (a, b) = tokio::join!(
+            pipeline_a.translate(&chunk, count, &args.source, &args.target),
+            pipeline_b.translate(&chunk, count, &args.source, &args.target)
+        );
+        translations_a.extend(a?.0.split("\n\n").map(str::to_string));
+        translations_b.extend(b?.0.split("\n\n").map(str::to_string));
+    }
+
+    let name = |backend: &BackendArgs| backend.kind().to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default();
+    let (name_a, name_b) = (name(&backend_a), name(&backend_b));
+    let (mut identical, mut scores) = (0, Vec::new());
+    for (index, text) in document.texts().iter().enumerate() {
+        let a = document.restore(index, translations_a[index].trim());
+        let b = document.restore(index, translations_b[index].trim());
+        println!("--- Paragraph {} ---", index + 1);
+        println!("{}", text.trim());
+        println!();
+        if a == b {
+            identical += 1;
+            println!("= {}", a);
+        } else {
+            match args.layout {
+                CompareLayout::SideBySide => print_side_by_side((&name_a, &a), (&name_b, &b), usize::from(args.width)),
+                CompareLayout::Unified => println!("{}", word_diff(&a, &b)),
+            }
+        }
+        println!();
+        scores.push(quality::chrf(&a, &b));
+    }
+    if !scores.is_empty() {
+        println!(
+            "{} of {} paragraphs translated the same by {} and {}; average chrF similarity {:.1}.",
+            identical,
+            scores.len(),
+            name_a,
+            name_b,
+            scores.iter().sum::<f64>() / scores.len() as f64
+        );
+    }
+    Ok(())
+}
+
+/// Prints two texts in columns next to each other, under the names of their backends.
+fn print_side_by_side((name_a, a): (&str, &str), (name_b, b): (&str, &str), width: usize) {
+    let column = (width - 3) / 2;
+    let (lines_a, lines_b) = (wrap(a, column), wrap(b, column));
+    println!("{} | {}", pad(name_a, column), name_b);
+    for i in 0..lines_a.len().max(lines_b.len()) {
+        let left = lines_a.get(i).map_or("", String::as_str);
+        let right = lines_b.get(i).map_or("", String::as_str);
+        println!("{}", format!("{} | {}", pad(left, column), right).trim_end());
+    }
+}
+
+/// Breaks text into lines of at most `width` columns between words; longer words are broken up.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && word_width(&line) + 1 + word_width(word) > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            for c in word.chars() {
+                if word_width(&line) + c.width().unwrap_or(0) > width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+fn word_width(word: &str) -> usize {
+    word.chars().map(|c| c.width().unwrap_or(0)).sum()
+}
+
+/// Pads text with spaces to `width` columns.
+fn pad(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(word_width(text))))
+}
+
+/// The words of `b` with those only in `a` in `[-...-]` and those only in `b` in `{+...+}`,
+/// after their longest common subsequence.
+fn word_diff(a: &str, b: &str) -> String {
+    let (a, b): (Vec<&str>, Vec<&str>) = (a.split_whitespace().collect(), b.split_whitespace().collect());
+    // common[i][j]: the length of the longest common subsequence of a[i..] and b[j..].
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] { common[i + 1][j + 1] + 1 } else { common[i + 1][j].max(common[i][j + 1]) };
+        }
+    }
+    let mut words = Vec::new();
+    let (mut removed, mut added): (Vec<&str>, Vec<&str>) = (Vec::new(), Vec::new());
+    let flush = |words: &mut Vec<String>, removed: &mut Vec<&str>, added: &mut Vec<&str>| {
+        if !removed.is_empty() {
+            words.push(format!("[-{}-]", removed.join(" ")));
+            removed.clear();
+        }
+        if !added.is_empty() {
+            words.push(format!("{{+{}+}}", added.join(" ")));
+            added.clear();
+        }
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            flush(&mut words, &mut removed, &mut added);
+            words.push(a[i].to_string());
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && common[i + 1][j] >= common[i][j + 1]) {
+            removed.push(a[i]);
+            i += 1;
+        } else {
+            added.push(b[j]);
+            j += 1;
+        }
+    }
+    flush(&mut words, &mut removed, &mut added);
+    words.join(" ")
+}
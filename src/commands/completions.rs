@@ -0,0 +1,21 @@
+use clap::{Args, Command};
+use clap_complete::Shell;
+use std::io::{self, Write};
+use text_translator::TranslatorError;
+
+/// Print a completion script for a shell, e.g. 'text-translator completions bash > ~/.local/share/bash-completion/completions/text-translator'
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    /// The shell to generate the script for
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+pub fn run(args: CompletionsArgs, mut command: Command) -> Result<(), TranslatorError> {
+    let name = command.get_name().to_string();
+    // Written in one go, so a closed pipe is an error rather than a panic inside the generator.
+    let mut script = Vec::new();
+    clap_complete::generate(args.shell, &mut command, name, &mut script);
+    io::stdout().write_all(&script)?;
+    Ok(())
+}
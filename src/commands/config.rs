@@ -0,0 +1,209 @@
+//! Settings from `~/.config/translator/config.toml`, so options used on every run don't have to
+//! be repeated on the command line.
+//!
+//! Top-level keys apply to every run; a `[profiles.<name>]` table selected with `--profile`
+//! overrides them. Options given on the command line (or through their environment variable)
+//! override both.
+//!
+//! ```toml
+//! requests_per_minute = 6
+//!
+//! [profiles.work]
+//! backend = "deepl"
+//! api_key = "..."
+//! api_url = ["https://mirror-1.example/translate", "https://mirror-2.example/translate"]
+//! target = ["hu", "de"]
+//! glossary = "work-glossary.csv"
+//! ```
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ValueEnum};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use text_translator::TranslatorError;
+
+/// Settings for one run. Every key is optional and named like the command-line option.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct Profile {
+    pub backend: Option<String>,
+    /// An endpoint, or mirrors to fail over to in order.
+    pub api_url: Option<List>,
+    pub round_robin: Option<bool>,
+    /// Public mirrors to add to the built-in ones with `round_robin`.
+    pub mirror: Option<List>,
+    pub mirror_requests_per_minute: Option<u32>,
+    pub api_key: Option<String>,
+    pub ensemble: Option<List>,
+    pub post_edit: Option<bool>,
+    pub llm_url: Option<String>,
+    pub llm_model: Option<String>,
+    pub llm_api_key: Option<String>,
+    pub ollama_url: Option<String>,
+    pub region: Option<String>,
+    pub model: Option<String>,
+    pub formality: Option<String>,
+    pub domain: Option<String>,
+    pub prompt_template: Option<PathBuf>,
+    pub offline_command: Option<String>,
+    pub batch: Option<bool>,
+    pub proxy: Option<String>,
+    pub ca_cert: Option<PathBuf>,
+    pub insecure: Option<bool>,
+    pub timeout: Option<f64>,
+    pub connect_timeout: Option<f64>,
+    pub max_retries: Option<u32>,
+    pub retry_base_delay: Option<f64>,
+    pub source: Option<String>,
+    pub target: Option<List>,
+    pub glossary: Option<PathBuf>,
+    pub no_translate_patterns: Option<Vec<String>>,
+    pub front_matter_fields: Option<Vec<String>>,
+    pub keys: Option<Vec<String>>,
+    pub protect_placeholders: Option<bool>,
+    pub redact: Option<bool>,
+    pub redact_patterns: Option<Vec<String>>,
+    pub skip_target_language: Option<bool>,
+    pub cache_file: Option<PathBuf>,
+    pub concurrency: Option<u32>,
+    pub requests_per_minute: Option<u32>,
+    pub request_delay: Option<f64>,
+    pub adaptive_pacing: Option<bool>,
+    pub chunk_size: Option<u64>,
+}
+
+/// One value, a comma-separated list of them, or an array, like `target = "hu,de"`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum List {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl List {
+    pub fn to_vec(&self) -> Vec<String> {
+        match self {
+            List::One(values) => values.split(',').map(|value| value.trim().to_string()).collect(),
+            List::Many(values) => values.clone(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Config {
+    #[serde(flatten)]
+    defaults: Profile,
+    #[serde(default)]
+    profiles: HashMap<String, Profile>,
+}
+
+/// The config file read when `--config` isn't given.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("translator").join("config.toml"))
+}
+
+/// Loads the settings of the selected profile, on top of the file's top-level settings.
+///
+/// A missing default config file means no settings; a missing `--config` file or an unknown
+/// profile is an error. Relative paths in the file are relative to the file itself.
+pub fn load(path: Option<&Path>, profile: Option<&str>) -> Result<Profile, TranslatorError> {
+    let path = match path {
+        Some(path) => Some(path.to_path_buf()),
+        None => default_path().filter(|path| path.exists()),
+    };
+    let Some(path) = path else {
+        return match profile {
+            Some(profile) => Err(format!("Profile '{}' not found: there is no config file", profile).into()),
+            None => Ok(Profile::default()),
+        };
+    };
+    let content = fs::read_to_string(&path).map_err(|e| format!("Cannot read config file {:?}: {}", path, e))?;
+    let mut config: Config = toml::from_str(&content).map_err(|e| format!("Invalid config file {:?}: {}", path, e))?;
+
+    let mut settings = config.defaults;
+    if let Some(name) = profile {
+        let profile = config
+            .profiles
+            .remove(name)
+            .ok_or_else(|| format!("Profile '{}' not found in {:?}", name, path))?;
+        settings = profile.or(settings);
+    }
+    if let Some(dir) = path.parent() {
+        for file in [&mut settings.prompt_template, &mut settings.glossary, &mut settings.cache_file, &mut settings.ca_cert] {
+            if let Some(file) = file.as_mut().filter(|file| file.is_relative()) {
+                *file = dir.join(&*file);
+            }
+        }
+    }
+    Ok(settings)
+}
+
+impl Profile {
+    /// Takes the settings missing from this profile from `defaults`.
+    fn or(self, defaults: Profile) -> Profile {
+        Profile {
+            backend: self.backend.or(defaults.backend),
+            api_url: self.api_url.or(defaults.api_url),
+            round_robin: self.round_robin.or(defaults.round_robin),
+            mirror: self.mirror.or(defaults.mirror),
+            mirror_requests_per_minute: self.mirror_requests_per_minute.or(defaults.mirror_requests_per_minute),
+            api_key: self.api_key.or(defaults.api_key),
+            ensemble: self.ensemble.or(defaults.ensemble),
+            post_edit: self.post_edit.or(defaults.post_edit),
+            llm_url: self.llm_url.or(defaults.llm_url),
+            llm_model: self.llm_model.or(defaults.llm_model),
+            llm_api_key: self.llm_api_key.or(defaults.llm_api_key),
+            ollama_url: self.ollama_url.or(defaults.ollama_url),
+            region: self.region.or(defaults.region),
+            model: self.model.or(defaults.model),
+            formality: self.formality.or(defaults.formality),
+            domain: self.domain.or(defaults.domain),
+            prompt_template: self.prompt_template.or(defaults.prompt_template),
+            offline_command: self.offline_command.or(defaults.offline_command),
+            batch: self.batch.or(defaults.batch),
+            proxy: self.proxy.or(defaults.proxy),
+            ca_cert: self.ca_cert.or(defaults.ca_cert),
+            insecure: self.insecure.or(defaults.insecure),
+            timeout: self.timeout.or(defaults.timeout),
+            connect_timeout: self.connect_timeout.or(defaults.connect_timeout),
+            max_retries: self.max_retries.or(defaults.max_retries),
+            retry_base_delay: self.retry_base_delay.or(defaults.retry_base_delay),
+            source: self.source.or(defaults.source),
+            target: self.target.or(defaults.target),
+            glossary: self.glossary.or(defaults.glossary),
+            no_translate_patterns: self.no_translate_patterns.or(defaults.no_translate_patterns),
+            front_matter_fields: self.front_matter_fields.or(defaults.front_matter_fields),
+            keys: self.keys.or(defaults.keys),
+            protect_placeholders: self.protect_placeholders.or(defaults.protect_placeholders),
+            redact: self.redact.or(defaults.redact),
+            redact_patterns: self.redact_patterns.or(defaults.redact_patterns),
+            skip_target_language: self.skip_target_language.or(defaults.skip_target_language),
+            cache_file: self.cache_file.or(defaults.cache_file),
+            concurrency: self.concurrency.or(defaults.concurrency),
+            requests_per_minute: self.requests_per_minute.or(defaults.requests_per_minute),
+            request_delay: self.request_delay.or(defaults.request_delay),
+            adaptive_pacing: self.adaptive_pacing.or(defaults.adaptive_pacing),
+            chunk_size: self.chunk_size.or(defaults.chunk_size),
+        }
+    }
+}
+
+/// Whether an option was given on the command line or through its environment variable.
+pub fn is_explicit(matches: &ArgMatches, id: &str) -> bool {
+    matches!(matches.value_source(id), Some(ValueSource::CommandLine | ValueSource::EnvVariable))
+}
+
+/// Overwrites an option with a setting from the config file, unless the option was given explicitly.
+pub fn apply<T>(matches: &ArgMatches, id: &str, option: &mut T, setting: Option<T>) {
+    if let (false, Some(setting)) = (is_explicit(matches, id), setting) {
+        *option = setting;
+    }
+}
+
+/// Parses a setting naming one of the values of a command-line enum, like `backend = "deepl"`.
+pub fn parse_enum<T: ValueEnum>(key: &str, value: Option<&str>) -> Result<Option<T>, TranslatorError> {
+    value
+        .map(|value| T::from_str(value, true).map_err(|_| format!("Invalid value '{}' for '{}' in the config file", value, key).into()))
+        .transpose()
+}
@@ -0,0 +1,42 @@
+use clap::{ArgMatches, Args};
+use std::fs;
+use std::path::PathBuf;
+use text_translator::TranslatorError;
+use text_translator::{truncate_at_char_boundary, Progress, MAX_CHUNK_SIZE};
+
+use super::{config, BackendArgs, Subcommand};
+
+/// Detect the language of a text file
+#[derive(Args, Debug)]
+pub struct DetectArgs {
+    /// Path to the text file to examine
+    input_file: PathBuf,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+}
+
+impl Subcommand for DetectArgs {
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.backend.apply_profile(profile, matches)
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(args: DetectArgs) -> Result<(), TranslatorError> {
+    let content = fs::read_to_string(&args.input_file)?;
+    let translator = args.backend.build(Progress::hidden())?;
+    // A sample from the beginning is enough, and keeps the request under the API size limit.
+    let detections = translator.detect(truncate_at_char_boundary(&content, MAX_CHUNK_SIZE)).await?;
+
+    if detections.is_empty() {
+        println!("Could not detect the language of {:?}.", args.input_file);
+    }
+    for detection in &detections {
+        println!("{:<8} {:.1}%", detection.language, detection.confidence);
+    }
+    Ok(())
+}
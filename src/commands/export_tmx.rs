@@ -0,0 +1,58 @@
+use clap::{ArgMatches, Args};
+use std::path::PathBuf;
+use text_translator::TranslatorError;
+use text_translator::{Tmx, TranslationCache};
+
+use super::{config, Subcommand};
+
+/// Save the cached translations of a language pair as a TMX translation memory
+#[derive(Args, Debug)]
+pub struct ExportTmxArgs {
+    /// The TMX file to write
+    tmx_file: PathBuf,
+
+    /// Source language of the translations
+    #[arg(short, long, default_value = "en")]
+    source: String,
+
+    /// Target language of the translations, or several separated by commas
+    #[arg(short, long, default_value = "hu", value_delimiter = ',')]
+    target: Vec<String>,
+
+    /// Translation cache database (defaults to 'translator/cache.sqlite' in the user's cache directory)
+    #[arg(long)]
+    cache_file: Option<PathBuf>,
+}
+
+impl Subcommand for ExportTmxArgs {
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        config::apply(matches, "source", &mut self.source, profile.source.clone());
+        config::apply(matches, "target", &mut self.target, profile.target.as_ref().map(config::List::to_vec));
+        config::apply(matches, "cache_file", &mut self.cache_file, profile.cache_file.clone().map(Some));
+        Ok(())
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(args: ExportTmxArgs) -> Result<(), TranslatorError> {
+    let cache_file = args
+        .cache_file
+        .or_else(TranslationCache::default_path)
+        .ok_or("No cache directory found; give the cache with '--cache-file'")?;
+    let cache = TranslationCache::open(&cache_file)?;
+
+    let mut tmx = Tmx::new(&args.source);
+    for target in &args.target {
+        let entries = cache.entries(&args.source, target)?;
+        println!("{} -> {}: {} segments", args.source, target, entries.len());
+        for (text, translation) in entries {
+            tmx.add(&text, target, &translation);
+        }
+    }
+    tmx.save(&args.tmx_file)?;
+    println!("Translation memory saved to: {:?}", args.tmx_file);
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use clap::{ArgMatches, Args};
+use std::fs;
+use std::path::PathBuf;
+use text_translator::format::{self, Format, FormatOptions};
+use text_translator::output;
+use text_translator::{ReviewSheet, TranslatorError, MAX_CHUNK_SIZE};
+
+use super::{config, Subcommand};
+
+/// Patch the translations edited in a review sheet of 'translate --export-review' back into
+/// the translated file
+///
+/// The translated file is written again from the original with the translations of the
+/// sheet; segments left without a translation keep their original text.
+#[derive(Args, Debug)]
+pub struct ImportReviewArgs {
+    /// The edited review sheet
+    review_file: PathBuf,
+
+    /// The original file the sheet was exported from
+    input_file: PathBuf,
+
+    /// Where to save the patched translation
+    output_file: PathBuf,
+
+    /// Format of the original (guessed from the file extension by default)
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// Target language of the translation, for formats that record it (e.g. XLIFF)
+    #[arg(short, long)]
+    target: Option<String>,
+
+    /// The maximum chunk size the translation was made with, which decides how long paragraphs are split
+    #[arg(long, default_value_t = MAX_CHUNK_SIZE as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    chunk_size: u64,
+}
+
+impl Subcommand for ImportReviewArgs {
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        let target = profile.target.as_ref().and_then(|targets| targets.to_vec().into_iter().next());
+        config::apply(matches, "target", &mut self.target, target.map(Some));
+        config::apply(matches, "chunk_size", &mut self.chunk_size, profile.chunk_size);
+        Ok(())
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(args: ImportReviewArgs) -> Result<(), TranslatorError> {
+    let format = args.format.unwrap_or_else(|| Format::from_path(&args.input_file));
+    if matches!(format, Format::Epub | Format::Docx | Format::Pdf) {
+        return Err(format!("Review sheets can't be imported into {:?} files", format).into());
+    }
+    let mut sheet = ReviewSheet::load(&args.review_file)?;
+    sheet.rows.sort_by_key(|row| row.id);
+
+    let raw = fs::read_to_string(&args.input_file)?;
+    let crlf = raw.contains("\r\n");
+    let options = FormatOptions { max_segment_len: args.chunk_size as usize, target_language: args.target.clone(), ..FormatOptions::default() };
+    let document = format::parse(format, &raw.replace("\r\n", "\n"), &options)?;
+    let sources = document.texts();
+    if sheet.rows.len() != sources.len() {
+        return Err(format!(
+            "{:?} has {} rows but {:?} has {} segments; was the sheet exported from that file?",
+            args.review_file,
+            sheet.rows.len(),
+            args.input_file,
+            sources.len()
+        )
+        .into());
+    }
+    // Every row has to belong to the segment of its id, or the translations would end up in the wrong places.
+    for (index, (row, source)) in sheet.rows.iter().zip(&sources).enumerate() {
+        if row.id != index + 1 || row.source.trim() != source.trim() {
+            return Err(format!(
+                "Row {} of {:?} doesn't match segment {} of {:?}; was the sheet exported from that file with the same '--chunk-size'?",
+                row.id,
+                args.review_file,
+                index + 1,
+                args.input_file
+            )
+            .into());
+        }
+    }
+
+    let untranslated = sheet.rows.iter().filter(|row| row.translation.trim().is_empty()).count();
+    let texts: Vec<String> = sheet
+        .rows
+        .iter()
+        .zip(sources)
+        .map(|(row, source)| if row.translation.trim().is_empty() { source } else { row.translation.clone() })
+        .collect();
+    let text = document.render_texts(&texts);
+    output::write_atomic(&args.output_file, if crlf { text.replace('\n', "\r\n") } else { text })?;
+    println!("Patched {} segments into {:?}.", texts.len() - untranslated, args.output_file);
+    if untranslated > 0 {
+        println!("{} segments without a translation were left in the original language.", untranslated);
+    }
+    Ok(())
+}
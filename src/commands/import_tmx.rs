@@ -0,0 +1,56 @@
+use clap::{ArgMatches, Args};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use text_translator::TranslatorError;
+use text_translator::translator::primary_language;
+use text_translator::{Tmx, TranslationCache};
+
+use super::{config, Subcommand};
+
+/// Fill the translation cache from a TMX translation memory, so its segments are never sent to the server
+#[derive(Args, Debug)]
+pub struct ImportTmxArgs {
+    /// The TMX file to import
+    tmx_file: PathBuf,
+
+    /// Translation cache database (defaults to 'translator/cache.sqlite' in the user's cache directory)
+    #[arg(long)]
+    cache_file: Option<PathBuf>,
+}
+
+impl Subcommand for ImportTmxArgs {
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        config::apply(matches, "cache_file", &mut self.cache_file, profile.cache_file.clone().map(Some));
+        Ok(())
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(args: ImportTmxArgs) -> Result<(), TranslatorError> {
+    let tmx = Tmx::load(&args.tmx_file)?;
+    let cache_file = args
+        .cache_file
+        .or_else(TranslationCache::default_path)
+        .ok_or("No cache directory found; give the cache with '--cache-file'")?;
+    let cache = TranslationCache::open(&cache_file)?;
+
+    // Regional variants like `en-US` are stored under the language codes given to 'translate'.
+    let source = primary_language(&tmx.source_language);
+    let mut imported = BTreeMap::new();
+    for unit in &tmx.units {
+        for (language, translation) in &unit.translations {
+            let target = primary_language(language);
+            cache.put(&source, &target, &unit.source, translation)?;
+            *imported.entry(target).or_insert(0) += 1;
+        }
+    }
+
+    for (target, count) in &imported {
+        println!("{} -> {}: {} segments", source, target, count);
+    }
+    println!("Imported into {:?}.", cache_file);
+    Ok(())
+}
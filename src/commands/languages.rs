@@ -0,0 +1,42 @@
+use clap::{ArgMatches, Args};
+use text_translator::{Progress, TranslatorError};
+
+use super::{config, BackendArgs, Subcommand};
+
+/// List the languages supported by the translation server and the pairs it can translate
+#[derive(Args, Debug)]
+pub struct LanguagesArgs {
+    #[command(flatten)]
+    backend: BackendArgs,
+}
+
+impl Subcommand for LanguagesArgs {
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.backend.apply_profile(profile, matches)
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(args: LanguagesArgs) -> Result<(), TranslatorError> {
+    let translator = args.backend.build(Progress::hidden())?;
+    let languages = translator.languages().await?;
+
+    let name_width = languages.iter().map(|l| l.name.chars().count()).max().unwrap_or(0);
+    for language in &languages {
+        if language.targets.is_empty() {
+            println!("{:<8} {}", language.code, language.name);
+        } else {
+            println!(
+                "{:<8} {:<width$}  -> {}",
+                language.code,
+                language.name,
+                language.targets.join(", "),
+                width = name_width
+            );
+        }
+    }
+    Ok(())
+}
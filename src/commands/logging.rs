@@ -0,0 +1,99 @@
+use indicatif::{MultiProgress, ProgressBar};
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use text_translator::TranslatorError;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::prelude::*;
+
+/// Only this crate's own events are logged; the HTTP libraries are far too chatty.
+const TARGET: &str = "text_translator";
+
+/// Whether only errors are shown ('--quiet').
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Whether progress bars are drawn: not with '--quiet' or '--no-progress', nor when stderr is
+/// a file or a pipe, like in CI logs.
+static SHOW_PROGRESS: AtomicBool = AtomicBool::new(true);
+
+/// Progress bars are drawn through this, so log messages are printed above them instead of across them.
+fn bars() -> &'static MultiProgress {
+    static BARS: OnceLock<MultiProgress> = OnceLock::new();
+    BARS.get_or_init(MultiProgress::new)
+}
+
+/// Draws `bar` below the log messages, or returns a hidden bar when progress isn't shown.
+pub fn add_bar(bar: ProgressBar) -> ProgressBar {
+    if !SHOW_PROGRESS.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    bars().add(bar)
+}
+
+/// Whether status messages and warnings are left out, so that only errors are printed.
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Stops drawing `bar`, e.g. that of a file once it is done.
+pub fn remove_bar(bar: &ProgressBar) {
+    bars().remove(bar);
+}
+
+/// Runs `f` with the progress bars hidden, so what it prints isn't drawn over.
+pub fn suspend<R>(f: impl FnOnce() -> R) -> R {
+    bars().suspend(f)
+}
+
+/// Writes log messages to stderr, with the progress bars hidden while doing so.
+struct Console;
+
+impl Write for Console {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        bars().suspend(|| io::stderr().write_all(buf))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stderr().flush()
+    }
+}
+
+/// Sets up logging: warnings on the console (only errors when `quiet`), and with `-v` or `-vv`
+/// information and the requests sent too. The log file gets the request and response metadata
+/// of every run, and with `-vv` the bodies as well.
+pub fn init(verbose: u8, quiet: bool, no_progress: bool, log_file: Option<&Path>) -> Result<(), TranslatorError> {
+    QUIET.store(quiet, Ordering::Relaxed);
+    SHOW_PROGRESS.store(!quiet && !no_progress && io::stderr().is_terminal(), Ordering::Relaxed);
+    let console_level = match verbose {
+        _ if quiet => LevelFilter::ERROR,
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::INFO,
+        _ => LevelFilter::DEBUG,
+    };
+    let console = tracing_subscriber::fmt::layer()
+        .without_time()
+        .with_target(false)
+        .with_ansi(io::stderr().is_terminal())
+        .with_writer(|| Console)
+        .with_filter(Targets::new().with_target(TARGET, console_level));
+
+    let file = match log_file {
+        Some(path) => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            let file_level = if verbose >= 2 { LevelFilter::TRACE } else { LevelFilter::DEBUG };
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .with_filter(Targets::new().with_target(TARGET, file_level));
+            Some(layer)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry().with(console).with(file).init();
+    Ok(())
+}
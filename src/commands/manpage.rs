@@ -0,0 +1,26 @@
+use clap::{Args, Command};
+use clap_mangen::Man;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use text_translator::TranslatorError;
+
+/// Print the man page, or write the pages of the tool and all its subcommands into a directory
+#[derive(Args, Debug)]
+pub struct ManpageArgs {
+    /// Write 'text-translator.1' and a 'text-translator-<subcommand>.1' page for every subcommand into this directory
+    #[arg(short, long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+}
+
+pub fn run(args: ManpageArgs, command: Command) -> Result<(), TranslatorError> {
+    match args.output_dir {
+        Some(dir) => {
+            fs::create_dir_all(&dir)?;
+            clap_mangen::generate_to(command, &dir)?;
+            println!("Man pages written to {:?}", dir);
+        }
+        None => Man::new(command).render(&mut io::stdout())?,
+    }
+    Ok(())
+}
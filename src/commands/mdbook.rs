@@ -0,0 +1,153 @@
+use clap::{ArgMatches, Args};
+use std::fs;
+use std::path::{Path, PathBuf};
+use text_translator::TranslatorError;
+use text_translator::format::Format;
+use tracing::error;
+
+use super::{config, Subcommand};
+use super::logging;
+use super::translate::{self, TranslateOptions};
+
+/// Keeps mdBook's `{{#include file.rs}}` and other preprocessor helpers untranslated.
+const HELPER_PATTERN: &str = r"\{\{#[^}]*\}\}";
+
+/// Translate an mdBook project, writing a copy of the book for each target language
+#[derive(Args, Debug)]
+pub struct MdbookArgs {
+    /// Directory of the book, with its 'book.toml'
+    #[arg(required = true)]
+    book_dir: PathBuf,
+
+    /// Directory the translated book is written to, where '{target}' is replaced by the target
+    /// language (default: 'book-{target}' next to the book directory)
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    options: TranslateOptions,
+}
+
+impl Subcommand for MdbookArgs {
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.options.apply_profile(profile, matches)
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(args: MdbookArgs) -> Result<(), TranslatorError> {
+    if args.options.export_tmx.is_some() || args.options.export_review.is_some() || args.options.emit_alignment.is_some() || args.options.stats_json.is_some() || args.options.watch {
+        return Err("'--export-tmx', '--export-review', '--emit-alignment', '--stats-json' and '--watch' work on single files; they can't be used with 'mdbook'".into());
+    }
+    let config_path = args.book_dir.join("book.toml");
+    let config = fs::read_to_string(&config_path).map_err(|e| format!("Can't read {:?}, is this an mdBook? {}", config_path, e))?;
+    let book: toml::Table = toml::from_str(&config).map_err(|e| format!("Invalid {:?}: {}", config_path, e))?;
+    let src = book.get("book").and_then(|book| book.get("src")).and_then(|src| src.as_str()).unwrap_or("src");
+    let build_dir = book.get("build").and_then(|build| build.get("build-dir")).and_then(|dir| dir.as_str()).unwrap_or("book");
+
+    let book_dir = args.book_dir.canonicalize()?;
+    let template = match &args.out_dir {
+        Some(out_dir) => out_dir.clone(),
+        None => book_dir.parent().unwrap_or(&book_dir).join("book-{target}"),
+    };
+    let out_dirs: Vec<PathBuf> =
+        args.options.target.iter().map(|target| PathBuf::from(template.to_string_lossy().replace("{target}", target))).collect();
+    // Neither the rendered book nor the translations, which may live inside the book directory, are copied.
+    let mut skipped = vec![book_dir.join(build_dir), book_dir.join(".git")];
+    skipped.extend(out_dirs.iter().map(|out_dir| absolute(out_dir)));
+
+    let mut chapters = Vec::new();
+    collect_markdown(&book_dir.join(src), "", &[], &mut chapters)?;
+    chapters.sort();
+    let mut failed = 0;
+    for (target, out_dir) in args.options.target.iter().zip(&out_dirs) {
+        copy_dir(&book_dir, out_dir, &skipped)?;
+        fs::write(out_dir.join("book.toml"), with_language(&config, target))?;
+
+        let mut options = args.options.clone();
+        options.target = vec![target.clone()];
+        options.format = Some(Format::Markdown);
+        options.pipeline.text.no_translate_patterns.push(HELPER_PATTERN.to_string());
+        for chapter in &chapters {
+            if !logging::quiet() {
+                logging::suspend(|| println!());
+            }
+            let input_file = book_dir.join(src).join(chapter);
+            let output_file = out_dir.join(src).join(chapter);
+            if let Err(e) = translate::translate_file(input_file, Some(output_file), options.clone()).await {
+                error!("Error translating {}: {}", chapter, e);
+                failed += 1;
+            }
+        }
+        if !logging::quiet() {
+            println!("\nTranslated {} chapters of the book into {:?}.", chapters.len(), out_dir);
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} chapters could not be translated; they are left in the source language", failed).into());
+    }
+    Ok(())
+}
+
+/// Adds the Markdown files below `dir` (for a book, `SUMMARY.md` included) as paths relative to
+/// it, separated by '/'. Hidden and `skipped` directories are left out.
+pub(super) fn collect_markdown(dir: &Path, prefix: &str, skipped: &[PathBuf], files: &mut Vec<String>) -> Result<(), TranslatorError> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = format!("{}{}", prefix, name);
+        if entry.file_type()?.is_dir() {
+            if !name.starts_with('.') && !skipped.contains(&entry.path()) {
+                collect_markdown(&entry.path(), &format!("{}/", relative), skipped, files)?;
+            }
+        } else if Format::from_path(&entry.path()) == Format::Markdown {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// Copies the book below `from` to `to`, leaving out the `skipped` directories. Symbolic links
+/// to directories are not followed.
+fn copy_dir(from: &Path, to: &Path, skipped: &[PathBuf]) -> Result<(), TranslatorError> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if !skipped.contains(&path) {
+                copy_dir(&path, &to.join(entry.file_name()), skipped)?;
+            }
+        } else if path.is_file() {
+            fs::copy(&path, to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+/// The path made absolute without requiring it to exist yet, so it can be compared with the book's.
+pub(super) fn absolute(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| std::env::current_dir().map(|dir| dir.join(path)).unwrap_or_else(|_| path.to_path_buf()))
+}
+
+/// The `book.toml` with `language` in its `[book]` table set to `target`, keeping the rest as it is.
+fn with_language(config: &str, target: &str) -> String {
+    let language = format!("language = \"{}\"\n", target);
+    let mut lines: Vec<String> = config.split_inclusive('\n').map(str::to_string).collect();
+    if lines.last().is_some_and(|line| !line.ends_with('\n')) {
+        lines.last_mut().unwrap().push('\n');
+    }
+    let Some(section) = lines.iter().position(|line| line.trim() == "[book]") else {
+        lines.push(format!("\n[book]\n{}", language));
+        return lines.concat();
+    };
+    let end = lines[section + 1..].iter().position(|line| line.trim_start().starts_with('[')).map_or(lines.len(), |end| section + 1 + end);
+    match lines[section + 1..end].iter().position(|line| line.split('=').next().is_some_and(|key| key.trim() == "language")) {
+        Some(line) => lines[section + 1 + line] = language,
+        None => lines.insert(section + 1, language),
+    }
+    lines.concat()
+}
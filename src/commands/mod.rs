@@ -0,0 +1,522 @@
+use clap::{ArgMatches, Args, ValueEnum};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use text_translator::TranslatorError;
+use tracing::warn;
+use text_translator::backend::cassette::Cassette;
+use text_translator::backend::deepl::Formality;
+use text_translator::backend::ensemble::Ensemble;
+use text_translator::backend::failover::Failover;
+use text_translator::backend::openai::{self, OpenAiClient};
+use text_translator::backend::post_edit::PostEdit;
+use text_translator::backend::libretranslate::PUBLIC_MIRRORS;
+use text_translator::{Backend, BackendKind, BackendOptions, Glossary, NoTranslate, Progress, PromptTemplate, RateLimiter, Redactor, RetryPolicy, TranslationCache};
+
+pub mod bench_endpoints;
+mod checks;
+pub mod clip;
+pub mod compare;
+pub mod completions;
+pub mod config;
+pub mod detect;
+pub mod export_tmx;
+pub mod import_review;
+pub mod import_tmx;
+pub mod languages;
+pub mod logging;
+pub mod manpage;
+pub mod mdbook;
+pub mod retry_failed;
+pub mod repl;
+pub mod review;
+pub mod serve;
+pub mod site;
+pub mod translate;
+pub mod translate_dir;
+pub mod watch;
+
+/// A subcommand whose arguments can be completed from a config profile and then run.
+pub trait Subcommand {
+    /// Fills in the options that weren't given on the command line from a config profile.
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError>;
+
+    async fn run(self) -> Result<(), TranslatorError>;
+}
+
+/// Options selecting and configuring the translation service, shared by all subcommands.
+#[derive(Args, Debug, Clone)]
+pub struct BackendArgs {
+    /// The translation service to use; 'mock' makes pseudo-translations locally, for trying out a file without spending API quota,
+    /// and 'offline' translates with a local model, for confidential texts; it needs a local translator
+    /// installed, Marian ('marian-decoder') with an Opus-MT model by default (see '--offline-command')
+    #[arg(long, value_enum, default_value_t = BackendKind::LibreTranslate)]
+    backend: BackendKind,
+
+    /// The API endpoint URL (defaults to the public endpoint of the chosen backend).
+    /// Several URLs (repeated or separated by commas) are mirrors to fail over to when one is unavailable
+    #[arg(long, value_delimiter = ',')]
+    api_url: Vec<String>,
+
+    /// Send the requests to the endpoints in turn, each paced on its own by '--mirror-requests-per-minute'
+    /// (use '--concurrency' to keep them all busy). Without '--api-url', the built-in list of public
+    /// LibreTranslate mirrors is used, along with those given by '--mirror'
+    #[arg(long)]
+    round_robin: bool,
+
+    /// Another public LibreTranslate mirror for '--round-robin' and 'bench-endpoints' (repeated or separated by commas)
+    #[arg(long, value_name = "URL", value_delimiter = ',')]
+    mirror: Vec<String>,
+
+    /// Maximum number of requests per minute to each endpoint with '--round-robin' (0 for no limit)
+    #[arg(long, default_value_t = 6)]
+    mirror_requests_per_minute: u32,
+
+    /// Also translate every chunk with these services and let the LLM of '--llm-url' pick or merge
+    /// the best translation of each paragraph; their API keys are read from
+    /// TRANSLATOR_API_KEY_<NAME>, e.g. TRANSLATOR_API_KEY_DEEPL
+    #[arg(long, value_enum, value_name = "BACKEND", value_delimiter = ',')]
+    ensemble: Vec<BackendKind>,
+
+    /// Have an LLM (see '--llm-url') fix the grammar and fluency of every translation, keeping its meaning
+    #[arg(long)]
+    post_edit: bool,
+
+    /// OpenAI-compatible chat completions endpoint of the LLM used by '--ensemble' and '--post-edit', e.g. a local
+    /// 'http://localhost:11434/v1/chat/completions' (defaults to OpenAI)
+    #[arg(long, value_name = "URL")]
+    llm_url: Option<String>,
+
+    /// Model of the LLM used by '--ensemble' and '--post-edit'
+    #[arg(long, value_name = "MODEL")]
+    llm_model: Option<String>,
+
+    /// API key of the LLM used by '--ensemble' and '--post-edit'; local servers usually don't need one
+    #[arg(long, env = "TRANSLATOR_LLM_API_KEY", hide_env_values = true)]
+    llm_api_key: Option<String>,
+
+    /// API key or token for backends and LibreTranslate instances that require authentication; the
+    /// 'anthropic' and 'gemini' backends also read ANTHROPIC_API_KEY and GEMINI_API_KEY
+    #[arg(long, env = "TRANSLATOR_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// Address of the Ollama server (only used by the 'ollama' backend; default: 'http://localhost:11434')
+    #[arg(long, value_name = "URL")]
+    ollama_url: Option<String>,
+
+    /// Azure resource region (only used by the 'azure' backend)
+    #[arg(long)]
+    region: Option<String>,
+
+    /// Model name for the LLM backends ('openai', 'anthropic', 'gemini' and 'ollama', e.g. 'llama3:8b'), or the model of the 'offline' backend
+    /// (by default 'opus-mt-{source}-{target}', the directory of an Opus-MT model)
+    #[arg(long)]
+    model: Option<String>,
+
+    /// Formality of the translation, e.g. formal or informal address in German or Hungarian (used
+    /// by the 'deepl' backend, the LLM backends and the LLM of '--ensemble' and '--post-edit')
+    #[arg(long, value_enum)]
+    formality: Option<Formality>,
+
+    /// The field the text comes from, e.g. 'medical report' or 'legal contract', for better term
+    /// choices (used by the 'deepl' backend, the LLM backends and the LLM of '--ensemble' and '--post-edit')
+    #[arg(long, visible_alias = "context", value_name = "DOMAIN")]
+    domain: Option<String>,
+
+    /// File with the prompt for the LLM backends ('openai', 'anthropic', 'gemini' and 'ollama'); '{source}', '{target}' and '{text}' are substituted
+    #[arg(long)]
+    prompt_template: Option<PathBuf>,
+
+    /// The local translator run by the 'offline' backend, started once per language pair and kept running:
+    /// it has to write the translation of every line of its standard input to its standard output as soon
+    /// as it reads it. '{source}', '{target}' and '{model}' are substituted
+    /// (default: 'marian-decoder -c {model}/decoder.yml --mini-batch 1 --maxi-batch 1 --quiet')
+    #[arg(long, value_name = "COMMAND")]
+    offline_command: Option<String>,
+
+    /// Send the paragraphs of a chunk as an array in one request, so the server can't merge them
+    /// and they never have to be sent again one by one (only used by the 'libretranslate' backend)
+    #[arg(long)]
+    batch: bool,
+
+    /// Save every request and response to this file, to replay them later with '--replay'
+    #[arg(long, value_name = "FILE")]
+    record: Option<PathBuf>,
+
+    /// Answer requests with the responses recorded by '--record' instead of sending them, for tests and demos without the API
+    #[arg(long, value_name = "FILE", conflicts_with = "record")]
+    replay: Option<PathBuf>,
+
+    /// Proxy for all requests, e.g. 'http://proxy:3128' or 'socks5://proxy:1080' (by default the
+    /// HTTP_PROXY, HTTPS_PROXY and ALL_PROXY environment variables are used, except for NO_PROXY hosts)
+    #[arg(long, value_name = "URL")]
+    proxy: Option<String>,
+
+    /// Connect directly, ignoring the proxy environment variables
+    #[arg(long, conflicts_with = "proxy")]
+    no_proxy: bool,
+
+    /// PEM file with the certificate of an additional certificate authority to trust, e.g. the
+    /// internal CA of a self-hosted server
+    #[arg(long, value_name = "FILE")]
+    ca_cert: Option<PathBuf>,
+
+    /// Don't verify the server's TLS certificate, for servers with self-signed certificates (unsafe)
+    #[arg(long)]
+    insecure: bool,
+
+    /// Seconds a request may take in all before it is abandoned and retried; for the streamed
+    /// replies of the 'ollama' backend, seconds the server may send nothing, and for the 'offline'
+    /// backend, seconds the local translator may take for a chunk
+    #[arg(long, value_name = "SECONDS", default_value_t = 120.0)]
+    timeout: f64,
+
+    /// Seconds to wait for the connection to the server before retrying
+    #[arg(long, value_name = "SECONDS", default_value_t = 10.0)]
+    connect_timeout: f64,
+
+    /// How many times a failed request is retried
+    #[arg(long, default_value_t = 3)]
+    max_retries: u32,
+
+    /// Seconds to wait before retrying; doubled on every further retry
+    #[arg(long, default_value_t = 30.0, value_name = "SECONDS")]
+    retry_base_delay: f64,
+}
+
+impl BackendArgs {
+    /// Fills in the options that weren't given on the command line from a config profile.
+    pub fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        let backend = config::parse_enum("backend", profile.backend.as_deref())?;
+        config::apply(matches, "backend", &mut self.backend, backend);
+        config::apply(matches, "api_url", &mut self.api_url, profile.api_url.as_ref().map(config::List::to_vec));
+        config::apply(matches, "round_robin", &mut self.round_robin, profile.round_robin);
+        config::apply(matches, "mirror", &mut self.mirror, profile.mirror.as_ref().map(config::List::to_vec));
+        config::apply(matches, "mirror_requests_per_minute", &mut self.mirror_requests_per_minute, profile.mirror_requests_per_minute);
+        config::apply(matches, "api_key", &mut self.api_key, profile.api_key.clone().map(Some));
+        let ensemble = match &profile.ensemble {
+            Some(list) => Some(list.to_vec().iter().filter_map(|kind| config::parse_enum("ensemble", Some(kind)).transpose()).collect::<Result<_, _>>()?),
+            None => None,
+        };
+        config::apply(matches, "ensemble", &mut self.ensemble, ensemble);
+        config::apply(matches, "post_edit", &mut self.post_edit, profile.post_edit);
+        config::apply(matches, "llm_url", &mut self.llm_url, profile.llm_url.clone().map(Some));
+        config::apply(matches, "llm_model", &mut self.llm_model, profile.llm_model.clone().map(Some));
+        config::apply(matches, "llm_api_key", &mut self.llm_api_key, profile.llm_api_key.clone().map(Some));
+        config::apply(matches, "ollama_url", &mut self.ollama_url, profile.ollama_url.clone().map(Some));
+        config::apply(matches, "region", &mut self.region, profile.region.clone().map(Some));
+        config::apply(matches, "model", &mut self.model, profile.model.clone().map(Some));
+        let formality = config::parse_enum("formality", profile.formality.as_deref())?;
+        config::apply(matches, "formality", &mut self.formality, formality.map(Some));
+        config::apply(matches, "domain", &mut self.domain, profile.domain.clone().map(Some));
+        config::apply(matches, "prompt_template", &mut self.prompt_template, profile.prompt_template.clone().map(Some));
+        config::apply(matches, "offline_command", &mut self.offline_command, profile.offline_command.clone().map(Some));
+        config::apply(matches, "batch", &mut self.batch, profile.batch);
+        config::apply(matches, "proxy", &mut self.proxy, profile.proxy.clone().map(Some));
+        config::apply(matches, "ca_cert", &mut self.ca_cert, profile.ca_cert.clone().map(Some));
+        config::apply(matches, "insecure", &mut self.insecure, profile.insecure);
+        config::apply(matches, "timeout", &mut self.timeout, profile.timeout);
+        config::apply(matches, "connect_timeout", &mut self.connect_timeout, profile.connect_timeout);
+        config::apply(matches, "max_retries", &mut self.max_retries, profile.max_retries);
+        config::apply(matches, "retry_base_delay", &mut self.retry_base_delay, profile.retry_base_delay);
+        Ok(())
+    }
+
+    pub fn kind(&self) -> BackendKind {
+        self.backend
+    }
+
+    /// Switches to another translation service, at its public endpoint: the endpoints given for
+    /// the previous one don't serve this one.
+    pub fn switch_to(&mut self, kind: BackendKind) {
+        self.backend = kind;
+        self.api_url.clear();
+    }
+
+    /// The requests per minute sent to each endpoint with '--round-robin' (0 for no limit).
+    pub fn mirror_requests_per_minute(&self) -> u32 {
+        self.mirror_requests_per_minute
+    }
+
+    /// Whether requests are spread over the endpoints, each with its own rate limit.
+    pub fn round_robin(&self) -> bool {
+        self.round_robin
+    }
+
+    /// The endpoints to spread requests over: those given with '--api-url', or the built-in public
+    /// LibreTranslate mirrors, and the mirrors added with '--mirror'.
+    pub fn mirrors(&self) -> Result<Vec<String>, TranslatorError> {
+        let urls: Vec<String> = match self.api_url.is_empty() {
+            true if self.backend != BackendKind::LibreTranslate => {
+                let name = self.backend.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default();
+                return Err(format!("No public mirrors are known for '{}'; give its endpoints with '--api-url'", name).into());
+            }
+            true => PUBLIC_MIRRORS.iter().map(|url| url.to_string()).collect(),
+            false => self.api_url.clone(),
+        };
+        Ok(urls.into_iter().chain(self.mirror.iter().cloned()).collect())
+    }
+
+    /// The options for another translation service, with its own endpoints and key; the
+    /// endpoints of this one don't serve it, and its key wouldn't be accepted.
+    pub fn for_backend(&self, kind: BackendKind, api_url: &[String], api_key: Option<&str>) -> Self {
+        Self {
+            backend: kind,
+            api_url: api_url.to_vec(),
+            api_key: api_key.map(str::to_string),
+            round_robin: false,
+            ensemble: Vec::new(),
+            post_edit: false,
+            ..self.clone()
+        }
+    }
+
+    /// The options for sending requests to `url` alone.
+    pub fn for_endpoint(&self, url: &str) -> Self {
+        Self { api_url: vec![url.to_string()], round_robin: false, ensemble: Vec::new(), post_edit: false, ..self.clone() }
+    }
+
+    /// The main backend together with those of '--ensemble', and the LLM choosing among their translations.
+    fn ensemble(&self, main: Backend, client: &reqwest::Client) -> Result<Ensemble, TranslatorError> {
+        let mut candidates = vec![main];
+        for &kind in &self.ensemble {
+            let name = kind.to_possible_value().map(|value| value.get_name().to_uppercase()).unwrap_or_default();
+            let api_key = std::env::var(format!("TRANSLATOR_API_KEY_{}", name)).ok().or_else(|| provider_api_key(kind));
+            let options = BackendOptions { api_key, ..self.backend_options()? };
+            candidates.push(Backend::new(kind, client.clone(), options)?);
+        }
+        Ok(Ensemble::new(candidates, self.llm(client)))
+    }
+
+    /// The LLM of '--llm-url' that judges and edits translations.
+    fn llm(&self, client: &reqwest::Client) -> OpenAiClient {
+        OpenAiClient::new(
+            client.clone(),
+            self.llm_url.as_deref().unwrap_or(openai::DEFAULT_API_URL),
+            self.llm_model.as_deref().unwrap_or(openai::DEFAULT_MODEL),
+        )
+        .with_api_key(self.llm_api_key.clone())
+        .with_domain(self.domain.clone())
+        .with_formality(self.formality)
+    }
+
+    /// Whether no request reaches a server, as responses are replayed, made up by the mock backend
+    /// or translated by a local model.
+    pub fn offline(&self) -> bool {
+        self.replay.is_some() || matches!(self.backend, BackendKind::Mock | BackendKind::Offline)
+    }
+
+    /// Paces the requests to the server at `requests_per_minute`, or `request_delay` seconds apart
+    /// when given; `None` when the requests need no pacing here.
+    pub fn rate_limiter(&self, requests_per_minute: u32, request_delay: Option<f64>) -> Result<Option<RateLimiter>, TranslatorError> {
+        Ok(match request_delay {
+            // Without a server there is nothing to be polite to.
+            _ if self.offline() => None,
+            // Each mirror is paced by a limiter of its own.
+            _ if self.round_robin => None,
+            Some(delay) => Some(RateLimiter::with_interval(Duration::try_from_secs_f64(delay)?)),
+            None => Some(RateLimiter::per_minute(requests_per_minute, 1)),
+        })
+    }
+
+    /// An HTTP client with the configured proxy, certificates and timeouts.
+    pub fn http_client(&self) -> Result<reqwest::Client, TranslatorError> {
+        let mut client = reqwest::Client::builder()
+            .user_agent(format!(
+                "rust-text-translator/{}",
+                env!("CARGO_PKG_VERSION")
+            ))
+            .timeout(Duration::try_from_secs_f64(self.timeout)?)
+            .connect_timeout(Duration::try_from_secs_f64(self.connect_timeout)?);
+        if let Some(proxy) = &self.proxy {
+            let proxy = reqwest::Proxy::all(proxy.as_str())
+                .map_err(|e| format!("Invalid proxy URL '{}': {}", proxy, e))?
+                .no_proxy(reqwest::NoProxy::from_env());
+            client = client.proxy(proxy);
+        } else if self.no_proxy {
+            client = client.no_proxy();
+        }
+        if let Some(path) = &self.ca_cert {
+            let pem = std::fs::read(path).map_err(|e| format!("Can't read the CA certificate {:?}: {}", path, e))?;
+            let certificate = reqwest::Certificate::from_pem(&pem)
+                .map_err(|e| format!("{:?} is not a PEM certificate: {}", path, e))?;
+            client = client.add_root_certificate(certificate);
+        }
+        if self.insecure {
+            warn!("TLS certificates are not verified ('--insecure'); anyone on the network path can read and change the requests.");
+            client = client.danger_accept_invalid_certs(true);
+        }
+        Ok(client.build()?)
+    }
+
+    /// The settings of a service other than its endpoint and key.
+    fn backend_options(&self) -> Result<BackendOptions, TranslatorError> {
+        Ok(BackendOptions {
+            api_url: None,
+            api_key: None,
+            region: self.region.clone(),
+            model: self.model.clone(),
+            formality: self.formality,
+            domain: self.domain.clone(),
+            prompt_template: self.prompt_template.as_deref().map(PromptTemplate::from_file).transpose()?,
+            idle_timeout: Some(Duration::try_from_secs_f64(self.timeout)?),
+            offline_command: self.offline_command.clone(),
+            batch: self.batch,
+        })
+    }
+
+    /// How often and after what delay failed requests are retried.
+    pub fn retry_policy(&self) -> Result<RetryPolicy, TranslatorError> {
+        Ok(RetryPolicy { max_retries: self.max_retries, base_delay: Duration::try_from_secs_f64(self.retry_base_delay)? })
+    }
+
+    /// Creates the configured backend, reporting retries and errors to `progress`.
+    pub fn build(&self, progress: Progress) -> Result<Backend, TranslatorError> {
+        let client = self.http_client()?;
+        let uses_domain = self.backend == BackendKind::DeepL || self.backend.is_llm() || !self.ensemble.is_empty() || self.post_edit;
+        if self.domain.is_some() && !uses_domain {
+            warn!("'--domain' is only used by the 'deepl' backend, the LLM backends, '--ensemble' and '--post-edit'.");
+        }
+        if self.ollama_url.is_some() && self.backend != BackendKind::Ollama {
+            warn!("'--ollama-url' is only used by the 'ollama' backend.");
+        }
+        let api_url = match self.backend {
+            BackendKind::Ollama => self.api_url.first().or(self.ollama_url.as_ref()).cloned(),
+            _ => self.api_url.first().cloned(),
+        };
+        let options = BackendOptions { api_url, api_key: self.api_key.clone().or_else(|| provider_api_key(self.backend)), ..self.backend_options()? };
+        let retry = self.retry_policy()?;
+        let urls = if self.round_robin { self.mirrors()? } else { self.api_url.clone() };
+        let backend = if self.round_robin || urls.len() > 1 {
+            let endpoints = urls
+                .iter()
+                .map(|url| {
+                    let options = BackendOptions { api_url: Some(url.clone()), ..options.clone() };
+                    Ok((url.clone(), Backend::new(self.backend, client.clone(), options)?))
+                })
+                .collect::<Result<_, TranslatorError>>()?;
+            if self.round_robin {
+                Backend::Failover(Failover::round_robin(endpoints, self.mirror_requests_per_minute))
+            } else {
+                Backend::Failover(Failover::new(endpoints))
+            }
+        } else {
+            Backend::new(self.backend, client.clone(), options)?
+        };
+        let backend = if self.ensemble.is_empty() { backend } else { Backend::Ensemble(self.ensemble(backend, &client)?) };
+        let backend = if self.post_edit { Backend::PostEdit(PostEdit::new(backend, self.llm(&client))) } else { backend };
+        let backend = backend.with_progress(progress).with_retry_policy(retry);
+        let cassette = match (&self.record, &self.replay) {
+            (Some(path), _) => Some(Cassette::record(path)),
+            (_, Some(path)) => Some(Cassette::replay(path)?),
+            _ => None,
+        };
+        Ok(match cassette {
+            Some(cassette) => backend.with_cassette(Arc::new(cassette)),
+            None => backend,
+        })
+    }
+}
+
+/// The API key in the environment variable the provider's own tools use, e.g. ANTHROPIC_API_KEY.
+fn provider_api_key(kind: BackendKind) -> Option<String> {
+    kind.api_key_variable().and_then(|variable| std::env::var(variable).ok()).filter(|key| !key.is_empty())
+}
+
+/// How the text is prepared before it is sent and restored in its translation: the glossary, the
+/// spans kept untranslated and the personal data redacted.
+#[derive(Args, Debug, Clone)]
+pub struct TextArgs {
+    /// CSV file of source terms and the target terms they must always be translated to
+    #[arg(long)]
+    glossary: Option<PathBuf>,
+
+    /// Regular expression whose matches are kept untranslated, e.g. file paths or version numbers (repeatable)
+    #[arg(long = "no-translate-pattern", value_name = "REGEX")]
+    no_translate_patterns: Vec<String>,
+
+    /// Replace e-mail addresses, phone numbers and IBANs with tokens before sending the text, and
+    /// put them back into the translation
+    #[arg(long)]
+    redact: bool,
+
+    /// Regular expression whose matches are redacted like '--redact' does with personal data,
+    /// e.g. customer numbers (repeatable)
+    #[arg(long = "redact-pattern", value_name = "REGEX")]
+    redact_patterns: Vec<String>,
+}
+
+impl TextArgs {
+    /// Fills in the options that weren't given on the command line from a config profile.
+    pub fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) {
+        config::apply(matches, "glossary", &mut self.glossary, profile.glossary.clone().map(Some));
+        let patterns = profile.no_translate_patterns.clone();
+        config::apply(matches, "no_translate_patterns", &mut self.no_translate_patterns, patterns);
+        config::apply(matches, "redact", &mut self.redact, profile.redact);
+        config::apply(matches, "redact_patterns", &mut self.redact_patterns, profile.redact_patterns.clone());
+    }
+
+    /// Keeps the matches of '--no-translate-pattern' untranslated.
+    pub fn no_translate(&self) -> Result<NoTranslate, TranslatorError> {
+        Ok(NoTranslate::new(&self.no_translate_patterns)?)
+    }
+
+    /// Hides the personal data of '--redact' and '--redact-pattern' from the service.
+    pub fn redactor(&self) -> Result<Redactor, TranslatorError> {
+        Ok(Redactor::new(self.redact, &self.redact_patterns)?)
+    }
+
+    /// The terms of '--glossary', if one was given.
+    pub fn glossary(&self) -> Result<Option<Glossary>, TranslatorError> {
+        self.glossary.as_deref().map(Glossary::load).transpose()
+    }
+}
+
+/// Options of the pipeline the subcommands send text through: how it is prepared, cached and paced.
+#[derive(Args, Debug, Clone)]
+pub struct PipelineArgs {
+    #[command(flatten)]
+    text: TextArgs,
+
+    /// Translation cache database (defaults to 'translator/cache.sqlite' in the user's cache directory)
+    #[arg(long)]
+    cache_file: Option<PathBuf>,
+
+    /// Don't read or store translations in the persistent cache
+    #[arg(long, conflicts_with = "cache_file")]
+    no_cache: bool,
+
+    /// Maximum number of API requests per minute (0 for no limit, e.g. on self-hosted servers)
+    #[arg(long, default_value_t = 6)]
+    requests_per_minute: u32,
+
+    /// Minimum number of seconds between API requests, instead of '--requests-per-minute'
+    #[arg(long, value_name = "SECONDS", conflicts_with = "requests_per_minute")]
+    request_delay: Option<f64>,
+}
+
+impl PipelineArgs {
+    /// Fills in the options that weren't given on the command line from a config profile.
+    pub fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) {
+        self.text.apply_profile(profile, matches);
+        config::apply(matches, "cache_file", &mut self.cache_file, profile.cache_file.clone().map(Some));
+        config::apply(matches, "requests_per_minute", &mut self.requests_per_minute, profile.requests_per_minute);
+        // The two ways of pacing requests exclude each other; one given on the command line wins.
+        if !config::is_explicit(matches, "requests_per_minute") {
+            config::apply(matches, "request_delay", &mut self.request_delay, profile.request_delay.map(Some));
+        }
+    }
+
+    /// Opens the translation cache, unless caching is turned off.
+    pub fn open_cache(&self) -> Result<Option<TranslationCache>, TranslatorError> {
+        if self.no_cache {
+            return Ok(None);
+        }
+        self.cache_file.clone().or_else(TranslationCache::default_path).map(|path| TranslationCache::open(&path)).transpose()
+    }
+
+    /// Paces the requests of `backend` as '--requests-per-minute' or '--request-delay' asks.
+    pub fn rate_limiter(&self, backend: &BackendArgs) -> Result<RateLimiter, TranslatorError> {
+        Ok(backend.rate_limiter(self.requests_per_minute, self.request_delay)?.unwrap_or_else(RateLimiter::unlimited))
+    }
+}
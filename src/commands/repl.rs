@@ -0,0 +1,126 @@
+use clap::{ArgMatches, Args, ValueEnum};
+use std::io::{IsTerminal, Write};
+use text_translator::TranslatorError;
+use text_translator::{BackendKind, Progress, AUTO_LANGUAGE};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use super::translate::Pipeline;
+use super::{clip, config, BackendArgs, PipelineArgs, Subcommand};
+
+const HELP: &str = "\
+Type or paste text to translate it line by line. Commands:
+  :source LANG    translate from LANG ('auto' to detect it)
+  :target LANG    translate into LANG
+  :swap           swap the source and target languages
+  :backend NAME   switch to another translation service
+  :help           show this help
+  :quit           leave (or Ctrl-D)";
+
+/// Translate lines typed or pasted interactively, switching languages and backends on the way
+#[derive(Args, Debug)]
+pub struct ReplArgs {
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Source language to start with (e.g., 'en', or 'auto' to detect it)
+    #[arg(short, long, default_value = AUTO_LANGUAGE)]
+    source: String,
+
+    /// Target language to start with
+    #[arg(short, long, default_value = "hu")]
+    target: String,
+
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+}
+
+impl Subcommand for ReplArgs {
+    /// Fills in the options that weren't given on the command line from a config profile.
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.backend.apply_profile(profile, matches)?;
+        config::apply(matches, "source", &mut self.source, profile.source.clone());
+        let target = profile.target.as_ref().and_then(|target| target.to_vec().into_iter().next());
+        config::apply(matches, "target", &mut self.target, target);
+        self.pipeline.apply_profile(profile, matches);
+        Ok(())
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(mut args: ReplArgs) -> Result<(), TranslatorError> {
+    let mut rate_limiter = args.pipeline.rate_limiter(&args.backend)?;
+    let mut translator = args.backend.build(Progress::hidden())?;
+    let no_translate = args.pipeline.text.no_translate()?;
+    let redactor = args.pipeline.text.redactor()?;
+    let glossary = args.pipeline.text.glossary()?;
+    let cache = args.pipeline.open_cache()?;
+
+    let interactive = std::io::stdin().is_terminal();
+    if interactive {
+        eprintln!("{}", HELP);
+    }
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    loop {
+        if interactive {
+            eprint!("{} -> {}> ", args.source, args.target);
+            std::io::stderr().flush()?;
+        }
+        let Some(line) = lines.next_line().await? else { break };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(command) = line.strip_prefix(':') else {
+            let pipeline = Pipeline {
+                translator: &translator,
+                cache: cache.as_ref(),
+                limiter: &rate_limiter,
+                redactor: &redactor,
+                no_translate: &no_translate,
+                glossary: glossary.as_ref(),
+                skip_target_language: false,
+            };
+            match clip::translate(&pipeline, line, &args.source, &args.target).await {
+                Ok(translation) => println!("{}", translation),
+                // A failed line doesn't end the session.
+                Err(error) => eprintln!("Error: {}", error),
+            }
+            continue;
+        };
+        let (name, value) = command.split_once(char::is_whitespace).map_or((command, ""), |(name, value)| (name, value.trim()));
+        match (name, value) {
+            ("q" | "quit" | "exit", _) => break,
+            ("h" | "help", _) => eprintln!("{}", HELP),
+            ("s" | "source", language) if !language.is_empty() => args.source = language.to_string(),
+            ("t" | "target", language) if !language.is_empty() => args.target = language.to_string(),
+            ("swap", _) if args.source == AUTO_LANGUAGE => eprintln!("Error: the source language isn't known with 'auto'"),
+            ("swap", _) => std::mem::swap(&mut args.source, &mut args.target),
+            ("b" | "backend", name) if !name.is_empty() => match BackendKind::from_str(name, true) {
+                Ok(kind) => {
+                    let mut backend = args.backend.clone();
+                    backend.switch_to(kind);
+                    match backend.build(Progress::hidden()).and_then(|built| Ok((built, args.pipeline.rate_limiter(&backend)?))) {
+                        Ok((built, limiter)) => {
+                            rate_limiter = limiter;
+                            translator = built;
+                            args.backend = backend;
+                        }
+                        Err(error) => eprintln!("Error: {}", error),
+                    }
+                }
+                Err(_) => {
+                    let names: Vec<String> = BackendKind::value_variants()
+                        .iter()
+                        .filter_map(|kind| kind.to_possible_value().map(|value| value.get_name().to_string()))
+                        .collect();
+                    eprintln!("Error: unknown backend '{}'; choose one of {}", name, names.join(", "));
+                }
+            },
+            _ => eprintln!("Error: unknown command ':{}'; ':help' lists the commands", command),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,173 @@
+use clap::{ArgMatches, Args};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use encoding_rs::{Encoding, UTF_8};
+use text_translator::encoding::{self, InputEncoding};
+use text_translator::TranslatorError;
+use text_translator::failures::{self, Failure};
+use text_translator::format::{self, Document, Format, FormatOptions};
+use text_translator::output;
+use text_translator::{pack_segments, FailureReport, Progress, AUTO_LANGUAGE};
+
+use super::translate::Pipeline;
+use super::{config, BackendArgs, PipelineArgs, Subcommand};
+
+/// Translate the chunks a '--best-effort' run left untranslated and patch them into its output
+#[derive(Args, Debug)]
+pub struct RetryFailedArgs {
+    /// The failure report, or the output file it belongs to
+    #[arg(required = true)]
+    file: PathBuf,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+}
+
+impl Subcommand for RetryFailedArgs {
+    /// Fills in the options that weren't given on the command line from a config profile.
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.backend.apply_profile(profile, matches)?;
+        self.pipeline.apply_profile(profile, matches);
+        Ok(())
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(args: RetryFailedArgs) -> Result<(), TranslatorError> {
+    let report_path = if args.file.to_string_lossy().ends_with(".translator-failures.json") {
+        args.file.clone()
+    } else {
+        FailureReport::path_for(&args.file)
+    };
+    if !report_path.exists() {
+        return Err(format!("No failure report found at {:?}", report_path).into());
+    }
+    let mut report = FailureReport::load(&report_path)?;
+    let Some(output_file) = report.output_file.clone().filter(|path| path.as_os_str() != "-") else {
+        return Err("The failed run printed its translation instead of writing a file, so there is nothing to patch".into());
+    };
+    if matches!(report.format, Format::Epub | Format::Docx) {
+        return Err(format!("Failed chunks of {:?} files can't be patched in place; translate the file again with '--resume'", report.format).into());
+    }
+    if report.format == Format::Pdf {
+        return Err("Failed chunks of PDF files can't be patched in place; translate the file again with '--resume'".into());
+    }
+
+    // The input is split into chunks exactly like in the failed run, so the chunks can be found again.
+    let input_encoding = report_encoding(report.input_encoding.as_deref())?;
+    let output_encoding = report_encoding(report.output_encoding.as_deref())?;
+    let (raw, _) = encoding::decode(&fs::read(&report.input_file)?, InputEncoding::Fixed(input_encoding), &report.source)?;
+    let crlf = raw.contains("\r\n");
+    let content = raw.replace("\r\n", "\n");
+    let options = FormatOptions {
+        mark_fuzzy: report.mark_fuzzy,
+        target_language: Some(report.target.clone()),
+        max_segment_len: report.chunk_size,
+        front_matter_fields: report.front_matter_fields.clone(),
+        keys: report.keys.clone(),
+        columns: report.columns.clone(),
+        select: report.select.clone(),
+        select_xpath: report.select_xpath.clone(),
+        source_language: (report.source != AUTO_LANGUAGE).then(|| report.source.clone()),
+    };
+    let document = format::parse(report.format, &content, &options)?;
+    let segments = document.segments();
+    let chunks = pack_segments(&segments, report.chunk_size);
+    let mut first_segments = Vec::with_capacity(chunks.len());
+    let mut segment_count = 0;
+    for (_, count) in &chunks {
+        first_segments.push(segment_count);
+        segment_count += count;
+    }
+    for failure in &report.failures {
+        if chunks.get(failure.chunk - 1).map(|(chunk, _)| chunk) != Some(&failure.text) {
+            return Err(format!("{:?} changed since the failed run; translate it again instead", report.input_file).into());
+        }
+    }
+
+    let translator = args.backend.build(Progress::hidden())?.with_text_format(report.format.text_format());
+    let no_translate = args.pipeline.text.no_translate()?;
+    let redactor = args.pipeline.text.redactor()?;
+    let glossary = args.pipeline.text.glossary()?;
+    let cache = args.pipeline.open_cache()?;
+    let limiter = args.pipeline.rate_limiter(&args.backend)?;
+    // The server's rate limit headers adjust the pace as the run goes.
+    let limiter = Arc::new(limiter);
+    let translator = translator.with_rate_limiter(limiter.clone());
+    let pipeline = Pipeline {
+        translator: &translator,
+        cache: cache.as_ref(),
+        limiter: &limiter,
+        redactor: &redactor,
+        no_translate: &no_translate,
+        glossary: glossary.as_ref(),
+        skip_target_language: false,
+    };
+
+    let line_endings = |text: String| if crlf { text.replace('\n', "\r\n") } else { text };
+    let (mut output, _) = encoding::decode(&fs::read(&output_file)?, InputEncoding::Fixed(output_encoding), &report.target)?;
+    let mut remaining = Vec::new();
+    for failure in std::mem::take(&mut report.failures) {
+        let (chunk, count) = &chunks[failure.chunk - 1];
+        let first_segment = first_segments[failure.chunk - 1];
+        let range = first_segment..first_segment + count;
+        // The placeholder is found by rendering it again, exactly as the failed run wrote it.
+        let marked = render(&document, &report, range.clone(), &failures::mark_untranslated(failure.chunk, chunk));
+        let Some(start) = output.find(&line_endings(marked.clone())) else {
+            println!("Chunk {}: its placeholder is no longer in {:?}; skipped.", failure.chunk, output_file);
+            continue;
+        };
+        match pipeline.translate(chunk, *count, &report.source, &report.target).await {
+            Ok((translated, _, _)) => {
+                let translated = line_endings(render(&document, &report, range, &translated));
+                output.replace_range(start..start + line_endings(marked).len(), &translated);
+                println!("Chunk {}: translated.", failure.chunk);
+            }
+            Err(error) => {
+                println!("Chunk {}: failed again: {}", failure.chunk, error);
+                remaining.push(Failure { error: error.to_string(), ..failure });
+            }
+        }
+    }
+
+    // The patched output replaces the old one in one step, so a crash can't leave it half written.
+    output::write_atomic(&output_file, encoding::encode(&output, output_encoding).0)?;
+
+    if remaining.is_empty() {
+        fs::remove_file(&report_path)?;
+        println!("All failed chunks are translated now; {:?} is complete.", output_file);
+    } else {
+        println!("{} chunks still could not be translated; {:?} lists them.", remaining.len(), report_path);
+        report.failures = remaining;
+        report.save(&report_path)?;
+    }
+    Ok(())
+}
+
+/// Renders the segments of a chunk the way the translation run wrote them, from the
+/// translation of the whole chunk.
+fn render(document: &Document, report: &FailureReport, segments: std::ops::Range<usize>, translated: &str) -> String {
+    let translations: Vec<String> = translated.split("\n\n").map(str::to_string).collect();
+    match report.bilingual {
+        Some(layout) => {
+            let languages = (report.source.as_str(), report.target.as_str());
+            document.render_bilingual_range(segments, &translations, false, layout, languages).0
+        }
+        None => document.render_range(segments, &translations, false).0,
+    }
+}
+
+/// The encoding a file of the failed run was in, by the name the report gives it.
+fn report_encoding(name: Option<&str>) -> Result<&'static Encoding, TranslatorError> {
+    match name {
+        Some(name) => Ok(encoding::parse_encoding(name)?),
+        None => Ok(UTF_8),
+    }
+}
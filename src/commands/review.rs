@@ -0,0 +1,438 @@
+use clap::{ArgMatches, Args};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::fs;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use text_translator::TranslatorError;
+use text_translator::format::{self, Document, Format, FormatOptions};
+use text_translator::output;
+use text_translator::{Progress, RateLimiter};
+
+use super::translate::Pipeline;
+use super::{config, BackendArgs, Subcommand, TextArgs};
+
+/// Go through a translation segment by segment next to the original, editing, accepting or
+/// rejecting each one, and save the reviewed result
+#[derive(Args, Debug)]
+pub struct ReviewArgs {
+    /// The original file
+    input_file: PathBuf,
+
+    /// Its translation, as written by 'translate'
+    translation_file: PathBuf,
+
+    /// Where to save the reviewed translation (defaults to overwriting the translation file)
+    #[arg(short, long)]
+    output_file: Option<PathBuf>,
+
+    /// Format of the files (guessed from the file extension by default)
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Source language, for translating a segment again
+    #[arg(short, long, default_value = "en")]
+    source: String,
+
+    /// Target language, for translating a segment again
+    #[arg(short, long, default_value = "hu")]
+    target: String,
+
+    #[command(flatten)]
+    text: TextArgs,
+}
+
+impl Subcommand for ReviewArgs {
+    /// Fills in the options that weren't given on the command line from a config profile.
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.backend.apply_profile(profile, matches)?;
+        config::apply(matches, "source", &mut self.source, profile.source.clone());
+        let target = profile.target.as_ref().and_then(|targets| targets.to_vec().into_iter().next());
+        config::apply(matches, "target", &mut self.target, target);
+        self.text.apply_profile(profile, matches);
+        Ok(())
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+/// What the reviewer decided about a segment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pending,
+    Accepted,
+    Edited,
+    /// Saved in the source language, to be translated by hand.
+    Rejected,
+}
+
+impl Status {
+    fn label(self) -> &'static str {
+        match self {
+            Status::Pending => "not reviewed",
+            Status::Accepted => "accepted",
+            Status::Edited => "edited",
+            Status::Rejected => "rejected",
+        }
+    }
+}
+
+pub async fn run(args: ReviewArgs) -> Result<(), TranslatorError> {
+    let format = args.format.unwrap_or_else(|| Format::from_path(&args.input_file));
+    if !matches!(
+        format,
+        Format::Text
+            | Format::Markdown
+            | Format::Html
+            | Format::Srt
+            | Format::Vtt
+            | Format::Json
+            | Format::Yaml
+            | Format::Latex
+            | Format::Asciidoc
+            | Format::Rst
+            | Format::Android
+            | Format::Ios
+            | Format::Resx
+            | Format::Fluent
+            | Format::Properties
+            | Format::Env
+            | Format::Csv
+            | Format::Xml
+            | Format::Rust
+            | Format::Python
+            | Format::Javascript
+    ) {
+        return Err(format!(
+            "{:?} files can't be reviewed; only plain text, Markdown, HTML, subtitles, JSON, YAML, LaTeX, AsciiDoc, reStructuredText, XML, app resource files and source code comments can",
+            format
+        )
+        .into());
+    }
+    if !std::io::stdout().is_terminal() {
+        return Err("Reviewing needs a terminal".into());
+    }
+
+    // Long paragraphs are not split, as their originals and translations would split differently.
+    let options = FormatOptions { max_segment_len: usize::MAX, ..FormatOptions::default() };
+    let source_document = format::parse(format, &fs::read_to_string(&args.input_file)?.replace("\r\n", "\n"), &options)?;
+    let raw = fs::read_to_string(&args.translation_file)?;
+    let crlf = raw.contains("\r\n");
+    let translated_document = format::parse(format, &raw.replace("\r\n", "\n"), &options)?;
+    let (sources, translations) = (source_document.texts(), translated_document.texts());
+    if sources.len() != translations.len() {
+        return Err(format!(
+            "{:?} has {} segments but {:?} has {}; is it the translation of that file?",
+            args.input_file,
+            sources.len(),
+            args.translation_file,
+            translations.len()
+        )
+        .into());
+    }
+    if sources.is_empty() {
+        println!("{:?} has nothing to review.", args.input_file);
+        return Ok(());
+    }
+
+    let translator = args.backend.build(Progress::hidden())?.with_text_format(format.text_format());
+    let no_translate = args.text.no_translate()?;
+    let redactor = args.text.redactor()?;
+    let glossary = args.text.glossary()?;
+    // Segments are translated again one at a time when asked to, and never from the cache.
+    let limiter = RateLimiter::per_minute(0, 1);
+    let pipeline = Pipeline {
+        translator: &translator,
+        cache: None,
+        limiter: &limiter,
+        redactor: &redactor,
+        no_translate: &no_translate,
+        glossary: glossary.as_ref(),
+        skip_target_language: false,
+    };
+
+    let output_file = args.output_file.clone().unwrap_or_else(|| args.translation_file.clone());
+    let mut review = Review {
+        title: args.translation_file.display().to_string(),
+        source_document: &source_document,
+        translated_document: &translated_document,
+        statuses: vec![Status::Pending; sources.len()],
+        sources,
+        translations,
+        current: 0,
+        scroll: 0,
+        editor: None,
+        message: String::new(),
+        unsaved: false,
+        confirm_quit: false,
+    };
+    let save = |review: &Review| {
+        let text = review.render();
+        output::write_atomic(&output_file, if crlf { text.replace('\n', "\r\n") } else { text })
+    };
+
+    let mut terminal = ratatui::try_init()?;
+    let result = review.run(&mut terminal, &pipeline, &args.source, &args.target, &save).await;
+    ratatui::restore();
+    result?;
+
+    let count = |status| review.statuses.iter().filter(|&&s| s == status).count();
+    println!(
+        "Reviewed {} of {} segments: {} accepted, {} edited, {} rejected.",
+        review.statuses.len() - count(Status::Pending),
+        review.statuses.len(),
+        count(Status::Accepted),
+        count(Status::Edited),
+        count(Status::Rejected)
+    );
+    if review.unsaved {
+        println!("The changes were not saved.");
+    }
+    Ok(())
+}
+
+/// The state of a review session.
+struct Review<'a> {
+    title: String,
+    source_document: &'a Document,
+    translated_document: &'a Document,
+    /// The segments of the original and their translations, as they appear in the files.
+    sources: Vec<String>,
+    translations: Vec<String>,
+    statuses: Vec<Status>,
+    /// Index of the segment shown.
+    current: usize,
+    /// Lines the segment is scrolled down by.
+    scroll: u16,
+    /// The translation being edited.
+    editor: Option<Editor>,
+    /// Shown in the status line until the next key press.
+    message: String,
+    unsaved: bool,
+    /// Whether quitting has been asked for once with unsaved changes.
+    confirm_quit: bool,
+}
+
+impl Review<'_> {
+    /// Shows segments and handles keys until the reviewer quits.
+    async fn run(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        pipeline: &Pipeline<'_>,
+        source: &str,
+        target: &str,
+        save: &dyn Fn(&Review) -> std::io::Result<()>,
+    ) -> Result<(), TranslatorError> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else { continue };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            self.message.clear();
+            if let Some(editor) = &mut self.editor {
+                match key.code {
+                    KeyCode::Enter => {
+                        let text = editor.text.trim().to_string();
+                        self.editor = None;
+                        if text != self.translations[self.current] {
+                            self.translations[self.current] = text;
+                            self.statuses[self.current] = Status::Edited;
+                            self.unsaved = true;
+                        }
+                    }
+                    KeyCode::Esc => self.editor = None,
+                    _ => editor.handle(key),
+                }
+                continue;
+            }
+
+            let quit = matches!(key.code, KeyCode::Char('q') | KeyCode::Esc);
+            if !quit {
+                self.confirm_quit = false;
+            }
+            match key.code {
+                _ if quit => {
+                    if !self.unsaved || self.confirm_quit {
+                        return Ok(());
+                    }
+                    self.confirm_quit = true;
+                    self.message = "There are unsaved changes: press 's' to save them, or 'q' again to quit without saving".to_string();
+                }
+                KeyCode::Right | KeyCode::Char('n') | KeyCode::PageDown => self.go_to(self.current + 1),
+                KeyCode::Left | KeyCode::Char('p') | KeyCode::PageUp => self.go_to(self.current.saturating_sub(1)),
+                KeyCode::Down | KeyCode::Char('j') => self.scroll = self.scroll.saturating_add(1),
+                KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+                KeyCode::Home => self.go_to(0),
+                KeyCode::End => self.go_to(self.sources.len() - 1),
+                KeyCode::Char('u') => {
+                    let next = (1..=self.sources.len())
+                        .map(|offset| (self.current + offset) % self.sources.len())
+                        .find(|&index| self.statuses[index] == Status::Pending);
+                    match next {
+                        Some(index) => self.go_to(index),
+                        None => self.message = "Every segment has been reviewed".to_string(),
+                    }
+                }
+                KeyCode::Char('a') | KeyCode::Enter => self.decide(Status::Accepted),
+                KeyCode::Char('r') => self.decide(Status::Rejected),
+                KeyCode::Char('e') => self.editor = Some(Editor::new(&self.translations[self.current])),
+                KeyCode::Char('t') => {
+                    self.message = "Translating…".to_string();
+                    terminal.draw(|frame| self.draw(frame))?;
+                    let segment = self.source_document.segments()[self.current];
+                    self.message = match pipeline.translate(segment, 1, source, target).await {
+                        Ok((translated, _, _)) => {
+                            self.translations[self.current] = self.source_document.restore(self.current, &translated);
+                            self.statuses[self.current] = Status::Pending;
+                            self.unsaved = true;
+                            "Translated again".to_string()
+                        }
+                        Err(error) => format!("Could not translate the segment: {}", error),
+                    };
+                }
+                KeyCode::Char('s') => {
+                    self.message = match save(self) {
+                        Ok(()) => {
+                            self.unsaved = false;
+                            "Saved".to_string()
+                        }
+                        Err(error) => format!("Could not save: {}", error),
+                    };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn go_to(&mut self, index: usize) {
+        self.current = index.min(self.sources.len() - 1);
+        self.scroll = 0;
+    }
+
+    /// Records the decision about the current segment and moves on to the next one.
+    fn decide(&mut self, status: Status) {
+        if self.statuses[self.current] != status {
+            self.statuses[self.current] = status;
+            self.unsaved = true;
+        }
+        self.go_to(self.current + 1);
+    }
+
+    /// The translated document with the reviewed segments; rejected ones are left in the source language.
+    fn render(&self) -> String {
+        let texts: Vec<String> = self
+            .translations
+            .iter()
+            .zip(&self.sources)
+            .zip(&self.statuses)
+            .map(|((translation, source), status)| if *status == Status::Rejected { source } else { translation }.clone())
+            .collect();
+        self.translated_document.render_texts(&texts)
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, body, footer] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] = Layout::horizontal([Constraint::Percentage(50); 2]).areas(body);
+
+        let count = |status| self.statuses.iter().filter(|&&s| s == status).count();
+        let title = format!(
+            "{}{} | segment {} of {} | {} accepted, {} edited, {} rejected, {} to review",
+            self.title,
+            if self.unsaved { " [modified]" } else { "" },
+            self.current + 1,
+            self.sources.len(),
+            count(Status::Accepted),
+            count(Status::Edited),
+            count(Status::Rejected),
+            count(Status::Pending)
+        );
+        frame.render_widget(Line::from(title).bold(), header);
+
+        let pane = |text: Text<'static>, title: String| {
+            Paragraph::new(text).block(Block::bordered().title(title)).wrap(Wrap { trim: false }).scroll((self.scroll, 0))
+        };
+        frame.render_widget(pane(Text::from(self.sources[self.current].clone()), "Original".to_string()), left);
+        let status = self.statuses[self.current];
+        let translation = match &self.editor {
+            Some(editor) => pane(editor.lines(), "Translation (editing)".to_string()),
+            None => pane(Text::from(self.translations[self.current].clone()), format!("Translation ({})", status.label())),
+        };
+        frame.render_widget(translation, right);
+
+        let help = if self.editor.is_some() {
+            "Enter: keep the edit  Esc: cancel"
+        } else {
+            "←/→: previous/next  u: next to review  a: accept  r: reject  e: edit  t: translate again  s: save  q: quit"
+        };
+        let footer_text = if self.message.is_empty() { Line::from(help).dim() } else { Line::from(self.message.as_str()).yellow() };
+        frame.render_widget(footer_text, footer);
+    }
+}
+
+/// A single-segment text editor.
+struct Editor {
+    text: String,
+    /// Byte position of the cursor in `text`.
+    cursor: usize,
+}
+
+impl Editor {
+    fn new(text: &str) -> Self {
+        Self { text: text.to_string(), cursor: text.len() }
+    }
+
+    fn handle(&mut self, key: KeyEvent) {
+        let previous = self.text[..self.cursor].char_indices().next_back().map_or(0, |(index, _)| index);
+        let next = self.text[self.cursor..].chars().next().map_or(self.cursor, |c| self.cursor + c.len_utf8());
+        match key.code {
+            KeyCode::Char(c) => {
+                self.text.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+            }
+            KeyCode::Backspace => {
+                self.text.replace_range(previous..self.cursor, "");
+                self.cursor = previous;
+            }
+            KeyCode::Delete => self.text.replace_range(self.cursor..next, ""),
+            KeyCode::Left => self.cursor = previous,
+            KeyCode::Right => self.cursor = next,
+            KeyCode::Home => self.cursor = self.text[..self.cursor].rfind('\n').map_or(0, |index| index + 1),
+            KeyCode::End => self.cursor = self.text[self.cursor..].find('\n').map_or(self.text.len(), |index| self.cursor + index),
+            _ => {}
+        }
+    }
+
+    /// The text with the character under the cursor highlighted.
+    fn lines(&self) -> Text<'static> {
+        let cursor_style = Style::default().add_modifier(Modifier::REVERSED);
+        let mut lines = Vec::new();
+        let mut line_start = 0;
+        for line in self.text.split('\n') {
+            let line_end = line_start + line.len();
+            if (line_start..=line_end).contains(&self.cursor) {
+                let at = self.cursor - line_start;
+                let under = line[at..].chars().next().map_or(0, char::len_utf8);
+                lines.push(Line::from(vec![
+                    Span::raw(line[..at].to_string()),
+                    Span::styled(if under == 0 { " ".to_string() } else { line[at..at + under].to_string() }, cursor_style),
+                    Span::raw(line[at + under..].to_string()),
+                ]));
+            } else {
+                lines.push(Line::from(line.to_string()));
+            }
+            line_start = line_end + 1;
+        }
+        Text::from(lines)
+    }
+}
@@ -0,0 +1,252 @@
+use clap::{ArgMatches, Args};
+use futures::stream::{self, StreamExt};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use text_translator::TranslatorError;
+use text_translator::format::{self, Format, FormatOptions};
+use text_translator::{pack_segments, Language, Progress, TextFormat, MAX_CHUNK_SIZE};
+use tokio::sync::{mpsc, oneshot};
+
+use super::translate::Pipeline;
+use super::{config, BackendArgs, PipelineArgs, Subcommand};
+
+/// Largest request body accepted, to keep a misbehaving client from exhausting memory.
+const MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+/// Run a local HTTP translation server with a LibreTranslate-compatible API ('POST /translate',
+/// 'GET /languages') that sends the texts through the same chunking, cache, glossary and rate limiting
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Port to listen on
+    #[arg(long, default_value_t = 8080)]
+    port: u16,
+
+    /// Address to listen on; the default only accepts connections from this machine
+    #[arg(long, default_value = "127.0.0.1")]
+    host: String,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Source language of requests that don't give one
+    #[arg(short, long, default_value = "en")]
+    source: String,
+
+    /// Target language of requests that don't give one
+    #[arg(short, long, default_value = "hu")]
+    target: String,
+
+    #[command(flatten)]
+    pipeline: PipelineArgs,
+
+    /// Number of chunks translated in parallel, across all requests
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    concurrency: u32,
+
+    /// Maximum chunk size in bytes; servers with a larger character limit can take bigger chunks
+    #[arg(long, default_value_t = MAX_CHUNK_SIZE as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    chunk_size: u64,
+}
+
+impl Subcommand for ServeArgs {
+    /// Fills in the options that weren't given on the command line from a config profile.
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.backend.apply_profile(profile, matches)?;
+        config::apply(matches, "source", &mut self.source, profile.source.clone());
+        let target = profile.target.as_ref().and_then(|target| target.to_vec().into_iter().next());
+        config::apply(matches, "target", &mut self.target, target);
+        self.pipeline.apply_profile(profile, matches);
+        config::apply(matches, "concurrency", &mut self.concurrency, profile.concurrency);
+        config::apply(matches, "chunk_size", &mut self.chunk_size, profile.chunk_size);
+        Ok(())
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+/// The body of `POST /translate`, as LibreTranslate takes it.
+#[derive(Deserialize)]
+struct TranslateRequest {
+    q: String,
+    source: Option<String>,
+    target: Option<String>,
+    /// `text` or `html`.
+    #[serde(default)]
+    format: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TranslateResponse {
+    #[serde(rename = "translatedText")]
+    translated_text: String,
+}
+
+#[derive(Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// Work handed from the HTTP server to the translation pipeline, with the channel for its answer.
+enum Job {
+    Translate(TranslateRequest, oneshot::Sender<Result<String, String>>),
+    Languages(oneshot::Sender<Result<Vec<Language>, String>>),
+}
+
+pub async fn run(args: ServeArgs) -> Result<(), TranslatorError> {
+    let address: SocketAddr = format!("{}:{}", args.host, args.port)
+        .parse()
+        .map_err(|error| format!("Invalid address to listen on: {}", error))?;
+
+    let limiter = args.pipeline.rate_limiter(&args.backend)?;
+    let limiter = Arc::new(limiter);
+    // HTML is sent in the backend's HTML mode, so requests of each kind get their own backend.
+    let text_backend = args.backend.build(Progress::hidden())?.with_rate_limiter(limiter.clone());
+    let html_backend = args
+        .backend
+        .build(Progress::hidden())?
+        .with_text_format(TextFormat::Html)
+        .with_rate_limiter(limiter.clone());
+    let no_translate = args.pipeline.text.no_translate()?;
+    let redactor = args.pipeline.text.redactor()?;
+    let glossary = args.pipeline.text.glossary()?;
+    let cache = args.pipeline.open_cache()?;
+    let pipeline = |translator| Pipeline {
+        translator,
+        cache: cache.as_ref(),
+        limiter: &limiter,
+        redactor: &redactor,
+        no_translate: &no_translate,
+        glossary: glossary.as_ref(),
+        skip_target_language: false,
+    };
+    let (text_pipeline, html_pipeline) = (pipeline(&text_backend), pipeline(&html_backend));
+
+    // The server only passes requests on; the pipeline runs here, where the cache lives.
+    let (jobs, queue) = mpsc::channel::<Job>(64);
+    let make_service = make_service_fn(move |_| {
+        let jobs = jobs.clone();
+        async move { Ok::<_, Infallible>(service_fn(move |request| handle(request, jobs.clone()))) }
+    });
+    let server = Server::try_bind(&address).map_err(server_error)?.serve(make_service);
+    println!("Listening on http://{} (Ctrl-C to stop)", server.local_addr());
+    let server = tokio::spawn(server);
+
+    let jobs = stream::unfold(queue, |mut queue| async move { queue.recv().await.map(|job| (job, queue)) });
+    let work = jobs.for_each_concurrent(args.concurrency as usize, |job| async {
+        match job {
+            Job::Translate(request, reply) => {
+                let (pipeline, format) = match request.format.as_deref() {
+                    Some("html") => (&html_pipeline, Format::Html),
+                    _ => (&text_pipeline, Format::Text),
+                };
+                let source = request.source.as_deref().unwrap_or(&args.source);
+                let target = request.target.as_deref().unwrap_or(&args.target);
+                let result = translate(pipeline, format, &request.q, (source, target), args.chunk_size as usize).await;
+                let _ = reply.send(result.map_err(|error| error.to_string()));
+            }
+            Job::Languages(reply) => {
+                let _ = reply.send(text_backend.languages().await.map_err(|error| error.to_string()));
+            }
+        }
+    });
+
+    tokio::select! {
+        _ = work => {}
+        result = server => result.map_err(io::Error::other)?.map_err(server_error)?,
+        _ = tokio::signal::ctrl_c() => println!("Stopped."),
+    }
+    Ok(())
+}
+
+/// Translates the text of a request, split into chunks like a file.
+pub(super) async fn translate(
+    pipeline: &Pipeline<'_>,
+    format: Format,
+    text: &str,
+    (source, target): (&str, &str),
+    chunk_size: usize,
+) -> Result<String, TranslatorError> {
+    let options = FormatOptions { max_segment_len: chunk_size, ..FormatOptions::default() };
+    let document = format::parse(format, text, &options)?;
+    let segments = document.segments();
+    let mut translations = Vec::with_capacity(segments.len());
+    for (chunk, count) in pack_segments(&segments, chunk_size) {
+        let (translated, _, _) = pipeline.translate(&chunk, count, source, target).await?;
+        translations.extend(translated.split("\n\n").map(str::to_string));
+    }
+    Ok(document.render(&translations).0)
+}
+
+/// Answers an HTTP request, passing the work on to the pipeline.
+async fn handle(request: Request<Body>, jobs: mpsc::Sender<Job>) -> Result<Response<Body>, Infallible> {
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::POST, "/translate") => {
+            let body = match read_body(request).await {
+                Ok(body) => body,
+                Err(response) => return Ok(response),
+            };
+            match serde_json::from_slice::<TranslateRequest>(&body) {
+                Ok(translate_request) => {
+                    let (reply, answer) = oneshot::channel();
+                    let _ = jobs.send(Job::Translate(translate_request, reply)).await;
+                    match answer.await {
+                        Ok(Ok(translated_text)) => json(StatusCode::OK, &TranslateResponse { translated_text }),
+                        Ok(Err(error)) => json(StatusCode::BAD_GATEWAY, &ErrorResponse { error }),
+                        Err(_) => error(StatusCode::SERVICE_UNAVAILABLE, "The server is shutting down"),
+                    }
+                }
+                Err(parse_error) => error(StatusCode::BAD_REQUEST, &format!("Invalid request: {}", parse_error)),
+            }
+        }
+        (&Method::GET, "/languages") => {
+            let (reply, answer) = oneshot::channel();
+            let _ = jobs.send(Job::Languages(reply)).await;
+            match answer.await {
+                Ok(Ok(languages)) => json(StatusCode::OK, &languages),
+                Ok(Err(error)) => json(StatusCode::BAD_GATEWAY, &ErrorResponse { error }),
+                Err(_) => error(StatusCode::SERVICE_UNAVAILABLE, "The server is shutting down"),
+            }
+        }
+        (_, "/translate" | "/languages") => error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed"),
+        _ => error(StatusCode::NOT_FOUND, "Not found"),
+    };
+    Ok(response)
+}
+
+/// Reads the request body, or returns the error response for a body that is too large.
+async fn read_body(request: Request<Body>) -> Result<Vec<u8>, Response<Body>> {
+    let mut body = request.into_body();
+    let mut bytes = Vec::new();
+    while let Some(chunk) = hyper::body::HttpBody::data(&mut body).await {
+        let chunk = chunk.map_err(|read_error| error(StatusCode::BAD_REQUEST, &read_error.to_string()))?;
+        if bytes.len() + chunk.len() > MAX_BODY_SIZE {
+            return Err(error(StatusCode::PAYLOAD_TOO_LARGE, "Request body too large"));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+fn server_error(error: hyper::Error) -> TranslatorError {
+    TranslatorError::Io(io::Error::other(error))
+}
+
+fn json(status: StatusCode, body: &impl Serialize) -> Response<Body> {
+    let body = serde_json::to_vec(body).unwrap_or_default();
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    json(status, &ErrorResponse { error: message.to_string() })
+}
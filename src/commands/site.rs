@@ -0,0 +1,99 @@
+use clap::{ArgMatches, Args};
+use std::fs;
+use std::path::PathBuf;
+use text_translator::TranslatorError;
+use text_translator::format::Format;
+use tracing::error;
+
+use super::{config, Subcommand};
+use super::logging;
+use super::mdbook::{absolute, collect_markdown};
+use super::translate::{self, TranslateOptions};
+
+/// The front matter fields translated unless '--front-matter-field' names others.
+const FRONT_MATTER_FIELDS: &[&str] = &["title", "description"];
+
+/// Jekyll's rendered site, which is never translated.
+const JEKYLL_BUILD_DIR: &str = "_site";
+
+/// Translate the Markdown content of a Hugo or Jekyll site into one directory per language
+#[derive(Args, Debug)]
+pub struct SiteArgs {
+    /// Content directory of the site, e.g. 'content' or 'content/en' for Hugo, or the site itself for Jekyll
+    #[arg(required = true)]
+    content_dir: PathBuf,
+
+    /// Directory the translated pages are written to, where '{target}' is replaced by the target
+    /// language (default: '{target}' next to a content directory named after the source
+    /// language, like 'content/en', and inside the content directory otherwise)
+    #[arg(long)]
+    out_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    options: TranslateOptions,
+}
+
+impl Subcommand for SiteArgs {
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.options.apply_profile(profile, matches)
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(args: SiteArgs) -> Result<(), TranslatorError> {
+    if args.options.export_tmx.is_some() || args.options.export_review.is_some() || args.options.emit_alignment.is_some() || args.options.stats_json.is_some() || args.options.watch {
+        return Err("'--export-tmx', '--export-review', '--emit-alignment', '--stats-json' and '--watch' work on single files; they can't be used with 'site'".into());
+    }
+    let content_dir = args.content_dir.canonicalize()?;
+    let template = match &args.out_dir {
+        Some(out_dir) => out_dir.clone(),
+        None if content_dir.file_name().is_some_and(|name| *name == *args.options.source) => {
+            content_dir.parent().unwrap_or(&content_dir).join("{target}")
+        }
+        None => content_dir.join("{target}"),
+    };
+    let out_dirs: Vec<PathBuf> =
+        args.options.target.iter().map(|target| PathBuf::from(template.to_string_lossy().replace("{target}", target))).collect();
+    // The translations may live inside the content directory; they aren't translated again.
+    let mut skipped = vec![content_dir.join(JEKYLL_BUILD_DIR)];
+    skipped.extend(out_dirs.iter().map(|out_dir| absolute(out_dir)));
+
+    let mut pages = Vec::new();
+    collect_markdown(&content_dir, "", &skipped, &mut pages)?;
+    pages.sort();
+    if !logging::quiet() {
+        println!("Found {} pages to translate in {:?}.", pages.len(), args.content_dir);
+    }
+    let mut options = args.options.clone();
+    options.format = Some(Format::Markdown);
+    if options.front_matter_fields.is_empty() {
+        options.front_matter_fields = FRONT_MATTER_FIELDS.iter().map(|field| field.to_string()).collect();
+    }
+    let mut failed = 0;
+    for (target, out_dir) in args.options.target.iter().zip(&out_dirs) {
+        options.target = vec![target.clone()];
+        for page in &pages {
+            if !logging::quiet() {
+                logging::suspend(|| println!());
+            }
+            let output_file = out_dir.join(page);
+            if let Some(parent) = output_file.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            if let Err(e) = translate::translate_file(content_dir.join(page), Some(output_file), options.clone()).await {
+                error!("Error translating {}: {}", page, e);
+                failed += 1;
+            }
+        }
+        if !logging::quiet() {
+            println!("\nTranslated {} pages into {:?}.", pages.len(), out_dir);
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} pages could not be translated", failed).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,1974 @@
+use clap::{ArgMatches, Args, ValueEnum};
+use futures::stream::{self, StreamExt};
+use indicatif::{HumanDuration, ProgressBar, ProgressState, ProgressStyle};
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use encoding_rs::{Encoding, UTF_8};
+use reqwest::Url;
+use text_translator::TranslatorError;
+use text_translator::encoding::{self, InputEncoding};
+use text_translator::failures::{self, Failure};
+use text_translator::format::docx::Docx;
+use text_translator::format::epub::Epub;
+use text_translator::format::pdf;
+use text_translator::format::web::Article;
+use text_translator::format::csv::Table;
+use text_translator::format::{self, chapters, Bilingual, Document, Format, FormatOptions};
+use text_translator::fuzzy::{FuzzyAction, FuzzyMatching, FuzzyMetric};
+use text_translator::output;
+use text_translator::progress::{Event, Stats};
+use text_translator::backend::languagetool::LanguageToolClient;
+use text_translator::quality::{self, Alternatives, AlternativesReport};
+use text_translator::review_sheet::ReviewRow;
+use text_translator::{
+    check_language_pair, pack_segments, BackendKind, Blocks, truncate_at_char_boundary, Backend, Checkpoint,
+    ChunkWriter, ContextParagraph, FailureReport, Glossary, NoTranslate, Progress, RateLimiter, Redactor, ReviewSheet, Tmx, TranslationCache, Translator, AUTO_LANGUAGE, MAX_CHUNK_SIZE,
+};
+use tracing::{debug, info};
+
+use super::checks::{self, Checked, Flags, TranslatedChunk};
+use super::logging;
+use super::watch::Watcher;
+use super::{config, BackendArgs, PipelineArgs, Subcommand};
+
+/// Exit code used when the run is interrupted with Ctrl-C (128 + SIGINT, as shells report it).
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// The input or output path that stands for standard input or output.
+const STDIO: &str = "-";
+
+/// How many chunks of text '--stream' reads at a time.
+const STREAM_BLOCK_CHUNKS: usize = 16;
+
+/// Output file names used when translating into several languages without '--output-file'.
+const OUTPUT_TEMPLATE: &str = "{stem}.{target}.{ext}";
+
+/// What is done when a translated chunk seems to be in another language than the target.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum LanguageCheck {
+    /// Don't check the language of the translations.
+    Off,
+    /// Warn about the chunk.
+    #[default]
+    Warn,
+    /// Translate the chunk once more, bypassing the cache, and warn if that doesn't help.
+    Retry,
+}
+
+/// How named entities (names, product names, acronyms) are looked after.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntityMode {
+    /// Only report the entities a translation altered.
+    Check,
+    /// Keep the entities out of the text sent to the server, and report those that got lost.
+    Protect,
+}
+
+/// How the translation is divided into several output files.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitOutput {
+    /// One file per chapter ('ch01.md', 'ch02.md', …), starting at the top-level Markdown
+    /// headings or at plain text lines like 'Chapter 3'; text before the first chapter goes to 'ch00'
+    ByHeading,
+}
+
+/// How the progress of a run is shown.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ProgressFormat {
+    /// A progress bar.
+    #[default]
+    Bar,
+    /// One JSON object per event on stderr (chunk_started, chunk_done, retry, failure, finished).
+    Json,
+}
+
+/// Translate a text file
+#[derive(Args, Debug)]
+pub struct TranslateArgs {
+    /// Path to the input text file to translate ('-' reads standard input), or the 'https://' URL
+    /// of a web page whose article is translated into Markdown, or HTML with an '.html' output file
+    #[arg(required = true)]
+    input_file: PathBuf,
+
+    /// Path to the output file (optional, prints to console if not provided; '-' writes only the translation to standard output).
+    /// With several target languages it must be a template like '{stem}.{target}.{ext}'
+    #[arg(short, long)]
+    output_file: Option<PathBuf>,
+
+    /// Replace the input file with its translation, keeping the original as '<file>.bak'
+    #[arg(long, conflicts_with = "output_file")]
+    in_place: bool,
+
+    /// Don't keep a '.bak' copy of the original with '--in-place'
+    #[arg(long, requires = "in_place")]
+    no_backup: bool,
+
+    #[command(flatten)]
+    options: TranslateOptions,
+}
+
+/// How files are translated, shared by `translate` and `translate-dir`.
+#[derive(Args, Debug, Clone)]
+pub struct TranslateOptions {
+    /// Format of the input file (guessed from the file extension by default)
+    #[arg(long, value_enum)]
+    pub(super) format: Option<Format>,
+
+    /// Don't flag machine-translated gettext entries as 'fuzzy'
+    #[arg(long)]
+    no_fuzzy: bool,
+
+    #[command(flatten)]
+    backend: BackendArgs,
+
+    /// Source language for translation (e.g., 'en', or 'auto' to detect it)
+    #[arg(short, long, default_value = "en")]
+    pub(super) source: String,
+
+    /// Target language for translation (e.g., 'hu'), or several separated by commas (e.g., 'hu,de,fr')
+    #[arg(short, long, default_value = "hu", value_delimiter = ',')]
+    pub(super) target: Vec<String>,
+
+    /// Don't ask the server whether it supports the source and target languages before translating
+    #[arg(long)]
+    skip_language_check: bool,
+
+    /// Front matter field of Markdown files whose value is translated, e.g. 'title' (repeatable)
+    #[arg(long = "front-matter-field", value_name = "FIELD")]
+    pub(super) front_matter_fields: Vec<String>,
+
+    /// Only translate the values of keys matching this glob in .properties and .env files, e.g. 'app.title.*' (repeatable)
+    #[arg(long = "key", value_name = "GLOB")]
+    keys: Vec<String>,
+
+    /// Columns of CSV and TSV files to translate, by number (from 1) or header name, e.g. '2,5' or 'Description' (default: all)
+    #[arg(long, value_delimiter = ',', value_name = "COLUMNS")]
+    columns: Vec<String>,
+
+    /// Only translate the strings of a JSON document matching this JSONPath, e.g. '$.items[*].description' (repeatable)
+    #[arg(long, value_name = "JSONPATH")]
+    select: Vec<String>,
+
+    /// Only translate the content of the XML elements matching this XPath, e.g. '//note[@type="public"]' (repeatable)
+    #[arg(long, value_name = "XPATH")]
+    select_xpath: Vec<String>,
+
+    /// Keep placeholders such as '%s', '{0}', '{name}', '{{name}}' or '${name}' untranslated, and
+    /// fail the chunks whose translation loses or mangles any of them
+    #[arg(long)]
+    protect_placeholders: bool,
+
+    /// Leave paragraphs that are already in the target language as they are instead of sending
+    /// them, e.g. in partially translated documents
+    #[arg(long)]
+    skip_target_language: bool,
+
+    /// Keep the original text next to its translation, for proofreading: each paragraph followed
+    /// by its translation, or both side by side in a Markdown table (plain text and Markdown only)
+    #[arg(long, value_enum, value_name = "LAYOUT")]
+    bilingual: Option<Bilingual>,
+
+    /// Write the translation as several files into a directory, the output file or '<stem>.<target>'
+    /// next to the input, for easier proofreading (plain text and Markdown only)
+    #[arg(long, value_enum, value_name = "MODE", conflicts_with_all = ["bilingual", "stream"])]
+    split_output: Option<SplitOutput>,
+
+    /// Reuse the cached translation of a paragraph at least this similar (in percent) to the one
+    /// being translated, instead of translating it again
+    #[arg(long, value_name = "PERCENT", conflicts_with = "no_cache", value_parser = clap::value_parser!(u8).range(1..=100))]
+    fuzzy_threshold: Option<u8>,
+
+    /// How the similarity of paragraphs is measured for '--fuzzy-threshold'
+    #[arg(long, value_enum, default_value_t)]
+    fuzzy_metric: FuzzyMetric,
+
+    /// Whether translations of similar paragraphs are reused as they are, or flagged with a
+    /// '[[FUZZY MATCH n%]]' marker for review
+    #[arg(long, value_enum, default_value_t)]
+    fuzzy_action: FuzzyAction,
+
+    /// Check that every translated chunk is in the target language, as some servers occasionally
+    /// return the source text untouched
+    #[arg(long, value_enum, default_value_t)]
+    language_check: LanguageCheck,
+
+    /// Don't check translations for suspicious lengths, untranslated paragraphs and dropped numbers,
+    /// dates, measurements or URLs
+    #[arg(long)]
+    no_sanity_check: bool,
+
+    /// Check that names, product names and acronyms of the original appear unchanged in the
+    /// translation, listing those that don't in a report next to the output; 'protect' also
+    /// keeps them from being translated
+    #[arg(long, value_enum, value_name = "MODE")]
+    entities: Option<EntityMode>,
+
+    /// Check whether terms that recur in the file were translated the same way every time, and
+    /// list those that weren't, with their segments, in a report next to the output
+    #[arg(long)]
+    check_consistency: bool,
+
+    /// Like '--check-consistency', and replace the other translations of such terms with the
+    /// most frequent one (the word is replaced, its suffixes aren't adapted)
+    #[arg(long, conflicts_with_all = ["bilingual", "split_output"])]
+    fix_consistency: bool,
+
+    /// Check the grammar and spelling of the translations with LanguageTool, and list the mistakes
+    /// of every segment in a report next to the output and in the '--export-review' sheet
+    #[arg(long)]
+    check_grammar: bool,
+
+    /// The LanguageTool server to check with (defaults to the public API, which takes 20 requests per minute)
+    #[arg(long, value_name = "URL", requires = "check_grammar")]
+    languagetool_url: Option<String>,
+
+    /// Account name for LanguageTool Premium
+    #[arg(long, requires = "check_grammar")]
+    languagetool_username: Option<String>,
+
+    /// API key for LanguageTool Premium
+    #[arg(long, env = "TRANSLATOR_LANGUAGETOOL_API_KEY", hide_env_values = true)]
+    languagetool_api_key: Option<String>,
+
+    /// Translate every chunk back into the source language and list those that come back too
+    /// different from the original in a review report, as likely mistranslations
+    #[arg(long)]
+    verify_roundtrip: bool,
+
+    /// chrF score (0-100) of the back-translation below which '--verify-roundtrip' flags a chunk
+    #[arg(long, value_name = "SCORE", default_value_t = 50.0, requires = "verify_roundtrip")]
+    roundtrip_threshold: f64,
+
+    /// Ask for up to N other translations of every paragraph and list them in a report next to
+    /// the output, for an editor to pick from (only offered by the 'libretranslate' backend;
+    /// one more request per paragraph)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    alternatives: usize,
+
+    /// Save every paragraph of the run with its translations as a TMX translation memory
+    #[arg(long, value_name = "FILE")]
+    pub(super) export_tmx: Option<PathBuf>,
+
+    /// Save every segment with its translation and the problems the checks found with it as a
+    /// CSV sheet ('id,source,translation,flags') to edit, and patch back with 'import-review'
+    #[arg(long, value_name = "FILE")]
+    pub(super) export_review: Option<PathBuf>,
+
+    /// Save the translated segments with their originals as a parallel corpus: one pair per line,
+    /// source and translation separated by a tab
+    #[arg(long, value_name = "FILE")]
+    pub(super) emit_alignment: Option<PathBuf>,
+
+    /// Save the statistics of the run (characters, cache hits, retries, errors, latency and
+    /// throughput) as JSON, to compare the cost and speed of backends across runs
+    #[arg(long, value_name = "FILE")]
+    pub(super) stats_json: Option<PathBuf>,
+
+    /// Keep running and translate the input again whenever it changes; unchanged paragraphs come from the cache
+    #[arg(long)]
+    pub(super) watch: bool,
+
+    /// Continue an interrupted run, skipping chunks already stored in the checkpoint file
+    #[arg(long)]
+    resume: bool,
+
+    /// How progress is reported: a progress bar, or JSON lines on stderr for CI pipelines and other programs
+    #[arg(long, value_enum, default_value_t)]
+    pub(super) progress: ProgressFormat,
+
+    /// Read and translate plain text or CSV block by block, writing each translated block before
+    /// reading the next, so files larger than memory can be translated (one target language only)
+    #[arg(long, conflicts_with_all = ["bilingual", "resume", "verify_roundtrip", "alternatives", "export_tmx", "export_review", "emit_alignment", "entities", "check_consistency", "fix_consistency", "check_grammar", "dry_run", "best_effort", "context_paragraphs"])]
+    stream: bool,
+
+    /// Encoding of the input file, e.g. 'windows-1250' or 'iso-8859-2', or 'auto' to detect it
+    #[arg(long, value_name = "ENCODING", default_value = "auto")]
+    pub(super) input_encoding: InputEncoding,
+
+    /// Encoding of the output file (by default the encoding of the input, so legacy files round-trip)
+    #[arg(long, value_name = "ENCODING", value_parser = encoding::parse_encoding)]
+    output_encoding: Option<&'static Encoding>,
+
+    /// Only split the input into chunks and show how long (and for paid services, how much) translating it would take
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Keep going when a chunk can't be translated: it is left in the source language behind an
+    /// '[[UNTRANSLATED CHUNK n]]' marker and listed in a failure report for 'retry-failed'
+    #[arg(long)]
+    best_effort: bool,
+
+    #[command(flatten)]
+    pub(super) pipeline: PipelineArgs,
+
+    /// Send the last N translated paragraphs before each chunk along as context, so pronouns and
+    /// terms are translated consistently; backends that don't accept context ignore it. With
+    /// '--concurrency' above 1 a chunk only sees the chunks finished when it is sent
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    context_paragraphs: usize,
+
+    /// Number of chunks translated in parallel
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    concurrency: u32,
+
+    /// Find the pace the server allows instead of keeping a fixed one: start at 60 requests per
+    /// minute, halve the rate whenever the server rejects a request (429) or fails (5xx), and
+    /// speed up again slowly while it answers
+    #[arg(long, conflicts_with_all = ["requests_per_minute", "request_delay"])]
+    adaptive_pacing: bool,
+
+    /// Maximum chunk size in bytes; servers with a larger character limit can take bigger chunks
+    #[arg(long, default_value_t = MAX_CHUNK_SIZE as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    chunk_size: u64,
+
+    /// The bar of a whole 'translate-dir' run, advanced by the bytes of every chunk translated.
+    #[arg(skip)]
+    pub(super) overall_bar: Option<ProgressBar>,
+}
+
+impl Subcommand for TranslateArgs {
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.options.apply_profile(profile, matches)
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+impl TranslateOptions {
+    /// Fills in the options that weren't given on the command line from a config profile.
+    pub fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.backend.apply_profile(profile, matches)?;
+        config::apply(matches, "source", &mut self.source, profile.source.clone());
+        config::apply(matches, "target", &mut self.target, profile.target.as_ref().map(config::List::to_vec));
+        self.pipeline.apply_profile(profile, matches);
+        let fields = profile.front_matter_fields.clone();
+        config::apply(matches, "front_matter_fields", &mut self.front_matter_fields, fields);
+        config::apply(matches, "keys", &mut self.keys, profile.keys.clone());
+        config::apply(matches, "protect_placeholders", &mut self.protect_placeholders, profile.protect_placeholders);
+        config::apply(matches, "skip_target_language", &mut self.skip_target_language, profile.skip_target_language);
+        config::apply(matches, "concurrency", &mut self.concurrency, profile.concurrency);
+        config::apply(matches, "adaptive_pacing", &mut self.adaptive_pacing, profile.adaptive_pacing);
+        config::apply(matches, "chunk_size", &mut self.chunk_size, profile.chunk_size);
+        Ok(())
+    }
+}
+
+pub async fn run(mut args: TranslateArgs) -> Result<(), TranslatorError> {
+    if web_url(&args.input_file).is_some() && (args.in_place || args.options.watch) {
+        return Err("A web page can't be translated '--in-place' or watched with '--watch'".into());
+    }
+    if args.in_place {
+        prepare_in_place(&args)?;
+        args.output_file = Some(args.input_file.clone());
+    }
+    if !args.options.watch {
+        return translate_file(args.input_file, args.output_file, args.options).await.map(|_| ());
+    }
+    if args.input_file == Path::new(STDIO) {
+        return Err("'--watch' needs an input file to watch, not standard input".into());
+    }
+
+    let input_file = args.input_file.canonicalize()?;
+    let mut watcher = Watcher::new(input_file.parent().unwrap_or(Path::new("/")), false)?;
+    translate_file(args.input_file.clone(), args.output_file.clone(), args.options.clone()).await?;
+    println!("\nWatching {:?} for changes (Ctrl-C to stop).", args.input_file);
+    loop {
+        let changes = tokio::select! {
+            changes = watcher.changes() => changes?,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+        if changes.contains(&input_file) {
+            // A failed run doesn't end watching; the next change may fix it.
+            if let Err(error) = translate_file(args.input_file.clone(), args.output_file.clone(), args.options.clone()).await {
+                eprintln!("Error: {}", error);
+            }
+            println!("\nWatching {:?} for changes (Ctrl-C to stop).", args.input_file);
+        }
+    }
+}
+
+/// Checks that the input can be replaced by its translation and backs it up unless told not to.
+fn prepare_in_place(args: &TranslateArgs) -> Result<(), TranslatorError> {
+    let format = args.options.format.unwrap_or_else(|| Format::from_path(&args.input_file));
+    if args.input_file == Path::new(STDIO) {
+        return Err("'--in-place' needs an input file to replace, not standard input".into());
+    }
+    if args.options.target.len() > 1 {
+        return Err("'--in-place' replaces the input with one translation; give a single target language".into());
+    }
+    if args.options.watch {
+        return Err("'--in-place' can't be combined with '--watch', as every translation would change the input again".into());
+    }
+    if format == Format::Pdf {
+        return Err("PDF files are translated into plain text and can't be replaced by their translation".into());
+    }
+    if !args.no_backup {
+        let mut backup = args.input_file.as_os_str().to_owned();
+        backup.push(".bak");
+        fs::copy(&args.input_file, &backup)?;
+        println!("Original saved to: {:?}", PathBuf::from(backup));
+    }
+    Ok(())
+}
+
+/// Translates one file into every target language, returning the number of chunks it was split into.
+///
+/// The input is read and split into chunks once; with several targets, the output file is a
+/// template (see [`output_path`]).
+pub async fn translate_file(
+    input_file: PathBuf,
+    output_file: Option<PathBuf>,
+    args: TranslateOptions,
+) -> Result<usize, TranslatorError> {
+    let from_stdin = input_file == Path::new(STDIO);
+    let to_stdout = output_file.as_deref() == Some(Path::new(STDIO));
+    // When the translation goes to stdout, it's the only thing written there.
+    let console = Console { quiet: to_stdout };
+    let multiple_targets = args.target.len() > 1;
+    if multiple_targets && to_stdout {
+        return Err("Only one target language can be written to standard output".into());
+    }
+    if multiple_targets && output_file.as_ref().is_some_and(|path| !path.to_string_lossy().contains("{target}")) {
+        return Err("With several target languages the output file must contain '{target}', e.g. '{stem}.{target}.{ext}'".into());
+    }
+    if multiple_targets && args.export_review.as_ref().is_some_and(|path| !path.to_string_lossy().contains("{target}")) {
+        return Err("With several target languages the review sheet must contain '{target}', e.g. '{stem}.{target}.csv'".into());
+    }
+    if multiple_targets && args.emit_alignment.as_ref().is_some_and(|path| !path.to_string_lossy().contains("{target}")) {
+        return Err("With several target languages the alignment file must contain '{target}', e.g. '{stem}.{target}.tsv'".into());
+    }
+
+    // A web page is fetched and its article translated, named like a local file from then on.
+    let (input_file, page) = match web_url(&input_file) {
+        Some(_) if args.stream => return Err("A web page can't be streamed; translate it without '--stream'".into()),
+        Some(url) => {
+            console.info(format_args!("Fetching page: {}", url));
+            let (name, page) = fetch_article(&url, &args, output_file.as_deref()).await?;
+            (name, Some(page))
+        }
+        None => (input_file, None),
+    };
+
+    if args.stream {
+        return translate_stream(input_file, output_file, args).await;
+    }
+
+    // 1. Read the input file
+    console.info(format_args!("Reading file: {:?}", input_file));
+    let format = args.format.unwrap_or_else(|| Format::from_path(&input_file));
+    if args.bilingual.is_some() && !Bilingual::supports(format) {
+        return Err(format!("Bilingual output can only be made from plain text and Markdown, not {:?} files", format).into());
+    }
+    if args.split_output.is_some() && !matches!(format, Format::Text | Format::Markdown) {
+        return Err(format!("Only plain text and Markdown can be split into chapters, not {:?} files", format).into());
+    }
+    if args.split_output.is_some() && to_stdout {
+        return Err("'--split-output' writes several files; it can't write to standard output".into());
+    }
+    if args.fix_consistency && (to_stdout || matches!(format, Format::Epub | Format::Docx)) {
+        return Err(format!("'--fix-consistency' rewrites the output file; it can't fix {:?} files or standard output", format).into());
+    }
+    let chunk_size = args.chunk_size as usize;
+    let mut crlf = false;
+    let mut input_encoding = UTF_8;
+    let options = FormatOptions {
+        mark_fuzzy: !args.no_fuzzy,
+        target_language: args.target.first().cloned(),
+        max_segment_len: chunk_size,
+        front_matter_fields: args.front_matter_fields.clone(),
+        keys: args.keys.clone(),
+        columns: args.columns.clone(),
+        select: args.select.clone(),
+        select_xpath: args.select_xpath.clone(),
+        source_language: (args.source != AUTO_LANGUAGE).then(|| args.source.clone()),
+    };
+    let (content, documents, container) = if matches!(format, Format::Epub | Format::Docx) {
+        if from_stdin || to_stdout {
+            return Err(format!("{:?} files can't be read from standard input or written to standard output", format).into());
+        }
+        let (documents, container) = match format {
+            Format::Epub => {
+                let epub = Epub::open(&input_file)?;
+                (epub.documents()?, Container::Epub(epub))
+            }
+            _ => {
+                let docx = Docx::open(&input_file)?;
+                (docx.documents()?, Container::Docx(docx))
+            }
+        };
+        // The text of the book or document, used to detect its language.
+        let content = documents.iter().flat_map(Document::segments).collect::<Vec<_>>().join("\n\n");
+        (content, documents, Some(container))
+    } else if format == Format::Pdf {
+        if from_stdin {
+            return Err("PDF files can't be read from standard input".into());
+        }
+        // The text is written as Markdown when the output file asks for it, and as plain text otherwise.
+        let markdown = output_file.as_deref().is_some_and(|path| Format::from_path(path) == Format::Markdown);
+        let pages = pdf::extract_pages(&input_file)?;
+        console.info(format_args!("Extracted the text of {} pages.", pages.len()));
+        let document = pdf::parse(&pages, markdown, chunk_size);
+        let content = document.segments().join("\n\n");
+        (content, vec![document], None)
+    } else {
+        let bytes = if let Some(page) = page {
+            page.into_bytes()
+        } else if from_stdin {
+            let mut bytes = Vec::new();
+            io::stdin().read_to_end(&mut bytes)?;
+            bytes
+        } else {
+            fs::read(&input_file)?
+        };
+        let (raw, encoding) = encoding::decode(&bytes, args.input_encoding, &args.source)?;
+        if encoding != UTF_8 {
+            console.info(format_args!("Input encoding: {}", encoding.name()));
+        }
+        input_encoding = encoding;
+        // Line endings are normalized for parsing and restored in the output.
+        crlf = raw.contains("\r\n");
+        let content = raw.replace("\r\n", "\n");
+        let document = format::parse(format, &content, &options)?;
+        (content, vec![document], None)
+    };
+    let line_endings = |text: String| if crlf { text.replace('\n', "\r\n") } else { text };
+    // Legacy files are written back in their own encoding unless another one is asked for.
+    let output_encoding = args.output_encoding.unwrap_or(input_encoding);
+    if content.is_empty() {
+        console.info("Input file is empty. Nothing to translate.");
+        return Ok(0);
+    }
+
+    // 2. Split content into chunks to respect the API limit: the translatable segments (the
+    // paragraphs of plain text) are packed together.
+    let segments: Vec<&str> = documents.iter().flat_map(Document::segments).collect();
+    let (chunks, segment_counts): (Vec<String>, Vec<usize>) = pack_segments(&segments, chunk_size).into_iter().unzip();
+
+    console.info(format_args!("Text split into {} chunks for translation.", chunks.len()));
+
+    let no_translate = args.pipeline.text.no_translate()?
+        .with_placeholders(args.protect_placeholders)
+        .with_entities(args.entities == Some(EntityMode::Protect));
+    let redactor = args.pipeline.text.redactor()?;
+    let glossary = args.pipeline.text.glossary()?;
+    if let Some(glossary) = &glossary {
+        console.info(format_args!("Loaded {} glossary terms.", glossary.len()));
+    }
+
+    let cache = open_cache(&args)?;
+
+    if args.dry_run {
+        let pipeline_parts = (&redactor, &no_translate, glossary.as_ref(), cache.as_ref());
+        print_plan(&chunks, &args, pipeline_parts)?;
+        return Ok(chunks.len());
+    }
+
+    // 3. Translate each chunk
+    let progress = match args.progress {
+        ProgressFormat::Json => Progress::json(),
+        ProgressFormat::Bar if console.quiet => Progress::hidden(),
+        // The bar counts bytes, as chunks differ a lot in size.
+        ProgressFormat::Bar => Progress::new(logging::add_bar(ProgressBar::new(chunks.iter().map(|chunk| chunk.len() as u64).sum()))),
+    };
+    let bar = progress.bar().clone();
+    if args.overall_bar.is_some() {
+        bar.set_prefix(input_file.display().to_string());
+    }
+    let translator = args.backend.build(progress.clone())?.with_text_format(format.text_format());
+
+    // With '--source auto', ask the server which language the text is written in.
+    let source = if args.source == AUTO_LANGUAGE && translator.supports_detection() {
+        let detection = translator
+            .detect(&redactor.redact(truncate_at_char_boundary(&content, chunk_size)).0)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or("The server could not detect the source language")?;
+        console.info(format_args!(
+            "Detected source language: {} (confidence {:.1}%)",
+            detection.language, detection.confidence
+        ));
+        detection.language
+    } else {
+        args.source.clone()
+    };
+
+    // Fail fast on language pairs the server can't translate, before spending time on requests.
+    if !args.skip_language_check && translator.supports_language_list() && source != AUTO_LANGUAGE {
+        let languages = translator.languages().await?;
+        for target in &args.target {
+            check_language_pair(&languages, &source, target)?;
+        }
+    }
+
+    if args.verify_roundtrip && source == AUTO_LANGUAGE {
+        return Err("'--verify-roundtrip' needs the source language to translate back into; give it with '--source'".into());
+    }
+
+    // The server's rate limit headers adjust the pace as the run goes.
+    let limiter = Arc::new(rate_limiter(&args)?);
+    let translator = translator.with_rate_limiter(limiter.clone());
+
+    let grammar_checker = match args.check_grammar {
+        true => Some(
+            LanguageToolClient::new(args.backend.http_client()?, args.languagetool_url.clone())
+                .with_credentials(args.languagetool_username.clone().zip(args.languagetool_api_key.clone()))
+                .with_progress(progress.clone())
+                .with_retry_policy(args.backend.retry_policy()?),
+        ),
+        false => None,
+    };
+
+    let mut tmx = args.export_tmx.as_ref().map(|_| Tmx::new(&source));
+    let mut run_stats = Vec::new();
+    // Boilerplate such as headers and footers is translated once and reused for its repetitions.
+    let duplicate_of = duplicates(&chunks);
+    let repeated: HashSet<usize> = duplicate_of.iter().flatten().copied().collect();
+
+    // Ctrl-C stops the run between or during requests; finished chunks are already on disk.
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    for target in &args.target {
+        let output_file = match &output_file {
+            Some(template) if multiple_targets => Some(output_path(template, &input_file, target)),
+            Some(path) => Some(path.clone()),
+            // Locale files named after their language, like `en.json`, get a sibling named after the target.
+            None if matches!(format, Format::Json | Format::Yaml)
+                && input_file.file_stem().is_some_and(|stem| stem == args.source.as_str()) =>
+            {
+                Some(input_file.with_file_name(target).with_extension(input_file.extension().unwrap_or_default()))
+            }
+            // The chapters go into a directory named after the input and the language.
+            None if args.split_output.is_some() => {
+                Some(input_file.with_file_name(format!("{}.{}", input_file.file_stem().unwrap_or_default().to_string_lossy(), target)))
+            }
+            // The text of a PDF can't be written back into one.
+            None if format == Format::Pdf => Some(input_file.with_extension(format!("{}.txt", target))),
+            // Resource files go where the app looks up the resources of the target language.
+            None if format.resource_path(&input_file, target).is_some() => format.resource_path(&input_file, target),
+            None if multiple_targets => Some(output_path(Path::new(OUTPUT_TEMPLATE), &input_file, target)),
+            // A book or Word document can't be printed to the console, so it is saved next to the original.
+            None if container.is_some() => {
+                Some(input_file.with_extension(format!("{}.{}", target, input_file.extension().unwrap_or_default().to_string_lossy())))
+            }
+            None => None,
+        };
+        if let (Some(_), Some(output_dir)) = (args.split_output, &output_file) {
+            fs::create_dir_all(output_dir)?;
+        }
+        // A resource file may be the first of its language.
+        let resource_dir = output_file.as_deref().filter(|_| matches!(format, Format::Android | Format::Ios)).and_then(Path::parent);
+        if let Some(resource_dir) = resource_dir {
+            fs::create_dir_all(resource_dir)?;
+        }
+        // XLIFF and Qt files record the target language outside of the segments, so they are parsed again.
+        let documents = match format {
+            Format::Xliff | Format::Qt if Some(target) != options.target_language.as_ref() => {
+                let options = FormatOptions { target_language: Some(target.clone()), ..options.clone() };
+                vec![format::parse(format, &content, &options)?]
+            }
+            _ => documents.clone(),
+        };
+        let started = Instant::now();
+        let (retries_before, cache_hits_before) = (progress.retries(), cache.as_ref().map_or(0, TranslationCache::hits));
+        if multiple_targets {
+            console.info(format_args!("\nTranslating into {}.", target));
+            bar.reset();
+        }
+        // The chunks found in the cache need no request, the others wait for the rate limiter.
+        let pipeline_parts = (&redactor, &no_translate, glossary.as_ref(), cache.as_ref());
+        let cached = cached_chunks(&chunks, &source, target, pipeline_parts)?;
+        let sent = |index: usize| !cached[index] && duplicate_of[index].is_none();
+        let pending_requests = Arc::new(AtomicUsize::new((0..chunks.len()).filter(|&index| sent(index)).count()));
+        bar.set_style(bar_style(args.overall_bar.is_some(), limiter.clone(), pending_requests.clone()));
+
+        // Every translated chunk is persisted next to the output (or input) file, so a crash doesn't lose them.
+        // A pipeline from stdin to stdout has no file to put it next to.
+        let sidecar_file = [output_file.as_ref(), Some(&input_file)]
+            .into_iter()
+            .flatten()
+            .find(|path| path.as_path() != Path::new(STDIO))
+            .cloned();
+        let checkpoint_path = sidecar_file.as_deref().map(Checkpoint::path_for);
+        let mut checkpoint = Checkpoint::new(&source, target, &chunks);
+        if let Some(checkpoint_path) = checkpoint_path.as_ref().filter(|path| args.resume && path.exists()) {
+            let saved = Checkpoint::load(checkpoint_path)?;
+            if saved.matches(&source, target, &chunks) {
+                console.info(format_args!(
+                    "Resuming from {:?}: {} of {} chunks already translated.",
+                    checkpoint_path,
+                    saved.completed(),
+                    chunks.len()
+                ));
+                checkpoint = saved;
+            } else {
+                console.info(format_args!(
+                    "Checkpoint {:?} belongs to a different input or language pair; starting over.",
+                    checkpoint_path
+                ));
+            }
+        }
+
+        // A single document is rendered piece by piece as its chunks complete, and with an output
+        // file streamed to disk. A book or Word document can only be written once every part is translated.
+        let mut translated_chunks: Vec<TranslatedChunk> = Vec::new();
+        let mut output = String::new();
+        let mut rendered_segments = 0;
+        let mut lost_placeholders = 0;
+        let mut writer = match container {
+            None if !to_stdout && args.split_output.is_none() => output_file
+                .as_deref()
+                .map(|path| ChunkWriter::create(path).map(|writer| writer.with_encoding(output_encoding)))
+                .transpose()?,
+            _ => None,
+        };
+        let mut interrupted = false;
+        let mut failed_chunks = Vec::new();
+        let mut lost_glossary_terms = 0;
+        let mut lost_no_translate_spans = 0;
+        let mut alternative_paragraphs = Vec::new();
+        let mut latencies = Vec::new();
+        // The translations of chunks that are repeated later in the file, or the errors they failed with.
+        let mut repeated_translations: HashMap<usize, Result<String, String>> = HashMap::new();
+        let (mut duplicates, mut duplicate_characters) = (0, 0);
+        // The translation of every segment, `None` for those of failed chunks.
+        let mut segment_translations: Vec<Option<String>> = Vec::new();
+
+        let resumed: Vec<Option<String>> = (0..chunks.len())
+            .map(|index| checkpoint.translation(index).map(str::to_string))
+            .collect();
+        let resumed_chunks = resumed.iter().filter(|translation| translation.is_some()).count();
+
+        // Chunks are translated concurrently, but `buffered` yields the results in their original order.
+        let pipeline = Pipeline {
+            translator: &translator,
+            cache: cache.as_ref(),
+            limiter: &limiter,
+            redactor: &redactor,
+            no_translate: &no_translate,
+            glossary: glossary.as_ref(),
+            skip_target_language: args.skip_target_language,
+        };
+        let context_window = ContextWindow::new(args.context_paragraphs);
+        let (pipeline, progress, source, target) = (&pipeline, &progress, source.as_str(), target.as_str());
+        let grammar_checker = grammar_checker.as_ref();
+        let context_window = &context_window;
+        let duplicate_of = &duplicate_of;
+        let total = chunks.len();
+        // Pseudo-translations stay in the source language.
+        let language_check = if args.backend.kind() == BackendKind::Mock { LanguageCheck::Off } else { args.language_check };
+        let mut results = stream::iter(chunks.iter().zip(segment_counts.iter().copied()).zip(resumed).enumerate())
+            .map(|(index, ((chunk, segment_count), resumed))| async move {
+                let sent = Instant::now();
+                let context = context_window.before(index);
+                let mut result = match resumed {
+                    Some(translated) => {
+                        context_window.record(index, chunk, &translated);
+                        return (Ok((translated, 0, 0)), None, None, None, None, None);
+                    }
+                    // The translation of the first identical chunk is taken once it's done.
+                    None if duplicate_of[index].is_some() => return (Ok((String::new(), 0, 0)), None, None, None, None, None),
+                    None => {
+                        progress.emit(&Event::ChunkStarted { chunk: index + 1, chunks: total, target });
+                        pipeline.translate_in_context(chunk, segment_count, source, target, &context).await
+                    }
+                };
+                let latency = sent.elapsed();
+                let detect = |result: &Result<(String, usize, usize), _>| match result {
+                    Ok((translated, _, _)) if language_check != LanguageCheck::Off => {
+                        quality::wrong_language(translated, source, target)
+                    }
+                    _ => None,
+                };
+                let mut wrong_language = detect(&result);
+                if wrong_language.is_some() && language_check == LanguageCheck::Retry {
+                    // The cache may hold the wrong translation, so the retry goes to the server.
+                    let uncached = Pipeline { cache: None, ..*pipeline };
+                    let retried = uncached.translate_in_context(chunk, segment_count, source, target, &context).await;
+                    if retried.is_ok() {
+                        wrong_language = detect(&retried);
+                        result = retried;
+                    }
+                }
+                if let Ok((translated, _, _)) = &result {
+                    context_window.record(index, chunk, translated);
+                }
+                let back_translation = match &result {
+                    Ok((translated, _, _)) if args.verify_roundtrip => {
+                        Some(pipeline.back_translate(translated, segment_count, source, target).await)
+                    }
+                    _ => None,
+                };
+                let alternatives = match &result {
+                    Ok(_) if args.alternatives > 0 => Some(pipeline.alternatives(chunk, source, target, args.alternatives).await),
+                    _ => None,
+                };
+                let grammar = match (&result, grammar_checker) {
+                    (Ok((translated, _, _)), Some(checker)) => Some(checker.check(translated, target).await),
+                    _ => None,
+                };
+                (result, back_translation, alternatives, grammar, wrong_language, Some(latency))
+            })
+            .buffered(args.concurrency as usize)
+            .enumerate();
+
+        loop {
+            let next = tokio::select! {
+                next = results.next() => next,
+                _ = &mut ctrl_c => {
+                    interrupted = true;
+                    break;
+                }
+            };
+            let Some((index, (mut result, back_translation, alternatives, grammar, wrong_language, latency))) = next else { break };
+            latencies.extend(latency);
+            if let (Some(first), None) = (duplicate_of[index], checkpoint.translation(index)) {
+                duplicates += 1;
+                duplicate_characters += chunks[index].chars().count();
+                result = match &repeated_translations[&first] {
+                    Ok(translated) => Ok((translated.clone(), 0, 0)),
+                    Err(error) => Err(format!("Same text as chunk {}, which failed: {}", first + 1, error).into()),
+                };
+                context_window.record(index, &chunks[index], result.as_ref().map_or("", |(translated, _, _)| translated));
+            }
+            if repeated.contains(&index) {
+                let translation = result.as_ref().map(|(translated, _, _)| translated.clone()).map_err(ToString::to_string);
+                repeated_translations.insert(index, translation);
+            }
+            let (translated, lost_terms, lost_originals) = match result {
+                Ok(result) => result,
+                Err(error) if args.best_effort => {
+                    progress.emit(&Event::Failure { chunk: index + 1, target, error: error.to_string() });
+                    console.warn(format_args!("Chunk {} could not be translated: {}", index + 1, error));
+                    failed_chunks.push(Failure { chunk: index + 1, error: error.to_string(), text: chunks[index].clone() });
+                    (failures::mark_untranslated(index + 1, &chunks[index]), 0, 0)
+                }
+                Err(error) => {
+                    progress.emit(&Event::Failure { chunk: index + 1, target, error: error.to_string() });
+                    return Err(error);
+                }
+            };
+            lost_glossary_terms += lost_terms;
+            lost_no_translate_spans += lost_originals;
+            match alternatives {
+                Some(Ok(alternatives)) => {
+                    let paragraphs = chunks[index].split("\n\n").zip(translated.split("\n\n")).zip(alternatives);
+                    alternative_paragraphs.extend(paragraphs.filter(|(_, alternatives)| !alternatives.is_empty()).map(
+                        |((text, translation), alternatives)| Alternatives {
+                            chunk: index + 1,
+                            text: text.to_string(),
+                            translation: translation.to_string(),
+                            alternatives,
+                        },
+                    ));
+                }
+                Some(Err(error)) => console.warn(format_args!("No alternatives for chunk {}: {}", index + 1, error)),
+                None => {}
+            }
+
+            // Failed chunks are not stored, so '--resume' tries them again.
+            let failed = failed_chunks.last().is_some_and(|failure| failure.chunk == index + 1);
+            if failed {
+                segment_translations.extend(std::iter::repeat_n(None, segment_counts[index]));
+            } else {
+                segment_translations.extend(translated.split("\n\n").map(|segment| Some(segment.to_string())));
+            }
+            if !failed && checkpoint.translation(index).is_none() {
+                checkpoint.record(index, translated.clone());
+                if let Some(checkpoint_path) = &checkpoint_path {
+                    checkpoint.save(checkpoint_path)?;
+                }
+            }
+            if container.is_none() && args.split_output.is_none() {
+                let translations: Vec<String> = translated.split("\n\n").map(str::to_string).collect();
+                let segments = rendered_segments..rendered_segments + translations.len();
+                let to_end = index + 1 == chunks.len();
+                let (text, lost) = match args.bilingual {
+                    Some(layout) => {
+                        documents[0].render_bilingual_range(segments, &translations, to_end, layout, (source, target))
+                    }
+                    None => documents[0].render_range(segments, &translations, to_end),
+                };
+                rendered_segments += translations.len();
+                lost_placeholders += lost;
+                match writer.as_mut() {
+                    Some(writer) => writer.write(&line_endings(text))?,
+                    None if to_stdout => write_stdout(&line_endings(text), output_encoding)?,
+                    None => output.push_str(&text),
+                }
+            }
+            bar.inc(chunks[index].len() as u64);
+            if sent(index) {
+                pending_requests.fetch_sub(1, Ordering::Relaxed);
+            }
+            if let Some(overall) = &args.overall_bar {
+                overall.inc(chunks[index].len() as u64);
+            }
+            if !failed {
+                let characters = chunks[index].chars().count();
+                info!("Chunk {} of {} translated into {} ({} characters).", index + 1, chunks.len(), target, characters);
+                progress.emit(&Event::ChunkDone { chunk: index + 1, chunks: chunks.len(), target, characters });
+            }
+            translated_chunks.push(TranslatedChunk {
+                text: &chunks[index],
+                translation: translated,
+                failed,
+                segment_count: segment_counts[index],
+                wrong_language,
+                back_translation,
+                grammar,
+            });
+        }
+
+        if interrupted {
+            bar.abandon();
+            report_interruption(&checkpoint, checkpoint_path.as_deref(), writer.as_ref(), &line_endings(output));
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+
+        if args.overall_bar.is_some() {
+            bar.finish_and_clear();
+        } else {
+            bar.finish_with_message("Translation complete!");
+        }
+        let elapsed = started.elapsed().as_secs_f64();
+        let characters = chunks.iter().map(|chunk| chunk.chars().count()).sum();
+        let stats = Stats {
+            input_file: input_file.clone(),
+            output_file: output_file.clone(),
+            backend: args.backend.kind().to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default(),
+            source: source.to_string(),
+            target: target.to_string(),
+            chunks: chunks.len(),
+            resumed: resumed_chunks,
+            cache_hits: cache.as_ref().map_or(0, TranslationCache::hits) - cache_hits_before,
+            duplicates,
+            duplicate_characters,
+            retries: progress.retries() - retries_before,
+            errors: failed_chunks.len(),
+            characters,
+            average_chunk_latency_seconds: if latencies.is_empty() {
+                0.0
+            } else {
+                latencies.iter().map(Duration::as_secs_f64).sum::<f64>() / latencies.len() as f64
+            },
+            characters_per_second: if elapsed > 0.0 { characters as f64 / elapsed } else { 0.0 },
+            elapsed_seconds: elapsed,
+        };
+        progress.emit(&Event::Finished(&stats));
+        // The report sits next to the output (or input) file, like the checkpoint.
+        match sidecar_file.as_deref().map(FailureReport::path_for) {
+            Some(report_path) if !failed_chunks.is_empty() => {
+                let report = FailureReport {
+                    input_file: input_file.clone(),
+                    output_file: output_file.clone(),
+                    format,
+                    source: source.to_string(),
+                    target: target.to_string(),
+                    chunk_size,
+                    mark_fuzzy: !args.no_fuzzy,
+                    front_matter_fields: args.front_matter_fields.clone(),
+                    keys: args.keys.clone(),
+                    columns: args.columns.clone(),
+                    select: args.select.clone(),
+                    select_xpath: args.select_xpath.clone(),
+                    bilingual: args.bilingual,
+                    input_encoding: (input_encoding != UTF_8).then(|| input_encoding.name().to_string()),
+                    output_encoding: (output_encoding != UTF_8).then(|| output_encoding.name().to_string()),
+                    failures: failed_chunks.clone(),
+                };
+                report.save(&report_path)?;
+                console.warn(format_args!(
+                    "Warning: {} of {} chunks could not be translated; see {:?} and run 'retry-failed' to try them again.",
+                    failed_chunks.len(),
+                    chunks.len(),
+                    report_path
+                ));
+            }
+            Some(report_path) if report_path.exists() => fs::remove_file(report_path)?,
+            _ if !failed_chunks.is_empty() => console.warn(format_args!(
+                "Warning: {} of {} chunks could not be translated and were left untranslated.",
+                failed_chunks.len(),
+                chunks.len()
+            )),
+            _ => {}
+        }
+        let checked = Checked { input_file: &input_file, sidecar_file: sidecar_file.as_deref(), source, target, console };
+        let mut flags = Flags::new(&translated_chunks);
+        if language_check != LanguageCheck::Off {
+            checks::language(&checked, &translated_chunks, &mut flags, args.language_check);
+        }
+        if args.verify_roundtrip {
+            checks::back_translation(&checked, &translated_chunks, &mut flags, args.roundtrip_threshold)?;
+        }
+        if args.entities.is_some() {
+            checks::entities(&checked, &translated_chunks, &mut flags)?;
+        }
+        if !args.no_sanity_check {
+            checks::sanity(&checked, &translated_chunks, &mut flags);
+        }
+        if args.check_grammar {
+            checks::grammar(&checked, &translated_chunks, &mut flags)?;
+        }
+        // The translations with the terms made consistent, to write the output again with.
+        let fixed_translations = match args.check_consistency || args.fix_consistency {
+            true => checks::consistency(&checked, &segments, &translated_chunks, args.fix_consistency)?,
+            false => None,
+        };
+        if lost_glossary_terms > 0 {
+            console.warn(format_args!(
+                "Warning: {} glossary terms were dropped by the translation and appended to their chunks.",
+                lost_glossary_terms
+            ));
+        }
+        if lost_no_translate_spans > 0 {
+            console.warn(format_args!(
+                "Warning: {} do-not-translate matches or redacted spans were dropped by the translation and appended to their chunks.",
+                lost_no_translate_spans
+            ));
+        }
+
+        if args.alternatives > 0 {
+            let report = AlternativesReport {
+                input_file: input_file.clone(),
+                source: source.to_string(),
+                target: target.to_string(),
+                paragraphs: alternative_paragraphs,
+            };
+            match sidecar_file.as_deref().map(AlternativesReport::path_for) {
+                Some(report_path) if !report.paragraphs.is_empty() => {
+                    report.save(&report_path)?;
+                    console.info(format_args!(
+                        "Alternative translations of {} paragraphs saved to: {:?}",
+                        report.paragraphs.len(),
+                        report_path
+                    ));
+                }
+                Some(report_path) if report_path.exists() => fs::remove_file(report_path)?,
+                _ => {}
+            }
+        }
+        if let Some(path) = &args.export_review {
+            let path = if multiple_targets { output_path(path, &input_file, target) } else { path.clone() };
+            let mut sheet = ReviewSheet::default();
+            let segment_flags = flags.into_segments();
+            let mut segments = segment_translations.iter().zip(&segment_flags);
+            for document in &documents {
+                for (segment, (text, (translation, flags))) in document.texts().into_iter().zip(segments.by_ref()).enumerate() {
+                    sheet.rows.push(ReviewRow {
+                        id: sheet.rows.len() + 1,
+                        source: text,
+                        translation: translation.as_deref().map(|translation| document.restore(segment, translation)).unwrap_or_default(),
+                        flags: flags.clone(),
+                    });
+                }
+            }
+            sheet.save(&path)?;
+            let flagged = sheet.rows.iter().filter(|row| !row.flags.is_empty()).count();
+            console.info(format_args!("Review sheet of {} segments ({} flagged) saved to: {:?}", sheet.rows.len(), flagged, path));
+        }
+        if let Some(path) = &args.emit_alignment {
+            let path = if multiple_targets { output_path(path, &input_file, target) } else { path.clone() };
+            let mut translations = segment_translations.as_slice();
+            let mut tsv = String::new();
+            let mut pairs = 0;
+            for document in &documents {
+                let (document_translations, rest) = translations.split_at(document.segments().len().min(translations.len()));
+                for (text, translation) in document.pairs(document_translations) {
+                    // Line breaks and tabs inside a segment would break up the line or its columns.
+                    let single_line = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+                    tsv.push_str(&format!("{}\t{}\n", single_line(&text), single_line(&translation)));
+                    pairs += 1;
+                }
+                translations = rest;
+            }
+            output::write_atomic(&path, tsv)?;
+            console.info(format_args!("{} aligned segment pairs saved to: {:?}", pairs, path));
+        }
+        if let Some(tmx) = &mut tmx {
+            let mut translations = segment_translations.as_slice();
+            for document in &documents {
+                let (document_translations, rest) = translations.split_at(document.segments().len().min(translations.len()));
+                for (text, translation) in document.pairs(document_translations) {
+                    tmx.add(&text, target, &translation);
+                }
+                translations = rest;
+            }
+        }
+
+        print_stats(console, &stats);
+        run_stats.push(stats);
+
+        // 4. Output the result
+        if let (Some(container), Some(output_path)) = (&container, &output_file) {
+            let mut translations = translated_chunks
+                .iter()
+                .flat_map(|chunk| chunk.translation.split("\n\n"))
+                .map(str::to_string);
+            let mut rendered = Vec::with_capacity(documents.len());
+            for document in &documents {
+                let document_translations: Vec<String> =
+                    translations.by_ref().take(document.segments().len()).collect();
+                let (text, lost) = document.render(&document_translations);
+                rendered.push(text);
+                lost_placeholders += lost;
+            }
+            report_lost_placeholders(console, lost_placeholders);
+
+            let partial_path = ChunkWriter::partial_path_for(output_path);
+            container.write(&partial_path, &rendered, target)?;
+            fs::rename(&partial_path, output_path)?;
+            console.info(format_args!("Translated {} saved to: {:?}", container.noun(), output_path));
+            remove_checkpoint(checkpoint_path.as_deref())?;
+            continue;
+        }
+
+        if let (Some(SplitOutput::ByHeading), Some(output_dir)) = (args.split_output, &output_file) {
+            let translations: Vec<String> =
+                translated_chunks.iter().flat_map(|chunk| chunk.translation.split("\n\n")).map(str::to_string).collect();
+            let starts = chapters::chapter_starts(&documents[0], format);
+            let (pieces, lost) = chapters::split(&documents[0], &translations, &starts);
+            lost_placeholders += lost;
+            // Text before the first heading is numbered 0; without any heading, the whole text is chapter 1.
+            let first_number = if pieces.len() > starts.len().max(1) { 0 } else { 1 };
+            let extension = input_file.extension().map_or("txt".into(), |extension| extension.to_string_lossy());
+            let width = (first_number + pieces.len() - 1).to_string().len().max(2);
+            let mut unmappable = 0;
+            for (index, piece) in pieces.iter().enumerate() {
+                let text = format!("{}\n", piece.trim_matches('\n'));
+                let (bytes, missing) = encoding::encode(&line_endings(text), output_encoding);
+                unmappable += missing;
+                let name = format!("ch{:0width$}.{}", first_number + index, extension);
+                output::write_atomic(&output_dir.join(name), bytes)?;
+            }
+            report_lost_placeholders(console, lost_placeholders);
+            report_unmappable(console, unmappable, output_encoding);
+            console.info(format_args!("Translated text saved to {} chapter files in: {:?}", pieces.len(), output_dir));
+            remove_checkpoint(checkpoint_path.as_deref())?;
+            continue;
+        }
+
+        if chunks.is_empty() {
+            // Nothing to translate; the document is copied as it is.
+            let (text, _) = documents[0].render(&[]);
+            match writer.as_mut() {
+                Some(writer) => writer.write(&line_endings(text))?,
+                None if to_stdout => write_stdout(&line_endings(text), output_encoding)?,
+                None => output = text,
+            }
+        }
+        report_lost_placeholders(console, lost_placeholders);
+        let fixed_output = fixed_translations.map(|translations| documents[0].render(&translations).0);
+
+        if let (Some(writer), Some(output_path)) = (writer, output_file) {
+            report_unmappable(console, writer.unmappable(), output_encoding);
+            writer.finish()?;
+            // The output streamed to disk is replaced with the consistent one.
+            if let Some(text) = fixed_output {
+                output::write_atomic(&output_path, encoding::encode(&line_endings(text), output_encoding).0)?;
+            }
+            console.info(format_args!("Translated text saved to: {:?}", output_path));
+        } else if !to_stdout {
+            if let Some(text) = fixed_output {
+                output = text;
+            }
+            println!("\n--- Translated Text ({} -> {}) ---", source, target);
+            println!("{}", line_endings(output));
+            println!("--- End of Translation ---");
+        }
+
+        remove_checkpoint(checkpoint_path.as_deref())?;
+    }
+    if args.overall_bar.is_some() {
+        logging::remove_bar(&bar);
+    }
+    if let Some(matches) = cache.as_ref().map(TranslationCache::fuzzy_matches).filter(|&matches| matches > 0) {
+        match args.fuzzy_action {
+            FuzzyAction::Reuse => console.info(format_args!("{} paragraphs reused the translation of a similar one.", matches)),
+            FuzzyAction::Review => console.warn(format_args!(
+                "{} paragraphs reused the translation of a similar one; review them at their '[[FUZZY MATCH n%]]' markers.",
+                matches
+            )),
+        }
+    }
+    if let Some(path) = &args.stats_json {
+        Stats::save_all(&run_stats, path)?;
+        console.info(format_args!("Statistics saved to: {:?}", path));
+    }
+    if let (Some(tmx), Some(path)) = (&tmx, &args.export_tmx) {
+        tmx.save(path)?;
+        console.info(format_args!("Translation memory of {} paragraphs saved to: {:?}", tmx.units.len(), path));
+    }
+    for (url, requests) in translator.endpoint_usage() {
+        console.info(format_args!("{} answered {} requests.", url, requests));
+    }
+    Ok(chunks.len())
+}
+
+/// Translates plain text or CSV for '--stream': the input is read in blocks of whole paragraphs
+/// or rows, and each block is translated and written out before the next one is read. Without
+/// an output file the translation goes to standard output.
+async fn translate_stream(
+    input_file: PathBuf,
+    output_file: Option<PathBuf>,
+    args: TranslateOptions,
+) -> Result<usize, TranslatorError> {
+    let format = args.format.unwrap_or_else(|| Format::from_path(&input_file));
+    if !matches!(format, Format::Text | Format::Csv) {
+        return Err(format!("'--stream' reads plain text and CSV, not {:?} files", format).into());
+    }
+    let [target] = args.target.as_slice() else {
+        return Err("'--stream' translates into one target language at a time".into());
+    };
+    let output_file = output_file.filter(|path| path != Path::new(STDIO));
+    let console = Console { quiet: output_file.is_none() };
+    let chunk_size = args.chunk_size as usize;
+
+    console.info(format_args!("Streaming file: {:?}", input_file));
+    let ((reader, input_encoding), length) = if input_file == Path::new(STDIO) {
+        (encoding::decode_reader(io::stdin(), args.input_encoding, &args.source)?, None)
+    } else {
+        let file = fs::File::open(&input_file)?;
+        let length = file.metadata()?.len();
+        (encoding::decode_reader(file, args.input_encoding, &args.source)?, Some(length))
+    };
+    if input_encoding != UTF_8 {
+        console.info(format_args!("Input encoding: {}", input_encoding.name()));
+    }
+    let output_encoding = args.output_encoding.unwrap_or(input_encoding);
+    let blocks = Blocks::new(reader, chunk_size * STREAM_BLOCK_CHUNKS);
+    let mut blocks = if format == Format::Csv { blocks.with_records() } else { blocks }.peekable();
+    // The header of a table is only in the first block.
+    let mut table = (format == Format::Csv).then(|| Table::new(&args.columns));
+
+    let no_translate = args.pipeline.text.no_translate()?.with_placeholders(args.protect_placeholders);
+    let redactor = args.pipeline.text.redactor()?;
+    let glossary = args.pipeline.text.glossary()?;
+    let cache = open_cache(&args)?;
+
+    let progress = match args.progress {
+        ProgressFormat::Json => Progress::json(),
+        ProgressFormat::Bar if console.quiet => Progress::hidden(),
+        ProgressFormat::Bar => Progress::new(logging::add_bar(ProgressBar::new(length.unwrap_or(0)))),
+    };
+    let bar = progress.bar().clone();
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+            .expect("the progress bar template is valid")
+            .progress_chars("=>-"),
+    );
+    let translator = args.backend.build(progress.clone())?.with_text_format(format.text_format());
+
+    // The source language is detected from the first block.
+    let source = match blocks.peek() {
+        Some(Ok(block)) if args.source == AUTO_LANGUAGE && translator.supports_detection() => {
+            let detection = translator
+                .detect(&redactor.redact(truncate_at_char_boundary(block, chunk_size)).0)
+                .await?
+                .into_iter()
+                .next()
+                .ok_or("The server could not detect the source language")?;
+            console.info(format_args!("Detected source language: {}", detection.language));
+            detection.language
+        }
+        _ => args.source.clone(),
+    };
+    if !args.skip_language_check && translator.supports_language_list() && source != AUTO_LANGUAGE {
+        check_language_pair(&translator.languages().await?, &source, target)?;
+    }
+    let limiter = Arc::new(rate_limiter(&args)?);
+    let translator = translator.with_rate_limiter(limiter.clone());
+    let pipeline = Pipeline {
+        translator: &translator,
+        cache: cache.as_ref(),
+        limiter: &limiter,
+        redactor: &redactor,
+        no_translate: &no_translate,
+        glossary: glossary.as_ref(),
+        skip_target_language: args.skip_target_language,
+    };
+
+    let mut writer = output_file
+        .as_deref()
+        .map(|path| ChunkWriter::create(path).map(|writer| writer.with_encoding(output_encoding)))
+        .transpose()?;
+    let options = FormatOptions {
+        mark_fuzzy: !args.no_fuzzy,
+        target_language: Some(target.clone()),
+        max_segment_len: chunk_size,
+        front_matter_fields: args.front_matter_fields.clone(),
+        keys: args.keys.clone(),
+        columns: args.columns.clone(),
+        select: args.select.clone(),
+        select_xpath: args.select_xpath.clone(),
+        source_language: (args.source != AUTO_LANGUAGE).then(|| args.source.clone()),
+    };
+    let mut chunk_count = 0;
+    let mut lost_placeholders = 0;
+    let ctrl_c = tokio::signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+
+    for block in blocks {
+        let block = block?;
+        // Line endings are normalized for parsing and restored in the output, block by block.
+        let crlf = block.contains("\r\n");
+        let content = block.replace("\r\n", "\n");
+        let document = match table.as_mut() {
+            Some(table) => table.parse(&content)?,
+            None => format::parse(format, &content, &options)?,
+        };
+        let chunks = pack_segments(&document.segments(), chunk_size);
+        let translations = stream::iter(&chunks)
+            .map(|(chunk, segment_count)| pipeline.translate(chunk, *segment_count, &source, target))
+            .buffered(args.concurrency as usize)
+            .collect::<Vec<_>>();
+        let translations = tokio::select! {
+            translations = translations => translations,
+            _ = &mut ctrl_c => {
+                bar.abandon();
+                eprintln!("\nInterrupted after {} chunks.", chunk_count);
+                if let Some(writer) = &writer {
+                    eprintln!("The translation so far is saved in {:?}.", writer.partial_path());
+                }
+                std::process::exit(EXIT_INTERRUPTED);
+            }
+        };
+
+        let mut segment_translations = Vec::new();
+        for translation in translations {
+            let (translated, _, _) = translation?;
+            segment_translations.extend(translated.split("\n\n").map(str::to_string));
+        }
+        let (text, lost) = document.render(&segment_translations);
+        lost_placeholders += lost;
+        let text = if crlf { text.replace('\n', "\r\n") } else { text };
+        match writer.as_mut() {
+            Some(writer) => writer.write(&text)?,
+            None => write_stdout(&text, output_encoding)?,
+        }
+        chunk_count += chunks.len();
+        // The progress is counted in decoded bytes, which is close enough for legacy encodings.
+        bar.inc(block.len() as u64);
+    }
+
+    bar.finish_with_message("Translation complete!");
+    report_lost_placeholders(console, lost_placeholders);
+    if let (Some(writer), Some(output_path)) = (writer, output_file) {
+        report_unmappable(console, writer.unmappable(), output_encoding);
+        writer.finish()?;
+        console.info(format_args!("Translated {} chunks, saved to: {:?}", chunk_count, output_path));
+    }
+    Ok(chunk_count)
+}
+
+/// Opens the translation cache of the run, unless caching is turned off.
+fn open_cache(args: &TranslateOptions) -> Result<Option<TranslationCache>, TranslatorError> {
+    Ok(match (args.pipeline.open_cache()?, args.fuzzy_threshold) {
+        (Some(cache), Some(threshold)) => Some(cache.with_fuzzy_matching(FuzzyMatching {
+            metric: args.fuzzy_metric,
+            threshold: f64::from(threshold) / 100.0,
+            action: args.fuzzy_action,
+        })),
+        (cache, _) => cache,
+    })
+}
+
+/// The pace '--adaptive-pacing' starts at, in requests per minute.
+const ADAPTIVE_START_PER_MINUTE: u32 = 60;
+
+/// Paces the API requests, spaced out to be polite to the public API (max 8/minute allowed on the default server).
+fn rate_limiter(args: &TranslateOptions) -> Result<RateLimiter, TranslatorError> {
+    Ok(match args.backend.rate_limiter(args.pipeline.requests_per_minute, args.pipeline.request_delay)? {
+        Some(_) if args.adaptive_pacing => RateLimiter::adaptive(ADAPTIVE_START_PER_MINUTE),
+        limiter => limiter.unwrap_or_else(RateLimiter::unlimited),
+    })
+}
+
+/// Fills in an output file template for a target language: `{stem}` and `{ext}` are the name
+/// and extension of the input file, `{target}` the language code. The default template puts
+/// the files next to the input file.
+pub(super) fn output_path(template: &Path, input_file: &Path, target: &str) -> PathBuf {
+    let stem = input_file.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = input_file.extension().unwrap_or_default().to_string_lossy();
+    let mut path = template.to_string_lossy().into_owned();
+    if ext.is_empty() {
+        path = path.replace(".{ext}", "");
+    }
+    let path = PathBuf::from(path.replace("{stem}", &stem).replace("{ext}", &ext).replace("{target}", target));
+    match input_file.parent() {
+        Some(dir) if template == Path::new(OUTPUT_TEMPLATE) => dir.join(path),
+        _ => path,
+    }
+}
+
+/// For every chunk, the index of the first identical chunk before it, if there is one.
+fn duplicates(chunks: &[String]) -> Vec<Option<usize>> {
+    let mut first: HashMap<&str, usize> = HashMap::new();
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(index, chunk)| match first.get(chunk.as_str()) {
+            Some(&first) => Some(first),
+            None => {
+                first.insert(chunk, index);
+                None
+            }
+        })
+        .collect()
+}
+
+/// The parts of a [`Pipeline`] that decide what is sent for a chunk, and so its cache key.
+type PipelineParts<'a> = (&'a Redactor, &'a NoTranslate, Option<&'a Glossary>, Option<&'a TranslationCache>);
+
+/// Which chunks are in the cache already, as they would be sent with their tokens, and cost no request.
+fn cached_chunks(
+    chunks: &[String],
+    source: &str,
+    target: &str,
+    (redactor, no_translate, glossary, cache): PipelineParts,
+) -> Result<Vec<bool>, TranslatorError> {
+    let Some(cache) = cache else { return Ok(vec![false; chunks.len()]) };
+    chunks
+        .iter()
+        .map(|chunk| {
+            let (chunk, _) = redactor.redact(chunk);
+            let (chunk, _) = no_translate.protect_placeholders(&chunk);
+            let (chunk, _) = no_translate.protect(&chunk);
+            let chunk = match glossary {
+                Some(glossary) => glossary.protect(&chunk).0,
+                None => chunk,
+            };
+            cache.contains(source, target, &chunk)
+        })
+        .collect()
+}
+
+/// The style of the progress bar of a file, labelled with its name below the bar of a whole
+/// directory (`labelled`).
+///
+/// The time left is estimated from the bytes translated so far, but never less than the rate
+/// limiter needs to release the `pending_requests`: early on, the first requests go out
+/// without waiting and would make the run look much faster than it is.
+fn bar_style(labelled: bool, limiter: Arc<RateLimiter>, pending_requests: Arc<AtomicUsize>) -> ProgressStyle {
+    let template = if labelled {
+        "{spinner:.green} {prefix} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})"
+    } else {
+        "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})"
+    };
+    ProgressStyle::default_bar()
+        .template(template)
+        .expect("the progress bar template is valid")
+        .with_key("eta", move |state: &ProgressState, w: &mut dyn fmt::Write| {
+            let paced = limiter.time_for(pending_requests.load(Ordering::Relaxed));
+            let _ = write!(w, "{:#}", HumanDuration(state.eta().max(paced)));
+        })
+        .progress_chars("=>-")
+}
+
+/// Describes the requests a run would send, with an estimate of its duration and cost, for '--dry-run'.
+fn print_plan(
+    chunks: &[String],
+    args: &TranslateOptions,
+    (redactor, no_translate, glossary, cache): PipelineParts,
+) -> Result<(), TranslatorError> {
+    let targets = args.target.len();
+    let characters: usize = chunks.iter().map(|chunk| chunk.chars().count()).sum();
+    println!("\nDry run: nothing is sent to the server.");
+    println!("Chunks:          {} (chunk size {} bytes)", chunks.len(), args.chunk_size);
+    println!("Characters:      {}", characters);
+    if let Some((index, largest)) = chunks.iter().enumerate().max_by_key(|(_, chunk)| chunk.len()) {
+        println!("Largest chunk:   {} bytes (chunk {})", largest.len(), index + 1);
+    }
+
+    // The cache is keyed by the source language, which a LibreTranslate server would detect for
+    // '--source auto'; here it is guessed locally instead. Other backends keep 'auto' as it is.
+    let source = match args.source.as_str() {
+        AUTO_LANGUAGE if args.backend.kind() == BackendKind::LibreTranslate => {
+            chunks.first().and_then(|chunk| quality::detect_language(chunk))
+        }
+        source => Some(source),
+    };
+    if let Some(source) = source.filter(|&source| args.source == AUTO_LANGUAGE && source != AUTO_LANGUAGE) {
+        println!("Source language: {} (detected locally, the server may tell otherwise)", source);
+    }
+    let duplicate_of = duplicates(chunks);
+    let duplicate_count = duplicate_of.iter().flatten().count();
+    let mut requests = 0;
+    for target in &args.target {
+        let cached = match source {
+            Some(source) => cached_chunks(chunks, source, target, (redactor, no_translate, glossary, cache))?,
+            None => vec![false; chunks.len()],
+        };
+        requests += (0..chunks.len()).filter(|&index| !cached[index] && duplicate_of[index].is_none()).count();
+    }
+    match source {
+        Some(_) => println!(
+            "Requests:        {} ({} chunks already in the cache, {} repeating an earlier chunk)",
+            requests,
+            chunks.len() * targets - requests - duplicate_count * targets,
+            duplicate_count * targets
+        ),
+        None => println!(
+            "Requests:        at most {} ({} repeating an earlier chunk; cache hits can't be estimated without '--source')",
+            requests,
+            duplicate_count * targets
+        ),
+    }
+
+    // Round-robin mirrors are each paced on their own, so together they take requests that much more often.
+    let mirrors = if args.backend.round_robin() && !args.backend.offline() { args.backend.mirrors()?.len() } else { 1 };
+    let interval = match args.pipeline.request_delay {
+        _ if args.backend.offline() => Duration::ZERO,
+        _ if args.backend.round_robin() => match args.backend.mirror_requests_per_minute() {
+            0 => Duration::ZERO,
+            per_minute => Duration::from_secs(60) / per_minute / mirrors.max(1) as u32,
+        },
+        _ if args.adaptive_pacing => Duration::from_secs(60) / ADAPTIVE_START_PER_MINUTE,
+        Some(delay) => Duration::try_from_secs_f64(delay)?,
+        None if args.pipeline.requests_per_minute > 0 => Duration::from_secs(60) / args.pipeline.requests_per_minute,
+        None => Duration::ZERO,
+    };
+    if interval.is_zero() {
+        println!("Estimated time:  no rate limit, depends on how fast the server answers");
+    } else {
+        // The first request goes out right away; the rate limit spaces out the rest.
+        let seconds = (interval.as_secs_f64() * requests.saturating_sub(1) as f64).round() as u64;
+        let spread = if mirrors > 1 { format!(" across {} mirrors", mirrors) } else { String::new() };
+        println!(
+            "Estimated time:  {}m {:02}s at one request every {:.1} seconds{}",
+            seconds / 60,
+            seconds % 60,
+            interval.as_secs_f64(),
+            spread
+        );
+    }
+
+    let kind = args.backend.kind();
+    match kind.price_per_million_characters() {
+        Some(price) => println!(
+            "Estimated cost:  ${:.2} at ${} per million characters ({:?} list price)",
+            price * (characters * targets) as f64 / 1_000_000.0,
+            price,
+            kind
+        ),
+        None if kind.is_llm() && kind != BackendKind::Ollama => println!("Estimated cost:  depends on the model and its price per token"),
+        None => println!("Estimated cost:  free"),
+    }
+    Ok(())
+}
+
+/// Prints the statistics of a run: characters, chunks, retries, errors, latency and throughput.
+fn print_stats(console: Console, stats: &Stats) {
+    console.info("\nStatistics:");
+    console.info(format_args!("Characters:       {}", stats.characters));
+    console.info(format_args!(
+        "Chunks:           {} ({} from the cache, {} resumed)",
+        stats.chunks, stats.cache_hits, stats.resumed
+    ));
+    if stats.duplicates > 0 {
+        console.info(format_args!(
+            "Duplicates:       {} chunks reused ({} characters not sent)",
+            stats.duplicates, stats.duplicate_characters
+        ));
+    }
+    console.info(format_args!("Retries:          {}", stats.retries));
+    console.info(format_args!("Errors:           {}", stats.errors));
+    console.info(format_args!("Average latency:  {:.2} s per chunk", stats.average_chunk_latency_seconds));
+    console.info(format_args!("Throughput:       {:.1} characters/s", stats.characters_per_second));
+    console.info(format_args!("Elapsed time:     {:.2} s", stats.elapsed_seconds));
+}
+
+/// Warns about placeholders the translation dropped, which were appended to their segments.
+fn report_lost_placeholders(console: Console, lost_placeholders: usize) {
+    if lost_placeholders > 0 {
+        console.warn(format_args!(
+            "Warning: {} protected spans (code, links, ...) were dropped by the translation and appended to their segments.",
+            lost_placeholders
+        ));
+    }
+}
+
+/// Warns about characters of the translation that the output encoding has no code for.
+fn report_unmappable(console: Console, unmappable: usize, encoding: &'static Encoding) {
+    if unmappable > 0 {
+        console.warn(format_args!(
+            "{} characters can't be written in {} and were replaced with '?'; try '--output-encoding utf-8'.",
+            unmappable,
+            encoding.name()
+        ));
+    }
+}
+
+/// Writes a piece of the translation to standard output as soon as it is available.
+///
+/// Characters the encoding has no code for are written as `?`.
+fn write_stdout(text: &str, encoding: &'static Encoding) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(&encoding::encode(text, encoding).0)?;
+    stdout.flush()
+}
+
+/// A book or document whose translatable parts are kept in a zip container; it is written
+/// once every part is translated.
+enum Container {
+    Epub(Epub),
+    Docx(Docx),
+}
+
+impl Container {
+    fn write(&self, path: &Path, translated: &[String], language: &str) -> Result<(), TranslatorError> {
+        match self {
+            Container::Epub(epub) => epub.write(path, translated, language),
+            Container::Docx(docx) => docx.write(path, translated, language),
+        }
+    }
+
+    /// What the file is called in messages.
+    fn noun(&self) -> &'static str {
+        match self {
+            Container::Epub(_) => "book",
+            Container::Docx(_) => "document",
+        }
+    }
+}
+
+/// Where status messages go: stdout, unless stdout carries the translation itself. They are
+/// printed above the progress bars.
+#[derive(Clone, Copy)]
+pub(super) struct Console {
+    quiet: bool,
+}
+
+impl Console {
+    pub(super) fn info(self, message: impl Display) {
+        if !self.quiet && !logging::quiet() {
+            logging::suspend(|| println!("{}", message));
+        }
+    }
+
+    /// Warnings still reach the user on stderr when stdout is taken, but not with '--quiet'.
+    pub(super) fn warn(self, message: impl Display) {
+        if logging::quiet() {
+            return;
+        }
+        if self.quiet {
+            logging::suspend(|| eprintln!("{}", message));
+        } else {
+            logging::suspend(|| println!("{}", message));
+        }
+    }
+}
+
+/// The URL of a web page given as the input file.
+fn web_url(input_file: &Path) -> Option<Url> {
+    let text = input_file.to_str()?;
+    Url::parse(text).ok().filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+/// Fetches a web page and extracts its article as Markdown or HTML, returning a file name in the
+/// current directory for it (after the last part of the URL's path) and its text.
+async fn fetch_article(url: &Url, args: &TranslateOptions, output_file: Option<&Path>) -> Result<(PathBuf, String), TranslatorError> {
+    let html = match args.format {
+        Some(Format::Html) => true,
+        Some(Format::Markdown) => false,
+        None => output_file.is_some_and(|path| Format::from_path(path) == Format::Html),
+        Some(format) => return Err(format!("A web page is translated as Markdown or HTML, not as {:?}", format).into()),
+    };
+    let response = args.backend.http_client()?.get(url.clone()).send().await?.error_for_status()?;
+    // Relative links are resolved against the page the redirects ended on.
+    let page_url = response.url().clone();
+    let article = Article::extract(&response.text().await?, &page_url);
+    let stem = page_url
+        .path_segments()
+        .and_then(|mut segments| segments.rfind(|segment| !segment.is_empty()))
+        .and_then(|segment| Path::new(segment).file_stem())
+        .map(|stem| stem.to_string_lossy().replace(|c: char| !c.is_alphanumeric() && c != '-' && c != '_', "-"))
+        .unwrap_or_else(|| page_url.host_str().unwrap_or("page").to_string());
+    Ok(match html {
+        true => (PathBuf::from(format!("{}.html", stem)), article.to_html()),
+        false => (PathBuf::from(format!("{}.md", stem)), article.to_markdown()),
+    })
+}
+
+/// Deletes the checkpoint once the run is complete and it is no longer needed.
+fn remove_checkpoint(checkpoint_path: Option<&Path>) -> Result<(), TranslatorError> {
+    if let Some(checkpoint_path) = checkpoint_path.filter(|path| path.exists()) {
+        fs::remove_file(checkpoint_path)?;
+    }
+    Ok(())
+}
+
+/// What a chunk goes through on its way to the backend and back.
+pub(super) struct Pipeline<'a> {
+    pub translator: &'a Backend,
+    pub cache: Option<&'a TranslationCache>,
+    pub limiter: &'a RateLimiter,
+    pub redactor: &'a Redactor,
+    pub no_translate: &'a NoTranslate,
+    pub glossary: Option<&'a Glossary>,
+    /// Whether paragraphs already in the target language are left as they are.
+    pub skip_target_language: bool,
+}
+
+impl Pipeline<'_> {
+    /// Translates a chunk of `segment_count` segments, returning the translation and the number
+    /// of glossary terms and do-not-translate matches or redacted spans the translation dropped.
+    pub async fn translate(
+        &self,
+        chunk: &str,
+        segment_count: usize,
+        source: &str,
+        target: &str,
+    ) -> Result<(String, usize, usize), TranslatorError> {
+        self.translate_in_context(chunk, segment_count, source, target, &[]).await
+    }
+
+    /// Like [`translate`](Self::translate), with the paragraphs before the chunk sent as context.
+    pub async fn translate_in_context(
+        &self,
+        chunk: &str,
+        segment_count: usize,
+        source: &str,
+        target: &str,
+        context: &[ContextParagraph],
+    ) -> Result<(String, usize, usize), TranslatorError> {
+        if !self.skip_target_language {
+            return self.send(chunk, segment_count, source, target, context).await;
+        }
+        // Paragraphs already in the target language are kept as they are, and only the rest is sent.
+        let segments: Vec<&str> = chunk.split("\n\n").collect();
+        let skipped: Vec<bool> = segments.iter().map(|segment| quality::is_in_language(segment, source, target)).collect();
+        let skipped_count = skipped.iter().filter(|&&skipped| skipped).count();
+        if skipped_count == 0 {
+            return self.send(chunk, segment_count, source, target, context).await;
+        }
+        debug!("Skipping {} paragraphs already in '{}'", skipped_count, target);
+        let rest: Vec<&str> = segments.iter().zip(&skipped).filter(|(_, &skipped)| !skipped).map(|(segment, _)| *segment).collect();
+        let (translated, lost_terms, lost_originals) = if rest.is_empty() {
+            (String::new(), 0, 0)
+        } else {
+            self.send(&rest.join("\n\n"), rest.len(), source, target, context).await?
+        };
+        let mut translations = translated.split("\n\n");
+        let merged: Vec<&str> = segments
+            .iter()
+            .zip(skipped)
+            .map(|(segment, skipped)| if skipped { segment } else { translations.next().unwrap_or_default() })
+            .collect();
+        Ok((merged.join("\n\n"), lost_terms, lost_originals))
+    }
+
+    /// Protects the chunk, sends it and restores the translation.
+    async fn send(
+        &self,
+        chunk: &str,
+        segment_count: usize,
+        source: &str,
+        target: &str,
+        context: &[ContextParagraph],
+    ) -> Result<(String, usize, usize), TranslatorError> {
+        // Personal data, placeholders, do-not-translate matches and glossary terms are replaced
+        // with tokens before sending. The context goes to the server too, so it is redacted as well,
+        // with tokens of its own, in case the translation copies one of them.
+        let (chunk, context, redacted, context_redacted) = self.redactor.redact_with_context(chunk, context);
+        let (chunk, placeholders) = self.no_translate.protect_placeholders(&chunk);
+        let (chunk, originals) = self.no_translate.protect(&chunk);
+        let (chunk, terms) = match self.glossary {
+            Some(glossary) => glossary.protect(&chunk),
+            None => (chunk, Vec::new()),
+        };
+        let translator = InContext { backend: self.translator, context: &context };
+        let translated =
+            translate_segments(&translator, self.cache, self.limiter, &chunk, segment_count, source, target).await?;
+        let (translated, lost_terms) = Glossary::restore(&translated, &terms);
+        let (translated, lost_originals) = NoTranslate::restore(&translated, &originals);
+        let translated = NoTranslate::restore_placeholders(&translated, &placeholders)?;
+        let (translated, lost_redacted) = Redactor::restore_with_context(&translated, &redacted, &context_redacted);
+        Ok((translated, lost_terms, lost_originals + lost_redacted))
+    }
+
+    /// Asks for up to `count` other translations of every paragraph of a chunk, protected like
+    /// the chunk itself. Paragraphs without letters get none.
+    pub async fn alternatives(&self, chunk: &str, source: &str, target: &str, count: usize) -> Result<Vec<Vec<String>>, TranslatorError> {
+        let mut alternatives = Vec::new();
+        for paragraph in chunk.split("\n\n") {
+            if !paragraph.chars().any(char::is_alphabetic) {
+                alternatives.push(Vec::new());
+                continue;
+            }
+            let (paragraph, redacted) = self.redactor.redact(paragraph);
+            let (paragraph, placeholders) = self.no_translate.protect_placeholders(&paragraph);
+            let (paragraph, originals) = self.no_translate.protect(&paragraph);
+            let (paragraph, terms) = match self.glossary {
+                Some(glossary) => glossary.protect(&paragraph),
+                None => (paragraph, Vec::new()),
+            };
+            self.limiter.acquire().await;
+            let mut restored = Vec::new();
+            for alternative in self.translator.alternatives(&paragraph, source, target, count).await? {
+                let (alternative, _) = Glossary::restore(&alternative, &terms);
+                let (alternative, _) = NoTranslate::restore(&alternative, &originals);
+                let alternative = NoTranslate::restore_placeholders(&alternative, &placeholders)?;
+                restored.push(Redactor::restore(&alternative, &redacted).0);
+            }
+            alternatives.push(restored);
+        }
+        Ok(alternatives)
+    }
+
+    /// Translates a translation of `segment_count` segments back from `target` into `source`.
+    pub async fn back_translate(
+        &self,
+        translated: &str,
+        segment_count: usize,
+        source: &str,
+        target: &str,
+    ) -> Result<String, TranslatorError> {
+        let (translated, redacted) = self.redactor.redact(translated);
+        let back_translated =
+            translate_segments(self.translator, self.cache, self.limiter, &translated, segment_count, target, source).await?;
+        Ok(Redactor::restore(&back_translated, &redacted).0)
+    }
+}
+
+/// A backend that sends the paragraphs before the text along with every request.
+struct InContext<'a> {
+    backend: &'a Backend,
+    context: &'a [ContextParagraph],
+}
+
+impl Translator for InContext<'_> {
+    async fn translate(&self, text: &str, source: &str, target: &str) -> Result<String, TranslatorError> {
+        self.backend.translate_in_context(text, source, target, self.context).await
+    }
+}
+
+/// The last paragraphs translated before each chunk, for `--context-paragraphs`.
+struct ContextWindow {
+    size: usize,
+    /// The last `size` paragraphs of every finished chunk with their translations, by chunk index.
+    finished: Mutex<BTreeMap<usize, Vec<ContextParagraph>>>,
+}
+
+impl ContextWindow {
+    fn new(size: usize) -> Self {
+        Self { size, finished: Mutex::new(BTreeMap::new()) }
+    }
+
+    /// Up to `size` paragraphs of the finished chunks before chunk `index`, oldest first.
+    fn before(&self, index: usize) -> Vec<ContextParagraph> {
+        let finished = self.finished.lock().unwrap();
+        let mut context: Vec<ContextParagraph> = Vec::with_capacity(self.size);
+        for paragraphs in finished.range(..index).rev().map(|(_, paragraphs)| paragraphs) {
+            if context.len() >= self.size {
+                break;
+            }
+            let needed = self.size - context.len();
+            context.splice(0..0, paragraphs[paragraphs.len().saturating_sub(needed)..].iter().cloned());
+        }
+        context
+    }
+
+    /// Remembers the last paragraphs of chunk `index` and their translations.
+    fn record(&self, index: usize, chunk: &str, translated: &str) {
+        if self.size == 0 {
+            return;
+        }
+        let mut paragraphs: Vec<ContextParagraph> = chunk
+            .split("\n\n")
+            .zip(translated.split("\n\n"))
+            .filter(|(source, _)| !source.trim().is_empty())
+            .map(|(source, translation)| ContextParagraph { source: source.to_string(), translation: translation.to_string() })
+            .collect();
+        paragraphs.drain(..paragraphs.len().saturating_sub(self.size));
+        self.finished.lock().unwrap().insert(index, paragraphs);
+    }
+}
+
+/// Translates a chunk, using the cache when possible and waiting for the rate limiter before API requests.
+async fn translate_chunk<T: Translator>(
+    translator: &T,
+    cache: Option<&TranslationCache>,
+    limiter: &RateLimiter,
+    chunk: &str,
+    source: &str,
+    target: &str,
+) -> Result<String, TranslatorError> {
+    let Some(cache) = cache else {
+        limiter.acquire().await;
+        return translator.translate(chunk, source, target).await;
+    };
+    if let Some(translated) = cache.lookup(source, target, chunk)? {
+        debug!("Cache hit for a chunk of {} bytes ({} -> {})", chunk.len(), source, target);
+        return Ok(translated);
+    }
+    limiter.acquire().await;
+    cache.translate(translator, chunk, source, target).await
+}
+
+/// Translates a chunk of `count` segments joined by blank lines, making sure the
+/// translation can be split back into exactly `count` segments.
+async fn translate_segments<T: Translator>(
+    translator: &T,
+    cache: Option<&TranslationCache>,
+    limiter: &RateLimiter,
+    chunk: &str,
+    count: usize,
+    source: &str,
+    target: &str,
+) -> Result<String, TranslatorError> {
+    let translated = translate_chunk(translator, cache, limiter, chunk, source, target).await?;
+    if translated.split("\n\n").count() == count {
+        return Ok(translated);
+    }
+
+    // The server merged or split segments, so they can't be matched up; translate them one by one.
+    let mut segments = Vec::with_capacity(count);
+    for segment in chunk.split("\n\n") {
+        let translated = translate_chunk(translator, cache, limiter, segment, source, target).await?;
+        segments.push(format::collapse_blank_lines(translated.trim()));
+    }
+    Ok(segments.join("\n\n"))
+}
+
+/// Tells the user what was saved before the run was interrupted and how to continue it.
+fn report_interruption(
+    checkpoint: &Checkpoint,
+    checkpoint_path: Option<&Path>,
+    writer: Option<&ChunkWriter>,
+    partial_output: &str,
+) {
+    let completed = checkpoint.completed();
+    let total = checkpoint.len();
+    eprintln!(
+        "\nInterrupted: {} of {} chunks translated, {} remaining.",
+        completed,
+        total,
+        total - completed
+    );
+    match writer {
+        Some(writer) => eprintln!("Partial translation saved to: {:?}", writer.partial_path()),
+        None if !partial_output.is_empty() => {
+            println!("\n--- Partial Translation ---");
+            println!("{}", partial_output);
+            println!("--- End of Partial Translation ---");
+        }
+        None => {}
+    }
+    if completed > 0 {
+        eprintln!(
+            "Progress saved to {:?}; run again with --resume to continue.",
+            checkpoint_path
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use clap::{ArgMatches, Args};
+use regex::Regex;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use encoding_rs::UTF_8;
+use indicatif::{ProgressBar, ProgressStyle};
+use text_translator::TranslatorError;
+use text_translator::encoding::InputEncoding;
+use text_translator::format::Format;
+use tracing::error;
+
+use super::{config, Subcommand};
+use super::logging;
+use super::watch::Watcher;
+use super::translate::{self, ProgressFormat, TranslateOptions};
+
+/// How many bytes at the start of a file are checked for NUL bytes to tell binaries apart.
+const BINARY_CHECK_LEN: usize = 8000;
+
+/// Translate every matching file in a directory
+#[derive(Args, Debug)]
+pub struct TranslateDirArgs {
+    /// Directory with the files to translate
+    #[arg(required = true)]
+    input_dir: PathBuf,
+
+    /// Directory the translated files are written to, mirroring the structure of the input directory
+    #[arg(long, required = true)]
+    out_dir: PathBuf,
+
+    /// Only translate files whose path relative to the input directory matches this glob, e.g. '**/*.md' (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files and directories matching this glob, e.g. 'node_modules/**' (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    #[command(flatten)]
+    options: TranslateOptions,
+}
+
+/// What happened to one file of the directory.
+enum Outcome {
+    Translated(usize),
+    Binary,
+    Failed(String),
+}
+
+impl Subcommand for TranslateDirArgs {
+    fn apply_profile(&mut self, profile: &config::Profile, matches: &ArgMatches) -> Result<(), TranslatorError> {
+        self.options.apply_profile(profile, matches)
+    }
+
+    async fn run(self) -> Result<(), TranslatorError> {
+        run(self).await
+    }
+}
+
+pub async fn run(args: TranslateDirArgs) -> Result<(), TranslatorError> {
+    if args.options.export_tmx.is_some() {
+        return Err("'--export-tmx' saves the paragraphs of one file; export those of a directory from the cache with 'export-tmx'".into());
+    }
+    if args.options.export_review.is_some() {
+        return Err("'--export-review' saves the segments of one file; it can't be used with 'translate-dir'".into());
+    }
+    if args.options.emit_alignment.is_some() {
+        return Err("'--emit-alignment' saves the segments of one file; it can't be used with 'translate-dir'".into());
+    }
+    if args.options.stats_json.is_some() {
+        return Err("'--stats-json' saves the statistics of one file; it can't be used with 'translate-dir'".into());
+    }
+    let include = args.include.iter().map(|glob| glob_to_regex(glob)).collect::<Result<Vec<_>, _>>()?;
+    let exclude = args.exclude.iter().map(|glob| glob_to_regex(glob)).collect::<Result<Vec<_>, _>>()?;
+
+    // The output directory may live inside the input directory; its files must not be picked up again.
+    // It is created first, so it can be told apart even when watching picks up files written later.
+    fs::create_dir_all(&args.out_dir)?;
+    let out_dir = args.out_dir.canonicalize().ok();
+    let find_files = || -> Result<Vec<String>, TranslatorError> {
+        let mut files = Vec::new();
+        collect_files(&args.input_dir, "", &exclude, out_dir.as_deref(), &mut files)?;
+        files.retain(|file| include.is_empty() || include.iter().any(|glob| glob.is_match(file)));
+        files.sort();
+        Ok(files)
+    };
+    let mut watcher = if args.options.watch { Some(Watcher::new(&args.input_dir.canonicalize()?, true)?) } else { None };
+    let files = find_files()?;
+    if !logging::quiet() {
+        println!("Found {} files to translate in {:?}.", files.len(), args.input_dir);
+    }
+
+    let mut summary = Vec::with_capacity(files.len());
+    let overall = overall_bar(&args, &files);
+    let count = files.len();
+    for (index, file) in files.into_iter().enumerate() {
+        let start = overall.as_ref().map_or(0, ProgressBar::position);
+        let outcome = translate_one(&args, &file, overall.as_ref()).await?;
+        if let Some(overall) = &overall {
+            file_done(&args, overall, start, &file, index + 1, count);
+        }
+        summary.push((file, outcome));
+    }
+    if let Some(overall) = overall {
+        overall.finish();
+    }
+    if !logging::quiet() {
+        print_summary(&summary);
+    }
+    let failed = summary.iter().filter(|(_, outcome)| matches!(outcome, Outcome::Failed(_))).count();
+
+    let Some(watcher) = watcher.as_mut() else {
+        if failed > 0 {
+            return Err(format!("{} of {} files could not be translated", failed, summary.len()).into());
+        }
+        return Ok(());
+    };
+    if !logging::quiet() {
+        println!("\nWatching {:?} for changes (Ctrl-C to stop).", args.input_dir);
+    }
+    loop {
+        let changes = tokio::select! {
+            changes = watcher.changes() => changes?,
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        };
+        // New files are picked up too, as long as they match the globs.
+        let mut summary = Vec::new();
+        let changed: Vec<String> = find_files()?
+            .into_iter()
+            .filter(|file| args.input_dir.join(file).canonicalize().is_ok_and(|path| changes.contains(&path)))
+            .collect();
+        let overall = overall_bar(&args, &changed);
+        let count = changed.len();
+        for (index, file) in changed.into_iter().enumerate() {
+            let start = overall.as_ref().map_or(0, ProgressBar::position);
+            let outcome = translate_one(&args, &file, overall.as_ref()).await?;
+            if let Some(overall) = &overall {
+                file_done(&args, overall, start, &file, index + 1, count);
+            }
+            summary.push((file, outcome));
+        }
+        if let Some(overall) = overall {
+            overall.finish();
+        }
+        if !summary.is_empty() && !logging::quiet() {
+            print_summary(&summary);
+            println!("\nWatching {:?} for changes (Ctrl-C to stop).", args.input_dir);
+        }
+    }
+}
+
+/// A bar for all `files` above the bar of the file being translated, measured in input bytes
+/// (once per target language); none when progress isn't shown as bars.
+fn overall_bar(args: &TranslateDirArgs, files: &[String]) -> Option<ProgressBar> {
+    if args.options.progress != ProgressFormat::Bar || files.is_empty() {
+        return None;
+    }
+    let bytes: u64 = files.iter().filter_map(|file| fs::metadata(args.input_dir.join(file)).ok()).map(|m| m.len()).sum();
+    let bar = logging::add_bar(ProgressBar::new(bytes * args.options.target.len() as u64));
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template("Total [{elapsed_precise}] [{bar:40.green/white}] {bytes}/{total_bytes}, {msg} ({eta})")
+            .expect("the progress bar template is valid")
+            .progress_chars("=>-"),
+    );
+    bar.set_message(format!("0/{} files", files.len()));
+    Some(bar)
+}
+
+/// Moves `overall` from `start` to the end of a file, the `done`-th of `count`: while it was
+/// translated, it only advanced by the bytes of its text.
+fn file_done(args: &TranslateDirArgs, overall: &ProgressBar, start: u64, file: &str, done: usize, count: usize) {
+    let bytes = fs::metadata(args.input_dir.join(file)).map_or(0, |metadata| metadata.len());
+    overall.set_position(start + bytes * args.options.target.len() as u64);
+    overall.set_message(format!("{}/{} files", done, count));
+}
+
+/// Translates one file of the directory, given by its path relative to the input directory.
+async fn translate_one(args: &TranslateDirArgs, file: &str, overall: Option<&ProgressBar>) -> Result<Outcome, TranslatorError> {
+    let input_file = args.input_dir.join(file);
+    let format = args.options.format.unwrap_or_else(|| Format::from_path(&input_file));
+    // Files in legacy encodings aren't valid UTF-8, so only a given UTF-8 input encoding rules them out.
+    let utf8_only = args.options.input_encoding == InputEncoding::Fixed(UTF_8);
+    if !matches!(format, Format::Epub | Format::Docx | Format::Pdf) && is_binary(&input_file, utf8_only)? {
+        return Ok(Outcome::Binary);
+    }
+
+    // With several target languages, each gets a directory of its own below the output directory.
+    let mut output_file = match args.options.target.as_slice() {
+        [_] => args.out_dir.join(file),
+        _ => args.out_dir.join("{target}").join(file),
+    };
+    // The text of a PDF is written as plain text.
+    if format == Format::Pdf {
+        output_file.set_extension("txt");
+    }
+    for target in &args.options.target {
+        let output_path = translate::output_path(&output_file, &input_file, target);
+        if let Some(parent) = output_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    if !logging::quiet() {
+        logging::suspend(|| println!());
+    }
+    let mut options = args.options.clone();
+    options.overall_bar = overall.cloned();
+    Ok(match translate::translate_file(input_file, Some(output_file), options).await {
+        Ok(chunks) => Outcome::Translated(chunks),
+        Err(error) => {
+            error!("Error translating {}: {}", file, error);
+            Outcome::Failed(error.to_string())
+        }
+    })
+}
+
+/// Adds the files below `dir` to `files` as paths relative to the input directory, separated by '/'.
+///
+/// `prefix` is the relative path of `dir` itself. Directories matching an exclude glob are
+/// not descended into, and neither is the output directory.
+fn collect_files(
+    dir: &Path,
+    prefix: &str,
+    exclude: &[Regex],
+    out_dir: Option<&Path>,
+    files: &mut Vec<String>,
+) -> Result<(), TranslatorError> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let relative = format!("{}{}", prefix, name.to_string_lossy());
+        // Symbolic links to directories are not followed, so links can't make the walk loop.
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            let relative_dir = format!("{}/", relative);
+            let is_out_dir = out_dir.is_some_and(|out_dir| entry.path().canonicalize().is_ok_and(|path| path == out_dir));
+            if !is_out_dir && !exclude.iter().any(|glob| glob.is_match(&relative_dir)) {
+                collect_files(&entry.path(), &relative_dir, exclude, out_dir, files)?;
+            }
+        } else if entry.path().is_file() && !exclude.iter().any(|glob| glob.is_match(&relative)) {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the file looks like a binary: it has a NUL byte near the start or, with `utf8_only`,
+/// isn't valid UTF-8 there.
+fn is_binary(path: &Path, utf8_only: bool) -> Result<bool, TranslatorError> {
+    let mut start = Vec::with_capacity(BINARY_CHECK_LEN);
+    fs::File::open(path)?.take(BINARY_CHECK_LEN as u64).read_to_end(&mut start)?;
+    if start.contains(&0) {
+        return Ok(true);
+    }
+    if !utf8_only {
+        return Ok(false);
+    }
+    // The check may have cut a multi-byte character in half at the end.
+    Ok(match std::str::from_utf8(&start) {
+        Ok(_) => false,
+        Err(error) => error.error_len().is_some(),
+    })
+}
+
+/// Converts a glob into an anchored regular expression over '/'-separated relative paths.
+///
+/// `*` and `?` don't match '/', `**` matches across directories (`**/` also matches no
+/// directory at all), and `{a,b}` matches either alternative. Character classes are kept.
+fn glob_to_regex(glob: &str) -> Result<Regex, regex::Error> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    let mut braces = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    pattern.push_str("(?:.*/)?");
+                } else {
+                    pattern.push_str(".*");
+                }
+            }
+            '*' => pattern.push_str("[^/]*"),
+            '?' => pattern.push_str("[^/]"),
+            '[' => {
+                pattern.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    pattern.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == '\\' {
+                        pattern.push('\\');
+                    }
+                    pattern.push(c);
+                    if c == ']' {
+                        break;
+                    }
+                }
+            }
+            '{' => {
+                braces += 1;
+                pattern.push_str("(?:");
+            }
+            ',' if braces > 0 => pattern.push('|'),
+            '}' if braces > 0 => {
+                braces -= 1;
+                pattern.push(')');
+            }
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern)
+}
+
+/// Prints a table with the outcome of every file.
+fn print_summary(summary: &[(String, Outcome)]) {
+    let width = summary.iter().map(|(file, _)| file.chars().count()).max().unwrap_or(0).max("File".len());
+    println!("\n{:<width$}  {:<10}  Chunks", "File", "Status");
+    for (file, outcome) in summary {
+        match outcome {
+            Outcome::Translated(chunks) => println!("{:<width$}  {:<10}  {}", file, "translated", chunks),
+            Outcome::Binary => println!("{:<width$}  {:<10}  -", file, "skipped"),
+            Outcome::Failed(error) => println!("{:<width$}  {:<10}  -  {}", file, "failed", error),
+        }
+    }
+    let count = |status: fn(&Outcome) -> bool| summary.iter().filter(|(_, outcome)| status(outcome)).count();
+    println!(
+        "\n{} translated, {} skipped (binary), {} failed.",
+        count(|outcome| matches!(outcome, Outcome::Translated(_))),
+        count(|outcome| matches!(outcome, Outcome::Binary)),
+        count(|outcome| matches!(outcome, Outcome::Failed(_))),
+    );
+}
@@ -0,0 +1,62 @@
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use text_translator::TranslatorError;
+use tokio::sync::mpsc;
+
+/// How long the files have to stay unchanged before a batch of changes is reported, so a file
+/// saved in several writes is translated once.
+const SETTLE_TIME: Duration = Duration::from_millis(500);
+
+/// Reports the files that change in a directory, for '--watch'.
+pub struct Watcher {
+    // Dropping the watcher stops the notifications.
+    _watcher: RecommendedWatcher,
+    events: mpsc::UnboundedReceiver<notify::Result<Event>>,
+}
+
+impl Watcher {
+    /// Starts watching `dir`, and with `recursive` its subdirectories too.
+    ///
+    /// Files are watched through their directory, as editors often save by replacing the file.
+    pub fn new(dir: &Path, recursive: bool) -> Result<Self, TranslatorError> {
+        let (sender, events) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })
+        .map_err(watch_error)?;
+        let mode = if recursive { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+        watcher.watch(dir, mode).map_err(watch_error)?;
+        Ok(Self { _watcher: watcher, events })
+    }
+
+    /// Waits until files are created or modified and returns their paths, once they settled.
+    pub async fn changes(&mut self) -> Result<BTreeSet<PathBuf>, TranslatorError> {
+        let mut changed = BTreeSet::new();
+        while changed.is_empty() {
+            self.collect(&mut changed).await?;
+        }
+        while let Ok(event) = tokio::time::timeout(SETTLE_TIME, self.collect(&mut changed)).await {
+            event?;
+        }
+        Ok(changed)
+    }
+
+    /// Adds the files of the next event that writes files to `changed`.
+    async fn collect(&mut self, changed: &mut BTreeSet<PathBuf>) -> Result<(), TranslatorError> {
+        let event = self.events.recv().await.ok_or("The file watcher stopped")?.map_err(watch_error)?;
+        if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            changed.extend(event.paths);
+        }
+        Ok(())
+    }
+}
+
+fn watch_error(error: notify::Error) -> TranslatorError {
+    match error.kind {
+        notify::ErrorKind::Io(error) => TranslatorError::Io(error),
+        _ => TranslatorError::Io(io::Error::other(error)),
+    }
+}
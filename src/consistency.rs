@@ -0,0 +1,200 @@
+//! Whether the terms that recur in a document were translated the same way every time.
+//!
+//! Without a dictionary the translation of a term is found by co-occurrence: the target word
+//! found in most of the segments with the term, and rarely elsewhere, is taken as its usual
+//! translation. Segments with the term but without that word are reported. Words are compared by
+//! their first letters, so that inflected forms (`számla`, `számlát`) count as one.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::error::TranslatorError;
+use crate::output;
+
+/// Letters words are compared by.
+const STEM_LEN: usize = 5;
+
+/// Shortest word taken for a term or its translation, which leaves out most articles and conjunctions.
+const MIN_WORD_LEN: usize = 4;
+
+/// How strongly (by the Dice coefficient of the segments they appear in) a target word must go
+/// with a term to be taken as its translation.
+const MIN_ASSOCIATION: f64 = 0.5;
+
+/// The recurring terms of a file translated in several ways.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ConsistencyReport {
+    pub input_file: PathBuf,
+    pub source: String,
+    pub target: String,
+    pub terms: Vec<TermUsage>,
+}
+
+/// A source term and the ways it was translated, the usual one first.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TermUsage {
+    pub term: String,
+    pub renderings: Vec<Rendering>,
+}
+
+/// A translation of a term and the segments (numbered from 1) using it; `None` for segments
+/// whose translation of the term couldn't be told.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Rendering {
+    pub translation: Option<String>,
+    pub segments: Vec<usize>,
+}
+
+impl ConsistencyReport {
+    /// The report belonging to an output (or input) file, e.g. `output.translator-consistency.json`.
+    pub fn path_for(file: &Path) -> PathBuf {
+        file.with_extension("translator-consistency.json")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TranslatorError> {
+        output::write_atomic(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// The words of a text with the same stem, and how often each form appears.
+#[derive(Default)]
+struct Word {
+    segments: BTreeSet<usize>,
+    forms: HashMap<String, usize>,
+}
+
+impl Word {
+    /// The most frequent form, the first in alphabetical order on a tie.
+    fn form(&self) -> String {
+        let mut forms: Vec<(&String, &usize)> = self.forms.iter().collect();
+        forms.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        forms.first().map(|(form, _)| form.to_string()).unwrap_or_default()
+    }
+}
+
+/// The terms of the source segments whose translations don't use the same word for them every time.
+pub fn check(pairs: &[(&str, &str)]) -> Vec<TermUsage> {
+    let sources = index(pairs.iter().map(|(source, _)| *source));
+    let targets = index(pairs.iter().map(|(_, translation)| *translation));
+
+    let mut terms: Vec<(&String, &Word)> = sources.iter().filter(|(_, word)| word.segments.len() >= 2).collect();
+    terms.sort_by_key(|(stem, _)| *stem);
+    let mut usages = Vec::new();
+    for (_, term) in terms {
+        let association = |word: &Word| {
+            let shared = term.segments.intersection(&word.segments).count();
+            (shared, 2.0 * shared as f64 / (term.segments.len() + word.segments.len()) as f64)
+        };
+        // The usual translation: the word going best with the term in at least two segments.
+        // Words found in most segments anyway, like auxiliary verbs, go with no term in particular.
+        let usual = targets
+            .iter()
+            .map(|(stem, word)| (stem, word, association(word)))
+            .filter(|(_, _, (shared, dice))| *shared >= 2 && *dice >= MIN_ASSOCIATION)
+            .max_by(|(a, _, (_, a_dice)), (b, _, (_, b_dice))| a_dice.total_cmp(b_dice).then(b.cmp(a)));
+        let Some((usual_stem, usual, _)) = usual else { continue };
+        let deviating: BTreeSet<usize> = term.segments.difference(&usual.segments).copied().collect();
+        if deviating.is_empty() {
+            continue;
+        }
+        let mut renderings =
+            vec![Rendering { translation: Some(usual.form()), segments: term.segments.intersection(&usual.segments).map(|i| i + 1).collect() }];
+        // The other translation: a word several of the other segments share, and none of the usual ones.
+        let other = targets
+            .iter()
+            .filter(|(stem, word)| *stem != usual_stem && word.segments.is_disjoint(&usual.segments))
+            .map(|(_, word)| (word, word.segments.intersection(&deviating).count()))
+            .filter(|(_, shared)| *shared >= 2)
+            .max_by_key(|(_, shared)| *shared);
+        let mut unknown = deviating.clone();
+        if let Some((word, _)) = other {
+            let segments: Vec<usize> = deviating.intersection(&word.segments).copied().collect();
+            unknown.retain(|i| !segments.contains(i));
+            renderings.push(Rendering { translation: Some(word.form()), segments: segments.iter().map(|i| i + 1).collect() });
+        }
+        if !unknown.is_empty() {
+            renderings.push(Rendering { translation: None, segments: unknown.iter().map(|i| i + 1).collect() });
+        }
+        usages.push(TermUsage { term: term.form(), renderings });
+    }
+    usages
+}
+
+/// Replaces the other known translations of every term with its usual one in the translated
+/// segments, returning the number of replacements. Only the word is replaced, so a suffix of the
+/// other translation is lost.
+pub fn fix(translations: &mut [String], usages: &[TermUsage]) -> usize {
+    let mut replaced = 0;
+    for usage in usages {
+        let Some(usual) = usage.renderings.first().and_then(|rendering| rendering.translation.as_deref()) else { continue };
+        for rendering in &usage.renderings[1..] {
+            let Some(other) = rendering.translation.as_deref() else { continue };
+            for &segment in &rendering.segments {
+                let Some(translation) = translations.get_mut(segment - 1) else { continue };
+                let mut fixed = String::with_capacity(translation.len());
+                for (is_word, piece) in pieces(translation) {
+                    if is_word && stem(&piece.to_lowercase()) == stem(other) {
+                        fixed.push_str(&match_case(usual, piece));
+                        replaced += 1;
+                    } else {
+                        fixed.push_str(piece);
+                    }
+                }
+                *translation = fixed;
+            }
+        }
+    }
+    replaced
+}
+
+/// The words of the texts by their stem, with the indexes of the texts they appear in.
+fn index<'a>(texts: impl Iterator<Item = &'a str>) -> HashMap<String, Word> {
+    let mut words: HashMap<String, Word> = HashMap::new();
+    for (i, text) in texts.enumerate() {
+        for (_, word) in pieces(text).into_iter().filter(|(is_word, _)| *is_word) {
+            let word = word.to_lowercase();
+            if word.chars().count() < MIN_WORD_LEN || !word.chars().all(char::is_alphabetic) {
+                continue;
+            }
+            let entry = words.entry(stem(&word)).or_default();
+            entry.segments.insert(i);
+            *entry.forms.entry(word).or_insert(0) += 1;
+        }
+    }
+    words
+}
+
+/// Splits text into runs of letters and digits and the runs between them, marked with whether they are words.
+fn pieces(text: &str) -> Vec<(bool, &str)> {
+    let mut pieces: Vec<(bool, &str)> = Vec::new();
+    let mut start = 0;
+    let mut in_word = false;
+    for (i, c) in text.char_indices() {
+        if c.is_alphanumeric() != in_word {
+            if i > start {
+                pieces.push((in_word, &text[start..i]));
+            }
+            start = i;
+            in_word = c.is_alphanumeric();
+        }
+    }
+    if start < text.len() {
+        pieces.push((in_word, &text[start..]));
+    }
+    pieces
+}
+
+fn stem(word: &str) -> String {
+    word.chars().take(STEM_LEN).collect()
+}
+
+/// `word` capitalized like `like`.
+fn match_case(word: &str, like: &str) -> String {
+    let mut chars = word.chars();
+    match (chars.next(), like.chars().next()) {
+        (Some(first), Some(like)) if like.is_uppercase() => first.to_uppercase().chain(chars).collect(),
+        _ => word.to_string(),
+    }
+}
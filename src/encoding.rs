@@ -0,0 +1,149 @@
+//! Reading and writing text in legacy encodings such as Windows-1250 or ISO-8859-2.
+
+use chardetng::EncodingDetector;
+use encoding_rs::{EncoderResult, Encoding, UTF_8};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use std::io::{BufRead, BufReader, Read};
+use std::str::FromStr;
+
+use crate::error::TranslatorError;
+
+/// How many bytes at the start of a stream are looked at to guess its encoding.
+const DETECTION_LEN: usize = 64 * 1024;
+
+/// Languages whose country top-level domain differs from the language code.
+const COUNTRY_DOMAINS: &[(&str, &str)] = &[
+    ("be", "by"), ("cs", "cz"), ("da", "dk"), ("el", "gr"), ("et", "ee"), ("he", "il"), ("ja", "jp"),
+    ("ko", "kr"), ("sl", "si"), ("sv", "se"), ("uk", "ua"), ("zh", "cn"),
+];
+
+/// The encoding of an input: detected from its content, or a given one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputEncoding {
+    /// UTF-8 if the input is valid UTF-8, otherwise the most likely legacy encoding.
+    #[default]
+    Auto,
+    Fixed(&'static Encoding),
+}
+
+impl FromStr for InputEncoding {
+    type Err = String;
+
+    fn from_str(label: &str) -> Result<Self, Self::Err> {
+        match label {
+            "auto" => Ok(InputEncoding::Auto),
+            label => parse_encoding(label).map(InputEncoding::Fixed),
+        }
+    }
+}
+
+/// Looks up an encoding by one of its WHATWG labels, e.g. `utf-8`, `windows-1250` or `latin2`.
+pub fn parse_encoding(label: &str) -> Result<&'static Encoding, String> {
+    Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| format!("unknown encoding '{}'", label))
+}
+
+/// Guesses the encoding of a text: a byte order mark wins, then valid UTF-8, then the legacy
+/// encoding the content looks most like, preferring those used for `language` (e.g. Windows-1250
+/// for Hungarian).
+pub fn detect(bytes: &[u8], language: &str) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(country_domain(language).as_deref().map(str::as_bytes), true)
+}
+
+/// The top-level domain of the country most associated with a language code, as a hint for the
+/// encoding detection; none for English and unknown codes.
+fn country_domain(language: &str) -> Option<String> {
+    let code = language.split(['-', '_']).next()?.to_ascii_lowercase();
+    if code.len() != 2 || !code.bytes().all(|b| b.is_ascii_lowercase()) || code == "en" {
+        return None;
+    }
+    let domain = COUNTRY_DOMAINS.iter().find(|(language, _)| *language == code).map_or(code.as_str(), |(_, domain)| domain);
+    Some(domain.to_string())
+}
+
+/// Decodes a whole input written in `language`, returning the text and the encoding it was in.
+pub fn decode(
+    bytes: &[u8],
+    encoding: InputEncoding,
+    language: &str,
+) -> Result<(String, &'static Encoding), TranslatorError> {
+    let encoding = match encoding {
+        InputEncoding::Auto => detect(bytes, language),
+        InputEncoding::Fixed(encoding) => encoding,
+    };
+    let (bytes, encoding) = match Encoding::for_bom(bytes) {
+        Some((bom_encoding, bom_len)) if bom_encoding == encoding => (&bytes[bom_len..], encoding),
+        _ => (bytes, encoding),
+    };
+    let text = encoding.decode_without_bom_handling_and_without_replacement(bytes).ok_or_else(|| {
+        TranslatorError::Parse(format!(
+            "The input is not valid {}; give its encoding with '--input-encoding'",
+            encoding.name()
+        ))
+    })?;
+    Ok((text.into_owned(), encoding))
+}
+
+/// Wraps a reader so it yields UTF-8, for inputs too large to decode at once.
+///
+/// With [`InputEncoding::Auto`] the encoding is guessed from the first 64 KiB. Invalid bytes
+/// are replaced with U+FFFD.
+pub fn decode_reader<R: Read + 'static>(
+    reader: R,
+    encoding: InputEncoding,
+    language: &str,
+) -> Result<(Box<dyn BufRead>, &'static Encoding), TranslatorError> {
+    let mut reader = BufReader::with_capacity(DETECTION_LEN, reader);
+    let encoding = match encoding {
+        InputEncoding::Auto => {
+            let start = reader.fill_buf()?;
+            // The sample may end in the middle of a character.
+            match std::str::from_utf8(start) {
+                Err(error) if error.error_len().is_none() => UTF_8,
+                _ => detect(start, language),
+            }
+        }
+        InputEncoding::Fixed(encoding) => encoding,
+    };
+    if encoding == UTF_8 {
+        return Ok((Box::new(reader), encoding));
+    }
+    let decoder = DecodeReaderBytesBuilder::new().encoding(Some(encoding)).build(reader);
+    Ok((Box::new(BufReader::new(decoder)), encoding))
+}
+
+/// Encodes a text for writing, returning the bytes and the number of characters the encoding
+/// has no code for; those are written as `?`.
+pub fn encode(text: &str, encoding: &'static Encoding) -> (Vec<u8>, usize) {
+    // Encoding into UTF-16 is not supported by encoding_rs; such files are written as UTF-8.
+    let encoding = encoding.output_encoding();
+    if encoding == UTF_8 {
+        return (text.as_bytes().to_vec(), 0);
+    }
+    let mut encoder = encoding.new_encoder();
+    let mut bytes = Vec::with_capacity(text.len() + 16);
+    let mut unmappable = 0;
+    let mut rest = text;
+    loop {
+        if let Some(needed) = encoder.max_buffer_length_from_utf8_without_replacement(rest.len()) {
+            bytes.reserve(needed);
+        }
+        let (result, read) = encoder.encode_from_utf8_to_vec_without_replacement(rest, &mut bytes, true);
+        rest = &rest[read..];
+        match result {
+            EncoderResult::InputEmpty => return (bytes, unmappable),
+            EncoderResult::OutputFull => {}
+            EncoderResult::Unmappable(_) => {
+                unmappable += 1;
+                bytes.push(b'?');
+            }
+        }
+    }
+}
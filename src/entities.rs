@@ -0,0 +1,101 @@
+//! Named entities: people, places, product names and acronyms, which a translation should keep
+//! as they are.
+//!
+//! They are told apart by their spelling alone: acronyms (`NASA`), words with capitals inside
+//! (`iPhone`, `GitHub`), and capitalized words that don't start a sentence (`Anna`, `New York`).
+//! In languages that capitalize every noun, like German, the last kind catches too much.
+
+use regex::Regex;
+
+/// Characters after which the next word starts a sentence.
+const SENTENCE_ENDS: [char; 4] = ['.', '!', '?', ':'];
+
+/// The named entities of `text`, each once, in the order they first appear.
+pub fn find(text: &str) -> Vec<String> {
+    let mut entities: Vec<String> = Vec::new();
+    let mut add = |entity: &str| {
+        if !entities.iter().any(|known| known == entity) {
+            entities.push(entity.to_string());
+        }
+    };
+    // The byte range of the entity being read, which may span several words.
+    let mut current: Option<(usize, usize)> = None;
+    let mut sentence_start = true;
+    let mut last_end = 0;
+    for (start, word) in words(text) {
+        let gap = &text[last_end..start];
+        last_end = start + word.len();
+        if gap.contains('\n') {
+            sentence_start = true;
+        }
+        let core_start = word.len() - word.trim_start_matches(|c: char| !c.is_alphanumeric()).len();
+        let core = word.trim_matches(|c: char| !c.is_alphanumeric());
+        // Punctuation between two words ends the entity.
+        let joined = core_start == 0 && current.is_some();
+        if !core.is_empty() && is_entity(core, sentence_start) {
+            let core_range = (start + core_start, start + core_start + core.len());
+            current = match current {
+                Some((entity_start, _)) if joined => Some((entity_start, core_range.1)),
+                Some((entity_start, entity_end)) => {
+                    add(&text[entity_start..entity_end]);
+                    Some(core_range)
+                }
+                None => Some(core_range),
+            };
+        } else if let Some((entity_start, entity_end)) = current.take() {
+            add(&text[entity_start..entity_end]);
+        }
+        if word.len() > core_start + core.len() {
+            // Trailing punctuation ends the entity, and a full stop the sentence.
+            if let Some((entity_start, entity_end)) = current.take() {
+                add(&text[entity_start..entity_end]);
+            }
+        }
+        let trailing = word.trim_end_matches(['"', '\'', ')', ']', '”', '’', '*', '_']);
+        if !core.is_empty() {
+            sentence_start = trailing.ends_with(SENTENCE_ENDS);
+        }
+    }
+    if let Some((entity_start, entity_end)) = current {
+        add(&text[entity_start..entity_end]);
+    }
+    entities
+}
+
+/// The entities of `source` that don't appear unchanged in `translation`. An entity may take
+/// a suffix, as names do in languages like Hungarian (`Anna` → `Annának`).
+pub fn altered(source: &str, translation: &str) -> Vec<String> {
+    find(source).into_iter().filter(|entity| !starts_word(translation, entity)).collect()
+}
+
+/// A pattern matching any of the entities of `text` as whole words, longest first; `None` if it has none.
+pub fn pattern(text: &str) -> Option<Regex> {
+    let mut entities = find(text);
+    if entities.is_empty() {
+        return None;
+    }
+    entities.sort_by_key(|entity| std::cmp::Reverse(entity.len()));
+    let alternatives: Vec<String> = entities.iter().map(|entity| regex::escape(entity)).collect();
+    Regex::new(&format!(r"\b(?:{})\b", alternatives.join("|"))).ok()
+}
+
+/// The words of `text` with their byte offsets.
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_whitespace().map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+fn is_entity(word: &str, sentence_start: bool) -> bool {
+    let mut chars = word.chars();
+    let Some(first) = chars.next() else { return false };
+    let letters = word.chars().filter(|c| c.is_alphabetic()).count();
+    let capitals = word.chars().filter(|c| c.is_uppercase()).count();
+    let acronym = capitals >= 2 && capitals == letters;
+    let inner_capitals = chars.any(char::is_uppercase) && word.chars().any(char::is_lowercase);
+    let capitalized = first.is_uppercase() && !sentence_start && word != "I";
+    acronym || inner_capitals || capitalized
+}
+
+/// Whether `prefix` appears in `text` at the start of a word.
+fn starts_word(text: &str, prefix: &str) -> bool {
+    text.match_indices(prefix).any(|(start, _)| !text[..start].chars().next_back().is_some_and(char::is_alphanumeric))
+}
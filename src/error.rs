@@ -0,0 +1,118 @@
+//! The errors of the translation pipeline, told apart so callers can react to each kind: retry
+//! later when the server is busy, or give up on a request it will never accept.
+
+use std::io;
+use thiserror::Error;
+
+/// Everything that can go wrong while translating.
+#[derive(Debug, Error)]
+pub enum TranslatorError {
+    /// The server could not be reached, or the connection broke.
+    #[error("Network error: {0}")]
+    Network(#[from] reqwest::Error),
+
+    /// The server kept answering 429 Too Many Requests until the retries ran out.
+    #[error("Rate limited by the server: {0}")]
+    RateLimited(String),
+
+    /// The server kept failing with server errors (5xx) until the retries ran out.
+    #[error("{0}")]
+    Unavailable(String),
+
+    /// The server rejected the request (4xx), e.g. for an invalid API key or an unsupported language.
+    #[error("{message}")]
+    Api { status: u16, message: String },
+
+    /// An input file, a config file or a server response could not be parsed.
+    #[error("{0}")]
+    Parse(String),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    /// The translation cache database failed.
+    #[error("Cache error: {0}")]
+    Cache(#[from] rusqlite::Error),
+
+    /// Invalid options, settings or input, or an operation the backend doesn't support.
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl TranslatorError {
+    /// Whether the request may succeed later or on another server, as opposed to errors in the request itself.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, TranslatorError::Network(_) | TranslatorError::RateLimited(_) | TranslatorError::Unavailable(_))
+    }
+
+    /// The process exit code for the error, following the BSD `sysexits.h` conventions so
+    /// scripts can tell a busy server (75, try again later) from a bad request (76).
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            TranslatorError::Invalid(_) => 64,
+            TranslatorError::Parse(_) => 65,
+            TranslatorError::Network(_) => 68,
+            TranslatorError::Unavailable(_) => 69,
+            TranslatorError::Io(_) | TranslatorError::Cache(_) => 74,
+            TranslatorError::RateLimited(_) => 75,
+            TranslatorError::Api { .. } => 76,
+        }
+    }
+}
+
+impl From<String> for TranslatorError {
+    fn from(message: String) -> Self {
+        TranslatorError::Invalid(message)
+    }
+}
+
+impl From<&str> for TranslatorError {
+    fn from(message: &str) -> Self {
+        TranslatorError::Invalid(message.to_string())
+    }
+}
+
+impl From<serde_json::Error> for TranslatorError {
+    fn from(error: serde_json::Error) -> Self {
+        TranslatorError::Parse(format!("Invalid JSON: {}", error))
+    }
+}
+
+impl From<quick_xml::Error> for TranslatorError {
+    fn from(error: quick_xml::Error) -> Self {
+        TranslatorError::Parse(format!("Invalid XML: {}", error))
+    }
+}
+
+impl From<quick_xml::events::attributes::AttrError> for TranslatorError {
+    fn from(error: quick_xml::events::attributes::AttrError) -> Self {
+        TranslatorError::Parse(format!("Invalid XML attribute: {}", error))
+    }
+}
+
+impl From<zip::result::ZipError> for TranslatorError {
+    fn from(error: zip::result::ZipError) -> Self {
+        match error {
+            zip::result::ZipError::Io(error) => TranslatorError::Io(error),
+            error => TranslatorError::Parse(format!("Invalid ZIP archive: {}", error)),
+        }
+    }
+}
+
+impl From<toml::de::Error> for TranslatorError {
+    fn from(error: toml::de::Error) -> Self {
+        TranslatorError::Parse(format!("Invalid TOML: {}", error))
+    }
+}
+
+impl From<std::time::TryFromFloatSecsError> for TranslatorError {
+    fn from(error: std::time::TryFromFloatSecsError) -> Self {
+        TranslatorError::Invalid(format!("Invalid number of seconds: {}", error))
+    }
+}
+
+impl From<regex::Error> for TranslatorError {
+    fn from(error: regex::Error) -> Self {
+        TranslatorError::Invalid(format!("Invalid regular expression: {}", error))
+    }
+}
@@ -0,0 +1,87 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::TranslatorError;
+use crate::output;
+use crate::format::{Bilingual, Format};
+
+/// Chunks that could not be translated in a best-effort run, with what is needed to retry them.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FailureReport {
+    pub input_file: PathBuf,
+    /// The file the placeholders were written to; `None` if the translation was printed.
+    pub output_file: Option<PathBuf>,
+    pub format: Format,
+    pub source: String,
+    pub target: String,
+    pub chunk_size: usize,
+    pub mark_fuzzy: bool,
+    /// The Markdown front matter fields that were translated.
+    #[serde(default)]
+    pub front_matter_fields: Vec<String>,
+    /// The keys of a key=value file that were translated.
+    #[serde(default)]
+    pub keys: Vec<String>,
+    /// The columns of a table that were translated.
+    #[serde(default)]
+    pub columns: Vec<String>,
+    /// The JSONPath selectors of the strings that were translated.
+    #[serde(default)]
+    pub select: Vec<String>,
+    /// The XPath selectors of the XML elements that were translated.
+    #[serde(default)]
+    pub select_xpath: Vec<String>,
+    /// The layout of a bilingual output file.
+    #[serde(default)]
+    pub bilingual: Option<Bilingual>,
+    /// The encoding the input was read in, e.g. `windows-1250`; `None` for UTF-8.
+    #[serde(default)]
+    pub input_encoding: Option<String>,
+    /// The encoding the output was written in; `None` for UTF-8.
+    #[serde(default)]
+    pub output_encoding: Option<String>,
+    pub failures: Vec<Failure>,
+}
+
+/// A chunk that could not be translated.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Failure {
+    /// Number of the chunk, counting from 1 as in its placeholder.
+    pub chunk: usize,
+    pub error: String,
+    /// The source text of the chunk.
+    pub text: String,
+}
+
+impl FailureReport {
+    /// The report belonging to an output (or input) file, e.g. `output.translator-failures.json`.
+    pub fn path_for(file: &Path) -> PathBuf {
+        file.with_extension("translator-failures.json")
+    }
+
+    pub fn load(path: &Path) -> Result<Self, TranslatorError> {
+        let json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TranslatorError> {
+        output::write_atomic(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// The marker put in front of the source text of an untranslated chunk, e.g. `[[UNTRANSLATED CHUNK 17]]`.
+pub fn marker(chunk: usize) -> String {
+    format!("[[UNTRANSLATED CHUNK {}]]", chunk)
+}
+
+/// Stands in for the translation of a chunk that failed: every segment is kept in the source
+/// language with the chunk's marker in front, so it can be found and retried later.
+pub fn mark_untranslated(chunk: usize, text: &str) -> String {
+    let marker = marker(chunk);
+    text.split("\n\n")
+        .map(|segment| format!("{} {}", marker, segment))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
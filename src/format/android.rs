@@ -0,0 +1,205 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::html::{find_closing_tag, Tag};
+use super::xliff::attribute;
+use super::Document;
+use crate::no_translate::protect_matches;
+
+/// Translates the string values of an Android `strings.xml` resource file.
+///
+/// `<string>`s and the `<item>`s of `<string-array>`s and `<plurals>` are translated unless they
+/// are marked `translatable="false"` or refer to another resource; comments and everything else
+/// are kept. Inline tags such as `<b>` or `<xliff:g>`, CDATA sections, format specifiers and
+/// escapes like `\n` are replaced by placeholders, and apostrophes and quotes in the
+/// translation are escaped the way Android requires.
+pub fn parse(content: &str) -> Document {
+    let mut document = Document::new();
+    let mut verbatim_start = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = content[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(content, start).filter(|tag| !tag.closing) else {
+            pos = start + 1;
+            continue;
+        };
+        pos = tag.end;
+        let opening = &content[start..tag.end];
+        let Some(name @ ("string" | "string-array" | "plurals")) = tag.name.as_deref() else {
+            continue;
+        };
+        if opening.ends_with("/>") {
+            continue;
+        }
+        let Some(end) = find_closing_tag(content, tag.end, name) else {
+            continue;
+        };
+        pos = end;
+        if attribute(opening, "translatable") == Some("false") {
+            continue;
+        }
+        let content_end = start + content[start..end].rfind("</").unwrap_or(end - start);
+        document.push_verbatim(&content[verbatim_start..tag.end]);
+        if name == "string" {
+            push_value(&mut document, &content[tag.end..content_end]);
+        } else {
+            push_items(&mut document, &content[tag.end..content_end]);
+        }
+        verbatim_start = content_end;
+    }
+    document.push_verbatim(&content[verbatim_start..]);
+
+    document
+}
+
+/// Adds the `<item>`s of a string array or plurals element.
+fn push_items(document: &mut Document, content: &str) {
+    let mut verbatim_start = 0;
+    let mut pos = 0;
+    while let Some(offset) = content[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(content, start).filter(|tag| !tag.closing) else {
+            pos = start + 1;
+            continue;
+        };
+        pos = tag.end;
+        if tag.name.as_deref() != Some("item") || content[..tag.end].ends_with("/>") {
+            continue;
+        }
+        let Some(end) = find_closing_tag(content, tag.end, "item") else {
+            continue;
+        };
+        let content_end = start + content[start..end].rfind("</").unwrap_or(end - start);
+        document.push_verbatim(&content[verbatim_start..tag.end]);
+        push_value(document, &content[tag.end..content_end]);
+        verbatim_start = content_end;
+        pos = end;
+    }
+    document.push_verbatim(&content[verbatim_start..]);
+}
+
+/// Adds the text of one string value.
+fn push_value(document: &mut Document, value: &str) {
+    let trimmed = value.trim();
+    // `@string/other` and `?attr/name` refer to other resources.
+    if trimmed.starts_with(['@', '?']) {
+        document.push_verbatim(value);
+        return;
+    }
+    // In a value in double quotes, apostrophes don't need escaping.
+    let quoted = trimmed.len() >= 2 && trimmed.starts_with('"') && trimmed.ends_with('"') && !trimmed.ends_with("\\\"");
+    let (before, text, after) = if quoted {
+        let start = value.find('"').unwrap_or(0) + 1;
+        let end = value.rfind('"').unwrap_or(value.len());
+        (&value[..start], &value[start..end], &value[end..])
+    } else {
+        ("", value, "")
+    };
+    let (text, protected) = protect_matches(std::slice::from_ref(markup()), "", text);
+    let text = text.replace("\\'", "'").replace("\\\"", "\"").replace("&amp;", "&").replace("&quot;", "\"").replace("&apos;", "'");
+    document.push_verbatim(before);
+    document.push_escaped_text(&text, protected, if quoted { escape_quoted } else { escape });
+    document.push_verbatim(after);
+}
+
+/// Inline tags, CDATA sections, escapes and format specifiers, which are kept as they are.
+fn markup() -> &'static Regex {
+    static MARKUP: OnceLock<Regex> = OnceLock::new();
+    MARKUP.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?s)<!\[CDATA\[.*?\]\]>|<[^>]*>",
+            r"|\\(?:u[0-9a-fA-F]{4}|[nt\\@?])",
+            r"|%(?:\d+\$)?[-+#0 ,(]*\d*(?:\.\d+)?[sSdioxXeEfgGcbBhH%]",
+        ))
+        .unwrap()
+    })
+}
+
+/// Escapes apostrophes, quotes and ampersands outside of tags, and a leading `@` or `?`.
+fn escape(text: &str) -> String {
+    let escaped = escape_chars(text, &['\'', '"']);
+    if escaped.starts_with(['@', '?']) {
+        format!("\\{}", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// Escapes the quotes and ampersands of text inside a double-quoted value.
+fn escape_quoted(text: &str) -> String {
+    escape_chars(text, &['"'])
+}
+
+fn escape_chars(text: &str, quotes: &[char]) -> String {
+    static ENTITY: OnceLock<Regex> = OnceLock::new();
+    let entity = ENTITY.get_or_init(|| Regex::new(r"^&(?:#\d+|#x[0-9a-fA-F]+|[A-Za-z]\w*);").unwrap());
+    let mut result = String::with_capacity(text.len());
+    let mut in_tag = false;
+    let mut previous = None;
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            c if !in_tag && quotes.contains(&c) && previous != Some('\\') => result.push('\\'),
+            '&' if !in_tag && !entity.is_match(&text[i..]) => {
+                result.push_str("&amp;");
+                previous = Some(c);
+                continue;
+            }
+            _ => {}
+        }
+        result.push(c);
+        previous = Some(c);
+    }
+    result
+}
+
+/// The resource qualifier of a language, e.g. `hu`, `pt-rBR` or `b+zh+Hans`.
+pub fn qualifier(language: &str) -> String {
+    let parts: Vec<&str> = language.split(['-', '_']).collect();
+    match parts.as_slice() {
+        [language] => language.to_ascii_lowercase(),
+        [language, region] if region.len() == 2 || (region.len() == 3 && region.chars().all(|c| c.is_ascii_digit())) => {
+            format!("{}-r{}", language.to_ascii_lowercase(), region.to_ascii_uppercase())
+        }
+        _ => format!("b+{}", parts.join("+")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    const DOCUMENT: &str = r#"<resources>
+    <!-- Greeting -->
+    <string name="hello">Hello <b>%1$s</b>, it\'s late</string>
+    <string name="app" translatable="false">MyApp</string>
+    <string name="alias">@string/hello</string>
+    <plurals name="files">
+        <item quantity="one">%d file</item>
+        <item quantity="other">%d files</item>
+    </plurals>
+</resources>
+"#;
+
+    #[test]
+    fn android_round_trips_and_skips_untranslatable_strings() {
+        let document = parse(DOCUMENT);
+        assert_eq!(document.texts(), ["Hello <b>%1$s</b>, it's late", "%d file", "%d files"]);
+        assert_eq!(document.segments()[0], "Hello ⟦0⟧⟦1⟧⟦2⟧, it's late");
+        assert_eq!(round_trip(&document), DOCUMENT);
+    }
+
+    #[test]
+    fn apostrophes_are_escaped_only_outside_double_quotes() {
+        let document = parse("<string name=\"a\">Plain</string>\n<string name=\"b\">\"Quoted\"</string>\n");
+        let (translated, lost) = document.render(&["l'eau & \"vin\"".to_string(), "l'eau \"vin\"".to_string()]);
+        assert_eq!(lost, 0);
+        assert_eq!(
+            translated,
+            "<string name=\"a\">l\\'eau &amp; \\\"vin\\\"</string>\n<string name=\"b\">\"l'eau \\\"vin\\\"\"</string>\n"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::Document;
+use crate::no_translate::protect_matches;
+
+/// Splits AsciiDoc into translatable prose and verbatim markup.
+///
+/// Paragraphs, list items, table rows, section titles and block titles become segments, with
+/// inline code, passthroughs, cross-references, macros, URLs and attribute references replaced
+/// by placeholders. Listing, literal, passthrough and comment blocks, block attributes, block
+/// macros like `include::` and attribute entries are kept as they are.
+pub fn parse(content: &str) -> Document {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut document = Document::new();
+    let mut in_table = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let text = line.trim_end();
+
+        if text.is_empty() || text == "+" {
+            document.push_verbatim(line);
+            i += 1;
+        } else if let Some(verbatim) = delimiter(text) {
+            document.push_verbatim(line);
+            i += 1;
+            if text.ends_with("===") {
+                in_table = !in_table;
+            } else if verbatim {
+                // Everything up to the closing delimiter, or to the end if there is none.
+                while i < lines.len() {
+                    document.push_verbatim(lines[i]);
+                    i += 1;
+                    if lines[i - 1].trim_end() == text {
+                        break;
+                    }
+                }
+            }
+        } else if in_table {
+            push_line(&mut document, line, table_cells());
+            i += 1;
+        } else if is_markup_line(text) {
+            document.push_verbatim(line);
+            i += 1;
+        } else if line.starts_with([' ', '\t']) {
+            // A literal paragraph, kept up to the next blank line.
+            while i < lines.len() && !lines[i].trim().is_empty() {
+                document.push_verbatim(lines[i]);
+                i += 1;
+            }
+        } else if let Some(marker) = heading_len(text) {
+            document.push_verbatim(&line[..marker]);
+            push_line(&mut document, &line[marker..], inline());
+            i += 1;
+        } else {
+            let mut end = i + 1;
+            while end < lines.len() && !lines[end].trim().is_empty() && !starts_block(lines[end].trim_end()) {
+                end += 1;
+            }
+            let paragraph = lines[i..end].concat();
+            let mut start = list_marker().find(&paragraph).map_or(0, |marker| marker.end());
+            if let Some(term) = description_term().captures(&paragraph[start..]) {
+                // A description list item: its term and its description are translated separately.
+                let (whole, term) = (term.get(0).unwrap(), term.get(1).unwrap());
+                document.push_verbatim(&paragraph[..start]);
+                push_line(&mut document, &paragraph[start..start + term.end()], inline());
+                document.push_verbatim(&paragraph[start + term.end()..start + whole.end()]);
+                start += whole.end();
+            } else {
+                document.push_verbatim(&paragraph[..start]);
+            }
+            push_line(&mut document, &paragraph[start..], inline());
+            i = end;
+        }
+    }
+    document
+}
+
+/// Adds text with the matches of `markup` protected; the whitespace around it is kept verbatim.
+fn push_line(document: &mut Document, text: &str, markup: &Regex) {
+    let (text, protected) = protect_matches(std::slice::from_ref(markup), "", text);
+    document.push_text(&text, protected);
+}
+
+/// Whether the line opens or closes a delimited block, and whether that block's content is kept verbatim.
+fn delimiter(text: &str) -> Option<bool> {
+    if text == "--" {
+        return Some(false);
+    }
+    if ["|===", ",===", ":===", "!==="].contains(&text) {
+        return Some(false);
+    }
+    let first = text.chars().next()?;
+    if text.len() < 4 || !text.chars().all(|c| c == first) {
+        return None;
+    }
+    match first {
+        // Listing, literal, passthrough and comment blocks.
+        '-' | '.' | '+' | '/' => Some(true),
+        // Example, sidebar and quote blocks hold prose.
+        '=' | '*' | '_' => Some(false),
+        _ => None,
+    }
+}
+
+/// Whether the line is markup of its own: a comment, an attribute entry, block attributes or
+/// an anchor, or a block macro such as `image::` or `include::`.
+fn is_markup_line(text: &str) -> bool {
+    static MARKUP: OnceLock<Regex> = OnceLock::new();
+    MARKUP
+        .get_or_init(|| Regex::new(r"^(?://|:!?[\w-]+!?:(?:\s|$)|\[.*\]$|[a-z][\w-]*::\S*\[.*\]$)").unwrap())
+        .is_match(text)
+}
+
+/// Whether a line ends the paragraph before it by starting something else.
+fn starts_block(text: &str) -> bool {
+    text == "+"
+        || delimiter(text).is_some()
+        || is_markup_line(text)
+        || heading_len(text).is_some()
+        || list_marker().is_match(text)
+}
+
+/// The length of the marker of a section title (`== `) or a block title (`.`) the line starts with.
+fn heading_len(text: &str) -> Option<usize> {
+    static SECTION: OnceLock<Regex> = OnceLock::new();
+    let section = SECTION.get_or_init(|| Regex::new(r"^(?:=+|#+) +").unwrap());
+    if let Some(marker) = section.find(text) {
+        return Some(marker.end());
+    }
+    // `. item` and `.. item` are list items instead.
+    let title = text.strip_prefix('.')?;
+    title.starts_with(|c: char| !c.is_whitespace() && c != '.').then_some(1)
+}
+
+/// The marker of a list item or an admonition paragraph.
+fn list_marker() -> &'static Regex {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    MARKER.get_or_init(|| {
+        Regex::new(r"^(?:\s*(?:\*+|-|\.+|\d+\.|[a-zA-Z]\.|<\d+>) +(?:\[[ x*]\] +)?|(?:NOTE|TIP|IMPORTANT|WARNING|CAUTION): +)").unwrap()
+    })
+}
+
+/// The term of a description list item, up to its `::` (or `;;`, `:::`, …) delimiter and the spaces after it.
+fn description_term() -> &'static Regex {
+    static TERM: OnceLock<Regex> = OnceLock::new();
+    TERM.get_or_init(|| Regex::new(r"^([^\n]+?)(?::{2,4}|;;)(?: +|\n|$)").unwrap())
+}
+
+/// Inline markup that is kept as it is.
+fn inline() -> &'static Regex {
+    static INLINE: OnceLock<Regex> = OnceLock::new();
+    INLINE.get_or_init(|| {
+        Regex::new(concat!(
+            r"(?m)`[^`\n]+`",
+            r"|\+\+\+.+?\+\+\+|\+[^+\s][^+\n]*\+",
+            r"|<<[^>\n]+>>",
+            r"|\b(?:link|xref|image|kbd|btn|menu|footnote|footnoteref|pass|stem|latexmath|asciimath|icon|mailto|anchor|indexterm2?):[^\s\[]*\[[^\]\n]*\]",
+            r"|https?://[^\s\[]+(?:\[[^\]\n]*\])?",
+            r"|\{[\w-]+\}",
+            r"|\[\[[^\]]+\]\]",
+            r"| \+$",
+        ))
+        .unwrap()
+    })
+}
+
+/// The cell separators of a table row, with their cell specifiers such as `2+|` or `a|`.
+fn table_cells() -> &'static Regex {
+    static CELLS: OnceLock<Regex> = OnceLock::new();
+    CELLS.get_or_init(|| Regex::new(r"(?:^|\s)(?:\d+(?:\.\d+)?[+*])?[<^>]?(?:\.[<^>])?[adehlmsv]?\|").unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    const DOCUMENT: &str = "= Title\n:toc: left\n\nSee `code` and <<intro>> for {product}.\n\n[source,rust]\n----\nlet x = 1;\n----\n\n* First item\n* Second item\n\nCPU:: The processor.\n";
+
+    #[test]
+    fn asciidoc_round_trips_and_keeps_listing_blocks() {
+        let document = parse(DOCUMENT);
+        assert_eq!(
+            document.texts(),
+            ["Title", "See `code` and <<intro>> for {product}.", "First item", "Second item", "CPU", "The processor."]
+        );
+        assert_eq!(document.segments()[1], "See ⟦0⟧ and ⟦1⟧ for ⟦2⟧.");
+        assert_eq!(round_trip(&document), DOCUMENT);
+    }
+
+    #[test]
+    fn table_cell_separators_become_placeholders() {
+        let document = parse("|===\n|Name |Value\n2+|Spanning cell\n|===\n");
+        assert_eq!(document.segments(), ["⟦0⟧Name⟦1⟧Value", "⟦0⟧Spanning cell"]);
+        assert_eq!(round_trip(&document), "|===\n|Name |Value\n2+|Spanning cell\n|===\n");
+    }
+}
@@ -0,0 +1,92 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use super::{Document, Format, Part};
+use crate::placeholder;
+
+/// How the original text and its translation are laid out in bilingual output.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Bilingual {
+    /// Every paragraph is followed by its translation, with the same Markdown prefix (heading, list item).
+    Interleaved,
+    /// A two-column Markdown table with the original paragraphs on the left and the translations on the right.
+    Columns,
+}
+
+impl Bilingual {
+    /// Whether bilingual output can be made from files of this format; structured formats like
+    /// JSON or subtitles would no longer be valid with both languages in them.
+    pub fn supports(format: Format) -> bool {
+        matches!(format, Format::Text | Format::Markdown)
+    }
+}
+
+impl Document {
+    /// Like [`render_range`](Document::render_range), but keeps the original text of every
+    /// segment next to its translation.
+    ///
+    /// With [`Bilingual::Columns`] only the segments are written, as table rows; the range
+    /// starting at the first segment also writes the table header naming the languages.
+    pub fn render_bilingual_range(
+        &self,
+        segments: Range<usize>,
+        translations: &[String],
+        to_end: bool,
+        layout: Bilingual,
+        (source, target): (&str, &str),
+    ) -> (String, usize) {
+        let mut result = String::new();
+        let mut translations = translations.iter();
+        let mut lost_placeholders = 0;
+        let mut index = 0;
+        // The markup at the start of the current line, such as `# ` or `- `, repeated before the translation.
+        let mut line_prefix = String::new();
+
+        if layout == Bilingual::Columns && segments.start == 0 {
+            result.push_str(&format!("| {} | {} |\n| --- | --- |\n", source, target));
+        }
+        for part in &self.parts {
+            match part {
+                Part::Verbatim(text) => {
+                    match text.rfind('\n') {
+                        Some(newline) => line_prefix = text[newline + 1..].to_string(),
+                        None => line_prefix.push_str(text),
+                    }
+                    let in_range = index >= segments.start && (index < segments.end || to_end);
+                    if in_range && layout == Bilingual::Interleaved {
+                        result.push_str(text);
+                    }
+                }
+                Part::Text { text, protected, escape } => {
+                    if segments.contains(&index) {
+                        let translation = translations.next().unwrap_or(text);
+                        let (original, _) = placeholder::restore(text, protected);
+                        let (translated, missing) = placeholder::restore(translation, protected);
+                        lost_placeholders += missing.len();
+                        match layout {
+                            Bilingual::Interleaved => {
+                                result.push_str(&escape(&original));
+                                result.push_str("\n\n");
+                                result.push_str(&line_prefix);
+                                result.push_str(&escape(&translated));
+                            }
+                            Bilingual::Columns => {
+                                result.push_str(&format!("| {} | {} |\n", table_cell(&original), table_cell(&translated)));
+                            }
+                        }
+                    }
+                    line_prefix.clear();
+                    index += 1;
+                }
+            }
+        }
+        (result, lost_placeholders)
+    }
+}
+
+/// Escapes text for a Markdown table cell, which has to stay on one line.
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
@@ -0,0 +1,102 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::{Document, Format};
+
+/// Lines of plain text that start a chapter: `Chapter 3`, `Part Two`, `Prologue`, a lone roman
+/// or arabic numeral, or a Hungarian `3. fejezet`.
+fn text_heading() -> &'static Regex {
+    static HEADING: OnceLock<Regex> = OnceLock::new();
+    HEADING.get_or_init(|| {
+        Regex::new(r"(?i)^\s*(?:(?:chapter|part|book)\s+\S.*|prologue|epilogue|[ivxlc]+\.?|\d+\.?|\d+\.\s*fejezet.*)\s*$")
+            .unwrap()
+    })
+}
+
+/// Where a chapter starts: in the verbatim text `lead` bytes before the segment with the
+/// index `segment`, e.g. at the `# ` of a Markdown heading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChapterStart {
+    pub segment: usize,
+    pub lead: usize,
+}
+
+/// Finds where the chapters of a plain text or Markdown document start. The text before the
+/// first heading belongs to no chapter.
+///
+/// In Markdown the chapters start at the headings of the highest level used (`#`, or `##` if
+/// there are none, …), outside of code blocks. In plain text they start at short lines standing
+/// alone between blank lines that look like chapter titles, such as `Chapter 3`.
+pub fn chapter_starts(document: &Document, format: Format) -> Vec<ChapterStart> {
+    let (text, _) = document.render(&[]);
+    let headings = match format {
+        Format::Markdown => markdown_headings(&text),
+        _ => text_headings(&text),
+    };
+
+    let offsets = document.segment_offsets(&[]);
+    let mut starts = Vec::new();
+    for heading in headings {
+        // The heading's own segment, or the next one if the heading has nothing to translate.
+        let Some(segment) = offsets.iter().position(|&offset| offset >= heading) else { break };
+        if starts.last().is_none_or(|start: &ChapterStart| start.segment != segment) {
+            starts.push(ChapterStart { segment, lead: offsets[segment] - heading });
+        }
+    }
+    starts
+}
+
+/// Splits the document rendered with `translations` into chapters at `starts`, with the text
+/// before the first chapter as a piece of its own if there is any.
+pub fn split(document: &Document, translations: &[String], starts: &[ChapterStart]) -> (Vec<String>, usize) {
+    let (text, lost_placeholders) = document.render(translations);
+    let offsets = document.segment_offsets(translations);
+    let mut pieces = Vec::with_capacity(starts.len() + 1);
+    let mut piece_start = 0;
+    for (index, start) in starts.iter().enumerate() {
+        let end = offsets[start.segment] - start.lead;
+        if index > 0 || !text[..end].trim().is_empty() {
+            pieces.push(text[piece_start..end].to_string());
+        }
+        piece_start = end;
+    }
+    pieces.push(text[piece_start..].to_string());
+    (pieces, lost_placeholders)
+}
+
+/// The byte offsets of the lines with the highest-level ATX headings.
+fn markdown_headings(text: &str) -> Vec<usize> {
+    let mut headings = Vec::new();
+    let mut in_code = false;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+        } else if !in_code {
+            let level = trimmed.len() - trimmed.trim_start_matches('#').len();
+            if (1..=6).contains(&level) && trimmed[level..].starts_with([' ', '\t']) {
+                headings.push((level, offset));
+            }
+        }
+        offset += line.len();
+    }
+    let top = headings.iter().map(|&(level, _)| level).min();
+    headings.into_iter().filter(|&(level, _)| Some(level) == top).map(|(_, offset)| offset).collect()
+}
+
+/// The byte offsets of the lines that look like chapter titles and stand alone between blank lines.
+fn text_headings(text: &str) -> Vec<usize> {
+    let lines: Vec<&str> = text.split_inclusive('\n').collect();
+    let is_blank = |index: Option<usize>| index.and_then(|index| lines.get(index)).is_none_or(|line| line.trim().is_empty());
+    let mut headings = Vec::new();
+    let mut offset = 0;
+    for (index, line) in lines.iter().enumerate() {
+        let alone = is_blank(index.checked_sub(1)) && is_blank(Some(index + 1));
+        if alone && line.trim().chars().count() <= 60 && text_heading().is_match(line.trim_end()) {
+            headings.push(offset);
+        }
+        offset += line.len();
+    }
+    headings
+}
@@ -0,0 +1,248 @@
+use regex::Regex;
+use std::ops::Range;
+use std::sync::OnceLock;
+use tree_sitter::{Node, Parser};
+
+use super::Document;
+use crate::error::TranslatorError;
+use crate::placeholder;
+
+/// The programming languages whose comments can be translated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syntax {
+    Rust,
+    Python,
+    Javascript,
+}
+
+impl Syntax {
+    fn grammar(self) -> tree_sitter::Language {
+        match self {
+            Syntax::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Syntax::Python => tree_sitter_python::LANGUAGE.into(),
+            Syntax::Javascript => tree_sitter_javascript::LANGUAGE.into(),
+        }
+    }
+}
+
+/// A part of a source file written in a human language.
+#[derive(Debug)]
+enum Span {
+    /// A `//`, `///`, `//!` or `#` comment up to the end of its line.
+    Line(Range<usize>),
+    /// A `/* */`, `/** */` or `/*! */` comment.
+    Block(Range<usize>),
+    /// The text of a Python docstring, between its quotes.
+    Docstring(Range<usize>),
+}
+
+/// Translates the comments and doc comments of a source file, and the docstrings of Python
+/// code, leaving the code itself untouched.
+///
+/// The file is parsed with tree-sitter, so comment markers inside strings aren't mistaken for
+/// comments. Consecutive line comments are translated together as one paragraph, with the
+/// markers at the start of their lines protected; blank comment lines, fenced code examples
+/// and tool directives like `# noqa` or `// eslint-disable` are kept as they are, and so are
+/// inline code, URLs and doc tags like `@param` or `:returns:`.
+pub fn parse(content: &str, syntax: Syntax) -> Result<Document, TranslatorError> {
+    let mut parser = Parser::new();
+    parser.set_language(&syntax.grammar()).map_err(|e| format!("Can't load the {:?} grammar: {}", syntax, e))?;
+    let tree = parser.parse(content, None).ok_or_else(|| format!("Can't parse the {:?} source", syntax))?;
+
+    let spans = collect_spans(content, tree.root_node(), syntax);
+    let mut document = Document::new();
+    let mut copied = 0;
+    let mut i = 0;
+    while i < spans.len() {
+        let (start, lines) = match &spans[i] {
+            Span::Line(range) => {
+                // Consecutive comments with the same marker, each on its own line.
+                let marker = line_marker(&content[range.clone()]);
+                let mut lines = vec![line_comment(content, range.start, range)];
+                while let Some(Span::Line(next)) = spans.get(i + 1) {
+                    let gap = &content[lines[lines.len() - 1].1.end..next.start];
+                    if gap.matches('\n').count() != 1 || !gap.trim().is_empty() || line_marker(&content[next.clone()]) != marker {
+                        break;
+                    }
+                    lines.push(line_comment(content, lines[lines.len() - 1].1.end, next));
+                    i += 1;
+                }
+                (range.start, lines)
+            }
+            Span::Block(range) => (range.start, block_comment(content, range)),
+            Span::Docstring(range) => (range.start, docstring_lines(content, range)),
+        };
+        document.push_verbatim(&content[copied..start]);
+        copied = lines.last().map_or(start, |(_, body)| body.end);
+        push_lines(&mut document, content, &lines);
+        i += 1;
+    }
+    document.push_verbatim(&content[copied..]);
+    Ok(document)
+}
+
+/// The comments and docstrings in the tree, in the order they appear.
+fn collect_spans(content: &str, root: Node, syntax: Syntax) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut cursor = root.walk();
+    loop {
+        let node = cursor.node();
+        let span = match node.kind() {
+            "line_comment" | "block_comment" | "comment" if content[node.byte_range()].starts_with("/*") => {
+                Some(Span::Block(node.byte_range()))
+            }
+            "line_comment" | "block_comment" | "comment" => Some(Span::Line(node.byte_range())),
+            _ if syntax == Syntax::Python => docstring(node).map(Span::Docstring),
+            _ => None,
+        };
+        let descend = span.is_none();
+        spans.extend(span);
+        if descend && cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                return spans;
+            }
+        }
+    }
+}
+
+/// The text of a docstring: a string that is the first statement of a module, class or function.
+fn docstring(node: Node) -> Option<Range<usize>> {
+    if node.kind() != "expression_statement" || node.named_child_count() != 1 {
+        return None;
+    }
+    let string = node.named_child(0).filter(|child| child.kind() == "string")?;
+    let parent = node.parent().filter(|parent| matches!(parent.kind(), "module" | "block"))?;
+    let mut cursor = parent.walk();
+    let first_statement = parent.named_children(&mut cursor).find(|child| child.kind() != "comment")?;
+    if first_statement.id() != node.id() {
+        return None;
+    }
+    let mut cursor = string.walk();
+    let children: Vec<Node> = string.children(&mut cursor).collect();
+    let (start, end) = (children.first()?, children.last()?);
+    if start.kind() != "string_start" || end.kind() != "string_end" || children.iter().any(|child| child.kind() == "interpolation") {
+        return None;
+    }
+    Some(start.end_byte()..end.start_byte())
+}
+
+/// The marker a line comment starts with.
+fn line_marker(comment: &str) -> &str {
+    ["///", "//!", "//", "#"].into_iter().find(|marker| comment.starts_with(marker)).unwrap_or_default()
+}
+
+/// The prefix (from `from`, up to and including the marker and a space after it) and the text of a line comment.
+fn line_comment(content: &str, from: usize, comment: &Range<usize>) -> (Range<usize>, Range<usize>) {
+    let text = content[comment.clone()].trim_end_matches(['\n', '\r']);
+    let marker = line_marker(text);
+    let prefix_len = marker.len() + usize::from(text[marker.len()..].starts_with(' '));
+    (from..comment.start + prefix_len, comment.start + prefix_len..comment.start + text.len())
+}
+
+/// The lines of a block comment, with its opening marker and the `*`s at the start of its lines as prefixes.
+fn block_comment(content: &str, comment: &Range<usize>) -> Vec<(Range<usize>, Range<usize>)> {
+    static MARGIN: OnceLock<Regex> = OnceLock::new();
+    let margin = MARGIN.get_or_init(|| Regex::new(r"^[ \t]*(?:\*[ \t]?)?").unwrap());
+    let text = &content[comment.clone()];
+    let open = if text.starts_with("/**") || text.starts_with("/*!") { 3 } else { 2 };
+    let close = if text.len() >= open + 2 && text.ends_with("*/") { 2 } else { 0 };
+    text_lines(content, comment.start, comment.start + open..comment.end - close, margin)
+}
+
+/// The lines of a docstring, with their indentation as prefixes.
+fn docstring_lines(content: &str, text: &Range<usize>) -> Vec<(Range<usize>, Range<usize>)> {
+    static INDENT: OnceLock<Regex> = OnceLock::new();
+    text_lines(content, text.start, text.clone(), INDENT.get_or_init(|| Regex::new(r"^[ \t]*").unwrap()))
+}
+
+/// Splits `text` into lines with a prefix (starting at `from` for the first line) and the text
+/// after it; the prefix of the other lines is their line break and the `margin` at their start.
+fn text_lines(content: &str, from: usize, text: Range<usize>, margin: &Regex) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut lines = Vec::new();
+    let mut prefix_start = from;
+    let mut line_start = text.start;
+    for (i, line) in content[text].split('\n').enumerate() {
+        // The first line only keeps a space after the opening marker in its prefix.
+        let skip = if i == 0 { usize::from(line.starts_with(' ')) } else { margin.find(line).map_or(0, |found| found.end()) };
+        lines.push((prefix_start..line_start + skip, line_start + skip..line_start + line.len()));
+        prefix_start = line_start + line.len();
+        line_start = prefix_start + 1;
+    }
+    lines
+}
+
+/// Adds the lines of a comment, grouping them into paragraphs in which the line prefixes are protected.
+fn push_lines(document: &mut Document, content: &str, lines: &[(Range<usize>, Range<usize>)]) {
+    let mut text = String::new();
+    let mut protected = Vec::new();
+    let mut in_paragraph = false;
+    let mut in_code = false;
+    for (prefix, body) in lines {
+        let line = &content[body.clone()];
+        let fence = line.trim_start().starts_with("```");
+        if fence || in_code || line.trim().is_empty() || directive().is_match(line) {
+            document.push_text(&std::mem::take(&mut text), std::mem::take(&mut protected));
+            document.push_verbatim(&content[prefix.start..body.end]);
+            in_paragraph = false;
+            in_code ^= fence;
+            continue;
+        }
+        if in_paragraph {
+            text.push_str(&placeholder::token(protected.len()));
+            protected.push(content[prefix.clone()].to_string());
+        } else {
+            document.push_verbatim(&content[prefix.clone()]);
+            in_paragraph = true;
+        }
+        let mut copied = 0;
+        for found in inline().find_iter(line) {
+            text.push_str(&line[copied..found.start()]);
+            text.push_str(&placeholder::token(protected.len()));
+            protected.push(found.as_str().to_string());
+            copied = found.end();
+        }
+        text.push_str(&line[copied..]);
+    }
+    document.push_text(&text, protected);
+}
+
+/// Comments that are read by tools rather than people, such as `# noqa` or `// eslint-disable-next-line`.
+fn directive() -> &'static Regex {
+    static DIRECTIVE: OnceLock<Regex> = OnceLock::new();
+    DIRECTIVE.get_or_init(|| {
+        Regex::new(r"^\s*(?:!|-\*-|type:|noqa|pylint:|fmt:|isort:|pragma\b|eslint|prettier-ignore|@ts-|istanbul\b|jshint|global\b|rustfmt::|clippy::)").unwrap()
+    })
+}
+
+/// Inline code, URLs and documentation tags, which stay untranslated inside comments.
+fn inline() -> &'static Regex {
+    static INLINE: OnceLock<Regex> = OnceLock::new();
+    INLINE.get_or_init(|| Regex::new(r"`[^`\n]*`|https?://\S+|@\w+(?:\s+\{[^}\n]*\})?|:\w+(?:\s+[\w.]+)?:").unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    const RUST: &str = "/// Adds `a` to the\n/// counter.\nfn add(a: u32) {\n    let s = \"// not a comment\";\n    // clippy::needless_return\n}\n";
+
+    #[test]
+    fn rust_comments_round_trip_and_strings_are_left_alone() {
+        let document = parse(RUST, Syntax::Rust).unwrap();
+        assert_eq!(document.texts(), ["Adds `a` to the\n/// counter."]);
+        assert_eq!(document.segments(), ["Adds ⟦0⟧ to the⟦1⟧counter."]);
+        assert_eq!(round_trip(&document), RUST);
+    }
+
+    #[test]
+    fn python_docstrings_are_translated_but_not_other_strings() {
+        let content = "def f():\n    \"\"\"Return the answer.\n\n    :returns: a number\n    \"\"\"\n    x = \"\"\"not a docstring\"\"\"  # noqa: E501\n    return 42\n";
+        let document = parse(content, Syntax::Python).unwrap();
+        assert_eq!(document.texts(), ["Return the answer.", ":returns: a number"]);
+        assert_eq!(round_trip(&document), content);
+    }
+}
@@ -0,0 +1,196 @@
+use super::Document;
+use crate::error::TranslatorError;
+
+/// Delimiters a table may use, in order of preference when they are equally frequent.
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+/// A CSV or TSV table, parsed piece by piece so huge files can be streamed in blocks of rows.
+///
+/// The first row is the header and is kept. The cells of the chosen columns in the rows after it
+/// become segments; every other cell, the delimiters and the quoting are kept as they are. The
+/// delimiter (a comma, semicolon, tab or pipe) is detected from the header.
+pub struct Table {
+    columns: Vec<String>,
+    /// Detected from the header.
+    delimiter: Option<char>,
+    /// The indexes of the translated columns once the header is known; all columns when empty.
+    selected: Option<Vec<usize>>,
+}
+
+impl Table {
+    /// A table translating the `columns` given by number (from 1) or header name, or all of them.
+    pub fn new(columns: &[String]) -> Self {
+        Self { columns: columns.to_vec(), delimiter: None, selected: None }
+    }
+
+    /// Parses the next rows of the table, which must end at the end of a row.
+    pub fn parse(&mut self, content: &str) -> Result<Document, TranslatorError> {
+        let delimiter = *self.delimiter.get_or_insert_with(|| detect_delimiter(content));
+        let mut document = Document::new();
+        let mut pos = 0;
+        if self.selected.is_none() {
+            let end = row_end(content, 0, delimiter);
+            self.selected = Some(self.resolve(&content[..end], delimiter)?);
+            document.push_verbatim(&content[..end]);
+            pos = end;
+        }
+        let selected = self.selected.as_deref().unwrap_or_default();
+
+        let mut column = 0;
+        while pos < content.len() {
+            let end = field_end(content, pos, delimiter);
+            let field = &content[pos..end];
+            if selected.is_empty() || selected.contains(&column) {
+                push_field(&mut document, field);
+            } else {
+                document.push_verbatim(field);
+            }
+            pos = end;
+            match content[pos..].chars().next() {
+                Some(c) if c == delimiter => column += 1,
+                _ => column = 0,
+            }
+            if let Some(c) = content[pos..].chars().next() {
+                document.push_verbatim(&content[pos..pos + c.len_utf8()]);
+                pos += c.len_utf8();
+            }
+        }
+        Ok(document)
+    }
+
+    /// The indexes of the chosen columns in the header row.
+    fn resolve(&self, header: &str, delimiter: char) -> Result<Vec<usize>, TranslatorError> {
+        let mut names = Vec::new();
+        let mut pos = 0;
+        while pos < header.len() {
+            let end = field_end(header, pos, delimiter);
+            names.push(unquote(header[pos..end].trim()));
+            pos = end + 1;
+        }
+        self.columns
+            .iter()
+            .map(|column| match column.trim().parse::<usize>() {
+                Ok(0) => Err("CSV columns are numbered from 1".into()),
+                Ok(number) => Ok(number - 1),
+                Err(_) => names
+                    .iter()
+                    .position(|name| name.eq_ignore_ascii_case(column.trim()))
+                    .ok_or_else(|| format!("The CSV header has no column named {:?}", column).into()),
+            })
+            .collect()
+    }
+}
+
+/// Adds a cell, translating the text inside its quotes if it has any.
+fn push_field(document: &mut Document, field: &str) {
+    match field.strip_prefix('"').and_then(|field| field.strip_suffix('"')) {
+        Some(inner) => {
+            document.push_verbatim("\"");
+            document.push_escaped_text(&inner.replace("\"\"", "\""), Vec::new(), escape_quoted);
+            document.push_verbatim("\"");
+        }
+        None => document.push_escaped_text(field, Vec::new(), quote_if_needed),
+    }
+}
+
+/// The end of the field starting at `start`: a delimiter or line break outside of quotes.
+fn field_end(content: &str, start: usize, delimiter: char) -> usize {
+    let rest = &content[start..];
+    let mut quoted_len = 0;
+    if rest.starts_with('"') {
+        // A quoted field runs up to the first quote that isn't doubled.
+        let mut i = 1;
+        loop {
+            match rest[i..].find('"') {
+                Some(quote) if rest[i + quote + 1..].starts_with('"') => i += quote + 2,
+                Some(quote) => break quoted_len = i + quote + 1,
+                None => return content.len(),
+            }
+        }
+    }
+    let len = rest[quoted_len..].find(['\n', delimiter]).unwrap_or(rest.len() - quoted_len);
+    start + quoted_len + len
+}
+
+/// The end of the row starting at `start`, after its line break.
+fn row_end(content: &str, start: usize, delimiter: char) -> usize {
+    let mut pos = start;
+    while pos < content.len() {
+        pos = field_end(content, pos, delimiter);
+        let at_line_end = content[pos..].starts_with('\n');
+        pos = (pos + 1).min(content.len());
+        if at_line_end {
+            break;
+        }
+    }
+    pos
+}
+
+/// The most frequent delimiter in the first line.
+fn detect_delimiter(content: &str) -> char {
+    let first_line = content.lines().next().unwrap_or_default();
+    let count = |delimiter: char| first_line.matches(delimiter).count();
+    // The first of the most frequent delimiters, or a comma if none appears.
+    DELIMITERS.into_iter().rev().max_by_key(|&delimiter| count(delimiter)).filter(|&delimiter| count(delimiter) > 0).unwrap_or(',')
+}
+
+fn unquote(field: &str) -> String {
+    match field.strip_prefix('"').and_then(|field| field.strip_suffix('"')) {
+        Some(inner) => inner.replace("\"\"", "\""),
+        None => field.to_string(),
+    }
+}
+
+fn escape_quoted(text: &str) -> String {
+    text.replace('"', "\"\"")
+}
+
+/// Quotes a translation that would otherwise be split into several cells or rows.
+fn quote_if_needed(text: &str) -> String {
+    if text.trim().is_empty() {
+        return text.to_string();
+    }
+    if text.contains(['"', '\n']) || DELIMITERS.iter().any(|&delimiter| text.contains(delimiter)) {
+        format!("\"{}\"", escape_quoted(text))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    #[test]
+    fn csv_round_trips_with_quoted_cells() {
+        let csv = "id,text,note\n1,Hello,\"Say \"\"hi\"\"\"\n2,\"Two, with a comma\",\"Line one\nline two\"\n";
+        let document = Table::new(&[]).parse(csv).unwrap();
+        assert_eq!(document.texts(), ["Hello", "Say \"hi\"", "Two, with a comma", "Line one\nline two"]);
+        assert_eq!(round_trip(&document), csv);
+    }
+
+    #[test]
+    fn only_chosen_columns_are_translated() {
+        let tsv = "key\tEnglish\tcomment\nopen\tOpen the file\tmenu\nclose\tClose\tmenu\n";
+        let document = Table::new(&["english".to_string()]).parse(tsv).unwrap();
+        assert_eq!(document.texts(), ["Open the file", "Close"]);
+        assert_eq!(round_trip(&document), tsv);
+    }
+
+    #[test]
+    fn rows_parsed_in_blocks_keep_the_header_columns() {
+        let mut table = Table::new(&["2".to_string()]);
+        let first = table.parse("a;b\nx;Hello\n").unwrap();
+        let second = table.parse("y;World\n").unwrap();
+        assert_eq!(first.texts(), ["Hello"]);
+        assert_eq!(second.texts(), ["World"]);
+        assert_eq!(round_trip(&second), "y;World\n");
+    }
+
+    #[test]
+    fn translations_needing_quotes_are_quoted() {
+        let document = Table::new(&[]).parse("text\nplain\n").unwrap();
+        assert_eq!(document.render(&["with, comma".to_string()]).0, "text\n\"with, comma\"\n");
+    }
+}
@@ -0,0 +1,225 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::TranslatorError;
+use crate::placeholder;
+use super::html::Tag;
+use super::xliff::set_attribute;
+use super::Document;
+
+const MAIN_DOCUMENT_PATH: &str = "word/document.xml";
+const STYLES_PATH: &str = "word/styles.xml";
+
+/// The text elements are written with this tag, so the spaces a translation puts at their
+/// edges are kept.
+const TEXT_TAG: &str = "<w:t xml:space=\"preserve\">";
+
+/// Markup that may sit between two runs without making them different.
+const RUN_BOUNDARY_ELEMENTS: &[&str] = &["w:t", "w:r", "w:prooferr"];
+
+/// A Word document: the text runs of its body, headers, footers and notes are translated,
+/// everything else is copied as-is.
+pub struct Docx {
+    /// All files of the container, in their original order.
+    entries: Vec<(String, Vec<u8>)>,
+    /// Indices into `entries` of the parts with text, the main document first.
+    text_parts: Vec<usize>,
+}
+
+impl Docx {
+    pub fn open(path: &Path) -> Result<Self, TranslatorError> {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let mut entries = Vec::with_capacity(archive.len());
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            let mut data = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut data)?;
+            entries.push((file.name().to_string(), data));
+        }
+
+        let main = entries
+            .iter()
+            .position(|(name, _)| name == MAIN_DOCUMENT_PATH)
+            .ok_or_else(|| TranslatorError::Parse(format!("Not a DOCX file: {} is missing", MAIN_DOCUMENT_PATH)))?;
+        let mut text_parts = vec![main];
+        text_parts.extend((0..entries.len()).filter(|&index| is_note_part(&entries[index].0)));
+        Ok(Self { entries, text_parts })
+    }
+
+    /// Parses the parts with text for translation, the main document first.
+    pub fn documents(&self) -> Result<Vec<Document>, TranslatorError> {
+        self.text_parts
+            .iter()
+            .map(|&index| {
+                let (name, data) = &self.entries[index];
+                let xml = std::str::from_utf8(data).map_err(|e| format!("{} is not valid UTF-8: {}", name, e))?;
+                Ok(parse(xml))
+            })
+            .collect()
+    }
+
+    /// Writes a copy of the document with the given translated parts (in the order of
+    /// `documents`) and the default language of its styles set to `language`.
+    pub fn write(&self, path: &Path, translated: &[String], language: &str) -> Result<(), TranslatorError> {
+        let mut zip = ZipWriter::new(File::create(path)?);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        for (index, (name, data)) in self.entries.iter().enumerate() {
+            zip.start_file(name.as_str(), options)?;
+            if name == STYLES_PATH {
+                let styles = String::from_utf8_lossy(data);
+                zip.write_all(set_language(&styles, language).as_bytes())?;
+            } else if let Some(position) = self.text_parts.iter().position(|&i| i == index) {
+                zip.write_all(translated[position].as_bytes())?;
+            } else {
+                zip.write_all(data)?;
+            }
+        }
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+/// Whether the part is a header, footer, footnotes or endnotes part.
+fn is_note_part(name: &str) -> bool {
+    let Some(file) = name.strip_prefix("word/").and_then(|file| file.strip_suffix(".xml")) else {
+        return false;
+    };
+    let numbered = |prefix: &str| file.strip_prefix(prefix).is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()));
+    numbered("header") || numbered("footer") || file == "footnotes" || file == "endnotes"
+}
+
+/// Splits WordprocessingML at paragraph boundaries and translates the text of each paragraph
+/// as one segment.
+///
+/// The run markup between the texts of a paragraph (bold, italic, tabs, breaks, …) is
+/// protected with placeholders, so the formatting lands on the translated words again.
+/// Neighbouring runs with the same properties are merged first, as Word often splits text
+/// into many runs for editing history and spell checking alone. The text is sent in HTML mode,
+/// as it keeps its XML escapes.
+pub fn parse(content: &str) -> Document {
+    let mut document = Document::new();
+    let mut segment_start = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = content[pos..].find('<') {
+        let tag_start = pos + offset;
+        let Some(tag) = Tag::parse(content, tag_start) else {
+            pos = tag_start + 1;
+            continue;
+        };
+        pos = tag.end;
+        if tag.name.as_deref() == Some("w:p") {
+            push_paragraph(&mut document, &content[segment_start..tag_start]);
+            document.push_verbatim(&content[tag_start..tag.end]);
+            segment_start = tag.end;
+        }
+    }
+    push_paragraph(&mut document, &content[segment_start..]);
+
+    document
+}
+
+/// A `<w:t>` element of a paragraph, as byte positions in the paragraph.
+struct Text {
+    tag_start: usize,
+    content_start: usize,
+    content_end: usize,
+    /// The `<w:rPr>` of the run the text belongs to.
+    properties: String,
+}
+
+/// Adds the markup and text of a paragraph, with the text as one segment.
+fn push_paragraph(document: &mut Document, paragraph: &str) {
+    let texts = texts(paragraph);
+    let (Some(first), Some(last)) = (texts.first(), texts.last()) else {
+        document.push_verbatim(paragraph);
+        return;
+    };
+
+    let mut text = String::new();
+    let mut protected = Vec::new();
+    for (index, current) in texts.iter().enumerate() {
+        if let Some(previous) = index.checked_sub(1).map(|index| &texts[index]) {
+            let markup = &paragraph[previous.content_end..current.tag_start];
+            if previous.properties != current.properties || !is_run_boundary(markup, &current.properties) {
+                text.push_str(&placeholder::token(protected.len()));
+                protected.push(format!("{}{}", markup, TEXT_TAG));
+            }
+        }
+        text.push_str(&paragraph[current.content_start..current.content_end]);
+    }
+
+    document.push_verbatim(&paragraph[..first.tag_start]);
+    document.push_verbatim(TEXT_TAG);
+    document.push_text(&text, protected);
+    document.push_verbatim(&paragraph[last.content_end..]);
+}
+
+/// The `<w:t>` elements of a paragraph with the properties of their runs.
+fn texts(paragraph: &str) -> Vec<Text> {
+    let mut texts = Vec::new();
+    let mut properties = String::new();
+    let mut pos = 0;
+    while let Some(offset) = paragraph[pos..].find('<') {
+        let tag_start = pos + offset;
+        let Some(tag) = Tag::parse(paragraph, tag_start) else {
+            pos = tag_start + 1;
+            continue;
+        };
+        pos = tag.end;
+        let self_closing = paragraph[..tag.end].ends_with("/>");
+        match tag.name.as_deref() {
+            Some("w:r") if !tag.closing => properties.clear(),
+            Some("w:rpr") if !tag.closing && !self_closing => {
+                let end = paragraph[tag_start..].find("</w:rPr>").map_or(paragraph.len(), |end| tag_start + end + 8);
+                properties = paragraph[tag_start..end].to_string();
+                pos = end;
+            }
+            Some("w:t") if !tag.closing && !self_closing => {
+                let content_end = paragraph[tag.end..].find("</w:t>").map_or(paragraph.len(), |end| tag.end + end);
+                texts.push(Text { tag_start, content_start: tag.end, content_end, properties: properties.clone() });
+                pos = content_end;
+            }
+            _ => {}
+        }
+    }
+    texts
+}
+
+/// Whether the markup between two texts only ends one run and starts another with `properties`.
+fn is_run_boundary(markup: &str, properties: &str) -> bool {
+    let markup = markup.replacen(properties, "", 1);
+    let mut pos = 0;
+    while let Some(offset) = markup[pos..].find('<') {
+        let start = pos + offset;
+        if !markup[pos..start].trim().is_empty() {
+            return false;
+        }
+        let Some(tag) = Tag::parse(&markup, start) else {
+            return false;
+        };
+        if !tag.name.as_deref().is_some_and(|name| RUN_BOUNDARY_ELEMENTS.contains(&name)) {
+            return false;
+        }
+        pos = tag.end;
+    }
+    markup[pos..].trim().is_empty()
+}
+
+/// Sets the language of the `<w:lang>` elements of the styles, used for spell checking.
+fn set_language(styles: &str, language: &str) -> String {
+    let mut result = String::with_capacity(styles.len());
+    let mut rest = styles;
+    while let Some(start) = rest.find("<w:lang ") {
+        let Some(end) = rest[start..].find('>').map(|i| start + i + 1) else { break };
+        result.push_str(&rest[..start]);
+        result.push_str(&set_attribute(&rest[start..end], "w:val", language));
+        rest = &rest[end..];
+    }
+    result.push_str(rest);
+    result
+}
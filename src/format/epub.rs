@@ -0,0 +1,172 @@
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::error::TranslatorError;
+use super::{html, Document};
+
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+const XHTML_MEDIA_TYPE: &str = "application/xhtml+xml";
+
+/// An EPUB book: its XHTML content documents are translated, everything else is copied as-is.
+pub struct Epub {
+    /// All files of the container, in their original order.
+    entries: Vec<(String, Vec<u8>)>,
+    /// Index into `entries` of the package (OPF) document.
+    package: usize,
+    /// Indices into `entries` of the XHTML content documents, in manifest order.
+    content_documents: Vec<usize>,
+}
+
+impl Epub {
+    pub fn open(path: &Path) -> Result<Self, TranslatorError> {
+        let mut archive = ZipArchive::new(File::open(path)?)?;
+        let mut entries = Vec::with_capacity(archive.len());
+        for index in 0..archive.len() {
+            let mut file = archive.by_index(index)?;
+            let mut data = Vec::with_capacity(file.size() as usize);
+            file.read_to_end(&mut data)?;
+            entries.push((file.name().to_string(), data));
+        }
+
+        let find = |name: &str| entries.iter().position(|(n, _)| n == name);
+        let container = find(CONTAINER_PATH).ok_or_else(|| TranslatorError::Parse("Not an EPUB file: META-INF/container.xml is missing".to_string()))?;
+        let package_path = attribute_values(&entries[container].1, "rootfile", "full-path")?
+            .into_iter()
+            .next()
+            .ok_or_else(|| TranslatorError::Parse("The EPUB container doesn't name a package document".to_string()))?;
+        let package = find(&package_path).ok_or_else(|| TranslatorError::Parse(format!("The EPUB package {} is missing", package_path)))?;
+
+        // Manifest hrefs are relative to the package document.
+        let base = package_path.rfind('/').map_or("", |i| &package_path[..=i]);
+        let content_documents = manifest_items(&entries[package].1)?
+            .into_iter()
+            .filter(|(_, media_type)| media_type == XHTML_MEDIA_TYPE)
+            .filter_map(|(href, _)| find(&format!("{}{}", base, percent_decode(&href))))
+            .collect();
+
+        Ok(Self {
+            entries,
+            package,
+            content_documents,
+        })
+    }
+
+    /// Parses the content documents for translation, in manifest order.
+    pub fn documents(&self) -> Result<Vec<Document>, TranslatorError> {
+        self.content_documents
+            .iter()
+            .map(|&index| {
+                let (name, data) = &self.entries[index];
+                let xhtml = std::str::from_utf8(data).map_err(|e| format!("{} is not valid UTF-8: {}", name, e))?;
+                Ok(super::parse_marked(xhtml, html::parse))
+            })
+            .collect()
+    }
+
+    /// Writes a copy of the book with the given translated content documents (in the order of
+    /// `documents`) and the `dc:language` metadata set to `language`.
+    pub fn write(&self, path: &Path, translated: &[String], language: &str) -> Result<(), TranslatorError> {
+        let mut zip = ZipWriter::new(File::create(path)?);
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        for (index, (name, data)) in self.entries.iter().enumerate() {
+            // The `mimetype` file must be stored uncompressed so readers can identify the file.
+            let options = if name == "mimetype" {
+                deflated.compression_method(CompressionMethod::Stored)
+            } else {
+                deflated
+            };
+            zip.start_file(name.as_str(), options)?;
+
+            if index == self.package {
+                let opf = String::from_utf8_lossy(data);
+                zip.write_all(set_language(&opf, language).as_bytes())?;
+            } else if let Some(position) = self.content_documents.iter().position(|&i| i == index) {
+                zip.write_all(translated[position].as_bytes())?;
+            } else {
+                zip.write_all(data)?;
+            }
+        }
+        zip.finish()?;
+        Ok(())
+    }
+}
+
+/// Collects the values of `attribute` on every `element` in the XML document.
+fn attribute_values(xml: &[u8], element: &str, attribute: &str) -> Result<Vec<String>, TranslatorError> {
+    let mut reader = Reader::from_reader(xml);
+    let mut values = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == element.as_bytes() => {
+                for attr in e.attributes() {
+                    let attr = attr?;
+                    if attr.key.local_name().as_ref() == attribute.as_bytes() {
+                        values.push(attr.unescape_value()?.into_owned());
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(values)
+}
+
+/// The `(href, media-type)` pairs of the package manifest.
+fn manifest_items(opf: &[u8]) -> Result<Vec<(String, String)>, TranslatorError> {
+    let hrefs = attribute_values(opf, "item", "href")?;
+    let media_types = attribute_values(opf, "item", "media-type")?;
+    if hrefs.len() != media_types.len() {
+        return Err(TranslatorError::Parse(
+            "Every manifest item of the EPUB package needs an href and a media-type".to_string(),
+        ));
+    }
+    Ok(hrefs.into_iter().zip(media_types).collect())
+}
+
+/// Replaces the content of the `<dc:language>` elements of the package document.
+fn set_language(opf: &str, language: &str) -> String {
+    let mut result = String::with_capacity(opf.len());
+    let mut rest = opf;
+    while let Some(start) = rest.find("<dc:language") {
+        let Some(open_end) = rest[start..].find('>').map(|i| start + i + 1) else { break };
+        let Some(close) = rest[open_end..].find("</dc:language>").map(|i| open_end + i) else { break };
+        result.push_str(&rest[..open_end]);
+        result.push_str(language);
+        rest = &rest[close..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Decodes `%XX` escapes in a manifest href.
+fn percent_decode(href: &str) -> String {
+    let bytes = href.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
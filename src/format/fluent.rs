@@ -0,0 +1,44 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::Document;
+use crate::no_translate::protect_matches;
+
+/// Translates the message values of a Mozilla Fluent (`.ftl`) file.
+///
+/// The text of every message, term, attribute and select expression variant is translated
+/// line by line; identifiers, variant keys, comments and the indentation are kept, and
+/// placeables such as `{ $name }`, `{ -brand }` or the selector `{ $count ->` are replaced by
+/// placeholders.
+pub fn parse(content: &str) -> Document {
+    static PREFIX: OnceLock<Regex> = OnceLock::new();
+    // The part of a line before its text: a message or term identifier, an attribute, a variant key or indentation.
+    let prefix = PREFIX.get_or_init(|| {
+        Regex::new(r"^(?:-?[A-Za-z][\w-]*[ \t]*=[ \t]*|[ \t]+\.[A-Za-z][\w-]*[ \t]*=[ \t]*|[ \t]+\*?\[[^\]]*\][ \t]*|[ \t]+)")
+            .unwrap()
+    });
+    let mut document = Document::new();
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end();
+        if text.trim().is_empty() || text.starts_with('#') {
+            document.push_verbatim(line);
+            continue;
+        }
+        // Lines that are neither a message nor indented continue a syntax error; they are kept.
+        let Some(prefix) = prefix.find(text) else {
+            document.push_verbatim(line);
+            continue;
+        };
+        document.push_verbatim(&line[..prefix.end()]);
+        let (text, protected) = protect_matches(std::slice::from_ref(placeables()), "", &text[prefix.end()..]);
+        document.push_text(&text, protected);
+        document.push_verbatim(&line[line.trim_end().len()..]);
+    }
+    document
+}
+
+/// Placeables, the start of a select expression and its closing brace.
+fn placeables() -> &'static Regex {
+    static PLACEABLES: OnceLock<Regex> = OnceLock::new();
+    PLACEABLES.get_or_init(|| Regex::new(r#"\{(?:[^{}"]|"[^"]*")*\}|\{[^{}]*->\s*$|^\}"#).unwrap())
+}
@@ -0,0 +1,134 @@
+use super::Document;
+
+/// Elements that start a new segment; the text between them is translated as one unit
+/// together with its inline markup (links, emphasis, …).
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "base", "blockquote", "body", "caption", "col", "colgroup", "dd", "details",
+    "dialog", "div", "dl", "dt", "fieldset", "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5",
+    "h6", "head", "header", "hgroup", "hr", "html", "li", "link", "main", "meta", "nav", "ol", "optgroup", "option",
+    "p", "section", "select", "summary", "table", "tbody", "td", "tfoot", "th", "thead", "title", "tr", "ul",
+];
+
+/// Elements whose content is kept verbatim.
+const RAW_ELEMENTS: &[&str] = &["script", "style", "pre", "textarea", "noscript"];
+
+/// Splits HTML at block-level element boundaries.
+///
+/// Segments keep their inline tags and are meant to be sent with the backend's HTML mode,
+/// which translates the text around the tags. Block-level tags, comments, doctypes and the
+/// content of `script`, `style` and `pre` elements stay untouched.
+pub fn parse(content: &str) -> Document {
+    let mut document = Document::new();
+    let mut segment_start = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = content[pos..].find('<') {
+        let tag_start = pos + offset;
+        let Some(tag) = Tag::parse(content, tag_start) else {
+            pos = tag_start + 1;
+            continue;
+        };
+
+        let is_boundary = match &tag.name {
+            None => true,
+            Some(name) => BLOCK_ELEMENTS.contains(&name.as_str()) || RAW_ELEMENTS.contains(&name.as_str()),
+        };
+        if !is_boundary {
+            pos = tag.end;
+            continue;
+        }
+
+        document.push_text(&content[segment_start..tag_start], Vec::new());
+        let mut verbatim_end = tag.end;
+        if let Some(name) = tag.name.as_deref().filter(|name| RAW_ELEMENTS.contains(name) && !tag.closing) {
+            verbatim_end = find_closing_tag(content, tag.end, name).unwrap_or(content.len());
+        }
+        document.push_verbatim(&content[tag_start..verbatim_end]);
+        segment_start = verbatim_end;
+        pos = verbatim_end;
+    }
+    document.push_text(&content[segment_start..], Vec::new());
+
+    document
+}
+
+/// A tag, comment or other markup declaration found in the source.
+pub(super) struct Tag {
+    /// Lower-case element name; `None` for comments, doctypes and processing instructions.
+    pub(super) name: Option<String>,
+    pub(super) closing: bool,
+    /// Position just after the closing `>`.
+    pub(super) end: usize,
+}
+
+impl Tag {
+    pub(super) fn parse(content: &str, start: usize) -> Option<Tag> {
+        let rest = &content[start..];
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").map_or(content.len(), |e| start + e + 3);
+            return Some(Tag { name: None, closing: false, end });
+        }
+        if rest.starts_with("<!") || rest.starts_with("<?") {
+            let end = rest.find('>').map_or(content.len(), |e| start + e + 1);
+            return Some(Tag { name: None, closing: false, end });
+        }
+
+        let closing = rest.starts_with("</");
+        let name_start = if closing { 2 } else { 1 };
+        let name_len = rest[name_start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == ':'))
+            .unwrap_or(rest.len() - name_start);
+        if name_len == 0 || !rest[name_start..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            // A lone `<` in text, such as `a < b`.
+            return None;
+        }
+        let name = rest[name_start..name_start + name_len].to_ascii_lowercase();
+
+        // Find the end of the tag, skipping `>` characters inside quoted attribute values.
+        let mut quote = None;
+        for (i, c) in rest.char_indices().skip(name_start + name_len) {
+            match (quote, c) {
+                (None, '"' | '\'') => quote = Some(c),
+                (Some(q), c) if c == q => quote = None,
+                (None, '>') => {
+                    return Some(Tag {
+                        name: Some(name),
+                        closing,
+                        end: start + i + 1,
+                    })
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Finds the end of the `</name>` tag closing a raw text element.
+pub(super) fn find_closing_tag(content: &str, from: usize, name: &str) -> Option<usize> {
+    let lower = content[from..].to_ascii_lowercase();
+    let start = from + lower.find(&format!("</{}", name))?;
+    Tag::parse(content, start).map(|tag| tag.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    #[test]
+    fn html_round_trips_with_inline_markup_in_blocks() {
+        let html = "<!DOCTYPE html>\n<html><body>\n<h1>Title</h1>\n<p>Some <a href=\"/x\" title='a > b'>linked</a> <em>text</em>.</p>\n</body></html>\n";
+        let document = parse(html);
+        assert_eq!(document.texts(), ["Title", "Some <a href=\"/x\" title='a > b'>linked</a> <em>text</em>."]);
+        assert_eq!(round_trip(&document), html);
+    }
+
+    #[test]
+    fn scripts_styles_comments_and_comparisons_are_handled() {
+        let html = "<p>If a < b</p><!-- <p>hidden</p> --><script>let p = \"<p>no</p>\";</script><PRE>kept <b>as is</b></PRE>";
+        let document = parse(html);
+        assert_eq!(document.texts(), ["If a < b"]);
+        assert_eq!(round_trip(&document), html);
+    }
+}
@@ -0,0 +1,79 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::Document;
+use crate::no_translate::protect_matches;
+
+/// Translates the values of an iOS or macOS `.strings` file.
+///
+/// Every `"key" = "value";` entry gets its value translated; keys and comments are kept.
+/// Escapes like `\n` and format specifiers like `%@` or `%1$d` are replaced by placeholders,
+/// and quotes in the translation are escaped.
+pub fn parse(content: &str) -> Document {
+    static ENTRY: OnceLock<Regex> = OnceLock::new();
+    let entry = ENTRY.get_or_init(|| {
+        Regex::new(r#"(?s)/\*.*?\*/|//[^\n]*|(?:"(?:[^"\\]|\\.)*"|[\w.-]+)\s*=\s*"((?:[^"\\]|\\.)*)""#).unwrap()
+    });
+    let mut document = Document::new();
+    let mut copied = 0;
+    for captures in entry.captures_iter(content) {
+        // Comments have no value.
+        let Some(value) = captures.get(1) else { continue };
+        document.push_verbatim(&content[copied..value.start()]);
+        let (text, protected) = protect_matches(std::slice::from_ref(markup()), "", value.as_str());
+        document.push_escaped_text(&text.replace("\\\"", "\""), protected, escape);
+        copied = value.end();
+    }
+    document.push_verbatim(&content[copied..]);
+    document
+}
+
+/// Escapes and format specifiers, which are kept as they are.
+fn markup() -> &'static Regex {
+    static MARKUP: OnceLock<Regex> = OnceLock::new();
+    MARKUP.get_or_init(|| {
+        Regex::new(concat!(
+            r"\\(?:[Uu][0-9a-fA-F]{4}|[nrt0\\])",
+            r"|%(?:\d+\$)?[-+#0 ']*\d*(?:\.\d+)?(?:hh|h|ll|l|q|L|z|t|j)?[@dDiuUxXoOfFeEgGcCsSpaA%]",
+        ))
+        .unwrap()
+    })
+}
+
+/// Escapes the quotes of a translation.
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut previous = None;
+    for c in text.chars() {
+        if c == '"' && previous != Some('\\') {
+            result.push('\\');
+        }
+        result.push(c);
+        previous = Some(c);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    const DOCUMENT: &str = "/* Greeting */\n\"hello\" = \"Hello %@,\\nsay \\\"hi\\\"\";\nkey.name = \"%1$d items\";\n";
+
+    #[test]
+    fn strings_round_trip_and_keep_keys_and_comments() {
+        let document = parse(DOCUMENT);
+        assert_eq!(document.texts(), ["Hello %@,\\nsay \"hi\"", "%1$d items"]);
+        assert_eq!(document.segments()[0], "Hello ⟦0⟧,⟦1⟧say \"hi\"");
+        assert_eq!(round_trip(&document), DOCUMENT);
+    }
+
+    #[test]
+    fn quotes_in_the_translation_are_escaped() {
+        let document = parse("\"title\" = \"Title\";\n");
+        let (translated, lost) = document.render(&["A \"cím\"".to_string()]);
+        assert_eq!(lost, 0);
+        assert_eq!(translated, "\"title\" = \"A \\\"cím\\\"\";\n");
+    }
+}
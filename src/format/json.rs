@@ -0,0 +1,252 @@
+use super::Document;
+use crate::error::TranslatorError;
+use crate::placeholder;
+
+/// Translates the string values of a JSON locale file such as `en.json`.
+///
+/// The file is scanned rather than deserialized, so keys, their order, numbers, literals and
+/// the original layout are written back unchanged. Interpolation placeholders like `{name}`
+/// and `%s` are protected.
+pub fn parse(content: &str) -> Document {
+    let mut document = Document::new();
+    let mut rest = content;
+
+    while let Some(start) = rest.find('"') {
+        document.push_verbatim(&rest[..start]);
+        let Some(len) = string_len(&rest[start..]) else {
+            document.push_verbatim(&rest[start..]);
+            return document;
+        };
+        let literal = &rest[start..start + len];
+        rest = &rest[start + len..];
+
+        if rest.trim_start().starts_with(':') {
+            // An object key.
+            document.push_verbatim(literal);
+            continue;
+        }
+        let (text, protected) = placeholder::protect_format_specifiers(&unescape(&literal[1..len - 1]));
+        document.push_verbatim("\"");
+        document.push_escaped_text(&text, protected, escape);
+        document.push_verbatim("\"");
+    }
+    document.push_verbatim(rest);
+
+    document
+}
+
+/// Translates only the string values of a JSON document that one of the JSONPath `selectors`
+/// matches, such as `$.items[*].description`; everything else is kept byte for byte.
+///
+/// Selectors support child names (`.name`, `['name']`), indexes (`[0]`), wildcards (`.*`,
+/// `[*]`) and recursive descent (`..name`).
+pub fn parse_selected(content: &str, selectors: &[String]) -> Result<Document, TranslatorError> {
+    let selectors = selectors.iter().map(|selector| Selector::parse(selector)).collect::<Result<Vec<_>, _>>()?;
+    let mut document = Document::new();
+    // The containers around the current position, with the key or index in each.
+    let mut path: Vec<PathElement> = Vec::new();
+    let mut copied = 0;
+    let mut pos = 0;
+    while let Some(offset) = content[pos..].find(['"', '{', '[', '}', ']', ',']) {
+        let start = pos + offset;
+        pos = start + 1;
+        match content.as_bytes()[start] {
+            b'{' => path.push(PathElement::Key(String::new())),
+            b'[' => path.push(PathElement::Index(0)),
+            b'}' | b']' => {
+                path.pop();
+            }
+            b',' => {
+                if let Some(PathElement::Index(index)) = path.last_mut() {
+                    *index += 1;
+                }
+            }
+            _ => {
+                let Some(len) = string_len(&content[start..]) else { break };
+                let literal = &content[start..start + len];
+                pos = start + len;
+                if content[pos..].trim_start().starts_with(':') {
+                    if let Some(PathElement::Key(key)) = path.last_mut() {
+                        *key = unescape(&literal[1..len - 1]);
+                    }
+                } else if selectors.iter().any(|selector| selector.matches(&path)) {
+                    document.push_verbatim(&content[copied..start + 1]);
+                    let (text, protected) = placeholder::protect_format_specifiers(&unescape(&literal[1..len - 1]));
+                    document.push_escaped_text(&text, protected, escape);
+                    copied = pos - 1;
+                }
+            }
+        }
+    }
+    document.push_verbatim(&content[copied..]);
+    Ok(document)
+}
+
+/// A step from a container to one of its values.
+#[derive(Debug, PartialEq)]
+enum PathElement {
+    Key(String),
+    Index(usize),
+}
+
+/// One step of a JSONPath selector.
+#[derive(Debug)]
+enum Step {
+    Child(PathElement),
+    /// Any child, `*`.
+    Wildcard,
+    /// Any number of levels, `..`.
+    Descendants,
+}
+
+/// A JSONPath expression matched against the path of a value.
+#[derive(Debug)]
+struct Selector {
+    steps: Vec<Step>,
+}
+
+impl Selector {
+    fn parse(selector: &str) -> Result<Self, TranslatorError> {
+        let invalid = |reason: &str| TranslatorError::from(format!("Invalid JSONPath {:?}: {}", selector, reason));
+        let mut rest = selector.trim().strip_prefix('$').ok_or_else(|| invalid("it must start with '$'"))?;
+        let mut steps = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("..") {
+                steps.push(Step::Descendants);
+                // `..name` and `..*` name the descendant directly, `..[0]` uses brackets.
+                rest = if after.starts_with('[') { after } else { &rest[1..] };
+            }
+            if let Some(after) = rest.strip_prefix('.') {
+                let end = after.find(['.', '[']).unwrap_or(after.len());
+                steps.push(match &after[..end] {
+                    "" => return Err(invalid("a name is missing after '.'")),
+                    "*" => Step::Wildcard,
+                    name => Step::Child(PathElement::Key(name.to_string())),
+                });
+                rest = &after[end..];
+            } else if let Some(after) = rest.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(|| invalid("a '[' is never closed"))?;
+                let inner = after[..end].trim();
+                let quoted = |quote: char| inner.len() >= 2 && inner.starts_with(quote) && inner.ends_with(quote);
+                steps.push(if inner == "*" {
+                    Step::Wildcard
+                } else if quoted('\'') || quoted('"') {
+                    Step::Child(PathElement::Key(inner[1..inner.len() - 1].to_string()))
+                } else {
+                    let index = inner.parse().map_err(|_| invalid("only names, indexes and '*' are supported in brackets"))?;
+                    Step::Child(PathElement::Index(index))
+                });
+                rest = &after[end + 1..];
+            } else {
+                return Err(invalid("expected '.' or '['"));
+            }
+        }
+        Ok(Self { steps })
+    }
+
+    fn matches(&self, path: &[PathElement]) -> bool {
+        matches_steps(&self.steps, path)
+    }
+}
+
+fn matches_steps(steps: &[Step], path: &[PathElement]) -> bool {
+    match steps.first() {
+        None => path.is_empty(),
+        Some(Step::Descendants) => (0..=path.len()).any(|skipped| matches_steps(&steps[1..], &path[skipped..])),
+        Some(Step::Wildcard) => !path.is_empty() && matches_steps(&steps[1..], &path[1..]),
+        Some(Step::Child(element)) => path.first() == Some(element) && matches_steps(&steps[1..], &path[1..]),
+    }
+}
+
+/// The length of the string literal `text` starts with, including both quotes.
+pub(super) fn string_len(text: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Decodes the contents of a JSON string literal.
+pub(super) fn unescape(literal: &str) -> String {
+    let mut result = String::with_capacity(literal.len());
+    let mut chars = literal.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('b') => result.push('\u{8}'),
+            Some('f') => result.push('\u{c}'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let mut code = u32::from_str_radix(&hex, 16).unwrap_or(0xfffd);
+                if (0xd800..0xdc00).contains(&code) && chars.as_str().starts_with("\\u") {
+                    // A surrogate pair.
+                    let low = chars.as_str().get(2..6).and_then(|hex| u32::from_str_radix(hex, 16).ok());
+                    if let Some(low @ 0xdc00..=0xdfff) = low {
+                        code = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+                        chars.by_ref().take(6).for_each(drop);
+                    }
+                }
+                result.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+            }
+            Some(other) => result.push(other),
+            None => {}
+        }
+    }
+    result
+}
+
+/// Encodes text for a JSON string literal.
+pub(super) fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            c if c.is_control() => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    #[test]
+    fn json_round_trips_with_keys_escapes_and_literals() {
+        let json = "{\n  \"greeting\": \"Hello, {name}!\",\n  \"count\": 3,\n  \"nested\": { \"quote\": \"Say \\\"hi\\\"\\n\\u00e9\" },\n  \"list\": [\"One\", true]\n}\n";
+        let document = parse(json);
+        assert_eq!(document.texts(), ["Hello, {name}!", "Say \"hi\"\né", "One"]);
+        assert_eq!(document.segments()[0], "Hello, ⟦0⟧!");
+        // The escapes are written in their canonical form.
+        assert_eq!(round_trip(&document), json.replace("\\u00e9", "é"));
+    }
+
+    #[test]
+    fn selectors_pick_the_values_to_translate() {
+        let json = r#"{"items": [{"id": "a1", "description": "First"}, {"id": "b2", "description": "Second"}], "meta": {"description": "Kept"}}"#;
+        let document = parse_selected(json, &["$.items[*].description".to_string()]).unwrap();
+        assert_eq!(document.texts(), ["First", "Second"]);
+        assert_eq!(round_trip(&document), json);
+        let document = parse_selected(json, &["$..description".to_string()]).unwrap();
+        assert_eq!(document.texts(), ["First", "Second", "Kept"]);
+        assert!(parse_selected(json, &["items".to_string()]).is_err());
+    }
+}
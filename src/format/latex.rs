@@ -0,0 +1,298 @@
+use super::Document;
+use crate::placeholder;
+
+/// Commands whose argument is prose of its own, translated as a separate segment.
+const TITLE_COMMANDS: &[&str] = &[
+    "part", "chapter", "section", "subsection", "subsubsection", "paragraph", "subparagraph", "caption", "title",
+];
+
+/// Commands whose argument is prose within the surrounding text, translated along with it.
+const INLINE_COMMANDS: &[&str] = &[
+    "emph", "textbf", "textit", "textsl", "textsc", "textup", "textrm", "textsf", "textmd", "underline", "footnote",
+];
+
+/// Math environments, kept in the text around them as placeholders.
+const MATH_ENVIRONMENTS: &[&str] = &[
+    "math", "displaymath", "equation", "align", "alignat", "flalign", "gather", "multline", "eqnarray", "split",
+];
+
+/// Environments whose content is never translated.
+const VERBATIM_ENVIRONMENTS: &[&str] =
+    &["verbatim", "Verbatim", "lstlisting", "minted", "comment", "tikzpicture", "filecontents", "alltt"];
+
+/// Environments taking a mandatory argument after `\begin{...}`, such as a table's column layout.
+const ARGUMENT_ENVIRONMENTS: &[&str] = &["tabular", "tabularx", "longtable", "array", "minipage", "multicols", "wrapfigure"];
+
+/// Splits LaTeX into translatable prose and verbatim markup.
+///
+/// Only the body between `\begin{document}` and `\end{document}` is translated, if the file has
+/// one. Paragraphs, items, section titles and captions become segments; math, commands with
+/// their arguments (labels, references, citations, …), comments and special characters inside
+/// them are replaced by placeholders, so a segment never ends inside `$...$` or an environment.
+/// Code listings and other verbatim environments are not translated at all.
+pub fn parse(content: &str) -> Document {
+    let start = content.find(r"\begin{document}").map_or(0, |start| start + r"\begin{document}".len());
+    let end = content[start..].rfind(r"\end{document}").map_or(content.len(), |end| start + end);
+
+    let mut document = Document::new();
+    document.push_verbatim(&content[..start]);
+    parse_body(&content[start..end], &mut document);
+    document.push_verbatim(&content[end..]);
+    document
+}
+
+/// Adds the segments and markup of a piece of the body to `document`.
+fn parse_body(text: &str, document: &mut Document) {
+    let mut run = Run::default();
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        let rest = &text[i..];
+        match c {
+            '\n' if is_blank_line(rest) => {
+                let len = rest.len() - rest.trim_start().len();
+                run.flush(document);
+                document.push_verbatim(&rest[..len]);
+                i += len;
+            }
+            '%' => {
+                let len = rest.find('\n').unwrap_or(rest.len());
+                run.protect(&rest[..len]);
+                i += len;
+            }
+            '$' => {
+                let len = math_len(rest);
+                run.protect(&rest[..len]);
+                i += len;
+            }
+            '{' | '}' | '~' | '&' | '^' | '_' | '#' => {
+                run.protect(&rest[..1]);
+                i += 1;
+            }
+            '\\' => i += command(rest, &mut run, document),
+            c => {
+                run.text.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    run.flush(document);
+}
+
+/// Handles the command at the start of `text`, returning its length.
+fn command(text: &str, run: &mut Run, document: &mut Document) -> usize {
+    let name_len = text[1..].find(|c: char| !c.is_ascii_alphabetic()).map_or(text.len(), |len| len + 1);
+    if name_len == 1 {
+        // A control symbol such as `\%` or `\\`, or inline or display math.
+        let len = match text[1..].chars().next() {
+            Some('(') => text.find(r"\)").map_or(text.len(), |end| end + 2),
+            Some('[') => text.find(r"\]").map_or(text.len(), |end| end + 2),
+            Some(c) => 1 + c.len_utf8(),
+            None => 1,
+        };
+        run.protect(&text[..len]);
+        return len;
+    }
+    let name = &text[1..name_len];
+    let mut len = name_len;
+    if text[len..].starts_with('*') {
+        len += 1;
+    }
+
+    match name {
+        "begin" => {
+            let environment_len = group_len(&text[len..], '{', '}');
+            let environment = text[len..len + environment_len].trim_matches(['{', '}']);
+            let environment = environment.trim_end_matches('*');
+            len += environment_len;
+            if MATH_ENVIRONMENTS.contains(&environment) {
+                len += environment_end(&text[len..], environment);
+                run.protect(&text[..len]);
+            } else if VERBATIM_ENVIRONMENTS.contains(&environment) {
+                len += environment_end(&text[len..], environment);
+                run.flush(document);
+                document.push_verbatim(&text[..len]);
+            } else {
+                len += arguments_len(&text[len..], ARGUMENT_ENVIRONMENTS.contains(&environment));
+                run.flush(document);
+                document.push_verbatim(&text[..len]);
+            }
+        }
+        "end" | "item" => {
+            len += arguments_len(&text[len..], name == "end");
+            run.flush(document);
+            document.push_verbatim(&text[..len]);
+        }
+        "verb" => {
+            // `\verb|...|`, with any character as the delimiter.
+            if let Some(delimiter) = text[len..].chars().next() {
+                let after = len + delimiter.len_utf8();
+                len = text[after..].find(delimiter).map_or(text.len(), |end| after + end + delimiter.len_utf8());
+            }
+            run.protect(&text[..len]);
+        }
+        _ if TITLE_COMMANDS.contains(&name) => {
+            while text[len..].starts_with('[') {
+                len += group_len(&text[len..], '[', ']');
+            }
+            run.flush(document);
+            if !text[len..].starts_with('{') {
+                document.push_verbatim(&text[..len]);
+                return len;
+            }
+            let title_len = group_len(&text[len..], '{', '}');
+            document.push_verbatim(&text[..=len]);
+            let title = &text[len + 1..len + title_len];
+            match title.strip_suffix('}') {
+                Some(title) => {
+                    parse_body(title, document);
+                    document.push_verbatim("}");
+                }
+                None => parse_body(title, document),
+            }
+            len += title_len;
+        }
+        // The braces around the argument are protected as they come, and the prose between them is translated.
+        _ if INLINE_COMMANDS.contains(&name) => run.protect(&text[..len]),
+        _ => {
+            len += arguments_len(&text[len..], true);
+            run.protect(&text[..len]);
+        }
+    }
+    len
+}
+
+/// Whether `text`, starting with a line break, starts with a blank line.
+fn is_blank_line(text: &str) -> bool {
+    text[1..].trim_start_matches([' ', '\t', '\r']).starts_with('\n')
+}
+
+/// The length of the optional `[...]` arguments at the start of `text`, and of the mandatory
+/// `{...}` ones with `mandatory`.
+fn arguments_len(text: &str, mandatory: bool) -> usize {
+    let mut len = 0;
+    loop {
+        match text[len..].chars().next() {
+            Some('[') => len += group_len(&text[len..], '[', ']'),
+            Some('{') if mandatory => len += group_len(&text[len..], '{', '}'),
+            _ => return len,
+        }
+    }
+}
+
+/// The length of the group `text` starts with, up to its matching `close`, or all of `text` if
+/// it is never closed. Escaped delimiters such as `\{` don't count.
+fn group_len(text: &str, open: char, close: char) -> usize {
+    let mut depth = 0;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == open => depth += 1,
+            c if c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + c.len_utf8();
+                }
+            }
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// The length of `text` up to and including the `\end` of the environment, allowing it to be
+/// nested, or all of `text` if it is never ended.
+fn environment_end(text: &str, environment: &str) -> usize {
+    let (mut depth, mut i) = (1, 0);
+    while let Some(found) = text[i..].find('\\') {
+        i += found;
+        let rest = &text[i..];
+        for (keyword, step) in [(r"\begin{", 1), (r"\end{", -1)] {
+            let Some(name) = rest.strip_prefix(keyword) else { continue };
+            let Some(close) = name.find('}') else { continue };
+            if name[..close].trim_end_matches('*') == environment {
+                depth += step;
+                if depth == 0 {
+                    return i + keyword.len() + close + 1;
+                }
+            }
+        }
+        i += 1;
+    }
+    text.len()
+}
+
+/// The length of the inline (`$...$`) or display (`$$...$$`) math `text` starts with.
+fn math_len(text: &str) -> usize {
+    let delimiter = if text.starts_with("$$") { "$$" } else { "$" };
+    let mut escaped = false;
+    for (i, c) in text.char_indices().skip(delimiter.len()) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '$' if text[i..].starts_with(delimiter) => return i + delimiter.len(),
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// The prose of a paragraph being collected into a segment, with its markup protected.
+#[derive(Default)]
+struct Run {
+    text: String,
+    protected: Vec<String>,
+    /// Where the last token ends in `text`, so markup right after it joins the same token.
+    token_end: usize,
+}
+
+impl Run {
+    fn protect(&mut self, markup: &str) {
+        match self.protected.last_mut() {
+            Some(last) if self.token_end == self.text.len() => last.push_str(markup),
+            _ => {
+                self.text.push_str(&placeholder::token(self.protected.len()));
+                self.protected.push(markup.to_string());
+                self.token_end = self.text.len();
+            }
+        }
+    }
+
+    fn flush(&mut self, document: &mut Document) {
+        if !self.text.is_empty() {
+            document.push_text(&std::mem::take(&mut self.text), std::mem::take(&mut self.protected));
+        }
+        self.token_end = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    const DOCUMENT: &str = "\\documentclass{article}\n\\title{Not in the body}\n\\begin{document}\n\\section{Intro}\nThe value $x^2$ is \\emph{large}, see \\ref{eq:1}. % why\n\n\\begin{verbatim}\nnot translated\n\\end{verbatim}\n\\end{document}\n";
+
+    #[test]
+    fn latex_round_trips_and_translates_only_the_body() {
+        let document = parse(DOCUMENT);
+        assert_eq!(document.texts(), ["Intro", "The value $x^2$ is \\emph{large}, see \\ref{eq:1}. % why"]);
+        assert_eq!(round_trip(&document), DOCUMENT);
+    }
+
+    #[test]
+    fn math_commands_and_comments_become_placeholders() {
+        let document = parse(DOCUMENT);
+        assert_eq!(document.segments()[1], "The value ⟦0⟧ is ⟦1⟧large⟦2⟧, see ⟦3⟧. ⟦4⟧");
+        let (translated, lost) = document.render(&["Bevezetés".to_string(), "Az ⟦0⟧ érték ⟦1⟧nagy⟦2⟧, lásd ⟦3⟧. ⟦4⟧".to_string()]);
+        assert_eq!(lost, 0);
+        assert!(translated.contains("\\section{Bevezetés}\nAz $x^2$ érték \\emph{nagy}, lásd \\ref{eq:1}. % why\n"));
+    }
+
+    #[test]
+    fn math_environments_stay_inside_their_paragraph() {
+        let document = parse("Solve\n\\begin{equation}\na = b\n\n\\end{equation}\nfor $a$.\n");
+        assert_eq!(document.segments(), ["Solve\n⟦0⟧\nfor ⟦1⟧."]);
+    }
+}
@@ -0,0 +1,334 @@
+use pulldown_cmark::{Event, LinkType, MetadataBlockKind, Options, Parser, Tag, TagEnd};
+use regex::Regex;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use super::{Document, Escape};
+use crate::placeholder;
+
+/// Splits Markdown into translatable prose and verbatim markup.
+///
+/// Every run of inline content (a paragraph, heading, table cell, …) becomes one segment, taken
+/// from the source as written so escapes and emphasis markers are kept. Code spans, inline HTML,
+/// link targets, images and autolinks inside it are replaced by placeholders, while code blocks,
+/// HTML blocks and front matter are not translated at all, except for the values of the
+/// top-level `front_matter_fields` of the front matter that are single-line strings.
+pub fn parse(content: &str, front_matter_fields: &[String]) -> Document {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+    let events: Vec<(Event, Range<usize>)> = Parser::new_ext(content, options).into_offset_iter().collect();
+
+    let mut document = Document::new();
+    let mut copied = 0;
+    let mut run: Option<InlineRun> = None;
+    // Nesting depth of blocks whose content is never translated.
+    let mut verbatim_depth = 0;
+
+    let mut i = 0;
+    while i < events.len() {
+        let (event, range) = &events[i];
+        if verbatim_depth == 0 && is_inline(event) {
+            i = run
+                .get_or_insert_with(|| InlineRun::new(range.start))
+                .push(content, &events, i);
+        } else {
+            if let Some(run) = run.take() {
+                run.finish(content, &mut document, &mut copied);
+            }
+            if let Event::Start(Tag::MetadataBlock(kind)) = event {
+                if !front_matter_fields.is_empty() {
+                    // The fields are between the opening and the closing fence.
+                    let block = &content[range.clone()];
+                    let start = range.start + block.find('\n').map_or(block.len(), |end| end + 1);
+                    let end = range.start + block.trim_end().rfind('\n').map_or(0, |end| end + 1).max(start - range.start);
+                    document.push_verbatim(&content[copied..start]);
+                    front_matter(&content[start..end], *kind, front_matter_fields, &mut document);
+                    copied = end;
+                }
+            }
+            match event {
+                Event::Start(Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_)) => verbatim_depth += 1,
+                Event::End(TagEnd::CodeBlock | TagEnd::HtmlBlock | TagEnd::MetadataBlock(_)) => verbatim_depth -= 1,
+                _ => {}
+            }
+        }
+        i += 1;
+    }
+    if let Some(run) = run.take() {
+        run.finish(content, &mut document, &mut copied);
+    }
+    document.push_verbatim(&content[copied..]);
+
+    document
+}
+
+/// Adds the lines of YAML (`---`) or TOML (`+++`) front matter, with the string values of the
+/// top-level `fields` as segments.
+fn front_matter(lines: &str, kind: MetadataBlockKind, fields: &[String], document: &mut Document) {
+    static YAML: OnceLock<Regex> = OnceLock::new();
+    static TOML: OnceLock<Regex> = OnceLock::new();
+    let field = match kind {
+        MetadataBlockKind::YamlStyle => YAML.get_or_init(|| Regex::new(r"^([A-Za-z_][\w-]*)\s*:[ \t]*").unwrap()),
+        MetadataBlockKind::PlusesStyle => TOML.get_or_init(|| Regex::new(r"^([A-Za-z_][\w-]*)\s*=[ \t]*").unwrap()),
+    };
+    let mut in_table = false;
+    for line in lines.split_inclusive('\n') {
+        // In TOML, the fields after a `[table]` header belong to that table.
+        in_table |= kind == MetadataBlockKind::PlusesStyle && line.starts_with('[');
+        let translated = (!in_table)
+            .then(|| field.captures(line))
+            .flatten()
+            .filter(|captures| fields.iter().any(|field| *field == captures[1]))
+            .and_then(|captures| {
+                let prefix = captures.get(0).unwrap().end();
+                let value = line[prefix..].trim_end();
+                let (text, quote, escape) = front_matter_value(value, kind)?;
+                Some((prefix, value, text, quote, escape))
+            });
+        let Some((prefix, value, text, quote, escape)) = translated else {
+            document.push_verbatim(line);
+            continue;
+        };
+        document.push_verbatim(&line[..prefix]);
+        document.push_verbatim(quote);
+        document.push_escaped_text(&text, Vec::new(), escape);
+        document.push_verbatim(quote);
+        document.push_verbatim(&line[prefix + value.len()..]);
+    }
+}
+
+/// The text of a front matter value that is a string, with the quote around it and the escape
+/// writing its translation; `None` for lists, tables, numbers and other values.
+fn front_matter_value(value: &str, kind: MetadataBlockKind) -> Option<(String, &'static str, Escape)> {
+    let quoted = |quote: char| value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote);
+    if quoted('"') {
+        let inner = &value[1..value.len() - 1];
+        return Some((inner.replace("\\\"", "\"").replace("\\\\", "\\"), "\"", double_quoted));
+    }
+    if quoted('\'') {
+        let inner = &value[1..value.len() - 1];
+        return Some(match kind {
+            MetadataBlockKind::YamlStyle => (inner.replace("''", "'"), "'", yaml_single_quoted),
+            MetadataBlockKind::PlusesStyle => (inner.to_string(), "'", toml_literal),
+        });
+    }
+    // Only YAML has unquoted strings.
+    let plain = kind == MetadataBlockKind::YamlStyle
+        && !value.is_empty()
+        && !value.starts_with(['[', '{', '|', '>', '&', '*', '!', '#', '"', '\'']);
+    plain.then(|| (value.to_string(), "", yaml_plain as Escape))
+}
+
+fn double_quoted(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn yaml_single_quoted(text: &str) -> String {
+    text.replace('\'', "''")
+}
+
+/// TOML literal strings can't hold an apostrophe, so a typographic one is written instead.
+fn toml_literal(text: &str) -> String {
+    text.replace('\'', "\u{2019}")
+}
+
+/// Quotes a translation that would no longer be a plain YAML string, e.g. because it contains `: `.
+fn yaml_plain(text: &str) -> String {
+    let needs_quotes = text.contains(": ")
+        || text.contains(" #")
+        || text.ends_with(':')
+        || text.starts_with(['[', ']', '{', '}', ',', '|', '>', '&', '*', '!', '#', '%', '@', '`', '"', '\'', '-', '?', ':']);
+    if needs_quotes {
+        format!("\"{}\"", double_quoted(text))
+    } else {
+        text.to_string()
+    }
+}
+
+fn is_inline(event: &Event) -> bool {
+    match event {
+        Event::Text(_)
+        | Event::Code(_)
+        | Event::InlineHtml(_)
+        | Event::InlineMath(_)
+        | Event::FootnoteReference(_)
+        | Event::SoftBreak
+        | Event::HardBreak => true,
+        Event::Start(tag) => matches!(
+            tag,
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link { .. } | Tag::Image { .. }
+        ),
+        Event::End(tag) => matches!(
+            tag,
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Link | TagEnd::Image
+        ),
+        _ => false,
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LineBreak {
+    Soft,
+    Hard,
+}
+
+/// A sequence of inline events being turned into one translatable segment.
+struct InlineRun {
+    start: usize,
+    /// End of the source consumed so far.
+    cursor: usize,
+    text: String,
+    protected: Vec<String>,
+    after_break: Option<LineBreak>,
+}
+
+impl InlineRun {
+    fn new(start: usize) -> Self {
+        Self {
+            start,
+            cursor: start,
+            text: String::new(),
+            protected: Vec::new(),
+            after_break: None,
+        }
+    }
+
+    /// Adds the event at `index` and returns the index of the last event it consumed.
+    fn push(&mut self, content: &str, events: &[(Event, Range<usize>)], index: usize) -> usize {
+        let (event, range) = &events[index];
+        match event {
+            Event::Text(_) => {
+                self.gap(content, range.start);
+                self.text.push_str(&content[range.clone()]);
+                self.cursor = range.end;
+            }
+            Event::Code(_) | Event::InlineHtml(_) | Event::InlineMath(_) | Event::FootnoteReference(_) => {
+                self.gap(content, range.start);
+                self.protect(&content[range.clone()]);
+                self.cursor = range.end;
+            }
+            Event::SoftBreak => {
+                // Soft breaks are joined into a single line, so container prefixes like
+                // `> ` on the following lines don't end up inside the text.
+                self.gap(content, range.start);
+                self.text.push(' ');
+                self.cursor = range.end;
+                self.after_break = Some(LineBreak::Soft);
+                return index;
+            }
+            Event::HardBreak => {
+                self.gap(content, range.start);
+                self.protect(&content[range.clone()]);
+                self.cursor = range.end;
+                self.after_break = Some(LineBreak::Hard);
+                return index;
+            }
+            Event::Start(Tag::Image { .. })
+            | Event::Start(Tag::Link {
+                link_type: LinkType::Autolink | LinkType::Email,
+                ..
+            }) => {
+                // Images and bare URLs are kept whole, skipping the events inside them.
+                self.gap(content, range.start);
+                self.protect(&content[range.clone()]);
+                self.cursor = range.end;
+                let mut last = index;
+                while events.get(last + 1).is_some_and(|(_, r)| r.start < range.end) {
+                    last += 1;
+                }
+                return last;
+            }
+            Event::End(TagEnd::Link) => {
+                // The part after the link text, e.g. `](https://…)` or `][reference]`.
+                let tail = content[self.cursor..range.end].to_string();
+                self.protect(&tail);
+                self.cursor = range.end;
+            }
+            Event::End(_) => {
+                // Closing emphasis markers stay in the text.
+                self.gap(content, range.end);
+            }
+            // Opening markers are added with the text that follows them.
+            _ => {}
+        }
+        self.after_break = None;
+        index
+    }
+
+    /// Adds the source between the last event and `position`, e.g. emphasis markers or escapes.
+    fn gap(&mut self, content: &str, position: usize) {
+        if position <= self.cursor {
+            return;
+        }
+        let mut gap = &content[self.cursor..position];
+        if let Some(line_break) = self.after_break.take() {
+            let text_start = gap
+                .find(|c: char| !matches!(c, ' ' | '\t' | '>'))
+                .unwrap_or(gap.len());
+            let (prefix, rest) = gap.split_at(text_start);
+            if line_break == LineBreak::Hard {
+                if let Some(last) = self.protected.last_mut() {
+                    last.push_str(prefix);
+                }
+            }
+            gap = rest;
+        }
+        self.text.push_str(gap);
+        self.cursor = position;
+    }
+
+    fn protect(&mut self, span: &str) {
+        self.text.push_str(&placeholder::token(self.protected.len()));
+        self.protected.push(span.to_string());
+    }
+
+    fn finish(self, content: &str, document: &mut Document, copied: &mut usize) {
+        document.push_verbatim(&content[*copied..self.start]);
+        document.push_text(&self.text, self.protected);
+        *copied = self.cursor;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    #[test]
+    fn markdown_round_trips_with_lists_quotes_and_tables() {
+        let markdown = "# Title\n\nSome *emphasis* and **strong** text,\nover two lines.\n\n- one\n- two\n\n> Quoted\n> text.\n\n| Name | Note |\n|------|------|\n| A    | Hi   |\n";
+        let document = parse(markdown, &[]);
+        assert_eq!(
+            document.texts(),
+            ["Title", "Some *emphasis* and **strong** text, over two lines.", "one", "two", "Quoted text.", "Name", "Note", "A", "Hi"]
+        );
+        assert!(round_trip(&document).starts_with("# Title\n\nSome *emphasis* and **strong** text, over two lines.\n\n- one\n"));
+    }
+
+    #[test]
+    fn code_and_link_targets_are_not_translated() {
+        let markdown = "Run `cargo build` as the [guide](https://example.com/guide \"Guide\") says.\n\n```rust\nlet text = \"not translated\";\n```\n";
+        let document = parse(markdown, &[]);
+        assert_eq!(document.segments(), ["Run ⟦0⟧ as the [guide⟦1⟧ says."]);
+        let (translated, lost) = document.render(&["Futtasd a ⟦0⟧ parancsot, ahogy az [útmutató⟦1⟧ mondja.".to_string()]);
+        assert_eq!(lost, 0);
+        assert_eq!(
+            translated,
+            "Futtasd a `cargo build` parancsot, ahogy az [útmutató](https://example.com/guide \"Guide\") mondja.\n\n```rust\nlet text = \"not translated\";\n```\n"
+        );
+    }
+
+    #[test]
+    fn front_matter_fields_are_translated_and_quoted_when_needed() {
+        let markdown = "---\ntitle: Getting started\nlayout: post\n---\n\nBody.\n";
+        let document = parse(markdown, &["title".to_string()]);
+        assert_eq!(document.texts(), ["Getting started", "Body."]);
+        assert_eq!(round_trip(&document), markdown);
+        let (translated, _) = document.render(&["Első lépések: telepítés".to_string(), "Szöveg.".to_string()]);
+        assert_eq!(translated, "---\ntitle: \"Első lépések: telepítés\"\nlayout: post\n---\n\nSzöveg.\n");
+    }
+}
@@ -0,0 +1,564 @@
+//! Format-aware translation: documents are split into verbatim markup and translatable text.
+
+pub mod android;
+pub mod asciidoc;
+pub mod bilingual;
+pub mod chapters;
+pub mod code;
+pub mod csv;
+pub mod docx;
+pub mod epub;
+pub mod fluent;
+pub mod html;
+pub mod ios;
+pub mod json;
+pub mod latex;
+pub mod markdown;
+pub mod pdf;
+pub mod po;
+pub mod properties;
+pub mod qt;
+pub mod resx;
+pub mod rst;
+pub mod subtitle;
+pub mod web;
+pub mod xliff;
+pub mod xml;
+pub mod yaml;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::error::TranslatorError;
+use crate::chunking::{split_paragraph, MAX_CHUNK_SIZE};
+use crate::{no_translate, placeholder};
+use crate::translator::TextFormat;
+
+pub use bilingual::Bilingual;
+
+/// The supported input formats.
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Plain text split into paragraphs at blank lines.
+    #[default]
+    Text,
+    /// Markdown; code, link targets, images and front matter (unless its fields are chosen) are left untouched.
+    Markdown,
+    /// HTML, split at block-level elements and translated with the backend's HTML mode.
+    Html,
+    /// SubRip subtitles; only the cue text is translated.
+    Srt,
+    /// WebVTT subtitles; only the cue text is translated.
+    Vtt,
+    /// EPUB e-books; the XHTML content documents are translated like HTML.
+    Epub,
+    /// Gettext catalogs; untranslated `msgid`s are translated into their `msgstr`.
+    Po,
+    /// JSON locale files; only string values are translated, keys and structure are kept.
+    Json,
+    /// YAML locale files; only string values are translated, keys and structure are kept.
+    Yaml,
+    /// XLIFF 1.2 and 2.0 files; untranslated units get a machine-translated target.
+    Xliff,
+    /// PDF documents; the text of every page is extracted and written as plain text or Markdown.
+    Pdf,
+    /// Word documents; the text runs are translated and their formatting is kept.
+    Docx,
+    /// LaTeX sources; prose, section titles and captions are translated, math and commands are kept.
+    Latex,
+    /// AsciiDoc; listings, block macros, attributes and cross-references are left untouched.
+    Asciidoc,
+    /// reStructuredText; directives, roles, literal blocks and references are left untouched.
+    Rst,
+    /// Android `strings.xml` resources; strings, string arrays and plurals are translated.
+    Android,
+    /// iOS and macOS `.strings` files; the values are translated, keys and comments are kept.
+    Ios,
+    /// .NET `.resx` and `.resw` resources; the string values are translated.
+    Resx,
+    /// Mozilla Fluent files; message values are translated, placeables and selectors are kept.
+    Fluent,
+    /// Qt Linguist `.ts` files; unfinished messages get a machine-translated translation.
+    Qt,
+    /// Java `.properties` files; values are translated, keys, comments and escapes are kept.
+    Properties,
+    /// `.env` files of `KEY=value` lines; values are translated, keys and comments are kept.
+    Env,
+    /// CSV and TSV tables; the cells of the chosen columns are translated, the header row is kept.
+    Csv,
+    /// Other XML documents; text nodes, or the content of the elements chosen by XPath, are translated.
+    Xml,
+    /// Rust sources; only comments and doc comments are translated, the code is kept.
+    Rust,
+    /// Python sources; only comments and docstrings are translated, the code is kept.
+    Python,
+    /// JavaScript sources; only comments and JSDoc are translated, the code is kept.
+    Javascript,
+}
+
+/// Settings that change how some formats are parsed.
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// Flag machine-translated gettext entries as `fuzzy` so translators review them.
+    pub mark_fuzzy: bool,
+    /// Language code written into files that record it, such as XLIFF's target language.
+    pub target_language: Option<String>,
+    /// Plain text paragraphs longer than this many bytes are split into several segments.
+    pub max_segment_len: usize,
+    /// Top-level front matter fields of Markdown files whose values are translated, such as `title`.
+    pub front_matter_fields: Vec<String>,
+    /// Globs of the keys of key=value files whose values are translated; all of them when empty.
+    pub keys: Vec<String>,
+    /// The columns of tables whose cells are translated, by number (from 1) or header name; all of them when empty.
+    pub columns: Vec<String>,
+    /// JSONPath selectors of the string values of JSON documents to translate; all of them when empty.
+    pub select: Vec<String>,
+    /// XPath selectors of the elements of XML documents whose content is translated; all text when empty.
+    pub select_xpath: Vec<String>,
+    /// Language of the text, for formats that mark parts of it as written in another one.
+    pub source_language: Option<String>,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            mark_fuzzy: true,
+            target_language: None,
+            max_segment_len: MAX_CHUNK_SIZE,
+            front_matter_fields: Vec::new(),
+            keys: Vec::new(),
+            columns: Vec::new(),
+            select: Vec::new(),
+            select_xpath: Vec::new(),
+            source_language: None,
+        }
+    }
+}
+
+/// Whether a file starts like a Qt Linguist file (`<?xml` or `<TS`), reading only its first bytes.
+fn is_qt_linguist(path: &Path) -> bool {
+    let mut start = [0; 256];
+    let Ok(read) = std::fs::File::open(path).and_then(|mut file| std::io::Read::read(&mut file, &mut start)) else { return false };
+    let start = String::from_utf8_lossy(&start[..read]);
+    let start = start.trim_start_matches('\u{feff}').trim_start();
+    start.starts_with("<?xml") || start.starts_with("<TS")
+}
+
+impl Format {
+    /// Guesses the format from the file extension, falling back to plain text. `.ts` files are
+    /// Qt Linguist files only if they start like XML; TypeScript sources are plain text.
+    pub fn from_path(path: &Path) -> Self {
+        let in_values_dir = path
+            .parent()
+            .and_then(Path::file_name)
+            .is_some_and(|dir| dir == "values" || dir.to_string_lossy().starts_with("values-"));
+        match path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("xml") if in_values_dir || path.file_name().is_some_and(|name| name == "strings.xml") => Format::Android,
+            Some("strings") => Format::Ios,
+            Some("resx" | "resw") => Format::Resx,
+            Some("ftl") => Format::Fluent,
+            Some("ts") if is_qt_linguist(path) => Format::Qt,
+            Some("properties") => Format::Properties,
+            Some("env") => Format::Env,
+            Some("csv" | "tsv") => Format::Csv,
+            // `.env`, `.env.local` and the like.
+            _ if path.file_name().is_some_and(|name| name.to_string_lossy().starts_with(".env")) => Format::Env,
+            Some("md" | "markdown") => Format::Markdown,
+            Some("html" | "htm" | "xhtml") => Format::Html,
+            Some("srt") => Format::Srt,
+            Some("vtt") => Format::Vtt,
+            Some("epub") => Format::Epub,
+            Some("po" | "pot") => Format::Po,
+            Some("json") => Format::Json,
+            Some("yaml" | "yml") => Format::Yaml,
+            Some("xlf" | "xliff") => Format::Xliff,
+            Some("pdf") => Format::Pdf,
+            Some("docx") => Format::Docx,
+            Some("tex" | "ltx") => Format::Latex,
+            Some("adoc" | "asciidoc" | "asc") => Format::Asciidoc,
+            Some("rst" | "rest") => Format::Rst,
+            Some("xml") => Format::Xml,
+            Some("rs") => Format::Rust,
+            Some("py" | "pyi") => Format::Python,
+            Some("js" | "mjs" | "cjs" | "jsx") => Format::Javascript,
+            _ => Format::Text,
+        }
+    }
+
+    /// Where the translation of a resource file goes for the platform to find it, such as
+    /// `values-hu/strings.xml`, `hu.lproj/Localizable.strings` or `Strings.hu.resx`; `None` for
+    /// other formats and resource files outside the usual layout.
+    pub fn resource_path(self, path: &Path, target: &str) -> Option<PathBuf> {
+        // A file in the current directory has an empty parent.
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => std::env::current_dir().ok()?,
+        };
+        let dir_name = dir.file_name()?.to_string_lossy();
+        let file_name = path.file_name()?;
+        match self {
+            Format::Android if dir_name == "values" => {
+                Some(dir.with_file_name(format!("values-{}", android::qualifier(target))).join(file_name))
+            }
+            Format::Ios if dir_name.ends_with(".lproj") => {
+                Some(dir.with_file_name(format!("{}.lproj", target)).join(file_name))
+            }
+            Format::Resx => {
+                let extension = path.extension()?.to_string_lossy();
+                Some(path.with_extension(format!("{}.{}", target, extension)))
+            }
+            _ => None,
+        }
+    }
+
+    /// How the backend should treat the segments of this format.
+    pub fn text_format(self) -> TextFormat {
+        match self {
+            Format::Html | Format::Epub | Format::Xliff | Format::Docx | Format::Xml => TextFormat::Html,
+            Format::Text
+            | Format::Markdown
+            | Format::Srt
+            | Format::Vtt
+            | Format::Po
+            | Format::Json
+            | Format::Yaml
+            | Format::Pdf
+            | Format::Latex
+            | Format::Asciidoc
+            | Format::Rst
+            | Format::Android
+            | Format::Ios
+            | Format::Resx
+            | Format::Fluent
+            | Format::Qt
+            | Format::Properties
+            | Format::Env
+            | Format::Csv
+            | Format::Rust
+            | Format::Python
+            | Format::Javascript => TextFormat::Text,
+        }
+    }
+}
+
+/// Parses `content` into a document of verbatim markup and translatable segments.
+///
+/// Plain text is split into paragraphs, keeping the blank lines between them exactly as they
+/// were. In plain text, Markdown and HTML, spans between `<!-- notranslate -->` markers are
+/// kept verbatim.
+pub fn parse(format: Format, content: &str, options: &FormatOptions) -> Result<Document, TranslatorError> {
+    Ok(match format {
+        Format::Text => parse_marked(content, |text| paragraphs(text, options.max_segment_len)),
+        Format::Markdown => parse_marked(content, |text| markdown::parse(text, &options.front_matter_fields)),
+        Format::Html => parse_marked(content, html::parse),
+        Format::Srt | Format::Vtt => subtitle::parse(content),
+        Format::Po => po::parse(content, options),
+        Format::Json if !options.select.is_empty() => json::parse_selected(content, &options.select)?,
+        Format::Json => json::parse(content),
+        Format::Yaml => yaml::parse(content),
+        Format::Xliff => xliff::parse(content, options),
+        Format::Latex => latex::parse(content),
+        Format::Asciidoc => asciidoc::parse(content),
+        Format::Rst => rst::parse(content),
+        Format::Android => android::parse(content),
+        Format::Ios => ios::parse(content),
+        Format::Resx => resx::parse(content),
+        Format::Fluent => fluent::parse(content),
+        Format::Qt => qt::parse(content, options),
+        Format::Properties => properties::parse(content, &options.keys),
+        Format::Env => properties::parse_env(content, &options.keys),
+        Format::Csv => csv::Table::new(&options.columns).parse(content)?,
+        Format::Xml => xml::parse(content, options)?,
+        Format::Rust => code::parse(content, code::Syntax::Rust)?,
+        Format::Python => code::parse(content, code::Syntax::Python)?,
+        Format::Javascript => code::parse(content, code::Syntax::Javascript)?,
+        Format::Epub => return Err("EPUB files are containers, open them with `epub::Epub::open`".into()),
+        Format::Docx => return Err("DOCX files are containers, open them with `docx::Docx::open`".into()),
+        Format::Pdf => return Err("PDF files are binary, extract their text with `pdf::extract_pages`".into()),
+    })
+}
+
+/// A piece of a document: either kept as-is or sent for translation.
+#[derive(Debug, Clone)]
+enum Part {
+    Verbatim(String),
+    Text {
+        /// Text to translate, with protected spans replaced by placeholder tokens.
+        text: String,
+        protected: Vec<String>,
+        /// Applied to the translation before it is written, e.g. to quote it for the file format.
+        escape: Escape,
+    },
+}
+
+/// Turns translated text into its representation in the file.
+pub type Escape = fn(&str) -> String;
+
+fn no_escape(text: &str) -> String {
+    text.to_string()
+}
+
+/// A document split into verbatim markup and translatable segments.
+///
+/// Segments never contain blank lines, so several of them can be sent in one request
+/// joined by `"\n\n"` and told apart again in the translation.
+#[derive(Debug, Clone, Default)]
+pub struct Document {
+    parts: Vec<Part>,
+}
+
+impl Document {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push_verbatim(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        match self.parts.last_mut() {
+            Some(Part::Verbatim(last)) => last.push_str(text),
+            _ => self.parts.push(Part::Verbatim(text.to_string())),
+        }
+    }
+
+    /// Adds a translatable segment whose placeholder tokens stand for the `protected` spans.
+    ///
+    /// Surrounding whitespace is kept verbatim, and segments without any letters to
+    /// translate (only whitespace or placeholders) are not sent to the API at all.
+    pub fn push_text(&mut self, text: &str, protected: Vec<String>) {
+        self.push_escaped_text(text, protected, no_escape);
+    }
+
+    /// Like [`push_text`](Self::push_text) for text that has to be escaped when it is
+    /// written back, such as quoted strings; the surrounding whitespace is escaped too.
+    pub fn push_escaped_text(&mut self, text: &str, protected: Vec<String>, escape: Escape) {
+        let trimmed = text.trim();
+        if trimmed.is_empty() || !has_translatable_content(trimmed) {
+            let (restored, _) = placeholder::restore(text, &protected);
+            self.push_verbatim(&escape(&restored));
+            return;
+        }
+
+        let start = text.len() - text.trim_start().len();
+        self.push_verbatim(&escape(&text[..start]));
+        self.parts.push(Part::Text {
+            text: collapse_blank_lines(trimmed),
+            protected,
+            escape,
+        });
+        self.push_verbatim(&escape(&text[start + trimmed.len()..]));
+    }
+
+    /// Adds the parts of another document at the end of this one.
+    pub fn append(&mut self, other: Document) {
+        for part in other.parts {
+            match part {
+                Part::Verbatim(text) => self.push_verbatim(&text),
+                text => self.parts.push(text),
+            }
+        }
+    }
+
+    /// The translatable segments in document order.
+    pub fn segments(&self) -> Vec<&str> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::Text { text, .. } => Some(text.as_str()),
+                Part::Verbatim(_) => None,
+            })
+            .collect()
+    }
+
+    /// The byte offset at which every segment starts in the document rendered with the
+    /// translations of the segments (see [`render`](Self::render)); with fewer translations,
+    /// the remaining segments are measured untranslated.
+    pub fn segment_offsets(&self, translations: &[String]) -> Vec<usize> {
+        let mut offsets = Vec::new();
+        let mut translations = translations.iter();
+        let mut len = 0;
+        for part in &self.parts {
+            match part {
+                Part::Verbatim(text) => len += text.len(),
+                Part::Text { text, protected, escape } => {
+                    offsets.push(len);
+                    let translation = translations.next().unwrap_or(text);
+                    len += escape(&placeholder::restore(translation, protected).0).len();
+                }
+            }
+        }
+        offsets
+    }
+
+    /// The segments paired with their translations, both with their protected spans put back, e.g.
+    /// to keep them in a translation memory. Segments without a translation are left out.
+    pub fn pairs(&self, translations: &[Option<String>]) -> Vec<(String, String)> {
+        let texts = self.parts.iter().filter_map(|part| match part {
+            Part::Text { text, protected, .. } => Some((text, protected)),
+            Part::Verbatim(_) => None,
+        });
+        texts
+            .zip(translations)
+            .filter_map(|((text, protected), translation)| {
+                let translation = translation.as_ref()?;
+                Some((placeholder::restore(text, protected).0, placeholder::restore(translation, protected).0))
+            })
+            .collect()
+    }
+
+    /// The segments with their protected spans put back, as they appear in the file.
+    pub fn texts(&self) -> Vec<String> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::Text { text, protected, .. } => Some(placeholder::restore(text, protected).0),
+                Part::Verbatim(_) => None,
+            })
+            .collect()
+    }
+
+    /// Puts the protected spans of the segment with the index `segment` back into a translation of it.
+    pub fn restore(&self, segment: usize, translation: &str) -> String {
+        let mut protected = self.parts.iter().filter_map(|part| match part {
+            Part::Text { protected, .. } => Some(protected),
+            Part::Verbatim(_) => None,
+        });
+        match protected.nth(segment) {
+            Some(protected) => placeholder::restore(translation, protected).0,
+            None => translation.to_string(),
+        }
+    }
+
+    /// Reassembles the document with new texts for its segments, taken as they are (see
+    /// [`texts`](Self::texts)) rather than as translations with placeholders.
+    pub fn render_texts(&self, texts: &[String]) -> String {
+        let mut result = String::new();
+        let mut texts = texts.iter();
+        for part in &self.parts {
+            match part {
+                Part::Verbatim(text) => result.push_str(text),
+                Part::Text { text, protected, escape } => match texts.next() {
+                    Some(text) => result.push_str(&escape(text)),
+                    None => result.push_str(&escape(&placeholder::restore(text, protected).0)),
+                },
+            }
+        }
+        result
+    }
+
+    /// Reassembles the document with the translations of its segments, in the order of `segments`.
+    ///
+    /// Returns the document and the number of placeholders the translations had lost.
+    pub fn render(&self, translations: &[String]) -> (String, usize) {
+        let segment_count = self.segments().len();
+        self.render_range(0..segment_count, translations, true)
+    }
+
+    /// Renders the part of the document belonging to a range of segments, so a document can be
+    /// written piece by piece as its translations arrive.
+    ///
+    /// The piece runs from just after the segment before the range to the last segment of the
+    /// range, or to the end of the document with `to_end`. `translations` holds the
+    /// translations of the segments in the range.
+    pub fn render_range(&self, segments: Range<usize>, translations: &[String], to_end: bool) -> (String, usize) {
+        let mut result = String::new();
+        let mut translations = translations.iter();
+        let mut lost_placeholders = 0;
+        let mut index = 0;
+
+        for part in &self.parts {
+            match part {
+                Part::Verbatim(text) => {
+                    if index >= segments.start && (index < segments.end || to_end) {
+                        result.push_str(text);
+                    }
+                }
+                Part::Text { text, protected, escape } => {
+                    if segments.contains(&index) {
+                        let translation = translations.next().unwrap_or(text);
+                        let (restored, missing) = placeholder::restore(translation, protected);
+                        lost_placeholders += missing.len();
+                        result.push_str(&escape(&restored));
+                    }
+                    index += 1;
+                }
+            }
+        }
+        (result, lost_placeholders)
+    }
+}
+
+/// Parses the text outside `<!-- notranslate -->` markers with `parse` and keeps the marked spans verbatim.
+pub(crate) fn parse_marked(content: &str, parse: impl Fn(&str) -> Document) -> Document {
+    let mut document = Document::new();
+    for (piece, marked) in no_translate::split_marked(content) {
+        if marked {
+            document.push_verbatim(piece);
+        } else {
+            document.append(parse(piece));
+        }
+    }
+    document
+}
+
+/// Splits plain text into paragraphs at blank lines, keeping the blank lines verbatim.
+///
+/// Paragraphs longer than `max_len` bytes are split into several segments at sentence
+/// boundaries (see [`split_paragraph`]), with the whitespace between them kept verbatim.
+fn paragraphs(content: &str, max_len: usize) -> Document {
+    let mut document = Document::new();
+    let mut rest = content;
+    loop {
+        let end = rest.find("\n\n").unwrap_or(rest.len());
+        let paragraph = &rest[..end];
+        if paragraph.len() > max_len {
+            let mut copied = 0;
+            for piece in split_paragraph(paragraph, max_len) {
+                // The pieces are slices of the paragraph; find where this one starts.
+                let start = piece.as_ptr() as usize - paragraph.as_ptr() as usize;
+                document.push_verbatim(&paragraph[copied..start]);
+                document.push_text(piece, Vec::new());
+                copied = start + piece.len();
+            }
+            document.push_verbatim(&paragraph[copied..]);
+        } else {
+            document.push_text(paragraph, Vec::new());
+        }
+
+        if end == rest.len() {
+            return document;
+        }
+        let separator_end = end + rest[end..].len() - rest[end..].trim_start_matches('\n').len();
+        document.push_verbatim(&rest[end..separator_end]);
+        rest = &rest[separator_end..];
+    }
+}
+
+/// Whether the text contains anything besides placeholder tokens, digits and punctuation.
+fn has_translatable_content(text: &str) -> bool {
+    placeholder::strip_tokens(text).chars().any(char::is_alphabetic)
+}
+
+/// Replaces runs of blank lines with single line breaks, so the segment can't be confused
+/// with the separator between segments.
+pub fn collapse_blank_lines(text: &str) -> String {
+    let mut result = text.to_string();
+    while result.contains("\n\n") {
+        result = result.replace("\n\n", "\n");
+    }
+    result
+}
+
+/// Renders the document with every segment translated as itself, checking that no placeholder
+/// went missing on the way, so the format tests can compare the result with their input.
+#[cfg(test)]
+pub(crate) fn round_trip(document: &Document) -> String {
+    let translations: Vec<String> = document.segments().iter().map(|segment| segment.to_string()).collect();
+    let (rendered, lost_placeholders) = document.render(&translations);
+    assert_eq!(lost_placeholders, 0);
+    rendered
+}
@@ -0,0 +1,52 @@
+use std::path::Path;
+
+use crate::error::TranslatorError;
+use super::{paragraphs, Document};
+
+/// Extracts the text of every page of a PDF file.
+///
+/// The lines of a paragraph are joined, since PDFs break them to fit the page, and the
+/// paragraphs are separated by blank lines.
+pub fn extract_pages(path: &Path) -> Result<Vec<String>, TranslatorError> {
+    let pages = pdf_extract::extract_text_by_pages(path)
+        .map_err(|error| TranslatorError::Parse(format!("Can't extract the text of {:?}: {}", path, error)))?;
+    Ok(pages.iter().map(|page| reflow(page)).collect())
+}
+
+/// Builds a plain text or Markdown document of the pages, each introduced by a page marker
+/// that is kept verbatim: `--- Page 1 ---` in plain text, `<!-- Page 1 -->` in Markdown.
+pub fn parse(pages: &[String], markdown: bool, max_len: usize) -> Document {
+    let mut document = Document::new();
+    for (index, page) in pages.iter().enumerate() {
+        if index > 0 {
+            document.push_verbatim("\n\n");
+        }
+        match markdown {
+            true => document.push_verbatim(&format!("<!-- Page {} -->\n\n", index + 1)),
+            false => document.push_verbatim(&format!("--- Page {} ---\n\n", index + 1)),
+        }
+        document.append(paragraphs(page, max_len));
+    }
+    document.push_verbatim("\n");
+    document
+}
+
+/// Joins the lines of each paragraph with spaces and separates paragraphs by one blank line.
+fn reflow(page: &str) -> String {
+    let mut result = Vec::new();
+    let mut paragraph = Vec::new();
+    for line in page.lines().map(str::trim) {
+        if line.is_empty() {
+            if !paragraph.is_empty() {
+                result.push(paragraph.join(" "));
+                paragraph.clear();
+            }
+        } else {
+            paragraph.push(line);
+        }
+    }
+    if !paragraph.is_empty() {
+        result.push(paragraph.join(" "));
+    }
+    result.join("\n\n")
+}
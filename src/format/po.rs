@@ -0,0 +1,233 @@
+use super::{Document, FormatOptions};
+
+/// Splits a gettext catalog into entries and translates the ones without a translation.
+///
+/// The header, comments and already translated entries are kept verbatim. For an untranslated
+/// entry the `msgid` is translated into its `msgstr`; entries with plural forms get the singular
+/// translated into `msgstr[0]` and the plural into the other forms, as many as the catalog's
+/// `Plural-Forms` header asks for. Machine translations are flagged `fuzzy` unless disabled.
+pub fn parse(content: &str, options: &FormatOptions) -> Document {
+    let mut document = Document::new();
+    let mut lines = content.split_inclusive('\n').peekable();
+    let mut plural_forms = None;
+
+    while lines.peek().is_some() {
+        // Blank lines between entries.
+        while let Some(line) = lines.next_if(|line| line.trim().is_empty()) {
+            document.push_verbatim(line);
+        }
+
+        let mut block = Vec::new();
+        while let Some(line) = lines.next_if(|line| !line.trim().is_empty()) {
+            block.push(line);
+        }
+        let entry = Entry::parse(&block);
+
+        if entry.msgid.as_deref() == Some("") {
+            // The header entry, whose `msgstr` holds the catalog's metadata.
+            plural_forms = entry.msgstr.first().and_then(|header| nplurals(header));
+        }
+        if !entry.is_untranslated() {
+            block.iter().for_each(|line| document.push_verbatim(line));
+            continue;
+        }
+
+        let msgid = entry.msgid.as_deref().unwrap_or_default();
+        let msgstr_start = entry.msgstr_start.unwrap_or(block.len());
+        let keywords_start = block.iter().position(|line| !line.starts_with('#')).unwrap_or(block.len());
+        let mut comments = block[..keywords_start].to_vec();
+        let flag_line;
+        if options.mark_fuzzy {
+            match comments.iter().position(|line| line.starts_with("#,")) {
+                Some(i) if comments[i].contains("fuzzy") => {}
+                Some(i) => {
+                    flag_line = format!("{}, fuzzy\n", comments[i].trim_end());
+                    comments[i] = &flag_line;
+                }
+                None => {
+                    // Flags come after the other comments but before the previous-msgid ones.
+                    let i = comments.iter().position(|line| line.starts_with("#|")).unwrap_or(comments.len());
+                    comments.insert(i, "#, fuzzy\n");
+                }
+            }
+        }
+        comments.iter().for_each(|line| document.push_verbatim(line));
+        block[keywords_start..msgstr_start].iter().for_each(|line| document.push_verbatim(line));
+
+        match &entry.msgid_plural {
+            None => push_msgstr(&mut document, "msgstr", msgid),
+            Some(msgid_plural) => {
+                let forms = plural_forms.unwrap_or(entry.msgstr.len().max(2));
+                for form in 0..forms {
+                    let source = if form == 0 { msgid } else { msgid_plural };
+                    push_msgstr(&mut document, &format!("msgstr[{form}]"), source);
+                }
+            }
+        }
+        // Anything after the msgstr lines, such as obsolete entries without a blank line.
+        block[entry.msgstr_end..].iter().for_each(|line| document.push_verbatim(line));
+    }
+
+    document
+}
+
+/// The strings of one catalog entry.
+#[derive(Default)]
+struct Entry {
+    msgid: Option<String>,
+    msgid_plural: Option<String>,
+    msgstr: Vec<String>,
+    /// Line range of the `msgstr` keywords and their continuation lines.
+    msgstr_start: Option<usize>,
+    msgstr_end: usize,
+}
+
+impl Entry {
+    fn parse(block: &[&str]) -> Self {
+        let mut entry = Entry::default();
+        // The string the current line continues, if any.
+        let mut current: Option<&mut String> = None;
+
+        for (i, line) in block.iter().enumerate() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                current = None;
+                continue;
+            }
+            if line.starts_with('"') {
+                if let Some(string) = current.as_deref_mut() {
+                    string.push_str(&unquote(line));
+                    if entry.msgstr_start.is_some() {
+                        entry.msgstr_end = i + 1;
+                    }
+                }
+                continue;
+            }
+
+            let (keyword, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = unquote(value.trim());
+            current = match keyword {
+                "msgid" => Some(entry.msgid.insert(value)),
+                "msgid_plural" => Some(entry.msgid_plural.insert(value)),
+                _ if keyword == "msgstr" || keyword.starts_with("msgstr[") => {
+                    entry.msgstr_start.get_or_insert(i);
+                    entry.msgstr_end = i + 1;
+                    entry.msgstr.push(value);
+                    entry.msgstr.last_mut()
+                }
+                // `msgctxt` and anything unknown is kept as it is.
+                _ => None,
+            };
+        }
+        entry
+    }
+
+    fn is_untranslated(&self) -> bool {
+        matches!(&self.msgid, Some(msgid) if !msgid.is_empty())
+            && self.msgstr_start.is_some()
+            && self.msgstr.iter().all(String::is_empty)
+    }
+}
+
+/// Writes `keyword "…"` with the translation of `source` in place of the quoted string.
+fn push_msgstr(document: &mut Document, keyword: &str, source: &str) {
+    document.push_verbatim(&format!("{keyword} \""));
+    document.push_escaped_text(source, Vec::new(), escape);
+    document.push_verbatim("\"\n");
+}
+
+/// The number of plural forms from a `Plural-Forms: nplurals=N; plural=…;` header line.
+fn nplurals(header: &str) -> Option<usize> {
+    let line = header.lines().find_map(|line| line.strip_prefix("Plural-Forms:"))?;
+    let value = line.split(';').find_map(|field| field.trim().strip_prefix("nplurals="))?;
+    value.trim().parse().ok().filter(|&n| n > 0)
+}
+
+/// Decodes a quoted C string, as used in catalogs.
+fn unquote(quoted: &str) -> String {
+    let inner = quoted.strip_prefix('"').and_then(|s| s.strip_suffix('"')).unwrap_or(quoted);
+    let mut result = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some(other) => result.push(other),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Encodes text for a quoted catalog string.
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '"' => result.push_str("\\\""),
+            '\n' => result.push_str("\\n"),
+            '\t' => result.push_str("\\t"),
+            '\r' => result.push_str("\\r"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    fn options(mark_fuzzy: bool) -> FormatOptions {
+        FormatOptions { mark_fuzzy, ..FormatOptions::default() }
+    }
+
+    const HEADER: &str = "msgid \"\"\nmsgstr \"\"\n\"Content-Type: text/plain; charset=UTF-8\\n\"\n\"Plural-Forms: nplurals=3; plural=(n==1 ? 0 : n%10>=2 && n%10<=4 ? 1 : 2);\\n\"\n";
+
+    #[test]
+    fn translated_catalog_is_kept_byte_for_byte() {
+        let po = format!("{HEADER}\n# A comment\n#: src/main.rs:1\nmsgctxt \"menu\"\nmsgid \"Open\"\nmsgstr \"Megnyitás\"\n");
+        let document = parse(&po, &options(true));
+        assert!(document.segments().is_empty());
+        assert_eq!(round_trip(&document), po);
+    }
+
+    #[test]
+    fn multi_line_msgid_is_unescaped_and_escaped_again() {
+        let po = "msgid \"\"\n\"Say \\\"hi\\\"\\n\"\n\"to\\tall\"\nmsgstr \"\"\n";
+        let document = parse(po, &options(false));
+        assert_eq!(document.texts(), ["Say \"hi\"\nto\tall"]);
+        let translated = round_trip(&document);
+        assert_eq!(translated, "msgid \"\"\n\"Say \\\"hi\\\"\\n\"\n\"to\\tall\"\nmsgstr \"Say \\\"hi\\\"\\nto\\tall\"\n");
+        // The translation reads back as the same string, and the entry as translated.
+        let entry = Entry::parse(&translated.split_inclusive('\n').collect::<Vec<_>>());
+        assert_eq!(entry.msgstr, [entry.msgid.clone().unwrap()]);
+        assert!(parse(&translated, &options(false)).segments().is_empty());
+    }
+
+    #[test]
+    fn plural_forms_follow_the_header() {
+        let po = format!("{HEADER}\nmsgid \"One file\"\nmsgid_plural \"%d files\"\nmsgstr[0] \"\"\nmsgstr[1] \"\"\n");
+        let document = parse(&po, &options(true));
+        assert_eq!(document.texts(), ["One file", "%d files", "%d files"]);
+        let translated = round_trip(&document);
+        assert!(translated.ends_with(
+            "#, fuzzy\nmsgid \"One file\"\nmsgid_plural \"%d files\"\nmsgstr[0] \"One file\"\nmsgstr[1] \"%d files\"\nmsgstr[2] \"%d files\"\n"
+        ));
+        assert!(translated.starts_with(HEADER));
+    }
+
+    #[test]
+    fn fuzzy_is_added_to_existing_flags() {
+        let po = "#: a.c:1\n#, c-format\nmsgid \"%s saved\"\nmsgstr \"\"\n";
+        let translated = round_trip(&parse(po, &options(true)));
+        assert_eq!(translated, "#: a.c:1\n#, c-format, fuzzy\nmsgid \"%s saved\"\nmsgstr \"%s saved\"\n");
+    }
+}
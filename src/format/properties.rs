@@ -0,0 +1,215 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::{Document, Escape};
+use crate::no_translate::protect_matches;
+
+/// Translates the values of a Java `.properties` file.
+///
+/// Keys, comments, separators and the order of the entries are kept. `\uXXXX` escapes are
+/// decoded for translation, while escapes like `\n` and the backslashes continuing a value on the
+/// next line are replaced by placeholders. Non-ASCII characters of the translation are written as
+/// escapes, as Java reads these files as ISO-8859-1, unless the file already holds them as they
+/// are and so must be read as UTF-8. With `keys`, only the values of keys matching one of these
+/// globs are translated.
+pub fn parse(content: &str, keys: &[String]) -> Document {
+    static ENTRY: OnceLock<Regex> = OnceLock::new();
+    // A key, with escaped separators in it, then the separator and the value up to a line end that isn't escaped.
+    let entry = ENTRY.get_or_init(|| {
+        Regex::new(r"(?m)^([ \t\f]*)((?:[^\s:=\\]|\\.)+)([ \t\f]*[:=]?[ \t\f]*)((?:[^\\\n]|\\(?:\r?\n|.))*)$").unwrap()
+    });
+    let escape: Escape = if content.is_ascii() { escape_non_ascii } else { escape };
+
+    let mut document = Document::new();
+    let mut copied = 0;
+    for captures in entry.captures_iter(content) {
+        let (whole, key, value) = (captures.get(0).unwrap(), &captures[2], captures.get(4).unwrap());
+        let line = content[whole.start()..].trim_start();
+        if line.starts_with(['#', '!']) || !is_selected(key, keys) {
+            continue;
+        }
+        document.push_verbatim(&content[copied..value.start()]);
+        let (text, protected) = protect_matches(std::slice::from_ref(escapes()), "", &decode_unicode(value.as_str()));
+        let text = unescape_chars(&text);
+        document.push_escaped_text(&text, protected, escape);
+        copied = value.end();
+    }
+    document.push_verbatim(&content[copied..]);
+    document
+}
+
+/// Translates the values of a `.env` file of `KEY=value` lines.
+///
+/// Keys, `export` prefixes and comments are kept; values in double quotes get the quotes in
+/// their translation escaped, and unquoted values are quoted when their translation needs it.
+/// With `keys`, only the values of keys matching one of these globs are translated.
+pub fn parse_env(content: &str, keys: &[String]) -> Document {
+    static ENTRY: OnceLock<Regex> = OnceLock::new();
+    let entry = ENTRY.get_or_init(|| {
+        Regex::new(r#"(?m)^([ \t]*(?:export[ \t]+)?)([A-Za-z_][\w.-]*)([ \t]*=[ \t]*)("(?:[^"\\]|\\.)*"|'[^']*'|[^\s#'"][^\n#]*?)?[ \t]*(?:#.*)?$"#)
+            .unwrap()
+    });
+    let mut document = Document::new();
+    let mut copied = 0;
+    for captures in entry.captures_iter(content) {
+        let Some(value) = captures.get(4).filter(|_| is_selected(&captures[2], keys)) else { continue };
+        let raw = value.as_str();
+        let (start, text, escape): (usize, String, Escape) = if raw.starts_with('"') {
+            (1, raw[1..raw.len() - 1].replace("\\\"", "\""), escape_double_quoted)
+        } else if raw.starts_with('\'') {
+            // Nothing can be escaped in single quotes.
+            (1, raw[1..raw.len() - 1].to_string(), |text| text.replace('\'', "\u{2019}"))
+        } else {
+            (0, raw.to_string(), quote_if_needed)
+        };
+        document.push_verbatim(&content[copied..value.start() + start]);
+        document.push_escaped_text(&text, Vec::new(), escape);
+        copied = value.end() - start;
+    }
+    document.push_verbatim(&content[copied..]);
+    document
+}
+
+/// Whether a key is selected for translation by the `keys` globs, or there are none.
+fn is_selected(key: &str, keys: &[String]) -> bool {
+    keys.is_empty() || keys.iter().any(|glob| glob_matches(glob, key))
+}
+
+/// Matches a key against a glob in which `*` stands for any run of characters and `?` for one.
+fn glob_matches(glob: &str, key: &str) -> bool {
+    match glob.chars().next() {
+        None => key.is_empty(),
+        Some('*') => (0..=key.len()).filter(|&i| key.is_char_boundary(i)).any(|i| glob_matches(&glob[1..], &key[i..])),
+        Some('?') => key.chars().next().is_some_and(|c| glob_matches(&glob[1..], &key[c.len_utf8()..])),
+        Some(c) => key.starts_with(c) && glob_matches(&glob[c.len_utf8()..], &key[c.len_utf8()..]),
+    }
+}
+
+/// Escapes and line continuations that are kept as they are, as well as `{0}` message format arguments.
+fn escapes() -> &'static Regex {
+    static ESCAPES: OnceLock<Regex> = OnceLock::new();
+    ESCAPES.get_or_init(|| Regex::new(r"\\\r?\n[ \t\f]*|\\[nrtf\\]|\{\d+(?:,[^{}]*)?\}").unwrap())
+}
+
+/// Decodes `\uXXXX` escapes, including surrogate pairs, leaving invalid ones as they are.
+fn decode_unicode(text: &str) -> String {
+    static UNICODE: OnceLock<Regex> = OnceLock::new();
+    let unicode = UNICODE.get_or_init(|| Regex::new(r"(?:\\u[0-9a-fA-F]{4})+").unwrap());
+    unicode
+        .replace_all(text, |captures: &regex::Captures| {
+            let units: Vec<u16> =
+                captures[0].split("\\u").skip(1).filter_map(|unit| u16::from_str_radix(unit, 16).ok()).collect();
+            char::decode_utf16(units).map(|c| c.map_or_else(|_| "\u{FFFD}".to_string(), String::from)).collect::<String>()
+        })
+        .into_owned()
+}
+
+/// Removes the backslash from other escaped characters, such as `\=` or `\:`.
+fn unescape_chars(text: &str) -> String {
+    static ESCAPED: OnceLock<Regex> = OnceLock::new();
+    ESCAPED.get_or_init(|| Regex::new(r"\\([^nrtfu\\])").unwrap()).replace_all(text, "$1").into_owned()
+}
+
+/// Escapes the backslashes and line breaks of a translation, except for the escapes and line
+/// continuations kept from the source.
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next_if(|next| matches!(next, 'n' | 'r' | 't' | 'f' | 'u' | '\\' | '\n')) {
+                Some(next) => {
+                    result.push('\\');
+                    result.push(next);
+                }
+                None => result.push_str("\\\\"),
+            },
+            '\n' => result.push_str("\\n"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Like [`escape`], also writing the characters outside of ASCII as `\uXXXX` escapes.
+fn escape_non_ascii(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in escape(text).chars() {
+        if c.is_ascii() {
+            result.push(c);
+        } else {
+            let mut units = [0; 2];
+            for unit in c.encode_utf16(&mut units) {
+                result.push_str(&format!("\\u{:04x}", unit));
+            }
+        }
+    }
+    result
+}
+
+fn escape_double_quoted(text: &str) -> String {
+    text.replace('"', "\\\"")
+}
+
+/// Quotes a translation that would no longer be read back as one unquoted value, e.g. because it contains `#`.
+fn quote_if_needed(text: &str) -> String {
+    if text.contains(['#', '"', '\'', '\n']) {
+        format!("\"{}\"", escape_double_quoted(text))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    #[test]
+    fn properties_round_trip_with_escapes_and_continuations() {
+        let properties = "# Comment\n! Another comment\ngreeting = Hello, {0}!\nmultiline: First line\\n\\\n    second line\nkey\\ with\\ spaces Value\nempty=\n";
+        let document = parse(properties, &[]);
+        assert_eq!(document.texts(), ["Hello, {0}!", "First line\\n\\\n    second line", "Value"]);
+        assert_eq!(round_trip(&document), properties);
+    }
+
+    #[test]
+    fn unicode_escapes_are_decoded_and_written_again() {
+        let properties = "title=Caf\\u00e9 \\ud83d\\ude00\n";
+        let document = parse(properties, &[]);
+        assert_eq!(document.texts(), ["Café 😀"]);
+        assert_eq!(round_trip(&document), properties);
+    }
+
+    #[test]
+    fn non_ascii_is_escaped_unless_the_file_is_utf8() {
+        let translation = ["C:\\Kávé".to_string()];
+        assert_eq!(parse("path=Coffee\n", &[]).render(&translation).0, "path=C:\\\\K\\u00e1v\\u00e9\n");
+        let utf8 = parse("# Café\npath=Coffee\n", &[]);
+        assert_eq!(utf8.render(&translation).0, "# Café\npath=C:\\\\Kávé\n");
+    }
+
+    #[test]
+    fn line_breaks_in_the_translation_are_escaped_but_kept_escapes_are_not() {
+        let document = parse("# Café\nhelp=Line one\\nLine two\n", &[]);
+        assert_eq!(document.segments(), ["Line one⟦0⟧Line two"]);
+        let (rendered, _) = document.render(&["Első sor⟦0⟧Második\nsor".to_string()]);
+        assert_eq!(rendered, "# Café\nhelp=Első sor\\nMásodik\\nsor\n");
+    }
+
+    #[test]
+    fn only_selected_keys_are_translated() {
+        let properties = "menu.open=Open\nmenu.close=Close\nversion=1.0 beta\n";
+        let document = parse(properties, &["menu.*".to_string()]);
+        assert_eq!(document.texts(), ["Open", "Close"]);
+        assert_eq!(round_trip(&document), properties);
+    }
+
+    #[test]
+    fn env_values_keep_their_quotes() {
+        let env = "# Settings\nexport TITLE=\"Say \\\"hi\\\"\"\nNAME='Anna'\nGREETING=Hello world # inline comment\nPORT=8080\n";
+        let document = parse_env(env, &[]);
+        assert_eq!(document.texts(), ["Say \"hi\"", "Anna", "Hello world"]);
+        assert_eq!(round_trip(&document), env);
+    }
+}
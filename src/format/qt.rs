@@ -0,0 +1,112 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::html::{find_closing_tag, Tag};
+use super::xliff::{attribute, set_attribute, Element};
+use super::{Document, FormatOptions};
+use crate::no_translate::protect_matches;
+
+/// The translation type that marks translations still to be reviewed in Qt Linguist.
+const UNFINISHED: &str = "unfinished";
+
+/// Fills in the `<translation>` of the messages of a Qt Linguist (`.ts`) file that have none yet.
+///
+/// A message is translated when its translation is missing or empty, unless it is obsolete or
+/// vanished. Plural messages get the translation in each of their `<numerusform>`s. New
+/// translations stay marked `unfinished` for review unless `options` says otherwise, and the
+/// file's language is set when `options` has one. Qt's `%1`, `%L1` and `%n` are protected.
+pub fn parse(content: &str, options: &FormatOptions) -> Document {
+    let mut document = Document::new();
+    let mut verbatim_start = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = content[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(content, start).filter(|tag| !tag.closing) else {
+            pos = start + 1;
+            continue;
+        };
+        pos = tag.end;
+        let opening = &content[start..tag.end];
+        match tag.name.as_deref() {
+            Some("ts") => {
+                if let Some(target) = options.target_language.as_deref() {
+                    document.push_verbatim(&content[verbatim_start..start]);
+                    document.push_verbatim(&set_attribute(opening, "language", &target.replace('-', "_")));
+                    verbatim_start = tag.end;
+                }
+            }
+            Some("message") => {
+                let Some(end) = find_closing_tag(content, tag.end, "message") else {
+                    continue;
+                };
+                document.push_verbatim(&content[verbatim_start..start]);
+                let message = &content[start..end];
+                if !push_message(&mut document, message, options.mark_fuzzy) {
+                    document.push_verbatim(message);
+                }
+                verbatim_start = end;
+                pos = end;
+            }
+            _ => {}
+        }
+    }
+    document.push_verbatim(&content[verbatim_start..]);
+
+    document
+}
+
+/// Adds a message with its translation filled in, or returns false if it doesn't need translating.
+fn push_message(document: &mut Document, message: &str, mark_unfinished: bool) -> bool {
+    let (Some(source), Some(translation)) = (Element::find(message, "source"), Element::find(message, "translation")) else {
+        return false;
+    };
+    let translation_tag = &message[translation.start..translation.content_start];
+    let content = &message[translation.content_start..translation.content_end];
+    let existing = Element::find(content, "numerusform").map_or(content, |form| &content[form.content_start..form.content_end]);
+    if matches!(attribute(translation_tag, "type"), Some("obsolete" | "vanished")) || !existing.trim().is_empty() {
+        return false;
+    }
+
+    let translation_tag = translation_tag.trim_end_matches("/>").trim_end_matches('>').trim_end();
+    let translation_tag = match attribute(translation_tag, "type") {
+        _ if mark_unfinished => set_attribute(&format!("{}>", translation_tag), "type", UNFINISHED),
+        Some(_) => format!("{}>", translation_tag.replacen(r#" type="unfinished""#, "", 1)),
+        None => format!("{}>", translation_tag),
+    };
+    let source_text = unescape(&message[source.content_start..source.content_end]);
+    document.push_verbatim(&message[..translation.start]);
+    document.push_verbatim(&translation_tag);
+    let mut numerus_forms = 0;
+    let mut pos = 0;
+    while let Some(form) = Element::find(&content[pos..], "numerusform") {
+        document.push_verbatim(&content[pos..pos + form.content_start]);
+        push_source(document, &source_text);
+        pos += form.content_end;
+        numerus_forms += 1;
+    }
+    if numerus_forms == 0 {
+        push_source(document, &source_text);
+    } else {
+        document.push_verbatim(&content[pos..]);
+    }
+    document.push_verbatim("</translation>");
+    document.push_verbatim(&message[translation.end..]);
+    true
+}
+
+/// Adds the text of a source as the segment its translation replaces.
+fn push_source(document: &mut Document, source: &str) {
+    static ARGUMENTS: OnceLock<Regex> = OnceLock::new();
+    let arguments = ARGUMENTS.get_or_init(|| Regex::new(r"%(?:L?\d{1,2}|n)").unwrap());
+    let (text, protected) = protect_matches(std::slice::from_ref(arguments), "", source);
+    document.push_escaped_text(&text, protected, escape);
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,119 @@
+use regex::Regex;
+use std::sync::OnceLock;
+
+use super::html::{find_closing_tag, Tag};
+use super::xliff::attribute;
+use super::Document;
+use crate::no_translate::protect_matches;
+
+/// Translates the string resources of a .NET `.resx` (or `.resw`) file.
+///
+/// The `<value>` of every `<data>` element is translated, except for resources of another type
+/// (with a `type` or `mimetype` attribute, such as images) and the designer's `>>` metadata.
+/// Comments and the schema are kept, and composite format items like `{0}` are replaced by
+/// placeholders.
+pub fn parse(content: &str) -> Document {
+    let mut document = Document::new();
+    let mut verbatim_start = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = content[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(content, start).filter(|tag| !tag.closing) else {
+            pos = start + 1;
+            continue;
+        };
+        pos = tag.end;
+        let opening = &content[start..tag.end];
+        if tag.name.as_deref() != Some("data") || opening.ends_with("/>") {
+            continue;
+        }
+        let Some(end) = find_closing_tag(content, tag.end, "data") else {
+            continue;
+        };
+        pos = end;
+        let is_string = attribute(opening, "type").is_none()
+            && attribute(opening, "mimetype").is_none()
+            && !attribute(opening, "name").is_some_and(|name| name.starts_with(">>") || name.starts_with("&gt;&gt;"));
+        let Some((value_start, value_end)) = is_string.then(|| value_range(&content[tag.end..end])).flatten() else {
+            continue;
+        };
+        document.push_verbatim(&content[verbatim_start..tag.end + value_start]);
+        let value = unescape(&content[tag.end + value_start..tag.end + value_end]);
+        let (text, protected) = protect_matches(std::slice::from_ref(format_items()), "", &value);
+        document.push_escaped_text(&text, protected, escape);
+        verbatim_start = tag.end + value_end;
+    }
+    document.push_verbatim(&content[verbatim_start..]);
+
+    document
+}
+
+/// The byte range of the content of the `<value>` element in the content of a `<data>` element.
+fn value_range(data: &str) -> Option<(usize, usize)> {
+    let mut pos = 0;
+    while let Some(offset) = data[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(data, start) else {
+            pos = start + 1;
+            continue;
+        };
+        pos = tag.end;
+        if tag.closing || tag.name.as_deref() != Some("value") || data[..tag.end].ends_with("/>") {
+            continue;
+        }
+        let end = find_closing_tag(data, tag.end, "value")?;
+        return Some((tag.end, tag.end + data[tag.end..end].rfind("</")?));
+    }
+    None
+}
+
+/// Composite format items such as `{0}` or `{1,-10:N2}`.
+fn format_items() -> &'static Regex {
+    static ITEMS: OnceLock<Regex> = OnceLock::new();
+    ITEMS.get_or_init(|| Regex::new(r"\{\d+(?:,-?\d+)?(?::[^{}]*)?\}").unwrap())
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    const DOCUMENT: &str = r#"<root>
+  <data name="Greeting" xml:space="preserve">
+    <value>Hello {0} &amp; welcome</value>
+    <comment>Shown at start</comment>
+  </data>
+  <data name="Logo" type="System.Drawing.Bitmap, System.Drawing">
+    <value>iVBORw0KGgo=</value>
+  </data>
+  <data name="&gt;&gt;button.Name">
+    <value>button</value>
+  </data>
+</root>
+"#;
+
+    #[test]
+    fn resx_round_trips_and_translates_only_string_values() {
+        let document = parse(DOCUMENT);
+        assert_eq!(document.texts(), ["Hello {0} & welcome"]);
+        assert_eq!(document.segments(), ["Hello ⟦0⟧ & welcome"]);
+        assert_eq!(round_trip(&document), DOCUMENT);
+    }
+
+    #[test]
+    fn markup_in_the_translation_is_escaped() {
+        let document = parse("<data name=\"a\"><value>a &lt; b</value></data>");
+        let (translated, lost) = document.render(&["a < b & c".to_string()]);
+        assert_eq!(lost, 0);
+        assert_eq!(translated, "<data name=\"a\"><value>a &lt; b &amp; c</value></data>");
+    }
+}
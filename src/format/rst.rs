@@ -0,0 +1,270 @@
+use regex::Regex;
+use std::sync::OnceLock;
+use unicode_width::UnicodeWidthStr;
+
+use super::{Document, Escape};
+use crate::no_translate::protect_matches;
+
+/// Directives whose content is prose, translated like the body text around them.
+const PROSE_DIRECTIVES: &[&str] = &[
+    "note", "warning", "tip", "hint", "important", "caution", "danger", "error", "attention", "admonition", "seealso",
+    "topic", "sidebar", "rubric", "epigraph", "highlights", "pull-quote", "compound", "container", "glossary",
+    "deprecated", "versionadded", "versionchanged", "only",
+];
+
+/// Splits reStructuredText into translatable prose and verbatim markup.
+///
+/// Every paragraph, list item and section title becomes one segment, with its lines joined so
+/// their indentation doesn't end up in the text; title adornments are redrawn to the length
+/// of the translation. Roles, inline literals, references, substitutions and URLs are replaced
+/// by placeholders. Literal blocks, tables, comments and directives are kept as they are,
+/// except for the content of admonitions and other directives holding prose.
+pub fn parse(content: &str) -> Document {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut document = Document::new();
+    parse_lines(&lines, &mut document);
+    document
+}
+
+fn parse_lines(lines: &[&str], document: &mut Document) {
+    // The indentation of the paragraph ending with `::`, whose indented literal block follows.
+    let mut literal_after: Option<usize> = None;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let text = line.trim_end();
+        let indent = indentation(line);
+        let next = lines.get(i + 1).map(|line| line.trim_end());
+
+        if text.is_empty() {
+            document.push_verbatim(line);
+            i += 1;
+            continue;
+        }
+        if let Some(literal_indent) = literal_after.take() {
+            if indent > literal_indent {
+                let end = block_end(lines, i + 1, literal_indent);
+                lines[i..end].iter().for_each(|line| document.push_verbatim(line));
+                i = end;
+                continue;
+            }
+        }
+
+        let trimmed = text.trim_start();
+        if let Some(directive) = trimmed.strip_prefix("..").filter(|rest| rest.is_empty() || rest.starts_with(' ')) {
+            let end = block_end(lines, i + 1, indent);
+            match directive.trim_start().split_once("::") {
+                Some((name, argument)) if PROSE_DIRECTIVES.contains(&name.trim()) => {
+                    document.push_verbatim(&line[..text.len() - argument.len()]);
+                    push_paragraph(document, argument);
+                    document.push_verbatim(&line[text.len()..]);
+                    // Options such as `:class:` come first, then the content.
+                    let mut start = i + 1;
+                    while start < end && is_field(lines[start].trim()) {
+                        document.push_verbatim(lines[start]);
+                        start += 1;
+                    }
+                    parse_lines(&lines[start..end], document);
+                }
+                // Footnotes and citations, such as `.. [1] Text`, are prose after their label.
+                None if directive.trim_start().starts_with('[') && directive.contains("] ") => {
+                    let label_end = text.find("] ").map_or(0, |end| end + 2);
+                    document.push_verbatim(&line[..label_end]);
+                    let mut note = text[label_end..].to_string();
+                    for line in &lines[i + 1..end] {
+                        note.push(' ');
+                        note.push_str(line.trim());
+                    }
+                    push_paragraph(document, &note);
+                    let last = lines[end - 1];
+                    document.push_verbatim(&last[last.trim_end().len()..]);
+                }
+                _ => lines[i..end].iter().for_each(|line| document.push_verbatim(line)),
+            }
+            i = end;
+        } else if let Some(title) = next.filter(|title| {
+            is_adornment(text) && !title.trim().is_empty() && lines.get(i + 2).is_some_and(|under| under.trim_end() == text)
+        }) {
+            // A title between an overline and an underline.
+            push_title(document, title, text.chars().next().unwrap_or('='), true);
+            document.push_verbatim(&lines[i + 2][text.len()..]);
+            i += 3;
+        } else if next.is_some_and(|next| indent == 0 && is_adornment(next) && next.len() >= text.width().min(4) && !is_adornment(text)) {
+            let under = lines[i + 1];
+            push_title(document, text, under.trim_end().chars().next().unwrap_or('='), false);
+            document.push_verbatim(&under[under.trim_end().len()..]);
+            i += 2;
+        } else if is_table_border(trimmed) || is_adornment(trimmed) {
+            // Tables and transitions are kept up to the next blank line.
+            while i < lines.len() && !lines[i].trim().is_empty() {
+                document.push_verbatim(lines[i]);
+                i += 1;
+            }
+        } else if trimmed == "::" {
+            document.push_verbatim(line);
+            literal_after = Some(indent);
+            i += 1;
+        } else {
+            let marker = list_marker(trimmed);
+            let continuation = if marker > 0 { indent + marker } else { indent };
+            let mut end = i + 1;
+            while end < lines.len()
+                && !lines[end].trim().is_empty()
+                && indentation(lines[end]) == continuation
+                && list_marker(lines[end].trim_start()) == 0
+            {
+                end += 1;
+            }
+            let start = text.len() - trimmed.len() + marker;
+            let mut paragraph = text[start..].to_string();
+            for line in &lines[i + 1..end] {
+                paragraph.push(' ');
+                paragraph.push_str(line.trim());
+            }
+            document.push_verbatim(&line[..start]);
+            push_paragraph(document, &paragraph);
+            let last = lines[end - 1];
+            document.push_verbatim(&last[last.trim_end().len()..]);
+            if paragraph.ends_with("::") {
+                literal_after = Some(indent);
+            }
+            i = end;
+        }
+    }
+}
+
+/// Adds a paragraph with its inline markup protected.
+fn push_paragraph(document: &mut Document, text: &str) {
+    let (text, protected) = protect_inline(text);
+    document.push_text(&text, protected);
+}
+
+/// Replaces roles, inline literals, references, substitutions and URLs with placeholder tokens.
+fn protect_inline(text: &str) -> (String, Vec<String>) {
+    static INLINE: OnceLock<Regex> = OnceLock::new();
+    let inline = INLINE.get_or_init(|| {
+        Regex::new(concat!(
+            r":[A-Za-z0-9_:+.-]+:`[^`]*`",
+            r"|``.+?``",
+            r"|`[^`]+`_{0,2}",
+            r"|\[[^\]\s]+\]_",
+            r"|\|[^|\s](?:[^|]*[^|\s])?\|_{0,2}",
+            r"|\b[A-Za-z0-9](?:[\w-]*[A-Za-z0-9])?__?\b",
+            r"|https?://[^\s>]+",
+            r"|::$",
+        ))
+        .unwrap()
+    });
+    protect_matches(std::slice::from_ref(inline), "", text)
+}
+
+/// Adds a section title, with its adornment drawn to fit the translation.
+fn push_title(document: &mut Document, line: &str, adornment: char, overline: bool) {
+    let title = line.trim();
+    if !overline {
+        document.push_verbatim(&line[..line.len() - line.trim_start().len()]);
+    }
+    let (text, protected) = protect_inline(title);
+    document.push_escaped_text(&text, protected, adorned(adornment, overline));
+}
+
+/// The escape drawing a title's adornment of `c` below it, and above it too with `overline`.
+fn adorned(c: char, overline: bool) -> Escape {
+    macro_rules! adornments {
+        ($($c:literal)*) => {
+            match (c, overline) {
+                $(
+                    ($c, false) => |title| adorn(title, $c, false),
+                    ($c, true) => |title| adorn(title, $c, true),
+                )*
+                _ => |title| adorn(title, '=', false),
+            }
+        };
+    }
+    adornments!('=' '-' '~' '^' '"' '\'' '`' '#' '*' '+' '<' '>' ':' '.' '_' '!' '$' '%' '&' ',' '/' ';' '?' '@' '[' ']' '\\' '{' '|' '}' '(' ')')
+}
+
+fn adorn(title: &str, c: char, overline: bool) -> String {
+    if title.is_empty() {
+        return String::new();
+    }
+    let line = c.to_string().repeat(title.width().max(1));
+    if overline {
+        format!("{}\n{}\n{}", line, title, line)
+    } else {
+        format!("{}\n{}", title, line)
+    }
+}
+
+/// Whether the line is the over- or underline of a title, or a transition: a repeated punctuation character.
+fn is_adornment(text: &str) -> bool {
+    let mut chars = text.chars();
+    let Some(first) = chars.next() else { return false };
+    first.is_ascii_punctuation() && text.len() >= 2 && chars.all(|c| c == first)
+}
+
+/// Whether the line is the border of a grid table (`+---+`) or a simple table (`=== ===`).
+fn is_table_border(text: &str) -> bool {
+    (text.starts_with("+-") || text.starts_with("+="))
+        || (text.contains(' ') && text.starts_with('=') && text.chars().all(|c| c == '=' || c == ' '))
+}
+
+/// The length of the bullet, enumerator or field name a list item starts with, with the spaces after it.
+fn list_marker(text: &str) -> usize {
+    static MARKER: OnceLock<Regex> = OnceLock::new();
+    let marker = MARKER.get_or_init(|| {
+        Regex::new(r"^(?:[-*+•‣⁃]|#\.|\d+[.)]|\(\d+\)|[a-zA-Z#][.)]|\([a-zA-Z#]\)|:[^:`\s][^:`]*:|\|)(?: +|$)").unwrap()
+    });
+    marker.find(text).map_or(0, |found| found.end())
+}
+
+/// Whether the line is a field of a field list, such as a directive option.
+fn is_field(text: &str) -> bool {
+    text.starts_with(':') && text[1..].contains(':')
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+/// The index of the first line from `start` on that isn't blank and not indented more than
+/// `indent`, ending the block indented under a line; trailing blank lines are left out of it.
+fn block_end(lines: &[&str], start: usize, indent: usize) -> usize {
+    let mut end = start;
+    let mut i = start;
+    while i < lines.len() {
+        if !lines[i].trim().is_empty() {
+            if indentation(lines[i]) <= indent {
+                break;
+            }
+            end = i + 1;
+        }
+        i += 1;
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    const DOCUMENT: &str = "Title\n=====\n\nSome text with ``code`` and\na link_.\n\n.. note:: Be careful.\n\n.. code-block:: rust\n\n    let x = 1;\n\nExample::\n\n    literal\n";
+
+    #[test]
+    fn rst_round_trips_and_keeps_literal_blocks() {
+        let document = parse(DOCUMENT);
+        assert_eq!(document.texts(), ["Title", "Some text with ``code`` and a link_.", "Be careful.", "Example::"]);
+        assert_eq!(document.segments()[1], "Some text with ⟦0⟧ and a ⟦1⟧.");
+        let joined = DOCUMENT.replace("and\na link_", "and a link_");
+        assert_eq!(round_trip(&document), joined);
+    }
+
+    #[test]
+    fn title_adornment_fits_the_translation() {
+        let document = parse("Title\n=====\n\nText.\n");
+        let (translated, lost) = document.render(&["Hosszabb cím".to_string(), "Szöveg.".to_string()]);
+        assert_eq!(lost, 0);
+        assert_eq!(translated, "Hosszabb cím\n============\n\nSzöveg.\n");
+    }
+}
@@ -0,0 +1,85 @@
+use super::Document;
+use crate::placeholder;
+
+/// Splits SRT and WebVTT subtitles into cues and translates only the cue text.
+///
+/// Cue numbers, identifiers, timestamps and cue settings are kept verbatim, as are WebVTT
+/// header, `NOTE`, `STYLE` and `REGION` blocks. Formatting tags such as `<i>` or `<v Speaker>`
+/// and SRT override codes such as `{\an8}` are protected with placeholders.
+pub fn parse(content: &str) -> Document {
+    let mut document = Document::new();
+    let mut lines = content.split_inclusive('\n').peekable();
+
+    while lines.peek().is_some() {
+        // Blank lines between cues.
+        while let Some(line) = lines.next_if(|line| line.trim().is_empty()) {
+            document.push_verbatim(line);
+        }
+
+        let mut block = Vec::new();
+        while let Some(line) = lines.next_if(|line| !line.trim().is_empty()) {
+            block.push(line);
+        }
+        let Some(timing) = block.iter().position(|line| line.contains("-->")) else {
+            block.iter().for_each(|line| document.push_verbatim(line));
+            continue;
+        };
+
+        block[..=timing].iter().for_each(|line| document.push_verbatim(line));
+        let cue_text: String = block[timing + 1..].concat();
+        let (text, protected) = protect_tags(&cue_text);
+        document.push_text(&text, protected);
+    }
+
+    document
+}
+
+/// Replaces `<…>` tags and `{\…}` override codes with placeholder tokens.
+fn protect_tags(text: &str) -> (String, Vec<String>) {
+    let mut result = String::with_capacity(text.len());
+    let mut protected = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(['<', '{']) {
+        let close = if rest[start..].starts_with('<') { '>' } else { '}' };
+        let is_tag = close == '>' || rest[start..].starts_with("{\\");
+        match rest[start..].find(close).filter(|_| is_tag) {
+            Some(len) => {
+                result.push_str(&rest[..start]);
+                result.push_str(&placeholder::token(protected.len()));
+                protected.push(rest[start..=start + len].to_string());
+                rest = &rest[start + len + 1..];
+            }
+            None => {
+                result.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+
+    (result, protected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    #[test]
+    fn srt_round_trips_with_timings_and_tags() {
+        let srt = "1\n00:00:01,000 --> 00:00:02,500\n<i>Hello</i> there!\n\n2\n00:00:03,000 --> 00:00:04,000\n{\\an8}Two lines\nof text.\n";
+        let document = parse(srt);
+        assert_eq!(document.texts(), ["<i>Hello</i> there!", "{\\an8}Two lines\nof text."]);
+        assert_eq!(round_trip(&document), srt);
+    }
+
+    #[test]
+    fn vtt_keeps_header_notes_and_cue_settings() {
+        let vtt = "WEBVTT\n\nNOTE This is not translated\n\nintro\n00:01.000 --> 00:02.000 align:start position:10%\n<v Anna>Good morning.\n";
+        let document = parse(vtt);
+        assert_eq!(document.texts(), ["<v Anna>Good morning."]);
+        assert!(document.segments()[0].starts_with(&placeholder::token(0)));
+        assert_eq!(round_trip(&document), vtt);
+    }
+}
@@ -0,0 +1,424 @@
+use reqwest::Url;
+use std::collections::HashMap;
+
+use super::html::{find_closing_tag, Tag};
+use super::xliff::attribute;
+
+/// Elements removed from a page with their content: scripts, navigation, forms and the like.
+const CLUTTER: &[&str] = &[
+    "script", "style", "noscript", "template", "nav", "header", "footer", "aside", "form", "button", "iframe", "svg",
+    "canvas", "select", "dialog", "menu",
+];
+
+/// Elements that may hold the article, scored by the text right inside them.
+const CONTAINERS: &[&str] = &["article", "main", "section", "div", "td", "body"];
+
+/// Elements that only wrap other content and are left out of the article.
+const WRAPPERS: &[&str] = &["div", "section", "span", "font", "center"];
+
+/// Attributes kept on the elements of the article.
+const KEPT_ATTRIBUTES: &[&str] = &["href", "src", "alt", "title"];
+
+/// The readable part of a web page.
+#[derive(Debug, Clone)]
+pub struct Article {
+    /// The title of the page, if it has one.
+    pub title: Option<String>,
+    /// The content of the article as HTML, without scripts, navigation, styling attributes and wrappers.
+    pub html: String,
+}
+
+impl Article {
+    /// Finds the article in a web page, like the reader view of browsers does: clutter such as
+    /// navigation, sidebars and forms is dropped, and the element with the most text of its own
+    /// (not counting link text) is taken as the article. Links and images are made absolute
+    /// against `base`.
+    pub fn extract(page: &str, base: &Url) -> Self {
+        let title = element_text(page, "title").or_else(|| element_text(page, "h1"));
+        let page = remove_clutter(page);
+        let (start, end) = best_container(&page);
+        Self { title, html: clean(&page[start..end], base) }
+    }
+
+    /// A complete HTML document with the article and its title.
+    pub fn to_html(&self) -> String {
+        let title = self.title.as_deref().map(escape).unwrap_or_default();
+        let heading = if self.title.is_some() { format!("<h1>{}</h1>\n", title) } else { String::new() };
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n<article>\n{}{}\n</article>\n</body>\n</html>\n",
+            title,
+            heading,
+            self.html.trim()
+        )
+    }
+
+    /// The article as Markdown, under its title.
+    pub fn to_markdown(&self) -> String {
+        let body = to_markdown(&self.html);
+        match &self.title {
+            Some(title) => format!("# {}\n\n{}\n", title, body),
+            None => format!("{}\n", body),
+        }
+    }
+}
+
+/// The text of the first element with this name, without tags and surrounding whitespace.
+fn element_text(page: &str, name: &str) -> Option<String> {
+    let lower = page.to_ascii_lowercase();
+    let start = lower.find(&format!("<{}", name))?;
+    let tag = Tag::parse(page, start)?;
+    let end = find_closing_tag(page, tag.end, name)?;
+    let inner = &page[tag.end..end];
+    let inner = &inner[..inner.rfind('<').unwrap_or(inner.len())];
+    let text = collapse_whitespace(&decode_entities(&strip_tags(inner)));
+    Some(text.trim().to_string()).filter(|text| !text.is_empty())
+}
+
+/// Removes comments and the clutter elements with their content.
+fn remove_clutter(page: &str) -> String {
+    let mut result = String::with_capacity(page.len());
+    let mut copied = 0;
+    let mut pos = 0;
+    while let Some(offset) = page[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(page, start) else {
+            pos = start + 1;
+            continue;
+        };
+        pos = tag.end;
+        let end = match tag.name.as_deref() {
+            // Comments, doctypes and processing instructions.
+            None => tag.end,
+            Some(name) if CLUTTER.contains(&name) && !tag.closing => element_end(page, tag.end, name).unwrap_or(page.len()),
+            Some(name) if CLUTTER.contains(&name) => tag.end,
+            Some(_) => continue,
+        };
+        result.push_str(&page[copied..start]);
+        copied = end;
+        pos = end;
+    }
+    result.push_str(&page[copied..]);
+    result
+}
+
+/// Where the element named `name` whose start tag ends at `from` ends, counting nested elements of the same name.
+fn element_end(page: &str, from: usize, name: &str) -> Option<usize> {
+    let mut depth = 1;
+    let mut pos = from;
+    while let Some(offset) = page[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(page, start) else {
+            pos = start + 1;
+            continue;
+        };
+        pos = tag.end;
+        if tag.name.as_deref() != Some(name) || page[start..tag.end].ends_with("/>") {
+            continue;
+        }
+        if !tag.closing {
+            depth += 1;
+        } else if depth == 1 {
+            return Some(tag.end);
+        } else {
+            depth -= 1;
+        }
+    }
+    None
+}
+
+/// The content of the container with the highest score: the text directly inside it, plus
+/// half of that of the containers right inside it. Text in links doesn't count, so link
+/// lists score low.
+fn best_container(page: &str) -> (usize, usize) {
+    // The open containers, with their name and where their content starts.
+    let mut open: Vec<(String, usize)> = Vec::new();
+    let mut ranges: HashMap<usize, usize> = HashMap::new();
+    let mut scores: HashMap<usize, f64> = HashMap::new();
+    let mut links = 0;
+    let mut pos = 0;
+    let mut text_start = 0;
+    let mut add_text = |open: &[(String, usize)], text: &str, links: usize| {
+        if links > 0 {
+            return;
+        }
+        let score = text.split_whitespace().map(str::len).sum::<usize>() as f64 + text.matches(',').count() as f64 * 10.0;
+        for (depth, (_, start)) in open.iter().rev().take(2).enumerate() {
+            *scores.entry(*start).or_default() += score / (depth + 1) as f64;
+        }
+    };
+    while let Some(offset) = page[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(page, start) else {
+            pos = start + 1;
+            continue;
+        };
+        add_text(&open, &page[text_start..start], links);
+        pos = tag.end;
+        text_start = tag.end;
+        let Some(name) = tag.name.as_deref() else { continue };
+        match (name, tag.closing) {
+            ("a", false) => links += 1,
+            ("a", true) => links = links.saturating_sub(1),
+            (name, false) if CONTAINERS.contains(&name) && !page[start..tag.end].ends_with("/>") => {
+                open.push((name.to_string(), tag.end));
+            }
+            (name, true) if CONTAINERS.contains(&name) => {
+                // Elements left open inside this one end with it.
+                if let Some(index) = open.iter().rposition(|(open_name, _)| open_name == name) {
+                    for (_, content_start) in open.drain(index..) {
+                        ranges.insert(content_start, start);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    add_text(&open, &page[text_start..], links);
+    for (_, content_start) in open {
+        ranges.insert(content_start, page.len());
+    }
+    scores
+        .into_iter()
+        .filter(|(start, _)| ranges.contains_key(start))
+        .max_by(|(start_a, a), (start_b, b)| a.total_cmp(b).then(start_b.cmp(start_a)))
+        .map_or((0, page.len()), |(start, _)| (start, ranges[&start]))
+}
+
+/// Drops wrapper elements and all attributes except links, image sources and titles, making
+/// links and sources absolute.
+fn clean(html: &str, base: &Url) -> String {
+    let mut result = String::with_capacity(html.len());
+    let mut copied = 0;
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(html, start) else {
+            pos = start + 1;
+            continue;
+        };
+        result.push_str(&html[copied..start]);
+        copied = tag.end;
+        pos = tag.end;
+        let Some(name) = tag.name.as_deref().filter(|name| !WRAPPERS.contains(name)) else { continue };
+        if tag.closing {
+            result.push_str(&format!("</{}>", name));
+            continue;
+        }
+        let original = &html[start..tag.end];
+        result.push('<');
+        result.push_str(name);
+        for attribute_name in KEPT_ATTRIBUTES {
+            let Some(value) = attribute(original, attribute_name) else { continue };
+            let value = match *attribute_name {
+                "href" | "src" => base.join(&decode_entities(value)).map_or_else(|_| value.to_string(), |url| escape(url.as_str())),
+                _ => value.to_string(),
+            };
+            result.push_str(&format!(" {}=\"{}\"", attribute_name, value.replace('"', "&quot;")));
+        }
+        result.push_str(if original.ends_with("/>") { " />" } else { ">" });
+    }
+    result.push_str(&html[copied..]);
+    result
+}
+
+/// Converts the HTML of an article to Markdown.
+fn to_markdown(html: &str) -> String {
+    let mut markdown = String::new();
+    let mut quote_depth = 0;
+    // The lists the current item is in, with the number of the next item of ordered ones.
+    let mut lists: Vec<Option<usize>> = Vec::new();
+    let mut link_targets: Vec<Option<String>> = Vec::new();
+    let mut pos = 0;
+    let break_block = |markdown: &mut String, quote_depth: usize| {
+        let trimmed = markdown.trim_end_matches([' ', '>']).trim_end().len();
+        markdown.truncate(trimmed);
+        if !markdown.is_empty() {
+            markdown.push_str("\n\n");
+        }
+        markdown.push_str(&"> ".repeat(quote_depth));
+    };
+    while pos < html.len() {
+        let start = html[pos..].find('<').map_or(html.len(), |offset| pos + offset);
+        let text = collapse_whitespace(&decode_entities(&html[pos..start]));
+        let at_line_start = markdown.is_empty() || markdown.ends_with('\n') || markdown.ends_with("> ") || markdown.ends_with("- ");
+        markdown.push_str(if at_line_start { text.trim_start() } else { &text });
+        if start == html.len() {
+            break;
+        }
+        let Some(tag) = Tag::parse(html, start) else {
+            markdown.push('<');
+            pos = start + 1;
+            continue;
+        };
+        pos = tag.end;
+        let Some(name) = tag.name.as_deref() else { continue };
+        let original = &html[start..tag.end];
+        match (name, tag.closing) {
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                break_block(&mut markdown, quote_depth);
+                let level = name[1..].parse().unwrap_or(1);
+                markdown.push_str(&format!("{} ", "#".repeat(level)));
+            }
+            ("pre", false) => {
+                let end = find_closing_tag(html, tag.end, "pre").unwrap_or(html.len());
+                let inner = &html[tag.end..end];
+                let inner = &inner[..inner.rfind("</").unwrap_or(inner.len())];
+                break_block(&mut markdown, quote_depth);
+                markdown.push_str(&format!("```\n{}\n```", decode_entities(&strip_tags(inner)).trim_matches('\n')));
+                break_block(&mut markdown, quote_depth);
+                pos = end;
+            }
+            ("blockquote", false) => {
+                quote_depth += 1;
+                break_block(&mut markdown, quote_depth);
+            }
+            ("blockquote", true) => {
+                quote_depth = quote_depth.saturating_sub(1);
+                break_block(&mut markdown, quote_depth);
+            }
+            ("ul" | "ol", false) => {
+                if lists.is_empty() {
+                    break_block(&mut markdown, quote_depth);
+                }
+                lists.push(if name == "ol" { Some(1) } else { None });
+            }
+            ("ul" | "ol", true) => {
+                lists.pop();
+                break_block(&mut markdown, quote_depth);
+            }
+            ("li", false) => {
+                let trimmed = markdown.trim_end_matches(' ').len();
+                markdown.truncate(trimmed);
+                if !markdown.is_empty() && !markdown.ends_with('\n') {
+                    markdown.push('\n');
+                }
+                markdown.push_str(&"> ".repeat(quote_depth));
+                markdown.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        markdown.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => markdown.push_str("- "),
+                }
+            }
+            ("p" | "table" | "figure" | "figcaption" | "dl" | "dt" | "dd" | "article" | "main" | "hr", _) => {
+                break_block(&mut markdown, quote_depth);
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => break_block(&mut markdown, quote_depth),
+            ("tr", true) | ("br", _) => {
+                markdown.push('\n');
+                markdown.push_str(&"> ".repeat(quote_depth));
+            }
+            ("td" | "th", false) if !markdown.ends_with('\n') && !markdown.is_empty() => markdown.push_str(" | "),
+            ("strong" | "b", _) => markdown.push_str("**"),
+            ("em" | "i", _) => markdown.push('*'),
+            ("code", _) => markdown.push('`'),
+            ("a", false) => {
+                let href = attribute(original, "href").map(decode_entities);
+                if href.is_some() {
+                    markdown.push('[');
+                }
+                link_targets.push(href);
+            }
+            ("a", true) => {
+                if let Some(href) = link_targets.pop().flatten() {
+                    markdown.push_str(&format!("]({})", href));
+                }
+            }
+            ("img", _) => {
+                let alt = attribute(original, "alt").map(decode_entities).unwrap_or_default();
+                if let Some(src) = attribute(original, "src") {
+                    markdown.push_str(&format!("![{}]({})", alt, decode_entities(src)));
+                }
+            }
+            _ => {}
+        }
+    }
+    let lines: Vec<&str> = markdown.lines().map(str::trim_end).collect();
+    let mut result = lines.join("\n");
+    while result.contains("\n\n\n") {
+        result = result.replace("\n\n\n", "\n\n");
+    }
+    result.trim().to_string()
+}
+
+fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut copied = 0;
+    let mut pos = 0;
+    while let Some(offset) = html[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(html, start) else {
+            pos = start + 1;
+            continue;
+        };
+        text.push_str(&html[copied..start]);
+        copied = tag.end;
+        pos = tag.end;
+    }
+    text.push_str(&html[copied..]);
+    text
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for (i, word) in text.split(|c: char| c.is_whitespace()).enumerate() {
+        if i > 0 && !result.ends_with(' ') {
+            result.push(' ');
+        }
+        result.push_str(word);
+    }
+    result
+}
+
+/// Decodes the named entities common in articles and numeric character references.
+fn decode_entities(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest[..rest.len().min(12)].find(';') else {
+            result.push('&');
+            rest = &rest[1..];
+            continue;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            "ndash" => Some('–'),
+            "mdash" => Some('—'),
+            "hellip" => Some('…'),
+            "lsquo" => Some('‘'),
+            "rsquo" => Some('’'),
+            "ldquo" => Some('“'),
+            "rdquo" => Some('”'),
+            _ => match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()).and_then(char::from_u32),
+            },
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,270 @@
+use super::html::{find_closing_tag, Tag};
+use super::{Document, FormatOptions};
+use crate::placeholder;
+
+/// Inline elements whose content is code rather than text (XLIFF 1.2 native-code elements).
+const CODE_ELEMENTS: &[&str] = &["ph", "bpt", "ept", "it"];
+
+/// The state given to machine-translated units.
+const TRANSLATED: &str = "translated";
+
+/// Fills in the `<target>` of XLIFF 1.2 `<trans-unit>`s and XLIFF 2.0 `<segment>`s that have no
+/// translation yet.
+///
+/// A 1.2 unit is translated when its target is missing or empty or has the state `new` or
+/// `needs-translation`, unless the unit is marked `translate="no"`; a 2.0 segment when its
+/// target is missing or empty or its state is `initial`. The new targets get the state
+/// `translated`, and the file's target language is set when `options` has one. Sources are
+/// sent with their inline markup in HTML mode; placeholder elements such as `<x/>` or
+/// `<ph>…</ph>` are protected.
+pub fn parse(content: &str, options: &FormatOptions) -> Document {
+    let mut document = Document::new();
+    let mut verbatim_start = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = content[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(content, start).filter(|tag| !tag.closing) else {
+            pos = start + 1;
+            continue;
+        };
+        pos = tag.end;
+        let opening = &content[start..tag.end];
+
+        match tag.name.as_deref() {
+            Some(name @ ("file" | "xliff")) => {
+                let (source_attribute, target_attribute) =
+                    if name == "file" { ("source-language", "target-language") } else { ("srcLang", "trgLang") };
+                let target = options.target_language.as_deref();
+                if let Some(target) = target.filter(|_| attribute(opening, source_attribute).is_some()) {
+                    document.push_verbatim(&content[verbatim_start..start]);
+                    document.push_verbatim(&set_attribute(opening, target_attribute, target));
+                    verbatim_start = tag.end;
+                }
+            }
+            Some(name @ ("trans-unit" | "segment")) => {
+                let Some(end) = find_closing_tag(content, tag.end, name) else {
+                    continue;
+                };
+                document.push_verbatim(&content[verbatim_start..start]);
+                let unit = &content[start..end];
+                if !push_unit(&mut document, unit, name == "segment") {
+                    document.push_verbatim(unit);
+                }
+                verbatim_start = end;
+                pos = end;
+            }
+            _ => {}
+        }
+    }
+    document.push_verbatim(&content[verbatim_start..]);
+
+    document
+}
+
+/// An element inside a unit.
+pub(super) struct Element {
+    pub(super) start: usize,
+    /// End of the start tag.
+    pub(super) content_start: usize,
+    /// Start of the end tag.
+    pub(super) content_end: usize,
+    pub(super) end: usize,
+}
+
+impl Element {
+    pub(super) fn find(unit: &str, name: &str) -> Option<Element> {
+        let mut pos = 0;
+        while let Some(offset) = unit[pos..].find('<') {
+            let start = pos + offset;
+            let Some(tag) = Tag::parse(unit, start) else {
+                pos = start + 1;
+                continue;
+            };
+            pos = tag.end;
+            if tag.closing || tag.name.as_deref() != Some(name) {
+                continue;
+            }
+            if unit[..tag.end].ends_with("/>") {
+                return Some(Element { start, content_start: tag.end, content_end: tag.end, end: tag.end });
+            }
+            let end = find_closing_tag(unit, tag.end, name)?;
+            let content_end = unit[..end].rfind("</")?;
+            return Some(Element { start, content_start: tag.end, content_end, end });
+        }
+        None
+    }
+}
+
+/// Adds a unit with its target filled in, or returns false if it doesn't need translating.
+fn push_unit(document: &mut Document, unit: &str, is_segment: bool) -> bool {
+    let Some(opening_end) = Tag::parse(unit, 0).map(|tag| tag.end) else {
+        return false;
+    };
+    let opening = &unit[..opening_end];
+    let Some(source) = Element::find(unit, "source") else {
+        return false;
+    };
+    let target = Element::find(unit, "target");
+    let target_is_empty = target.as_ref().is_none_or(|t| unit[t.content_start..t.content_end].trim().is_empty());
+
+    let needs_translation = if is_segment {
+        target_is_empty || attribute(opening, "state") == Some("initial")
+    } else {
+        let state = target.as_ref().and_then(|t| attribute(&unit[t.start..t.content_start], "state"));
+        attribute(opening, "translate") != Some("no")
+            && (target_is_empty || matches!(state, Some("new" | "needs-translation")))
+    };
+    if !needs_translation {
+        return false;
+    }
+
+    if is_segment {
+        document.push_verbatim(&set_attribute(opening, "state", TRANSLATED));
+    } else {
+        document.push_verbatim(opening);
+    }
+    let (text, protected) = protect_codes(&unit[source.content_start..source.content_end]);
+
+    match target {
+        Some(target) => {
+            document.push_verbatim(&unit[opening_end..target.start]);
+            let target_tag = unit[target.start..target.content_start].trim_end_matches("/>").trim_end_matches('>');
+            let target_tag = format!("{}>", target_tag.trim_end());
+            if is_segment {
+                document.push_verbatim(&target_tag);
+            } else {
+                document.push_verbatim(&set_attribute(&target_tag, "state", TRANSLATED));
+            }
+            document.push_text(&text, protected);
+            document.push_verbatim("</target>");
+            document.push_verbatim(&unit[target.end..]);
+        }
+        None => {
+            // Place the new target after the source, indented the same way.
+            let line_start = unit[..source.start].rfind('\n').map_or(0, |i| i + 1);
+            let indent = &unit[line_start..source.start];
+            let indent = if indent.trim().is_empty() { indent } else { "" };
+            document.push_verbatim(&unit[opening_end..source.end]);
+            let state = if is_segment { String::new() } else { format!(" state=\"{TRANSLATED}\"") };
+            document.push_verbatim(&format!("\n{indent}<target{state}>"));
+            document.push_text(&text, protected);
+            document.push_verbatim("</target>");
+            document.push_verbatim(&unit[source.end..]);
+        }
+    }
+    true
+}
+
+/// Replaces self-closing inline elements and native-code elements with placeholder tokens.
+fn protect_codes(text: &str) -> (String, Vec<String>) {
+    let mut result = String::with_capacity(text.len());
+    let mut protected = Vec::new();
+    let mut pos = 0;
+    let mut copied = 0;
+
+    while let Some(offset) = text[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(text, start) else {
+            pos = start + 1;
+            continue;
+        };
+        let end = match tag.name.as_deref() {
+            _ if tag.closing => None,
+            _ if text[..tag.end].ends_with("/>") => Some(tag.end),
+            Some(name) if CODE_ELEMENTS.contains(&name) => find_closing_tag(text, tag.end, name),
+            _ => None,
+        };
+        pos = end.unwrap_or(tag.end);
+        if let Some(end) = end {
+            result.push_str(&text[copied..start]);
+            result.push_str(&placeholder::token(protected.len()));
+            protected.push(text[start..end].to_string());
+            copied = end;
+        }
+    }
+    result.push_str(&text[copied..]);
+
+    (result, protected)
+}
+
+/// The value of an attribute in a start tag.
+pub(super) fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let (start, end) = attribute_value_range(tag, name)?;
+    Some(&tag[start..end])
+}
+
+/// The byte range of an attribute's value in a start tag, without the quotes.
+fn attribute_value_range(tag: &str, name: &str) -> Option<(usize, usize)> {
+    let mut pos = 0;
+    while let Some(offset) = tag[pos..].find(name) {
+        let start = pos + offset;
+        pos = start + name.len();
+        let preceded_by_space = tag[..start].ends_with(char::is_whitespace);
+        let rest = tag[pos..].trim_start();
+        let Some(value) = rest.strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        let Some(quote) = value.chars().next().filter(|c| matches!(c, '"' | '\'')) else {
+            continue;
+        };
+        if !preceded_by_space {
+            continue;
+        }
+        let value_start = tag.len() - value.len() + 1;
+        let value_end = value_start + tag[value_start..].find(quote)?;
+        return Some((value_start, value_end));
+    }
+    None
+}
+
+/// Sets an attribute in a start tag, adding it before the closing `>` if it is missing.
+pub(super) fn set_attribute(tag: &str, name: &str, value: &str) -> String {
+    match attribute_value_range(tag, name) {
+        Some((start, end)) => format!("{}{}{}", &tag[..start], value, &tag[end..]),
+        None => {
+            let close = if tag.ends_with("/>") { tag.len() - 2 } else { tag.len() - 1 };
+            let head = tag[..close].trim_end();
+            format!("{head} {name}=\"{value}\"{}", &tag[close..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    fn options(target: Option<&str>) -> FormatOptions {
+        FormatOptions { target_language: target.map(str::to_string), ..FormatOptions::default() }
+    }
+
+    #[test]
+    fn translated_units_are_kept_byte_for_byte() {
+        let xliff = "<xliff version=\"1.2\">\n  <file source-language=\"en\" target-language=\"hu\">\n    <body>\n      <trans-unit id=\"1\">\n        <source>Hello</source>\n        <target state=\"final\">Szia</target>\n      </trans-unit>\n      <trans-unit id=\"2\" translate=\"no\">\n        <source>ACME</source>\n      </trans-unit>\n    </body>\n  </file>\n</xliff>\n";
+        let document = parse(xliff, &options(None));
+        assert!(document.segments().is_empty());
+        assert_eq!(round_trip(&document), xliff);
+    }
+
+    #[test]
+    fn missing_target_is_added_after_the_source() {
+        let xliff = "<file source-language=\"en\" datatype=\"plaintext\">\n  <trans-unit id=\"1\">\n    <source>Click <x id=\"1\"/> to <b>save</b>.</source>\n  </trans-unit>\n</file>\n";
+        let document = parse(xliff, &options(Some("hu")));
+        assert_eq!(document.texts(), ["Click <x id=\"1\"/> to <b>save</b>."]);
+        assert_eq!(
+            round_trip(&document),
+            "<file source-language=\"en\" datatype=\"plaintext\" target-language=\"hu\">\n  <trans-unit id=\"1\">\n    <source>Click <x id=\"1\"/> to <b>save</b>.</source>\n    <target state=\"translated\">Click <x id=\"1\"/> to <b>save</b>.</target>\n  </trans-unit>\n</file>\n"
+        );
+    }
+
+    #[test]
+    fn xliff_2_segments_are_filled_in() {
+        let xliff = "<xliff version=\"2.0\" srcLang=\"en\">\n<unit id=\"u1\"><segment state=\"initial\"><source>Good <ph id=\"1\"/>morning</source><target/></segment></unit>\n</xliff>\n";
+        let document = parse(xliff, &options(Some("de")));
+        assert_eq!(
+            round_trip(&document),
+            "<xliff version=\"2.0\" srcLang=\"en\" trgLang=\"de\">\n<unit id=\"u1\"><segment state=\"translated\"><source>Good <ph id=\"1\"/>morning</source><target>Good <ph id=\"1\"/>morning</target></segment></unit>\n</xliff>\n"
+        );
+    }
+}
@@ -0,0 +1,275 @@
+use super::html::Tag;
+use super::xliff::attribute;
+use super::{Document, FormatOptions};
+use crate::error::TranslatorError;
+use crate::placeholder;
+
+/// Translates the text of a generic XML document, keeping its structure and attributes as they are.
+///
+/// Without selectors every text node is a segment of its own. With XPath `selectors`, only the
+/// content of the elements they match is translated, each element as one segment with the
+/// markup inside it replaced by placeholders. Elements marked `translate="no"`, or with an
+/// `xml:lang` other than the source language, are skipped along with their content.
+pub fn parse(content: &str, options: &FormatOptions) -> Result<Document, TranslatorError> {
+    let selectors = options.select_xpath.iter().map(|selector| Selector::parse(selector)).collect::<Result<Vec<_>, _>>()?;
+    let source = options.source_language.as_deref();
+    let mut document = Document::new();
+    // The open elements, with whether their content may be translated.
+    let mut stack: Vec<(String, String, bool)> = Vec::new();
+    let mut pos = 0;
+    while pos < content.len() {
+        let Some(offset) = content[pos..].find('<') else {
+            push_text_node(&mut document, &content[pos..], &stack, &selectors);
+            break;
+        };
+        let start = pos + offset;
+        push_text_node(&mut document, &content[pos..start], &stack, &selectors);
+        if content[start..].starts_with("<![CDATA[") {
+            let end = content[start..].find("]]>").map_or(content.len(), |end| start + end + 3);
+            document.push_verbatim(&content[start..end]);
+            pos = end;
+            continue;
+        }
+        let Some(tag) = Tag::parse(content, start) else {
+            document.push_verbatim("<");
+            pos = start + 1;
+            continue;
+        };
+        let opening = &content[start..tag.end];
+        document.push_verbatim(opening);
+        pos = tag.end;
+        if tag.name.is_none() || opening.ends_with("/>") {
+            continue;
+        }
+        if tag.closing {
+            stack.pop();
+            continue;
+        }
+        let name = tag_name(opening).to_string();
+        let translatable = stack.last().is_none_or(|(_, _, translatable)| *translatable) && is_translatable(opening, source);
+        stack.push((name.clone(), opening.to_string(), translatable));
+        let selected = translatable && selectors.iter().any(|selector| selector.matches(&stack));
+        if !selected {
+            continue;
+        }
+        // The whole content of a selected element is one segment.
+        let Some((content_end, end)) = element_end(content, tag.end, &name) else {
+            continue;
+        };
+        let (text, protected) = protect_markup(&content[tag.end..content_end], source);
+        document.push_text(&text, protected);
+        document.push_verbatim(&content[content_end..end]);
+        stack.pop();
+        pos = end;
+    }
+    Ok(document)
+}
+
+/// Adds text between tags: a segment when translating every text node inside the root element.
+fn push_text_node(document: &mut Document, text: &str, stack: &[(String, String, bool)], selectors: &[Selector]) {
+    match stack.last() {
+        Some((_, _, true)) if selectors.is_empty() => document.push_text(text, Vec::new()),
+        _ => document.push_verbatim(text),
+    }
+}
+
+/// Whether an element's own attributes allow translating it: it isn't marked `translate="no"`
+/// and its `xml:lang`, if any, is the source language.
+fn is_translatable(opening: &str, source: Option<&str>) -> bool {
+    let language_matches = match (attribute(opening, "xml:lang"), source) {
+        (Some(language), Some(source)) => {
+            let primary = |language: &str| language.split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+            primary(language) == primary(source)
+        }
+        _ => true,
+    };
+    attribute(opening, "translate") != Some("no") && language_matches
+}
+
+/// The element name of a start or end tag as written, with its namespace prefix.
+fn tag_name(tag: &str) -> &str {
+    let name = tag.trim_start_matches('<').trim_start_matches('/');
+    &name[..name.find(|c: char| c.is_whitespace() || c == '/' || c == '>').unwrap_or(name.len())]
+}
+
+/// Where the content of the element named `name` whose start tag ends at `from` ends, and
+/// where its end tag ends; elements of the same name may be nested inside it.
+fn element_end(content: &str, from: usize, name: &str) -> Option<(usize, usize)> {
+    let mut depth = 1;
+    let mut pos = from;
+    while let Some(offset) = content[pos..].find('<') {
+        let start = pos + offset;
+        let Some(tag) = Tag::parse(content, start) else {
+            pos = start + 1;
+            continue;
+        };
+        pos = tag.end;
+        if tag.name.is_none() || tag_name(&content[start..tag.end]) != name {
+            continue;
+        }
+        if tag.closing {
+            depth -= 1;
+            if depth == 0 {
+                return Some((start, tag.end));
+            }
+        } else if !content[start..tag.end].ends_with("/>") {
+            depth += 1;
+        }
+    }
+    None
+}
+
+/// Replaces the tags, comments and CDATA sections in the content of a selected element with
+/// placeholder tokens, and the elements inside it that aren't translated with their content.
+fn protect_markup(content: &str, source: Option<&str>) -> (String, Vec<String>) {
+    let mut text = String::new();
+    let mut protected: Vec<String> = Vec::new();
+    let mut pos = 0;
+    while let Some(offset) = content[pos..].find('<') {
+        let start = pos + offset;
+        let end = if content[start..].starts_with("<![CDATA[") {
+            content[start..].find("]]>").map_or(content.len(), |end| start + end + 3)
+        } else {
+            let Some(tag) = Tag::parse(content, start) else {
+                text.push_str(&content[pos..=start]);
+                pos = start + 1;
+                continue;
+            };
+            let opening = &content[start..tag.end];
+            let skipped = tag.name.is_some() && !tag.closing && !opening.ends_with("/>") && !is_translatable(opening, source);
+            match element_end(content, tag.end, tag_name(opening)).filter(|_| skipped) {
+                Some((_, end)) => end,
+                None => tag.end,
+            }
+        };
+        text.push_str(&content[pos..start]);
+        text.push_str(&placeholder::token(protected.len()));
+        protected.push(content[start..end].to_string());
+        pos = end;
+    }
+    text.push_str(&content[pos..]);
+    (text, protected)
+}
+
+/// One step of an XPath selector.
+#[derive(Debug)]
+struct Step {
+    /// `//` rather than `/` before it: any number of levels may come in between.
+    descendant: bool,
+    /// The element name, or `*` for any.
+    name: String,
+    /// An `[@name]` or `[@name='value']` predicate.
+    attribute: Option<(String, Option<String>)>,
+}
+
+/// An XPath location path matched against the open elements.
+#[derive(Debug)]
+struct Selector {
+    steps: Vec<Step>,
+}
+
+impl Selector {
+    /// Parses an absolute location path such as `/book/chapter/title`, `//note[@type='public']`
+    /// or `//p/text()`; the trailing `text()` is implied.
+    fn parse(selector: &str) -> Result<Self, TranslatorError> {
+        let invalid = |reason: &str| TranslatorError::from(format!("Invalid XPath {:?}: {}", selector, reason));
+        let selector = selector.trim();
+        let selector = selector.strip_suffix("/text()").unwrap_or(selector);
+        if !selector.starts_with('/') {
+            return Err(invalid("it must start with '/' or '//'"));
+        }
+        let mut steps = Vec::new();
+        let mut rest = selector;
+        while !rest.is_empty() {
+            let descendant = rest.starts_with("//");
+            rest = rest.trim_start_matches('/');
+            let end = step_end(rest);
+            let step = &rest[..end];
+            rest = &rest[end..];
+            let (name, predicate) = match step.split_once('[') {
+                Some((name, predicate)) => (name, Some(predicate.strip_suffix(']').ok_or_else(|| invalid("a '[' is never closed"))?)),
+                None => (step, None),
+            };
+            if name.is_empty() {
+                return Err(invalid("an element name is missing"));
+            }
+            let attribute = match predicate.map(str::trim) {
+                None => None,
+                Some(predicate) => {
+                    let predicate = predicate.strip_prefix('@').ok_or_else(|| invalid("only '[@name]' and '[@name='value']' predicates are supported"))?;
+                    Some(match predicate.split_once('=') {
+                        Some((name, value)) => (name.trim().to_string(), Some(value.trim().trim_matches(['\'', '"']).to_string())),
+                        None => (predicate.to_string(), None),
+                    })
+                }
+            };
+            steps.push(Step { descendant, name: name.to_string(), attribute });
+        }
+        Ok(Self { steps })
+    }
+
+    fn matches(&self, stack: &[(String, String, bool)]) -> bool {
+        matches_steps(&self.steps, stack)
+    }
+}
+
+/// The length of the step at the start of `text`, up to the next `/` outside of a predicate.
+fn step_end(text: &str) -> usize {
+    let mut in_predicate = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '[' => in_predicate = true,
+            ']' => in_predicate = false,
+            '/' if !in_predicate => return i,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// Whether the `steps` match the elements of `stack`, from the outermost one.
+fn matches_steps(steps: &[Step], stack: &[(String, String, bool)]) -> bool {
+    let Some(step) = steps.first() else { return stack.is_empty() };
+    let step_matches = |(name, opening, _): &(String, String, bool)| {
+        (step.name == "*" || step.name == *name)
+            && step.attribute.as_ref().is_none_or(|(attribute_name, value)| match attribute(opening, attribute_name) {
+                Some(actual) => value.as_deref().is_none_or(|value| actual == value),
+                None => false,
+            })
+    };
+    // A `//` step may skip any number of elements, a `/` step must match the next one.
+    let skippable = if step.descendant { stack.len() } else { stack.len().min(1) };
+    (0..skippable).any(|skipped| step_matches(&stack[skipped]) && matches_steps(&steps[1..], &stack[skipped + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    const DOCUMENT: &str = "<?xml version=\"1.0\"?>\n<book><title>Hello</title><code translate=\"no\">a &lt; b</code><p xml:lang=\"de\">Hallo</p><![CDATA[raw]]></book>\n";
+
+    #[test]
+    fn xml_round_trips_and_skips_untranslated_elements() {
+        let options = FormatOptions { source_language: Some("en".to_string()), ..FormatOptions::default() };
+        let document = parse(DOCUMENT, &options).unwrap();
+        assert_eq!(document.texts(), ["Hello"]);
+        assert_eq!(round_trip(&document), DOCUMENT);
+    }
+
+    #[test]
+    fn selected_elements_keep_their_markup_and_skip_translate_no() {
+        let options = FormatOptions { select_xpath: vec!["//p[@class='note']".to_string()], ..FormatOptions::default() };
+        let content = "<doc><p class=\"note\">Use <b>bold</b> and <i translate=\"no\">this name</i>.</p><p>Other</p></doc>";
+        let document = parse(content, &options).unwrap();
+        assert_eq!(document.segments(), ["Use ⟦0⟧bold⟦1⟧ and ⟦2⟧."]);
+        assert_eq!(document.texts(), ["Use <b>bold</b> and <i translate=\"no\">this name</i>."]);
+        assert_eq!(round_trip(&document), content);
+    }
+
+    #[test]
+    fn invalid_selectors_are_rejected() {
+        let options = FormatOptions { select_xpath: vec!["p[class]".to_string()], ..FormatOptions::default() };
+        assert!(parse("<p/>", &options).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+use super::{json, Document};
+use crate::placeholder;
+
+/// Plain scalars YAML reads as booleans or null rather than strings.
+const LITERALS: &[&str] = &["true", "false", "yes", "no", "on", "off", "null", "~"];
+
+/// Translates the string values of a YAML locale file such as `en.yml`.
+///
+/// The file is scanned line by line, so keys, comments, anchors and the layout are kept as
+/// they are. Plain, quoted and block scalars are translated; interpolation placeholders like
+/// `%{count}` and `{name}` are protected. Flow collections (`[…]`, `{…}`) are left untouched.
+pub fn parse(content: &str) -> Document {
+    let mut document = Document::new();
+    let mut lines = content.split_inclusive('\n').peekable();
+
+    while let Some(line) = lines.next() {
+        let body = line.trim_end_matches(['\n', '\r']);
+        let trimmed = body.trim_start();
+        let indent = body.len() - trimmed.len();
+        if trimmed.is_empty() || trimmed.starts_with(['#', '%']) || trimmed.starts_with("---") || trimmed.starts_with("...") {
+            document.push_verbatim(line);
+            continue;
+        }
+
+        let (prefix, value) = body.split_at(value_start(body, indent));
+        document.push_verbatim(prefix);
+        if value.starts_with(['|', '>']) {
+            document.push_verbatim(&line[prefix.len()..]);
+            let mut block = Vec::new();
+            while let Some(line) = lines.next_if(|line| {
+                let body = line.trim_end_matches(['\n', '\r']);
+                body.trim().is_empty() || body.len() - body.trim_start().len() > indent
+            }) {
+                block.push(line);
+            }
+            push_block_scalar(&mut document, &block, value.starts_with('>'));
+            continue;
+        }
+        push_scalar(&mut document, value);
+        document.push_verbatim(&line[body.len()..]);
+    }
+
+    document
+}
+
+/// Where the value starts on a line, after any `- ` list markers and `key:`.
+fn value_start(body: &str, indent: usize) -> usize {
+    let mut start = indent;
+    loop {
+        let rest = &body[start..];
+        if rest == "-" {
+            return body.len();
+        }
+        match rest.strip_prefix("- ") {
+            Some(item) => start = body.len() - item.trim_start().len(),
+            None => break,
+        }
+    }
+
+    let rest = &body[start..];
+    let key_len = match rest.chars().next() {
+        Some(quote @ ('"' | '\'')) => rest[1..].find(quote).map(|end| end + 2),
+        Some('[' | '{') => None,
+        _ => rest.find(": ").or_else(|| rest.ends_with(':').then(|| rest.len() - 1)),
+    };
+    match key_len {
+        Some(len) if rest[len..].trim_start().starts_with(':') && !rest[..len].contains(" #") => {
+            let after_colon = &rest[len..].trim_start()[1..];
+            body.len() - after_colon.trim_start().len()
+        }
+        _ => start,
+    }
+}
+
+/// Adds a scalar value, with any trailing comment kept verbatim.
+fn push_scalar(document: &mut Document, value: &str) {
+    if value.starts_with(['&', '!']) {
+        // An anchor or a tag before the actual value.
+        let end = value.find(' ').unwrap_or(value.len());
+        let next = value.len() - value[end..].trim_start().len();
+        document.push_verbatim(&value[..next]);
+        return push_scalar(document, &value[next..]);
+    }
+
+    match value.chars().next() {
+        Some('"') => match json::string_len(value) {
+            Some(len) => {
+                let (text, protected) = placeholder::protect_format_specifiers(&json::unescape(&value[1..len - 1]));
+                document.push_verbatim("\"");
+                document.push_escaped_text(&text, protected, json::escape);
+                document.push_verbatim(&value[len - 1..]);
+            }
+            None => document.push_verbatim(value),
+        },
+        Some('\'') => match single_quoted_len(value) {
+            Some(len) => {
+                let (text, protected) = placeholder::protect_format_specifiers(&value[1..len - 1].replace("''", "'"));
+                document.push_verbatim("'");
+                document.push_escaped_text(&text, protected, escape_single_quoted);
+                document.push_verbatim(&value[len - 1..]);
+            }
+            None => document.push_verbatim(value),
+        },
+        Some('*' | '[' | '{') | None => document.push_verbatim(value),
+        Some(_) => {
+            let end = value.find(" #").unwrap_or(value.len());
+            let text = value[..end].trim_end();
+            if LITERALS.contains(&text.to_ascii_lowercase().as_str()) {
+                document.push_verbatim(value);
+                return;
+            }
+            let (text, protected) = placeholder::protect_format_specifiers(text);
+            document.push_escaped_text(&text, protected, escape_plain);
+            document.push_verbatim(&value[value[..end].trim_end().len()..]);
+        }
+    }
+}
+
+/// Adds the lines of a `|` literal or `>` folded block scalar.
+///
+/// Literal blocks are translated line by line to keep their line breaks. The lines of a
+/// folded paragraph are joined into one, which reads the same once YAML folds it.
+fn push_block_scalar(document: &mut Document, block: &[&str], folded: bool) {
+    let mut lines = block.iter().peekable();
+    while let Some(line) = lines.next() {
+        let body = line.trim_end_matches(['\n', '\r']);
+        let text = body.trim_start();
+        if text.is_empty() {
+            document.push_verbatim(line);
+            continue;
+        }
+
+        let indent = &body[..body.len() - text.len()];
+        let mut paragraph = text.to_string();
+        while let Some(next) = lines.next_if(|next| folded && continues_paragraph(next, indent)) {
+            paragraph.push(' ');
+            paragraph.push_str(next.trim());
+        }
+        let eol = &line[body.len()..];
+        document.push_verbatim(indent);
+        let (paragraph, protected) = placeholder::protect_format_specifiers(&paragraph);
+        document.push_text(&paragraph, protected);
+        document.push_verbatim(if eol.is_empty() { "\n" } else { eol });
+    }
+}
+
+/// Whether a line of a folded block continues the paragraph indented by `indent`.
+fn continues_paragraph(line: &str, indent: &str) -> bool {
+    let body = line.trim_end_matches(['\n', '\r']);
+    body.strip_prefix(indent).is_some_and(|text| !text.is_empty() && !text.starts_with(char::is_whitespace))
+}
+
+/// The length of the single-quoted scalar `value` starts with, where `''` is an escaped quote.
+fn single_quoted_len(value: &str) -> Option<usize> {
+    let mut chars = value.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        if c == '\'' && chars.next_if(|&(_, c)| c == '\'').is_none() {
+            return Some(i + 1);
+        }
+    }
+    None
+}
+
+fn escape_single_quoted(text: &str) -> String {
+    text.replace('\'', "''")
+}
+
+/// Writes a translated plain scalar, quoting it if the translation would not read back as the
+/// same string.
+fn escape_plain(text: &str) -> String {
+    let needs_quotes = text.starts_with(|c: char| "-?:,[]{}#&*!|>'\"%@`".contains(c))
+        || text.ends_with(':')
+        || text.contains(": ")
+        || text.contains(" #")
+        || text.contains('\n')
+        || LITERALS.contains(&text.to_ascii_lowercase().as_str());
+    if needs_quotes && !text.is_empty() {
+        format!("\"{}\"", json::escape(text))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::round_trip;
+
+    #[test]
+    fn yaml_round_trips_with_comments_anchors_and_literals() {
+        let yaml = "# Locale\nen:\n  title: Welcome  # shown on top\n  quoted: \"It's %{count} items\"\n  single: 'Don''t go'\n  enabled: true\n  base: &base Hello\n  tags: [a, b]\n  list:\n    - First item\n";
+        let document = parse(yaml);
+        assert_eq!(document.texts(), ["Welcome", "It's %{count} items", "Don't go", "Hello", "First item"]);
+        assert_eq!(round_trip(&document), yaml);
+    }
+
+    #[test]
+    fn block_scalars_keep_literal_lines_and_join_folded_ones() {
+        let yaml = "literal: |\n  First line\n  second line\nfolded: >\n  One sentence\n  over two lines.\n\n  Next paragraph.\nafter: Done\n";
+        let document = parse(yaml);
+        assert_eq!(document.texts(), ["First line", "second line", "One sentence over two lines.", "Next paragraph.", "Done"]);
+        assert_eq!(
+            round_trip(&document),
+            "literal: |\n  First line\n  second line\nfolded: >\n  One sentence over two lines.\n\n  Next paragraph.\nafter: Done\n"
+        );
+    }
+
+    #[test]
+    fn translations_that_would_change_meaning_are_quoted() {
+        let document = parse("label: Status\n");
+        assert_eq!(document.render(&["Állapot: kész".to_string()]).0, "label: \"Állapot: kész\"\n");
+        assert_eq!(document.render(&["yes".to_string()]).0, "label: \"yes\"\n");
+    }
+}
@@ -0,0 +1,99 @@
+//! Fuzzy matching of paragraphs against the translation memory, so a paragraph that changed
+//! slightly since it was translated doesn't have to be translated again.
+
+use clap::ValueEnum;
+use std::collections::HashSet;
+
+/// How the similarity of two paragraphs is measured.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum FuzzyMetric {
+    /// Word-level edit distance: the share of words that have to be inserted, deleted or replaced.
+    #[default]
+    Levenshtein,
+    /// The share of character trigrams the two paragraphs have in common (Dice coefficient).
+    Trigram,
+}
+
+/// What is done with the translation of a similar paragraph.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum FuzzyAction {
+    /// Use it as the translation.
+    Reuse,
+    /// Use it, behind a '[[FUZZY MATCH n%]]' marker so it's reviewed.
+    #[default]
+    Review,
+}
+
+/// Settings of fuzzy matching.
+#[derive(Clone, Copy, Debug)]
+pub struct FuzzyMatching {
+    pub metric: FuzzyMetric,
+    /// Lowest similarity accepted, between 0 and 1.
+    pub threshold: f64,
+    pub action: FuzzyAction,
+}
+
+impl FuzzyMatching {
+    /// The similarity of two paragraphs between 0 (nothing in common) and 1 (the same).
+    pub fn similarity(&self, a: &str, b: &str) -> f64 {
+        match self.metric {
+            FuzzyMetric::Levenshtein => {
+                let a: Vec<&str> = a.split_whitespace().collect();
+                let b: Vec<&str> = b.split_whitespace().collect();
+                let longest = a.len().max(b.len());
+                if longest == 0 {
+                    return 1.0;
+                }
+                1.0 - levenshtein(&a, &b) as f64 / longest as f64
+            }
+            FuzzyMetric::Trigram => {
+                let (a, b) = (trigrams(a), trigrams(b));
+                if a.is_empty() && b.is_empty() {
+                    return 1.0;
+                }
+                2.0 * a.intersection(&b).count() as f64 / (a.len() + b.len()) as f64
+            }
+        }
+    }
+
+    /// Whether the lengths of two paragraphs are close enough for them to reach the threshold,
+    /// checked before the more expensive [`similarity`](Self::similarity).
+    pub fn may_match(&self, a: &str, b: &str) -> bool {
+        let (a, b) = (a.chars().count() as f64, b.chars().count() as f64);
+        a.min(b) >= self.threshold * a.max(b) * 0.5
+    }
+
+    /// The translation to use for a paragraph whose translation memory match has `similarity`.
+    pub fn apply(&self, translation: &str, similarity: f64) -> String {
+        match self.action {
+            FuzzyAction::Reuse => translation.to_string(),
+            FuzzyAction::Review => format!("{} {}", marker(similarity), translation),
+        }
+    }
+}
+
+/// The marker put in front of reused translations to be reviewed, e.g. `[[FUZZY MATCH 92%]]`.
+pub fn marker(similarity: f64) -> String {
+    format!("[[FUZZY MATCH {}%]]", (similarity * 100.0).floor())
+}
+
+/// Edit distance between two sequences.
+fn levenshtein<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, x) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, y) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(x != y);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// The character trigrams of the text, ignoring case.
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let chars: Vec<char> = text.to_lowercase().chars().collect();
+    chars.windows(3).map(|window| [window[0], window[1], window[2]]).collect()
+}
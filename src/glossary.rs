@@ -0,0 +1,172 @@
+//! Terminology that must be translated the same way throughout a document.
+//!
+//! Glossary terms are replaced with placeholder tokens before a chunk is sent and the tokens
+//! are replaced with the required target terms afterwards, so the translation engine can't
+//! pick a different word for them.
+
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::TranslatorError;
+use crate::placeholder;
+
+/// Kind of the glossary's placeholder tokens, e.g. `⟦g0⟧`.
+pub const TOKEN_KIND: &str = "g";
+
+/// Source terms and the target terms they must be translated to.
+#[derive(Debug, Clone)]
+pub struct Glossary {
+    /// Target terms by lower-case source term.
+    terms: HashMap<String, String>,
+    /// Matches any of the source terms, longest first, ignoring case.
+    pattern: Regex,
+}
+
+impl Glossary {
+    /// Builds a glossary from `(source term, target term)` pairs.
+    pub fn new(terms: impl IntoIterator<Item = (String, String)>) -> Result<Self, TranslatorError> {
+        let terms: HashMap<String, String> = terms
+            .into_iter()
+            .filter(|(source, _)| !source.trim().is_empty())
+            .map(|(source, target)| (source.trim().to_lowercase(), target.trim().to_string()))
+            .collect();
+        let mut sources: Vec<&String> = terms.keys().collect();
+        sources.sort_by_key(|source| std::cmp::Reverse(source.len()));
+        let alternatives: Vec<String> = sources.iter().map(|source| regex::escape(source)).collect();
+        let pattern = RegexBuilder::new(&alternatives.join("|")).case_insensitive(true).build()?;
+        Ok(Self { terms, pattern })
+    }
+
+    /// Loads a CSV file with a source term and the required target term on each line.
+    ///
+    /// Fields may be quoted; empty lines, lines starting with `#` and a `source,target`
+    /// header are skipped.
+    pub fn load(path: &Path) -> Result<Self, TranslatorError> {
+        let content = fs::read_to_string(path)?;
+        let mut terms = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let fields = split_csv_line(line);
+            let [source, target] = fields.as_slice() else {
+                return Err(TranslatorError::Parse(format!(
+                    "{}:{}: expected a source and a target term",
+                    path.display(),
+                    number + 1
+                )));
+            };
+            if number == 0 && source.eq_ignore_ascii_case("source") && target.eq_ignore_ascii_case("target") {
+                continue;
+            }
+            terms.push((source.clone(), target.clone()));
+        }
+        Self::new(terms)
+    }
+
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// Replaces the glossary terms in `text` with tokens, returning the text and the target terms
+    /// the tokens stand for. Terms only match whole words.
+    pub fn protect(&self, text: &str) -> (String, Vec<String>) {
+        if self.terms.is_empty() {
+            return (text.to_string(), Vec::new());
+        }
+        let mut result = String::with_capacity(text.len());
+        let mut targets = Vec::new();
+        let mut copied = 0;
+        let mut pos = 0;
+
+        while let Some(found) = self.pattern.find_at(text, pos) {
+            let before = text[..found.start()].chars().next_back();
+            let after = text[found.end()..].chars().next();
+            let joins_word = |edge: Option<char>, neighbour: Option<char>| {
+                edge.is_some_and(char::is_alphanumeric) && neighbour.is_some_and(char::is_alphanumeric)
+            };
+            let term = found.as_str();
+            let target = self.terms.get(&term.to_lowercase());
+            let Some(target) = target.filter(|_| {
+                !joins_word(term.chars().next(), before) && !joins_word(term.chars().next_back(), after)
+            }) else {
+                // Part of a longer word; look for a match starting at the next character.
+                pos = found.start() + term.chars().next().map_or(1, char::len_utf8);
+                continue;
+            };
+
+            result.push_str(&text[copied..found.start()]);
+            result.push_str(&placeholder::kind_token(TOKEN_KIND, targets.len()));
+            targets.push(target.clone());
+            copied = found.end();
+            pos = found.end();
+        }
+        result.push_str(&text[copied..]);
+
+        (result, targets)
+    }
+
+    /// Replaces the tokens of [`protect`](Self::protect) in a translation with the target terms.
+    ///
+    /// Terms whose token the translation dropped are appended, and their number returned.
+    pub fn restore(translation: &str, targets: &[String]) -> (String, usize) {
+        let (restored, missing) = placeholder::restore_kind(translation, TOKEN_KIND, targets);
+        (restored, missing.len())
+    }
+}
+
+/// Splits a CSV line into its fields, unquoting quoted ones.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => in_quotes = !in_quotes,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields.into_iter().map(|field| field.trim().to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glossary() -> Glossary {
+        Glossary::new([("pull request".to_string(), "beolvasztási kérelem".to_string()), ("pull".to_string(), "lehúzás".to_string())]).unwrap()
+    }
+
+    #[test]
+    fn terms_match_whole_words_ignoring_case_longest_first() {
+        let (text, targets) = glossary().protect("A Pull Request is no pulley, pull it.");
+        assert_eq!(text, "A ⟦g0⟧ is no pulley, ⟦g1⟧ it.");
+        assert_eq!(targets, ["beolvasztási kérelem", "lehúzás"]);
+    }
+
+    #[test]
+    fn restore_tolerates_mangled_tokens_and_appends_dropped_ones() {
+        let (_, targets) = glossary().protect("Open a pull request, then pull.");
+        assert_eq!(Glossary::restore("Nyiss egy ⟦G0⟧-t.", &targets), ("Nyiss egy beolvasztási kérelem-t. lehúzás".to_string(), 1));
+    }
+
+    #[test]
+    fn csv_fields_may_be_quoted() {
+        assert_eq!(split_csv_line(r#" "Hello, world" , "say ""hi""" "#), ["Hello, world", "say \"hi\""]);
+    }
+}
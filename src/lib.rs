@@ -0,0 +1,47 @@
+//! Reusable translation pipeline: text chunking and pluggable translation backends.
+//!
+//! The `text-translator` binary is a thin command-line wrapper around this library,
+//! so other Rust programs can embed the same pipeline directly.
+
+pub mod backend;
+pub mod cache;
+pub mod checkpoint;
+pub mod chunking;
+pub mod consistency;
+pub mod encoding;
+pub mod entities;
+pub mod error;
+pub mod failures;
+pub mod format;
+pub mod fuzzy;
+pub mod glossary;
+pub mod no_translate;
+pub mod output;
+pub mod placeholder;
+pub mod progress;
+pub mod prompt;
+pub mod quality;
+pub mod rate_limit;
+pub mod redact;
+pub mod review_sheet;
+pub mod tmx;
+pub mod translator;
+
+pub use cache::TranslationCache;
+pub use checkpoint::Checkpoint;
+pub use error::TranslatorError;
+pub use chunking::{pack_segments, split_into_chunks, truncate_at_char_boundary, Blocks, MAX_CHUNK_SIZE};
+pub use failures::FailureReport;
+pub use format::{Document, Format};
+pub use glossary::Glossary;
+pub use no_translate::NoTranslate;
+pub use output::ChunkWriter;
+pub use progress::Progress;
+pub use prompt::PromptTemplate;
+pub use rate_limit::RateLimiter;
+pub use redact::Redactor;
+pub use review_sheet::ReviewSheet;
+pub use tmx::Tmx;
+pub use backend::libretranslate::LibreTranslateClient;
+pub use backend::{Backend, BackendKind, BackendOptions, RetryPolicy};
+pub use translator::{check_language_pair, ContextParagraph, Detection, Language, TextFormat, Translator, AUTO_LANGUAGE};
@@ -0,0 +1,209 @@
+//! Text the user wants kept as it is: matches of do-not-translate patterns, interpolation
+//! placeholders such as `%s` or `{name}`, and spans between `<!-- notranslate -->` and
+//! `<!-- /notranslate -->` markers.
+//!
+//! Pattern matches and placeholders are replaced with tokens before a chunk is sent and restored
+//! afterwards. Marked spans are cut out of the document before it is split into segments.
+
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::entities;
+use crate::placeholder;
+use crate::TranslatorError;
+
+/// Kind of the placeholder tokens for pattern matches, e.g. `⟦n0⟧`.
+pub const TOKEN_KIND: &str = "n";
+
+/// Kind of the tokens for interpolation placeholders, e.g. `⟦p0⟧`.
+pub const PLACEHOLDER_KIND: &str = "p";
+
+/// Regular expressions whose matches are never translated, such as file paths or version numbers.
+#[derive(Debug, Clone, Default)]
+pub struct NoTranslate {
+    patterns: Vec<Regex>,
+    placeholders: bool,
+    entities: bool,
+}
+
+impl NoTranslate {
+    pub fn new(patterns: &[String]) -> Result<Self, regex::Error> {
+        let patterns = patterns.iter().map(|pattern| Regex::new(pattern)).collect::<Result<_, _>>()?;
+        Ok(Self { patterns, placeholders: false, entities: false })
+    }
+
+    /// Also protects printf, ICU and template placeholders such as `%s`, `{0}`, `{name}`,
+    /// `{{name}}` or `${name}`, which must come back exactly as they were.
+    pub fn with_placeholders(mut self, placeholders: bool) -> Self {
+        self.placeholders = placeholders;
+        self
+    }
+
+    /// Also protects named entities such as names, product names and acronyms (see [`entities`]).
+    pub fn with_entities(mut self, entities: bool) -> Self {
+        self.entities = entities;
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty() && !self.entities
+    }
+
+    /// Replaces the matches of any pattern in `text` with tokens, returning the text and the
+    /// originals the tokens stand for. Where matches overlap, the earliest (then longest) wins.
+    pub fn protect(&self, text: &str) -> (String, Vec<String>) {
+        match entities::pattern(text).filter(|_| self.entities) {
+            Some(entities) => {
+                let patterns: Vec<Regex> = self.patterns.iter().cloned().chain([entities]).collect();
+                protect_matches(&patterns, TOKEN_KIND, text)
+            }
+            None => protect_matches(&self.patterns, TOKEN_KIND, text),
+        }
+    }
+
+    /// Replaces the placeholders in `text` with tokens, if they are protected at all, returning
+    /// the text and the placeholders the tokens stand for.
+    pub fn protect_placeholders(&self, text: &str) -> (String, Vec<String>) {
+        if !self.placeholders {
+            return (text.to_string(), Vec::new());
+        }
+        placeholder::protect_format_specifiers_kind(text, PLACEHOLDER_KIND)
+    }
+
+    /// Puts the placeholders of [`protect_placeholders`](Self::protect_placeholders) back into a
+    /// translation.
+    ///
+    /// Unlike other protected text, a placeholder that went missing can't just be appended, as
+    /// its position matters: a lost, mangled or duplicated placeholder is an error.
+    pub fn restore_placeholders(translation: &str, originals: &[String]) -> Result<String, TranslatorError> {
+        if originals.is_empty() {
+            return Ok(translation.to_string());
+        }
+        let (restored, missing) = placeholder::restore_kind(translation, PLACEHOLDER_KIND, originals);
+        if !missing.is_empty() {
+            let lost: Vec<&str> = missing.iter().map(|&index| originals[index].as_str()).collect();
+            return Err(TranslatorError::Parse(format!("The translation lost the placeholders {}", lost.join(", "))));
+        }
+        let (_, mut found) = placeholder::protect_format_specifiers(&restored);
+        let mut expected = originals.to_vec();
+        found.sort();
+        expected.sort();
+        if found != expected {
+            return Err(TranslatorError::Parse(format!(
+                "The translation changed the placeholders {} into {}",
+                expected.join(", "),
+                found.join(", ")
+            )));
+        }
+        Ok(restored)
+    }
+
+    /// Puts the originals of [`protect`](Self::protect) back into a translation.
+    ///
+    /// Originals whose token the translation dropped are appended, and their number returned.
+    pub fn restore(translation: &str, originals: &[String]) -> (String, usize) {
+        let (restored, missing) = placeholder::restore_kind(translation, TOKEN_KIND, originals);
+        (restored, missing.len())
+    }
+}
+
+/// Replaces the matches of any of `patterns` in `text` with tokens of the given kind, returning
+/// the text and the originals the tokens stand for. Where matches overlap, the earliest (then
+/// longest) wins.
+pub(crate) fn protect_matches(patterns: &[Regex], kind: &str, text: &str) -> (String, Vec<String>) {
+    let mut originals = Vec::new();
+    let result = protect_matches_into(patterns, kind, text, &mut originals);
+    (result, originals)
+}
+
+/// Like [`protect_matches`], numbering the tokens on from the `originals` already protected,
+/// so several texts can share one table of tokens.
+pub(crate) fn protect_matches_into(patterns: &[Regex], kind: &str, text: &str, originals: &mut Vec<String>) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut pos = 0;
+
+    while pos < text.len() {
+        let found = patterns
+            .iter()
+            .filter_map(|pattern| pattern.find_at(text, pos))
+            .filter(|found| !found.is_empty())
+            .min_by_key(|found| (found.start(), std::cmp::Reverse(found.end())));
+        let Some(found) = found else { break };
+
+        result.push_str(&text[pos..found.start()]);
+        result.push_str(&placeholder::kind_token(kind, originals.len()));
+        originals.push(found.as_str().to_string());
+        pos = found.end();
+    }
+    result.push_str(&text[pos..]);
+    result
+}
+
+/// Splits `content` into pieces outside and inside `<!-- notranslate -->` … `<!-- /notranslate -->`
+/// markers, paired with whether they are marked. Marked pieces include their markers; a start
+/// marker without an end marker runs to the end of the content.
+pub fn split_marked(content: &str) -> Vec<(&str, bool)> {
+    static START: OnceLock<Regex> = OnceLock::new();
+    static END: OnceLock<Regex> = OnceLock::new();
+    let start_marker = START.get_or_init(|| Regex::new(r"<!--\s*notranslate\s*-->").unwrap());
+    let end_marker = END.get_or_init(|| Regex::new(r"<!--\s*/notranslate\s*-->").unwrap());
+
+    let mut pieces = Vec::new();
+    let mut pos = 0;
+    while let Some(start) = start_marker.find_at(content, pos) {
+        let end = end_marker.find_at(content, start.end()).map_or(content.len(), |end| end.end());
+        pieces.push((&content[pos..start.start()], false));
+        pieces.push((&content[start.start()..end], true));
+        pos = end;
+    }
+    pieces.push((&content[pos..], false));
+
+    pieces.retain(|(piece, _)| !piece.is_empty());
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn earliest_then_longest_match_is_protected_and_put_back() {
+        let no_translate = NoTranslate::new(&[r"v\d+".to_string(), r"v\d+\.\d+".to_string(), r"/usr/\S+".to_string()]).unwrap();
+        let (text, originals) = no_translate.protect("Install v1.2 into /usr/local.");
+        assert_eq!(text, "Install ⟦n0⟧ into ⟦n1⟧");
+        assert_eq!(originals, ["v1.2", "/usr/local."]);
+        assert_eq!(NoTranslate::restore("Telepítsd: ⟦N0⟧.", &originals), ("Telepítsd: v1.2. /usr/local.".to_string(), 1));
+    }
+
+    #[test]
+    fn placeholders_come_back_in_their_new_position() {
+        let no_translate = NoTranslate::default().with_placeholders(true);
+        let (text, originals) = no_translate.protect_placeholders("Hello %s, you have {count} messages");
+        assert_eq!(text, "Hello ⟦p0⟧, you have ⟦p1⟧ messages");
+        let restored = NoTranslate::restore_placeholders("⟦p1⟧ üzeneted van, ⟦ p0 ⟧", &originals).unwrap();
+        assert_eq!(restored, "{count} üzeneted van, %s");
+    }
+
+    #[test]
+    fn lost_or_added_placeholders_are_errors() {
+        let originals = ["%s".to_string(), "{count}".to_string()];
+        let lost = NoTranslate::restore_placeholders("Szia ⟦p0⟧", &originals).unwrap_err();
+        assert!(lost.to_string().contains("lost the placeholders {count}"), "{}", lost);
+        let added = NoTranslate::restore_placeholders("Szia ⟦p0⟧, ⟦p1⟧ %d", &originals).unwrap_err();
+        assert!(matches!(added, TranslatorError::Parse(_)));
+    }
+
+    #[test]
+    fn marked_spans_are_split_out_with_their_markers() {
+        let pieces = split_marked("Keep <!-- notranslate -->this<!--/notranslate --> and <!-- notranslate -->the rest");
+        assert_eq!(
+            pieces,
+            [
+                ("Keep ", false),
+                ("<!-- notranslate -->this<!--/notranslate -->", true),
+                (" and ", false),
+                ("<!-- notranslate -->the rest", true),
+            ]
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use encoding_rs::{Encoding, UTF_8};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::encoding;
+
+/// Writes translated chunks to a temporary file as soon as they are available and
+/// renames it over the real output once the translation is complete.
+///
+/// If the run is interrupted, everything translated so far is left in the
+/// `<output>.partial` file instead of being lost.
+pub struct ChunkWriter {
+    file: File,
+    partial_path: PathBuf,
+    output_path: PathBuf,
+    chunks_written: usize,
+    encoding: &'static Encoding,
+    unmappable: usize,
+}
+
+impl ChunkWriter {
+    pub fn create(output_path: &Path) -> io::Result<Self> {
+        let partial_path = Self::partial_path_for(output_path);
+        Ok(Self {
+            file: File::create(&partial_path)?,
+            partial_path,
+            output_path: output_path.to_path_buf(),
+            chunks_written: 0,
+            encoding: UTF_8,
+            unmappable: 0,
+        })
+    }
+
+    /// Writes the text in `encoding` instead of UTF-8.
+    pub fn with_encoding(mut self, encoding: &'static Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// How many characters written so far have no code in the output encoding.
+    pub fn unmappable(&self) -> usize {
+        self.unmappable
+    }
+
+    /// The temporary file the chunks are written to until the translation completes.
+    pub fn partial_path_for(output_path: &Path) -> PathBuf {
+        let mut name = output_path.as_os_str().to_owned();
+        name.push(".partial");
+        PathBuf::from(name)
+    }
+
+    pub fn partial_path(&self) -> &Path {
+        &self.partial_path
+    }
+
+    /// Appends text as it is, e.g. the next rendered piece of a document.
+    pub fn write(&mut self, text: &str) -> io::Result<()> {
+        self.write_encoded(text)?;
+        self.file.flush()
+    }
+
+    /// Appends a translated chunk, separated from the previous one by a blank line.
+    pub fn write_chunk(&mut self, chunk: &str) -> io::Result<()> {
+        if self.chunks_written > 0 {
+            self.write_encoded("\n\n")?;
+        }
+        self.write_encoded(chunk)?;
+        self.file.flush()?;
+        self.chunks_written += 1;
+        Ok(())
+    }
+
+    fn write_encoded(&mut self, text: &str) -> io::Result<()> {
+        let (bytes, unmappable) = encoding::encode(text, self.encoding);
+        self.unmappable += unmappable;
+        self.file.write_all(&bytes)
+    }
+
+    /// Moves the completed translation to the output path.
+    pub fn finish(self) -> io::Result<()> {
+        self.file.sync_all()?;
+        drop(self.file);
+        std::fs::rename(&self.partial_path, &self.output_path)
+    }
+}
+
+/// Writes a whole file to a temporary file next to it first and renames that into place, so a
+/// crash while writing never leaves a truncated file behind.
+pub fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let mut file = File::create(&tmp_path)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp_path, path)
+}
@@ -0,0 +1,225 @@
+//! Opaque tokens standing in for text that must survive translation unchanged.
+//!
+//! Protected spans are replaced by `⟦0⟧`, `⟦1⟧`, … before a text is sent to the API and put
+//! back afterwards. Translation engines tend to copy such symbols verbatim, but may add spaces
+//! inside them, so restoring is lenient about whitespace.
+//!
+//! Layers that protect spans independently of each other use tokens of their own kind, such as
+//! `⟦x0⟧`, so that restoring one layer's tokens leaves the others' alone.
+
+/// The token standing in for the protected span with the given index.
+pub fn token(index: usize) -> String {
+    kind_token("", index)
+}
+
+/// Like [`token`], for the tokens of another kind.
+pub fn kind_token(kind: &str, index: usize) -> String {
+    format!("⟦{}{}⟧", kind, index)
+}
+
+/// Replaces the tokens in `text` with the protected originals.
+///
+/// Originals whose token was dropped by the translation are appended at the end so nothing is
+/// lost; their indices are returned so the caller can warn about them.
+pub fn restore(text: &str, originals: &[String]) -> (String, Vec<usize>) {
+    restore_kind(text, "", originals)
+}
+
+/// Like [`restore`], for the tokens of another kind; tokens of other kinds are left as they are.
+pub fn restore_kind(text: &str, kind: &str, originals: &[String]) -> (String, Vec<usize>) {
+    restore_kind_with(text, kind, originals, &[])
+}
+
+/// Like [`restore_kind`], where the tokens numbered past the `originals` stand for the `borrowed`
+/// ones, e.g. those of context sent along with the text. They are put back where the
+/// translation copied them, but never appended when it didn't.
+pub fn restore_kind_with(text: &str, kind: &str, originals: &[String], borrowed: &[String]) -> (String, Vec<usize>) {
+    let mut result = String::with_capacity(text.len());
+    let mut used = vec![false; originals.len()];
+    let mut rest = text;
+
+    while let Some(start) = rest.find('⟦') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + '⟦'.len_utf8()..];
+        let restored = after.find('⟧').and_then(|end| {
+            // Engines may change the case of the kind, e.g. capitalizing it at the start of a sentence.
+            let inside = after[..end].trim();
+            let number = inside.get(kind.len()..).filter(|_| inside[..kind.len()].eq_ignore_ascii_case(kind))?;
+            let index: usize = number.trim_start().parse().ok()?;
+            let original = originals.get(index).or_else(|| borrowed.get(index.checked_sub(originals.len())?))?;
+            Some((index, original, end))
+        });
+        match restored {
+            Some((index, original, end)) => {
+                result.push_str(original);
+                if let Some(used) = used.get_mut(index) {
+                    *used = true;
+                }
+                rest = &after[end + '⟧'.len_utf8()..];
+            }
+            None => {
+                result.push('⟦');
+                rest = after;
+            }
+        }
+    }
+    result.push_str(rest);
+
+    let missing: Vec<usize> = (0..originals.len()).filter(|&i| !used[i]).collect();
+    for &index in &missing {
+        if !result.is_empty() && !result.ends_with(char::is_whitespace) {
+            result.push(' ');
+        }
+        result.push_str(&originals[index]);
+    }
+    (result, missing)
+}
+
+/// Removes all tokens from `text`, leaving only the text around them.
+pub fn strip_tokens(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('⟦') {
+        result.push_str(&rest[..start]);
+        match rest[start..].find('⟧') {
+            Some(end) => rest = &rest[start + end + '⟧'.len_utf8()..],
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Replaces interpolation placeholders such as `{name}`, `{{name}}`, `%{name}`, `${name}`, `%s`
+/// or `%1$d` with tokens, so programs can still fill them in after translation.
+pub fn protect_format_specifiers(text: &str) -> (String, Vec<String>) {
+    protect_format_specifiers_kind(text, "")
+}
+
+/// Like [`protect_format_specifiers`], with tokens of another kind.
+pub fn protect_format_specifiers_kind(text: &str, kind: &str) -> (String, Vec<String>) {
+    let mut result = String::with_capacity(text.len());
+    let mut protected = Vec::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find(['{', '%', '$']) {
+        match format_specifier_len(&rest[start..]) {
+            Some(len) => {
+                result.push_str(&rest[..start]);
+                result.push_str(&kind_token(kind, protected.len()));
+                protected.push(rest[start..start + len].to_string());
+                rest = &rest[start + len..];
+            }
+            None => {
+                result.push_str(&rest[..=start]);
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+
+    (result, protected)
+}
+
+/// The length of the placeholder `text` starts with, if it starts with one.
+fn format_specifier_len(text: &str) -> Option<usize> {
+    let is_name = |name: &str| {
+        !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | ':' | '-'))
+    };
+    let braced = |text: &str, open: &str, close: &str| {
+        let inner = text.strip_prefix(open)?;
+        let end = inner.find(close)?;
+        is_name(inner[..end].trim()).then_some(open.len() + end + close.len())
+    };
+
+    if text.starts_with("{{") {
+        return braced(text, "{{", "}}");
+    }
+    if text.starts_with('{') {
+        return braced(text, "{", "}");
+    }
+    if text.starts_with("%{") {
+        return braced(text, "%{", "}");
+    }
+    if text.starts_with('$') {
+        // Shell and template literal style `${name}`; a lone `$` is just a dollar sign.
+        return braced(text, "${", "}");
+    }
+    if text.starts_with("%%") {
+        return Some(2);
+    }
+    if let Some(inner) = text.strip_prefix("%(") {
+        // Python's `%(name)s`.
+        let end = inner.find(')')?;
+        let conversion = inner[end + 1..].chars().next().filter(char::is_ascii_alphabetic)?;
+        return is_name(&inner[..end]).then_some(2 + end + 1 + conversion.len_utf8());
+    }
+
+    // printf style: %[argument$][flags][width][.precision]conversion
+    let bytes = text.as_bytes();
+    let mut i = 1;
+    let digits = |i: &mut usize| {
+        while bytes.get(*i).is_some_and(u8::is_ascii_digit) {
+            *i += 1;
+        }
+    };
+    digits(&mut i);
+    if bytes.get(i) == Some(&b'$') {
+        i += 1;
+    } else {
+        i = 1;
+    }
+    while bytes.get(i).is_some_and(|b| b"-+#0".contains(b)) {
+        i += 1;
+    }
+    digits(&mut i);
+    if bytes.get(i) == Some(&b'.') {
+        i += 1;
+        digits(&mut i);
+    }
+    while bytes.get(i).is_some_and(|b| b"hlLqjzt".contains(b)) {
+        i += 1;
+    }
+    bytes.get(i).filter(|b| b"sdifFeEgGxXoucpaA@".contains(b)).map(|_| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn originals() -> Vec<String> {
+        vec!["<b>".to_string(), "</b>".to_string()]
+    }
+
+    #[test]
+    fn case_changed_and_space_padded_tokens_are_restored() {
+        let (restored, missing) = restore_kind("⟦X0⟧Hello⟦ x1 ⟧ ⟦x 0⟧ and ⟦g0⟧", "x", &originals());
+        assert_eq!(restored, "<b>Hello</b> <b> and ⟦g0⟧");
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn dropped_tokens_are_appended_and_reported() {
+        assert_eq!(restore("Hello⟦1⟧ ⟦7⟧", &originals()), ("Hello</b> ⟦7⟧ <b>".to_string(), vec![0]));
+        assert_eq!(restore("Hello ", &originals()), ("Hello <b> </b>".to_string(), vec![0, 1]));
+    }
+
+    #[test]
+    fn borrowed_tokens_are_restored_but_never_appended() {
+        let borrowed = ["anna@example.com".to_string()];
+        let (restored, missing) = restore_kind_with("⟦r2⟧ ⟦r0⟧", "r", &originals(), &borrowed);
+        assert_eq!(restored, "anna@example.com <b> </b>");
+        assert_eq!(missing, [1]);
+    }
+
+    #[test]
+    fn format_specifiers_are_protected() {
+        let (text, protected) = protect_format_specifiers("%1$s of {{total}} at 100% in ${dir}, %(name)s and %% or $5");
+        assert_eq!(text, "⟦0⟧ of ⟦1⟧ at 100% in ⟦2⟧, ⟦3⟧ and ⟦4⟧ or $5");
+        assert_eq!(protected, ["%1$s", "{{total}}", "${dir}", "%(name)s", "%%"]);
+        assert_eq!(strip_tokens(&text), " of  at 100% in ,  and  or $5");
+    }
+}
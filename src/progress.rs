@@ -0,0 +1,114 @@
+//! Reporting the progress of a run: on an indicatif progress bar for people, or as one JSON
+//! object per line on stderr for CI pipelines and GUIs.
+
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::error::TranslatorError;
+use crate::output;
+
+/// Something that happened during a run, written as `{"event": "chunk_done", ...}`.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    /// A chunk is sent to the server (chunks come from the cache or a checkpoint without it).
+    ChunkStarted { chunk: usize, chunks: usize, target: &'a str },
+    /// A chunk is translated and written to the output.
+    ChunkDone { chunk: usize, chunks: usize, target: &'a str, characters: usize },
+    /// A request failed and is sent again after `delay_seconds`.
+    Retry { attempt: u32, max_retries: u32, delay_seconds: f64, error: String },
+    /// A chunk could not be translated.
+    Failure { chunk: usize, target: &'a str, error: String },
+    /// The translation of a file into a target language is complete.
+    Finished(&'a Stats),
+}
+
+/// Statistics of a finished translation, for tracking the cost and throughput of backends.
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    pub input_file: PathBuf,
+    pub output_file: Option<PathBuf>,
+    pub backend: String,
+    pub source: String,
+    pub target: String,
+    pub chunks: usize,
+    /// Chunks taken from the checkpoint of an interrupted run.
+    pub resumed: usize,
+    /// Chunks whose translation came from the cache.
+    pub cache_hits: usize,
+    /// Chunks identical to an earlier chunk of the file, whose translation was reused.
+    pub duplicates: usize,
+    /// Characters of those chunks, which weren't sent again.
+    pub duplicate_characters: usize,
+    /// Requests sent again after failing.
+    pub retries: usize,
+    /// Chunks that could not be translated.
+    pub errors: usize,
+    /// Characters of the source text.
+    pub characters: usize,
+    /// Average time from sending a chunk (including waiting for the rate limiter) to its translation.
+    pub average_chunk_latency_seconds: f64,
+    pub characters_per_second: f64,
+    pub elapsed_seconds: f64,
+}
+
+impl Stats {
+    /// Saves the statistics of the translations of a run as a JSON array.
+    pub fn save_all(stats: &[Stats], path: &Path) -> Result<(), TranslatorError> {
+        output::write_atomic(path, serde_json::to_string_pretty(stats)?)?;
+        Ok(())
+    }
+}
+
+/// Where progress is reported; cloned into the backends to report their retries.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    bar: ProgressBar,
+    json: bool,
+    retries: Arc<AtomicUsize>,
+}
+
+impl Progress {
+    /// Reports on `bar`.
+    pub fn new(bar: ProgressBar) -> Self {
+        Self { bar, json: false, retries: Arc::default() }
+    }
+
+    /// Writes events as JSON lines on stderr, and nothing else.
+    pub fn json() -> Self {
+        Self { bar: ProgressBar::hidden(), json: true, retries: Arc::default() }
+    }
+
+    /// Discards everything.
+    pub fn hidden() -> Self {
+        Self::new(ProgressBar::hidden())
+    }
+
+    pub fn bar(&self) -> &ProgressBar {
+        &self.bar
+    }
+
+    /// Counts a request sent again, in all clones.
+    pub fn count_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of requests sent again so far.
+    pub fn retries(&self) -> usize {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Writes `event` on stderr when reporting JSON.
+    pub fn emit(&self, event: &Event) {
+        if self.json {
+            if let Ok(line) = serde_json::to_string(event) {
+                // Lines are written whole, so concurrent chunks don't interleave their events.
+                let _ = writeln!(io::stderr().lock(), "{}", line);
+            }
+        }
+    }
+}
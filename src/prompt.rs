@@ -0,0 +1,58 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Instructions used when no custom prompt template is given.
+pub const DEFAULT_TEMPLATE: &str = "Translate the following text from the language with code '{source}' \
+to the language with code '{target}'. Reply with the translation only, without any notes or explanations.\n\n{text}";
+
+/// A prompt for LLM backends with `{source}`, `{target}` and `{text}` placeholders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+    template: String,
+}
+
+impl Default for PromptTemplate {
+    fn default() -> Self {
+        Self::new(DEFAULT_TEMPLATE)
+    }
+}
+
+impl PromptTemplate {
+    pub fn new(template: impl Into<String>) -> Self {
+        Self {
+            template: template.into(),
+        }
+    }
+
+    /// Loads the template from a text file.
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(fs::read_to_string(path)?))
+    }
+
+    /// Substitutes the placeholders in a single pass, so placeholder-like sequences
+    /// inside the substituted text are left alone. Unknown placeholders are kept as-is.
+    pub fn render(&self, source: &str, target: &str, text: &str) -> String {
+        let mut result = String::with_capacity(self.template.len() + text.len());
+        let mut rest = self.template.as_str();
+
+        while let Some(start) = rest.find('{') {
+            result.push_str(&rest[..start]);
+            let after = &rest[start..];
+            let (value, len) = if after.starts_with("{source}") {
+                (source, "{source}".len())
+            } else if after.starts_with("{target}") {
+                (target, "{target}".len())
+            } else if after.starts_with("{text}") {
+                (text, "{text}".len())
+            } else {
+                ("{", 1)
+            };
+            result.push_str(value);
+            rest = &after[len..];
+        }
+        result.push_str(rest);
+
+        result
+    }
+}
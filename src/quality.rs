@@ -0,0 +1,479 @@
+//! Checks of translation quality that don't need a human reference translation.
+
+use serde::{Deserialize, Serialize};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use whatlang::{Detector, Lang};
+
+use crate::error::TranslatorError;
+use crate::output;
+use crate::placeholder;
+use crate::translator::primary_language;
+
+/// Longest character n-grams compared by [`chrf`].
+const CHRF_ORDER: usize = 6;
+
+/// Weight of recall relative to precision in [`chrf`], as in the standard chrF2.
+const CHRF_BETA: f64 = 2.0;
+
+/// The chrF score (0 to 100) of a hypothesis against a reference: the F-score of their
+/// character n-grams up to 6, ignoring whitespace.
+pub fn chrf(hypothesis: &str, reference: &str) -> f64 {
+    let hypothesis: Vec<char> = hypothesis.chars().filter(|c| !c.is_whitespace()).collect();
+    let reference: Vec<char> = reference.chars().filter(|c| !c.is_whitespace()).collect();
+    if hypothesis.is_empty() || reference.is_empty() {
+        return if hypothesis == reference { 100.0 } else { 0.0 };
+    }
+
+    let (mut precision, mut recall, mut orders) = (0.0, 0.0, 0);
+    for n in 1..=CHRF_ORDER {
+        let (hypothesis, reference) = (ngrams(&hypothesis, n), ngrams(&reference, n));
+        let (hypothesis_total, reference_total) = (hypothesis.values().sum::<usize>(), reference.values().sum::<usize>());
+        if hypothesis_total == 0 || reference_total == 0 {
+            break;
+        }
+        let common: usize = hypothesis
+            .iter()
+            .map(|(ngram, count)| (*count).min(reference.get(ngram).copied().unwrap_or(0)))
+            .sum();
+        precision += common as f64 / hypothesis_total as f64;
+        recall += common as f64 / reference_total as f64;
+        orders += 1;
+    }
+    let (precision, recall) = (precision / orders as f64, recall / orders as f64);
+    if precision + recall == 0.0 {
+        return 0.0;
+    }
+    let beta2 = CHRF_BETA * CHRF_BETA;
+    100.0 * (1.0 + beta2) * precision * recall / (beta2 * precision + recall)
+}
+
+fn ngrams(chars: &[char], n: usize) -> HashMap<&[char], usize> {
+    let mut counts = HashMap::new();
+    for ngram in chars.windows(n) {
+        *counts.entry(ngram).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Chunks whose translation, translated back into the source language, differs too much from the original.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct RoundtripReport {
+    pub input_file: PathBuf,
+    pub source: String,
+    pub target: String,
+    /// The chrF score below which chunks are listed.
+    pub threshold: f64,
+    /// Average chrF score of all checked chunks.
+    pub average_score: f64,
+    pub chunks: Vec<FlaggedChunk>,
+}
+
+/// A chunk whose back-translation scored below the threshold.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FlaggedChunk {
+    /// Number of the chunk, counting from 1.
+    pub chunk: usize,
+    pub score: f64,
+    pub text: String,
+    pub translation: String,
+    pub back_translation: String,
+}
+
+impl RoundtripReport {
+    /// The report belonging to an output (or input) file, e.g. `output.translator-review.json`.
+    pub fn path_for(file: &Path) -> PathBuf {
+        file.with_extension("translator-review.json")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TranslatorError> {
+        output::write_atomic(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Other translations the server offered for the paragraphs of a file, for an editor to pick from.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AlternativesReport {
+    pub input_file: PathBuf,
+    pub source: String,
+    pub target: String,
+    pub paragraphs: Vec<Alternatives>,
+}
+
+/// A paragraph with the translation used and the alternatives to it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Alternatives {
+    /// Number of the chunk the paragraph is in, counting from 1.
+    pub chunk: usize,
+    pub text: String,
+    pub translation: String,
+    pub alternatives: Vec<String>,
+}
+
+impl AlternativesReport {
+    /// The report belonging to an output (or input) file, e.g. `output.translator-alternatives.json`.
+    pub fn path_for(file: &Path) -> PathBuf {
+        file.with_extension("translator-alternatives.json")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TranslatorError> {
+        output::write_atomic(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Chunks whose translation altered or dropped named entities of the original.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EntityReport {
+    pub input_file: PathBuf,
+    pub source: String,
+    pub target: String,
+    pub chunks: Vec<AlteredEntities>,
+}
+
+/// A chunk with the entities its translation doesn't keep.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AlteredEntities {
+    /// Number of the chunk, counting from 1.
+    pub chunk: usize,
+    pub entities: Vec<String>,
+    pub text: String,
+    pub translation: String,
+}
+
+impl EntityReport {
+    /// The report belonging to an output (or input) file, e.g. `output.translator-entities.json`.
+    pub fn path_for(file: &Path) -> PathBuf {
+        file.with_extension("translator-entities.json")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TranslatorError> {
+        output::write_atomic(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Grammar and spelling mistakes LanguageTool found in the translated segments of a file.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct GrammarReport {
+    pub input_file: PathBuf,
+    pub target: String,
+    pub segments: Vec<GrammarSegment>,
+}
+
+/// A translated segment with the mistakes found in it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GrammarSegment {
+    /// Number of the segment, counting from 1 as in review sheets.
+    pub segment: usize,
+    pub translation: String,
+    pub mistakes: Vec<GrammarMistake>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GrammarMistake {
+    pub message: String,
+    /// The text the mistake is in.
+    pub excerpt: String,
+    pub replacements: Vec<String>,
+}
+
+impl GrammarReport {
+    /// The report belonging to an output (or input) file, e.g. `output.translator-grammar.json`.
+    pub fn path_for(file: &Path) -> PathBuf {
+        file.with_extension("translator-grammar.json")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TranslatorError> {
+        output::write_atomic(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// The name of the language a translation into `target` is written in instead, if it can be
+/// told reliably; `None` if it looks right or the language can't be detected.
+///
+/// Restricted to the source and target languages, detection is reliable even on short texts,
+/// and catches servers that return the source text untouched.
+pub fn wrong_language(text: &str, source: &str, target: &str) -> Option<&'static str> {
+    let (language, target) = detect(text, source, target)?;
+    (language != target).then(|| language.eng_name())
+}
+
+/// Whether `text` is reliably written in `target` already rather than in `source`, so it needn't
+/// be translated.
+pub fn is_in_language(text: &str, source: &str, target: &str) -> bool {
+    detect(text, source, target).is_some_and(|(language, target)| language == target)
+}
+
+/// The language `text` is written in, as far as it can be told reliably, with the whatlang
+/// language of `target`. Detection is restricted to the source and target languages.
+fn detect(text: &str, source: &str, target: &str) -> Option<(Lang, Lang)> {
+    let target = whatlang_language(target)?;
+    let detector = match whatlang_language(source) {
+        Some(source) if source != target => Detector::with_allowlist(vec![source, target]),
+        _ => Detector::new(),
+    };
+    let info = detector.detect(&placeholder::strip_tokens(text))?;
+    info.is_reliable().then(|| (info.lang(), target))
+}
+
+/// The languages whatlang can detect by language code; where several codes stand for the same
+/// language, the current one comes first.
+const WHATLANG_LANGUAGES: &[(&str, Lang)] = &[
+    ("af", Lang::Afr),
+    ("ak", Lang::Aka),
+    ("am", Lang::Amh),
+    ("ar", Lang::Ara),
+    ("az", Lang::Aze),
+    ("be", Lang::Bel),
+    ("bg", Lang::Bul),
+    ("bn", Lang::Ben),
+    ("ca", Lang::Cat),
+    ("cs", Lang::Ces),
+    ("da", Lang::Dan),
+    ("de", Lang::Deu),
+    ("el", Lang::Ell),
+    ("en", Lang::Eng),
+    ("eo", Lang::Epo),
+    ("es", Lang::Spa),
+    ("et", Lang::Est),
+    ("fa", Lang::Pes),
+    ("fi", Lang::Fin),
+    ("fr", Lang::Fra),
+    ("gu", Lang::Guj),
+    ("he", Lang::Heb),
+    ("iw", Lang::Heb),
+    ("hi", Lang::Hin),
+    ("hr", Lang::Hrv),
+    ("hu", Lang::Hun),
+    ("hy", Lang::Hye),
+    ("id", Lang::Ind),
+    ("it", Lang::Ita),
+    ("ja", Lang::Jpn),
+    ("jv", Lang::Jav),
+    ("ka", Lang::Kat),
+    ("km", Lang::Khm),
+    ("kn", Lang::Kan),
+    ("ko", Lang::Kor),
+    ("la", Lang::Lat),
+    ("lt", Lang::Lit),
+    ("lv", Lang::Lav),
+    ("mk", Lang::Mkd),
+    ("ml", Lang::Mal),
+    ("mr", Lang::Mar),
+    ("my", Lang::Mya),
+    ("nb", Lang::Nob),
+    ("no", Lang::Nob),
+    ("ne", Lang::Nep),
+    ("nl", Lang::Nld),
+    ("or", Lang::Ori),
+    ("pa", Lang::Pan),
+    ("pl", Lang::Pol),
+    ("pt", Lang::Por),
+    ("ro", Lang::Ron),
+    ("ru", Lang::Rus),
+    ("si", Lang::Sin),
+    ("sk", Lang::Slk),
+    ("sl", Lang::Slv),
+    ("sn", Lang::Sna),
+    ("sr", Lang::Srp),
+    ("sv", Lang::Swe),
+    ("ta", Lang::Tam),
+    ("te", Lang::Tel),
+    ("th", Lang::Tha),
+    ("tk", Lang::Tuk),
+    ("tl", Lang::Tgl),
+    ("fil", Lang::Tgl),
+    ("tr", Lang::Tur),
+    ("uk", Lang::Ukr),
+    ("ur", Lang::Urd),
+    ("uz", Lang::Uzb),
+    ("vi", Lang::Vie),
+    ("yi", Lang::Yid),
+    ("zh", Lang::Cmn),
+    ("zu", Lang::Zul),
+];
+
+/// The whatlang language of a language code, if it can detect it.
+fn whatlang_language(code: &str) -> Option<Lang> {
+    let code = primary_language(code);
+    WHATLANG_LANGUAGES.iter().find(|(known, _)| *known == code).map(|(_, language)| *language)
+}
+
+/// The code of the language `text` is written in, if it can be told reliably, for when the
+/// source language is to be detected but the server can't be asked.
+pub fn detect_language(text: &str) -> Option<&'static str> {
+    let info = Detector::new().detect(&placeholder::strip_tokens(text)).filter(|info| info.is_reliable())?;
+    WHATLANG_LANGUAGES.iter().find(|(_, language)| *language == info.lang()).map(|(code, _)| *code)
+}
+
+/// Translations shorter than this share of the source length (in characters) are suspicious.
+const MIN_LENGTH_RATIO: f64 = 0.5;
+
+/// Translations longer than this multiple of the source length are suspicious.
+const MAX_LENGTH_RATIO: f64 = 2.0;
+
+/// Paragraphs shorter than this many characters vary too much in length to be checked.
+const MIN_CHECKED_LENGTH: usize = 40;
+
+/// Something suspicious about a translation, found by [`sanity_check`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Issue {
+    /// The translation is much shorter than the source; the ratio of their lengths.
+    TooShort(f64),
+    /// The translation is much longer than the source; the ratio of their lengths.
+    TooLong(f64),
+    /// A paragraph of the source appears untranslated in the translation.
+    Untranslated(String),
+    /// A number of the source is missing from the translation.
+    MissingNumber(String),
+    /// A date of the source is missing from the translation, in any of its usual formats.
+    MissingDate(String),
+    /// The number of a measurement is in the translation, but without its unit.
+    MissingUnit(String),
+    /// A URL of the source is missing from the translation.
+    MissingUrl(String),
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::TooShort(ratio) => write!(f, "translation is only {:.0}% as long as the source", ratio * 100.0),
+            Issue::TooLong(ratio) => write!(f, "translation is {:.1} times as long as the source", ratio),
+            Issue::Untranslated(text) => write!(f, "left untranslated: {:?}", excerpt(text)),
+            Issue::MissingNumber(number) => write!(f, "number {} is missing", number),
+            Issue::MissingDate(date) => write!(f, "date {} is missing", date),
+            Issue::MissingUnit(measurement) => write!(f, "the unit of {} is missing or changed", measurement),
+            Issue::MissingUrl(url) => write!(f, "URL {} is missing", url),
+        }
+    }
+}
+
+/// Checks a translated chunk against its source for signs of a bad translation: a length far
+/// from the source's, paragraphs left untranslated, and numbers, dates, measurements or URLs dropped.
+pub fn sanity_check(source: &str, translation: &str) -> Vec<Issue> {
+    let source = placeholder::strip_tokens(source);
+    let translation = placeholder::strip_tokens(translation);
+    let mut issues = Vec::new();
+
+    let (source_length, translation_length) = (source.chars().count(), translation.chars().count());
+    if source_length >= MIN_CHECKED_LENGTH {
+        let ratio = translation_length as f64 / source_length as f64;
+        if ratio < MIN_LENGTH_RATIO {
+            issues.push(Issue::TooShort(ratio));
+        } else if ratio > MAX_LENGTH_RATIO {
+            issues.push(Issue::TooLong(ratio));
+        }
+    }
+
+    for paragraph in source.split("\n\n").map(str::trim) {
+        if paragraph.chars().count() >= MIN_CHECKED_LENGTH && translation.contains(paragraph) {
+            issues.push(Issue::Untranslated(paragraph.to_string()));
+        }
+    }
+
+    let urls = url_pattern();
+    for url in urls.find_iter(&source).map(|url| url.as_str()) {
+        if !translation.contains(url) {
+            issues.push(Issue::MissingUrl(url.to_string()));
+        }
+    }
+
+    // Numbers are compared by their digits, as the translation may group them differently (1,000.5 and 1 000,5),
+    // and as often as they appear, as a table may lose one of several equal numbers.
+    let (source, translation) = (urls.replace_all(&source, ""), urls.replace_all(&translation, ""));
+    let mut translated_numbers: HashMap<String, usize> = HashMap::new();
+    let translated_dates = date_pattern().find_iter(&translation).flat_map(|date| date_parts(date.as_str()));
+    for digits in translated_dates.chain(numbers(&date_pattern().replace_all(&translation, " ")).map(|(_, digits)| digits)) {
+        *translated_numbers.entry(digits).or_insert(0) += 1;
+    }
+    let mut take = |digits: &String| match translated_numbers.get_mut(digits) {
+        Some(count) if *count > 0 => {
+            *count -= 1;
+            true
+        }
+        _ => false,
+    };
+    // A date may be written in another order or with other separators (2024-03-05 and 05.03.2024).
+    for date in date_pattern().find_iter(&source).map(|date| date.as_str()) {
+        if !date_parts(date).iter().all(&mut take) {
+            issues.push(Issue::MissingDate(date.to_string()));
+        }
+    }
+    for (number, digits) in numbers(&date_pattern().replace_all(&source, " ")) {
+        if !take(&digits) {
+            issues.push(Issue::MissingNumber(number.to_string()));
+        }
+    }
+
+    let translated_measurements: Vec<(String, &str)> = measurements(&translation).map(|(_, digits, unit)| (digits, unit)).collect();
+    let translated_digits: Vec<String> = numbers(&translation).map(|(_, digits)| digits).collect();
+    for (measurement, digits, unit) in measurements(&source) {
+        if translated_digits.contains(&digits) && !translated_measurements.contains(&(digits.clone(), unit)) {
+            issues.push(Issue::MissingUnit(measurement.to_string()));
+        }
+    }
+    issues
+}
+
+/// Dates written with numbers alone, e.g. 2024-03-05, 05.03.2024, 3/5/24 or 2024. 03. 05.
+fn date_pattern() -> &'static Regex {
+    static DATE: OnceLock<Regex> = OnceLock::new();
+    DATE.get_or_init(|| {
+        Regex::new(r"\b(?:\d{4}(?:-\d{1,2}-\d{1,2}|\. ?\d{1,2}\. ?\d{1,2}\.?)|\d{1,2}[./]\d{1,2}[./](?:\d{4}|\d{2})\b)").unwrap()
+    })
+}
+
+/// The day, month and year of a date.
+fn date_parts(date: &str) -> Vec<String> {
+    date.split(|c: char| !c.is_ascii_digit()).filter(|part| !part.is_empty()).map(digits).collect()
+}
+
+/// Numbers with a unit symbol, like 5 kg, 230V or 20 %, with the digits of the number and the unit.
+fn measurements(text: &str) -> impl Iterator<Item = (&str, String, &str)> {
+    static MEASUREMENT: OnceLock<Regex> = OnceLock::new();
+    let measurement = MEASUREMENT.get_or_init(|| {
+        Regex::new(
+            r"(\d+(?:[.,]\d+)?)[ \u{a0}\u{202f}]?(%|°[CF]|(?:mm|cm|km|mg|kg|ml|kWh|kW|mAh|kHz|MHz|GHz|Hz|KB|MB|GB|TB|px|m|g|l|W|V)\b)",
+        )
+        .unwrap()
+    });
+    measurement.captures_iter(text).map(|captures| {
+        let (number, unit) = (&captures[1], captures.get(2).map_or("", |unit| unit.as_str()));
+        (captures.get(0).map_or("", |all| all.as_str()), digits(number), unit)
+    })
+}
+
+fn url_pattern() -> &'static Regex {
+    static URL: OnceLock<Regex> = OnceLock::new();
+    URL.get_or_init(|| Regex::new(r#"(?:https?://|www\.)[^\s<>"')\]]*[^\s<>"')\].,;:!?]"#).unwrap())
+}
+
+/// The numbers in the text with their digits alone.
+fn numbers(text: &str) -> impl Iterator<Item = (&str, String)> {
+    static NUMBER: OnceLock<Regex> = OnceLock::new();
+    let number = NUMBER.get_or_init(|| Regex::new(r"\d+(?:[.,\u{a0}\u{202f}]\d+| \d{3}\b)*").unwrap());
+    number.find_iter(text).map(|number| (number.as_str(), digits(number.as_str())))
+}
+
+/// The digits of a number without leading zeros, so that 03 and 3 compare equal.
+fn digits(number: &str) -> String {
+    let digits: String = number.chars().filter(char::is_ascii_digit).collect();
+    match digits.trim_start_matches('0') {
+        "" if !digits.is_empty() => "0".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+/// The beginning of a long text, to quote it in a warning.
+fn excerpt(text: &str) -> String {
+    match text.char_indices().nth(60) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
@@ -0,0 +1,234 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token-bucket rate limiter shared by concurrent requests.
+///
+/// Each request takes a token; tokens refill at a steady rate up to `capacity`.
+/// When the bucket is empty, callers reserve the next token and sleep until it is due,
+/// so waiting requests are released in order and evenly spaced.
+///
+/// Backends adapt the limiter to what the server reports: [`pause_for`](Self::pause_for) holds
+/// back all requests after a `Retry-After`, and [`adapt`](Self::adapt) follows the server's quota.
+/// An [`adaptive`](Self::adaptive) limiter also finds the pace by itself from the responses.
+pub struct RateLimiter {
+    state: Mutex<Bucket>,
+    capacity: f64,
+    /// Whether [`back_off`](Self::back_off) and [`speed_up`](Self::speed_up) change the pace.
+    adaptive: bool,
+}
+
+/// Bounds of the pace of an adaptive limiter, in requests per second.
+const ADAPTIVE_MIN_RATE: f64 = 1.0 / 60.0;
+const ADAPTIVE_MAX_RATE: f64 = 10.0;
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+    /// Tokens added per second; `None` disables the limit.
+    rate: Option<f64>,
+    /// No request is released before this time.
+    paused_until: Option<Instant>,
+    /// The requests left and the time until the reset the server reported last.
+    quota: Option<(u64, Duration)>,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant, capacity: f64) {
+        if let Some(rate) = self.rate {
+            let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        }
+        self.last_refill = now;
+    }
+}
+
+impl RateLimiter {
+    /// Allows `requests_per_minute` requests with bursts of up to `burst` requests; 0 means unlimited.
+    pub fn per_minute(requests_per_minute: u32, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+        Self {
+            state: Mutex::new(Bucket {
+                tokens: capacity,
+                last_refill: Instant::now(),
+                rate: (requests_per_minute > 0).then(|| f64::from(requests_per_minute) / 60.0),
+                paused_until: None,
+                quota: None,
+            }),
+            capacity,
+            adaptive: false,
+        }
+    }
+
+    /// Starts at `requests_per_minute` and looks for the fastest pace the server accepts: every
+    /// rejected request halves the rate, every answered one raises it by one request per minute.
+    pub fn adaptive(requests_per_minute: u32) -> Self {
+        Self { adaptive: true, ..Self::per_minute(requests_per_minute.max(1), 1) }
+    }
+
+    /// Spaces requests at least `interval` apart; a zero interval means unlimited.
+    pub fn with_interval(interval: Duration) -> Self {
+        let limiter = Self::unlimited();
+        if !interval.is_zero() {
+            limiter.state.lock().unwrap().rate = Some(1.0 / interval.as_secs_f64());
+        }
+        limiter
+    }
+
+    /// A limiter that never waits.
+    pub fn unlimited() -> Self {
+        Self::per_minute(0, 1)
+    }
+
+    /// How long it takes at least until `requests` more requests are released at the current
+    /// pace, including a pause the server asked for.
+    pub fn time_for(&self, requests: usize) -> Duration {
+        let now = Instant::now();
+        let mut bucket = self.state.lock().unwrap();
+        bucket.refill(now, self.capacity);
+        let paused = bucket.paused_until.map_or(Duration::ZERO, |until| until.saturating_duration_since(now));
+        let Some(rate) = bucket.rate else { return paused };
+        let waiting = (requests as f64 - bucket.tokens).max(0.0) / rate;
+        paused + Duration::from_secs_f64(waiting)
+    }
+
+    /// Holds back every request for `duration`, e.g. as long as the server's `Retry-After` asks.
+    pub fn pause_for(&self, duration: Duration) {
+        let until = Instant::now() + duration;
+        let mut bucket = self.state.lock().unwrap();
+        bucket.paused_until = Some(bucket.paused_until.map_or(until, |paused| paused.max(until)));
+    }
+
+    /// Spaces requests so that the `remaining` requests the server still allows last until its
+    /// quota resets in `reset`. This may be faster than the configured rate when the server has
+    /// more headroom, or slower when the quota is running out.
+    pub fn adapt(&self, remaining: u64, reset: Duration) {
+        self.state.lock().unwrap().quota = Some((remaining, reset));
+        if remaining == 0 {
+            self.pause_for(reset);
+            return;
+        }
+        if reset.is_zero() {
+            return;
+        }
+        let mut bucket = self.state.lock().unwrap();
+        bucket.refill(Instant::now(), self.capacity);
+        bucket.rate = Some(remaining as f64 / reset.as_secs_f64());
+    }
+
+    /// Halves the pace of an adaptive limiter, after the server turned a request away or failed
+    /// under load.
+    pub fn back_off(&self) {
+        self.change_rate(|rate| rate / 2.0);
+    }
+
+    /// Raises the pace of an adaptive limiter a little, after the server answered a request.
+    pub fn speed_up(&self) {
+        self.change_rate(|rate| rate + 1.0 / 60.0);
+    }
+
+    fn change_rate(&self, change: impl FnOnce(f64) -> f64) {
+        if !self.adaptive {
+            return;
+        }
+        let mut bucket = self.state.lock().unwrap();
+        bucket.refill(Instant::now(), self.capacity);
+        if let Some(rate) = bucket.rate {
+            bucket.rate = Some(change(rate).clamp(ADAPTIVE_MIN_RATE, ADAPTIVE_MAX_RATE));
+        }
+    }
+
+    /// The requests the server still allowed and the time until its quota resets, as it reported
+    /// them last; `None` if it never did.
+    pub fn quota(&self) -> Option<(u64, Duration)> {
+        self.state.lock().unwrap().quota
+    }
+
+    /// Waits until the next request may be sent.
+    pub async fn acquire(&self) {
+        let wait = {
+            let mut bucket = self.state.lock().unwrap();
+            let now = Instant::now();
+            let paused = bucket.paused_until.map_or(Duration::ZERO, |until| until.saturating_duration_since(now));
+            match bucket.rate {
+                None => paused,
+                Some(rate) => {
+                    bucket.refill(now, self.capacity);
+                    // Taking the token may leave the bucket in debt, which is the caller's wait time.
+                    bucket.tokens -= 1.0;
+                    if bucket.tokens >= 0.0 {
+                        paused
+                    } else {
+                        paused.max(Duration::from_secs_f64(-bucket.tokens / rate))
+                    }
+                }
+            }
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exhausted_quota_pauses_until_the_reset() {
+        let limiter = RateLimiter::per_minute(60, 1);
+        limiter.adapt(0, Duration::from_secs(30));
+        assert_eq!(limiter.quota(), Some((0, Duration::from_secs(30))));
+        let wait = limiter.time_for(1);
+        assert!(wait > Duration::from_secs(29) && wait <= Duration::from_secs(30), "{:?}", wait);
+    }
+
+    #[test]
+    fn remaining_quota_sets_the_pace() {
+        let limiter = RateLimiter::per_minute(60, 1);
+        limiter.adapt(10, Duration::from_secs(100));
+        // One token is left in the bucket, the next two come 10 s apart.
+        let wait = limiter.time_for(3);
+        assert!(wait > Duration::from_millis(19_900) && wait <= Duration::from_secs(20), "{:?}", wait);
+    }
+
+    #[test]
+    fn time_for_counts_the_burst_and_the_pause() {
+        let limiter = RateLimiter::per_minute(30, 2);
+        assert!(limiter.time_for(2) < Duration::from_millis(100));
+        let wait = limiter.time_for(4);
+        assert!(wait > Duration::from_millis(3_900) && wait <= Duration::from_secs(4), "{:?}", wait);
+
+        let unlimited = RateLimiter::unlimited();
+        assert_eq!(unlimited.time_for(1000), Duration::ZERO);
+        unlimited.pause_for(Duration::from_secs(5));
+        assert!(unlimited.time_for(1000) > Duration::from_millis(4_900));
+    }
+
+    /// The time until the second next request is released, which is the spacing at the current pace.
+    fn spacing(limiter: &RateLimiter) -> f64 {
+        limiter.time_for(2).as_secs_f64()
+    }
+
+    #[test]
+    fn adaptive_limiter_backs_off_and_speeds_up_within_bounds() {
+        let limiter = RateLimiter::adaptive(60);
+        assert!((spacing(&limiter) - 1.0).abs() < 0.05);
+        limiter.back_off();
+        assert!((spacing(&limiter) - 2.0).abs() < 0.05);
+        limiter.speed_up();
+        assert!((spacing(&limiter) - 60.0 / 31.0).abs() < 0.05);
+        (0..20).for_each(|_| limiter.back_off());
+        assert!((spacing(&limiter) - 60.0).abs() < 0.5);
+        (0..1000).for_each(|_| limiter.speed_up());
+        assert!((spacing(&limiter) - 0.1).abs() < 0.05);
+    }
+
+    #[test]
+    fn fixed_limiter_ignores_back_off_and_speed_up() {
+        let limiter = RateLimiter::per_minute(60, 1);
+        limiter.back_off();
+        limiter.speed_up();
+        assert!((spacing(&limiter) - 1.0).abs() < 0.05);
+    }
+}
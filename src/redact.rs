@@ -0,0 +1,128 @@
+//! Personal data kept from the translation server: e-mail addresses, phone numbers, IBANs and
+//! matches of custom patterns are replaced with tokens before any text is sent, and put back
+//! into the translation afterwards.
+//!
+//! The tokens are numbered per text, so nothing of the originals leaves the machine, not even
+//! in the translation cache, which stores what was sent. Context paragraphs sent along with a
+//! text continue its numbering, so the same token never stands for two different originals.
+
+use regex::Regex;
+
+use crate::no_translate::{protect_matches, protect_matches_into};
+use crate::placeholder;
+use crate::translator::ContextParagraph;
+
+/// Kind of the tokens for redacted text, e.g. `⟦r0⟧`.
+pub const TOKEN_KIND: &str = "r";
+
+/// E-mail addresses.
+const EMAIL: &str = r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}";
+
+/// International bank account numbers, with or without spaces between the groups of four.
+const IBAN: &str = r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,3})?\b";
+
+/// Phone numbers in international (`+36 30 123 4567`) or national (`(06) 30-123-4567`) form.
+/// Numbers without a leading `+` or `0` are left alone, so years and amounts aren't caught.
+const PHONE: &str = r"(?:\+\d{1,3}[ .-]?|\(0\d{0,4}\)[ .-]?|\b0)\d{1,4}(?:[ .-]?\d{2,4}){2,4}\b";
+
+/// What is redacted: nothing by default.
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    patterns: Vec<Regex>,
+}
+
+impl Redactor {
+    /// Redacts the matches of `patterns`, and e-mail addresses, phone numbers and IBANs too with `builtin`.
+    pub fn new(builtin: bool, patterns: &[String]) -> Result<Self, regex::Error> {
+        let builtin = if builtin { &[EMAIL, IBAN, PHONE][..] } else { &[] };
+        let patterns = builtin
+            .iter()
+            .copied()
+            .chain(patterns.iter().map(String::as_str))
+            .map(Regex::new)
+            .collect::<Result<_, _>>()?;
+        Ok(Self { patterns })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Replaces the personal data in `text` with tokens, returning the text and the originals
+    /// the tokens stand for.
+    pub fn redact(&self, text: &str) -> (String, Vec<String>) {
+        protect_matches(&self.patterns, TOKEN_KIND, text)
+    }
+
+    /// Like [`redact`](Self::redact), for a text and the context paragraphs sent along with it:
+    /// the tokens of the context are numbered on from those of the text. Returns the text and the
+    /// context, and the originals of each.
+    pub fn redact_with_context(&self, text: &str, context: &[ContextParagraph]) -> (String, Vec<ContextParagraph>, Vec<String>, Vec<String>) {
+        let (text, mut originals) = self.redact(text);
+        let count = originals.len();
+        let context = context
+            .iter()
+            .map(|paragraph| ContextParagraph {
+                source: protect_matches_into(&self.patterns, TOKEN_KIND, &paragraph.source, &mut originals),
+                translation: protect_matches_into(&self.patterns, TOKEN_KIND, &paragraph.translation, &mut originals),
+            })
+            .collect();
+        let context_originals = originals.split_off(count);
+        (text, context, originals, context_originals)
+    }
+
+    /// Puts the originals of [`redact`](Self::redact) back into a translation.
+    ///
+    /// Originals whose token the translation dropped are appended, and their number returned.
+    pub fn restore(translation: &str, originals: &[String]) -> (String, usize) {
+        Self::restore_with_context(translation, originals, &[])
+    }
+
+    /// Like [`restore`](Self::restore) after [`redact_with_context`](Self::redact_with_context):
+    /// tokens copied from the context get the context's originals, which are never appended.
+    pub fn restore_with_context(translation: &str, originals: &[String], context_originals: &[String]) -> (String, usize) {
+        let (restored, missing) = placeholder::restore_kind_with(translation, TOKEN_KIND, originals, context_originals);
+        (restored, missing.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn email() -> Redactor {
+        Redactor::new(true, &[]).unwrap()
+    }
+
+    #[test]
+    fn personal_data_is_replaced_and_put_back() {
+        let (redacted, originals) = email().redact("Write to anna@example.com or call +36 30 123 4567.");
+        assert_eq!(redacted, "Write to ⟦r0⟧ or call ⟦r1⟧.");
+        let (restored, missing) = Redactor::restore("Írj ide: ⟦R0⟧, vagy hívd: ⟦ r1 ⟧.", &originals);
+        assert_eq!(restored, "Írj ide: anna@example.com, vagy hívd: +36 30 123 4567.");
+        assert_eq!(missing, 0);
+    }
+
+    #[test]
+    fn dropped_token_is_appended() {
+        let (_, originals) = email().redact("Mail anna@example.com.");
+        assert_eq!(Redactor::restore("Írj neki.", &originals), ("Írj neki. anna@example.com".to_string(), 1));
+    }
+
+    #[test]
+    fn context_tokens_keep_their_own_originals() {
+        let context = [ContextParagraph { source: "Ask bob@example.com.".to_string(), translation: "Kérdezd bob@example.com-ot.".to_string() }];
+        let (text, context, originals, context_originals) = email().redact_with_context("Then anna@example.com.", &context);
+        assert_eq!(text, "Then ⟦r0⟧.");
+        assert_eq!(context[0].source, "Ask ⟦r1⟧.");
+        assert_eq!(context[0].translation, "Kérdezd ⟦r2⟧-ot.");
+        assert_eq!(originals, ["anna@example.com"]);
+
+        // A token copied from the context stands for the context's address, not the text's.
+        let (restored, missing) = Redactor::restore_with_context("Aztán ⟦r0⟧, nem ⟦r1⟧.", &originals, &context_originals);
+        assert_eq!(restored, "Aztán anna@example.com, nem bob@example.com.");
+        assert_eq!(missing, 0);
+        // The context's addresses are not appended when the translation doesn't use them.
+        assert_eq!(Redactor::restore_with_context("Aztán ⟦r0⟧.", &originals, &context_originals).0, "Aztán anna@example.com.");
+    }
+}
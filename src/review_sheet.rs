@@ -0,0 +1,104 @@
+//! Review sheets: CSV files with one row per segment of a translation, for editing the
+//! translations in a spreadsheet and patching them back into the output file.
+
+use std::fs;
+use std::path::Path;
+
+use crate::error::TranslatorError;
+use crate::output;
+
+/// The header of a review sheet.
+const HEADER: [&str; 4] = ["id", "source", "translation", "flags"];
+
+/// Separates the flags of a row.
+const FLAG_SEPARATOR: &str = "; ";
+
+/// A segment with its translation and the checks that found something wrong with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewRow {
+    /// The number of the segment in the file, from 1.
+    pub id: usize,
+    pub source: String,
+    pub translation: String,
+    pub flags: Vec<String>,
+}
+
+/// The rows of a review sheet.
+#[derive(Debug, Default)]
+pub struct ReviewSheet {
+    pub rows: Vec<ReviewRow>,
+}
+
+impl ReviewSheet {
+    /// Reads a review sheet; extra columns after the four of the header are ignored.
+    pub fn load(path: &Path) -> Result<Self, TranslatorError> {
+        let content = fs::read_to_string(path)?;
+        let mut records = parse_records(content.strip_prefix('\u{feff}').unwrap_or(&content)).into_iter();
+        let header = records.next().unwrap_or_default();
+        if header.len() < HEADER.len() || header.iter().zip(HEADER).any(|(name, expected)| !name.trim().eq_ignore_ascii_case(expected)) {
+            return Err(format!("{:?} is not a review sheet; its header must be '{}'", path, HEADER.join(",")).into());
+        }
+        let mut rows = Vec::new();
+        for (line, record) in records.enumerate() {
+            if record.iter().all(|field| field.trim().is_empty()) {
+                continue;
+            }
+            let field = |index: usize| record.get(index).cloned().unwrap_or_default();
+            let id = field(0).trim().parse().map_err(|_| format!("Row {} of {:?} has no valid id: {:?}", line + 2, path, field(0)))?;
+            let flags = field(3).split(FLAG_SEPARATOR.trim()).map(str::trim).filter(|flag| !flag.is_empty()).map(str::to_string).collect();
+            rows.push(ReviewRow { id, source: field(1), translation: field(2), flags });
+        }
+        Ok(Self { rows })
+    }
+
+    /// Writes the sheet as CSV; cells with commas, quotes or line breaks are quoted.
+    pub fn save(&self, path: &Path) -> Result<(), TranslatorError> {
+        let mut csv = format!("{}\n", HEADER.join(","));
+        for row in &self.rows {
+            let fields = [row.id.to_string(), row.source.clone(), row.translation.clone(), row.flags.join(FLAG_SEPARATOR)];
+            csv.push_str(&fields.iter().map(|field| quote(field)).collect::<Vec<_>>().join(","));
+            csv.push('\n');
+        }
+        output::write_atomic(path, csv)?;
+        Ok(())
+    }
+}
+
+fn quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) || field.trim() != field {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Splits CSV into records of fields; quoted fields may hold commas, doubled quotes and line breaks.
+fn parse_records(content: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted && chars.peek() == Some(&'\n') => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
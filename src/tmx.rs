@@ -0,0 +1,159 @@
+//! TMX 1.4 files, the interchange format of translation memories.
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::TranslatorError;
+use crate::output;
+use crate::translator::same_language;
+
+/// A translation memory: source segments with their translations into one or more languages.
+#[derive(Debug, Default)]
+pub struct Tmx {
+    pub source_language: String,
+    pub units: Vec<TranslationUnit>,
+    /// Index of the unit of each source text, so repeated segments share one unit.
+    index: HashMap<String, usize>,
+}
+
+/// A source segment and its translations as `(language, text)` pairs.
+#[derive(Debug, Clone)]
+pub struct TranslationUnit {
+    pub source: String,
+    pub translations: Vec<(String, String)>,
+}
+
+impl Tmx {
+    pub fn new(source_language: &str) -> Self {
+        Self { source_language: source_language.to_string(), ..Self::default() }
+    }
+
+    /// Adds the translation of a segment; a segment translated into the same language twice keeps the later one.
+    pub fn add(&mut self, source: &str, target: &str, translation: &str) {
+        let index = *self.index.entry(source.to_string()).or_insert_with(|| {
+            self.units.push(TranslationUnit { source: source.to_string(), translations: Vec::new() });
+            self.units.len() - 1
+        });
+        let translations = &mut self.units[index].translations;
+        translations.retain(|(language, _)| language != target);
+        translations.push((target.to_string(), translation.to_string()));
+    }
+
+    /// Reads a TMX file. The text of inline elements standing for native markup (`<bpt>`,
+    /// `<ept>`, `<ph>`, `<it>`, `<ut>`) is left out of the segments.
+    pub fn load(path: &Path) -> Result<Self, TranslatorError> {
+        let xml = fs::read(path)?;
+        let mut reader = Reader::from_reader(xml.as_slice());
+        let mut buf = Vec::new();
+        let mut tmx = Tmx::default();
+        // Source language of the current unit and its variants as `(language, text)`.
+        let mut unit_source = None;
+        let mut variants: Vec<(String, String)> = Vec::new();
+        let mut language = String::new();
+        let mut segment: Option<String> = None;
+        let mut native_depth = 0;
+
+        loop {
+            match reader.read_event_into(&mut buf)? {
+                Event::Start(e) | Event::Empty(e) if matches!(e.local_name().as_ref(), b"header" | b"tu") => {
+                    let srclang = attribute(&e, "srclang")?;
+                    if e.local_name().as_ref() == b"header" {
+                        tmx.source_language = srclang.unwrap_or_default();
+                    } else {
+                        unit_source = srclang;
+                        variants.clear();
+                    }
+                }
+                Event::Start(e) if e.local_name().as_ref() == b"tuv" => {
+                    language = attribute(&e, "lang")?.unwrap_or_default();
+                }
+                Event::Start(e) if e.local_name().as_ref() == b"seg" => segment = Some(String::new()),
+                Event::Start(e) if is_native(e.local_name().as_ref()) => native_depth += 1,
+                Event::End(e) if is_native(e.local_name().as_ref()) => native_depth -= 1,
+                Event::Text(text) if native_depth == 0 => {
+                    if let Some(segment) = &mut segment {
+                        segment.push_str(&text.unescape()?);
+                    }
+                }
+                Event::CData(text) if native_depth == 0 => {
+                    if let Some(segment) = &mut segment {
+                        segment.push_str(&String::from_utf8_lossy(&text));
+                    }
+                }
+                Event::End(e) if e.local_name().as_ref() == b"seg" => {
+                    if let Some(segment) = segment.take() {
+                        variants.push((language.clone(), segment));
+                    }
+                }
+                Event::End(e) if e.local_name().as_ref() == b"tu" => {
+                    let source_language = unit_source.take().unwrap_or_else(|| tmx.source_language.clone());
+                    // Units without a variant in the source language can't be matched to any text.
+                    if let Some(source) = variants.iter().position(|(language, _)| same_language(language, &source_language)) {
+                        let (_, source) = variants.remove(source);
+                        for (language, translation) in variants.drain(..) {
+                            tmx.add(&source, &language, &translation);
+                        }
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+        if tmx.source_language.is_empty() || tmx.source_language == "*all*" {
+            return Err(TranslatorError::Parse(format!("{:?} doesn't name the source language of its segments", path)));
+        }
+        Ok(tmx)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), TranslatorError> {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tmx version=\"1.4\">\n");
+        xml.push_str(&format!(
+            "  <header creationtool=\"{}\" creationtoolversion=\"{}\" segtype=\"paragraph\" o-tmf=\"text-translator\" adminlang=\"en\" srclang=\"{}\" datatype=\"plaintext\"/>\n  <body>\n",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+            escape(&self.source_language)
+        ));
+        for unit in &self.units {
+            xml.push_str("    <tu>\n");
+            xml.push_str(&variant(&self.source_language, &unit.source));
+            for (language, translation) in &unit.translations {
+                xml.push_str(&variant(language, translation));
+            }
+            xml.push_str("    </tu>\n");
+        }
+        xml.push_str("  </body>\n</tmx>\n");
+        output::write_atomic(path, xml)?;
+        Ok(())
+    }
+}
+
+fn variant(language: &str, text: &str) -> String {
+    format!("      <tuv xml:lang=\"{}\"><seg>{}</seg></tuv>\n", escape(language), escape(text))
+}
+
+/// Inline elements whose content is native markup rather than text.
+fn is_native(name: &[u8]) -> bool {
+    matches!(name, b"bpt" | b"ept" | b"ph" | b"it" | b"ut")
+}
+
+/// The value of an attribute, matched by its local name (so `xml:lang` is found as `lang`).
+fn attribute(element: &quick_xml::events::BytesStart, name: &str) -> Result<Option<String>, TranslatorError> {
+    for attr in element.attributes() {
+        let attr = attr?;
+        if attr.key.local_name().as_ref() == name.as_bytes() {
+            return Ok(Some(attr.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::TranslatorError;
+
+/// Source language code asking the backend to detect the language itself.
+pub const AUTO_LANGUAGE: &str = "auto";
+
+/// Whether two language codes name the same language, ignoring case and the region (`en-US` is `en`).
+pub fn same_language(a: &str, b: &str) -> bool {
+    primary_language(a) == primary_language(b)
+}
+
+/// The language subtag of a code in lower case, e.g. `pt` for `PT-BR`.
+pub fn primary_language(code: &str) -> String {
+    code.split(['-', '_']).next().unwrap_or(code).to_ascii_lowercase()
+}
+
+/// How the text sent for translation should be interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum TextFormat {
+    #[default]
+    Text,
+    /// HTML markup; the backend translates the text and keeps the tags.
+    Html,
+}
+
+impl TextFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TextFormat::Text => "text",
+            TextFormat::Html => "html",
+        }
+    }
+}
+
+/// A language supported by a translation server.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Language {
+    pub code: String,
+    pub name: String,
+    /// Codes of the languages this one can be translated to, if the server reports them.
+    #[serde(default)]
+    pub targets: Vec<String>,
+}
+
+/// A guess of the language a text is written in.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Detection {
+    pub language: String,
+    /// Confidence of the guess in percent.
+    pub confidence: f64,
+}
+
+/// Checks that `source` can be translated to `target` according to the server's language list.
+pub fn check_language_pair(languages: &[Language], source: &str, target: &str) -> Result<(), TranslatorError> {
+    let Some(source_language) = languages.iter().find(|l| l.code == source) else {
+        return Err(format!(
+            "Source language '{}' is not supported by the server. Supported languages: {}",
+            source,
+            join_codes(languages.iter().map(|l| l.code.as_str()))
+        )
+        .into());
+    };
+    if !languages.iter().any(|l| l.code == target) {
+        return Err(format!(
+            "Target language '{}' is not supported by the server. Supported languages: {}",
+            target,
+            join_codes(languages.iter().map(|l| l.code.as_str()))
+        )
+        .into());
+    }
+    // Servers that don't report pairings are assumed to translate between any two languages.
+    if !source_language.targets.is_empty() && !source_language.targets.iter().any(|t| t == target) {
+        return Err(format!(
+            "The server can't translate from '{}' to '{}'. Available targets for '{}': {}",
+            source,
+            target,
+            source,
+            join_codes(source_language.targets.iter().map(String::as_str))
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn join_codes<'a>(codes: impl Iterator<Item = &'a str>) -> String {
+    codes.collect::<Vec<_>>().join(", ")
+}
+
+/// A paragraph that came before the text being translated, with its translation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextParagraph {
+    pub source: String,
+    pub translation: String,
+}
+
+/// A service able to translate a piece of text from one language to another.
+#[allow(async_fn_in_trait)]
+pub trait Translator {
+    /// Translates a single chunk of text from the `source` to the `target` language.
+    async fn translate(
+        &self,
+        text: &str,
+        source: &str,
+        target: &str,
+    ) -> Result<String, TranslatorError>;
+
+    /// Like [`translate`](Self::translate), with the paragraphs before the text as context, so
+    /// pronouns and terms can be translated consistently. Services that can't take context
+    /// translate the text alone.
+    async fn translate_in_context(
+        &self,
+        text: &str,
+        source: &str,
+        target: &str,
+        context: &[ContextParagraph],
+    ) -> Result<String, TranslatorError> {
+        let _ = context;
+        self.translate(text, source, target).await
+    }
+}